@@ -1,7 +1,9 @@
 /*! This is a player for WebGPU traces.
 !*/
 
-use player::{GlobalPlay as _, IdentityPassThroughFactory};
+#[cfg(not(feature = "winit"))]
+use player::headless;
+use player::{compat, stats, GlobalPlay as _, IdentityPassThroughFactory};
 use wgc::{device::trace, gfx_select};
 
 use std::{
@@ -15,19 +17,59 @@ fn main() {
 
     env_logger::init();
 
-    //TODO: setting for the backend bits
     //TODO: setting for the target frame, or controls
 
-    let dir = match std::env::args().nth(1) {
-        Some(arg) if Path::new(&arg).is_dir() => PathBuf::from(arg),
-        _ => panic!("Provide the dir path as the parameter"),
-    };
+    let mut args = std::env::args().skip(1).peekable();
+    match args.peek().map(String::as_str) {
+        Some("stats") => {
+            let dir = PathBuf::from(args.nth(1).expect("Provide the dir path after 'stats'"));
+            let actions = load_actions(&dir, None);
+            print!("{}", stats::TraceStats::new(&actions, &dir));
+            return;
+        }
+        Some("diff") => {
+            let old_dir = PathBuf::from(args.nth(1).expect("Provide two dir paths after 'diff'"));
+            let new_dir = PathBuf::from(args.next().expect("Provide two dir paths after 'diff'"));
+            let old = stats::TraceStats::new(&load_actions(&old_dir, None), &old_dir);
+            let new = stats::TraceStats::new(&load_actions(&new_dir, None), &new_dir);
+            print!("{}", stats::TraceDiff::new(&old, &new));
+            return;
+        }
+        _ => {}
+    }
 
-    log::info!("Loading trace '{:?}'", dir);
-    let file = fs::File::open(dir.join(trace::FILE_NAME)).unwrap();
-    let mut actions: Vec<trace::Action> = ron::de::from_reader(file).unwrap();
-    actions.reverse(); // allows us to pop from the top
-    log::info!("Found {} actions", actions.len());
+    let mut dir = None;
+    let mut backend_override = None;
+    let mut check_only = false;
+    let mut remap = false;
+    let mut frame_dir = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--backend" => {
+                let name = args
+                    .next()
+                    .expect("Provide the backend name after --backend");
+                backend_override = Some(parse_backend(&name));
+            }
+            "--check" => check_only = true,
+            "--remap" => remap = true,
+            "--frames" => {
+                let path = args
+                    .next()
+                    .expect("Provide the output dir path after --frames");
+                frame_dir = Some(PathBuf::from(path));
+            }
+            _ if Path::new(&arg).is_dir() => dir = Some(PathBuf::from(arg)),
+            _ => panic!("Unknown argument '{}'", arg),
+        }
+    }
+    let dir = dir.expect("Provide the dir path as the parameter");
+    #[cfg(feature = "winit")]
+    if frame_dir.is_some() {
+        log::warn!("Frames are only written without the winit feature");
+    }
+
+    let mut actions = load_actions(&dir, backend_override);
 
     #[cfg(feature = "winit")]
     let event_loop = {
@@ -48,28 +90,55 @@ fn main() {
     let surface =
         global.instance_create_surface(&window, wgc::id::TypedId::zip(0, 1, wgt::Backend::Empty));
 
+    let backend = match actions.first() {
+        Some(&trace::Action::Init { backend, .. }) => backend_override.unwrap_or(backend),
+        _ => panic!("Expected Action::Init"),
+    };
+    log::info!("Initializing the device for backend: {:?}", backend);
+    let adapter = global
+        .request_adapter(
+            &wgc::instance::RequestAdapterOptions {
+                power_preference: wgt::PowerPreference::LowPower,
+                force_fallback_adapter: false,
+                #[cfg(feature = "winit")]
+                compatible_surface: Some(surface),
+                #[cfg(not(feature = "winit"))]
+                compatible_surface: None,
+            },
+            wgc::instance::AdapterInputs::IdSet(&[wgc::id::TypedId::zip(0, 0, backend)], |id| {
+                id.backend()
+            }),
+        )
+        .expect("Unable to find an adapter for selected backend");
+
+    let info = gfx_select!(adapter => global.adapter_get_info(adapter)).unwrap();
+    log::info!("Picked '{}'", info.name);
+
+    let capabilities = gfx_select!(adapter => global.query_capabilities(adapter, &actions));
+    if remap {
+        let report = compat::remap(
+            &mut actions,
+            &capabilities,
+            compat::RemapOptions {
+                lower_limits: true,
+                substitute_formats: true,
+                drop_timestamps: true,
+            },
+        );
+        log::info!("Remapped the trace: {:?}", report);
+    }
+    let incompatibilities = compat::check(&actions, &capabilities);
+    for incompatibility in incompatibilities.iter() {
+        println!("Incompatible {}", incompatibility);
+    }
+    if check_only {
+        println!("Found {} incompatibilities", incompatibilities.len());
+        return;
+    }
+
+    actions.reverse(); // allows us to pop from the top
     let device = match actions.pop() {
-        Some(trace::Action::Init { desc, backend }) => {
-            log::info!("Initializing the device for backend: {:?}", backend);
-            let adapter = global
-                .request_adapter(
-                    &wgc::instance::RequestAdapterOptions {
-                        power_preference: wgt::PowerPreference::LowPower,
-                        force_fallback_adapter: false,
-                        #[cfg(feature = "winit")]
-                        compatible_surface: Some(surface),
-                        #[cfg(not(feature = "winit"))]
-                        compatible_surface: None,
-                    },
-                    wgc::instance::AdapterInputs::IdSet(
-                        &[wgc::id::TypedId::zip(0, 0, backend)],
-                        |id| id.backend(),
-                    ),
-                )
-                .expect("Unable to find an adapter for selected backend");
-
-            let info = gfx_select!(adapter => global.adapter_get_info(adapter)).unwrap();
-            log::info!("Picked '{}'", info.name);
+        Some(trace::Action::Init { desc, .. }) => {
             let id = wgc::id::TypedId::zip(1, 0, backend);
             let (_, error) = gfx_select!(adapter => global.adapter_request_device(
                 adapter,
@@ -82,20 +151,78 @@ fn main() {
             }
             id
         }
-        _ => panic!("Expected Action::Init"),
+        _ => unreachable!(),
     };
 
     log::info!("Executing actions");
     #[cfg(not(feature = "winit"))]
     {
+        let frame_output = match frame_dir {
+            Some(frame_dir) => {
+                fs::create_dir_all(&frame_dir).unwrap();
+                headless::FrameOutput::Directory(frame_dir)
+            }
+            None => headless::FrameOutput::None,
+        };
+        let mut surfaces = headless::HeadlessSurfaces::new(&actions, frame_output);
+
         gfx_select!(device => global.device_start_capture(device));
 
+        // `Init` is the action 0
+        let mut action_index = 0;
+        let mut checksum_count = 0;
+        let mut divergence = None;
         while let Some(action) = actions.pop() {
-            gfx_select!(device => global.process(device, action, &dir, &mut command_buffer_id_manager));
+            action_index += 1;
+            match action {
+                trace::Action::BufferChecksum {
+                    id,
+                    range,
+                    checksum,
+                } => {
+                    let actual =
+                        gfx_select!(device => global.buffer_checksum(device, id, range.clone()));
+                    if actual != checksum {
+                        let what = format!("{:?} in {:?}", id, range);
+                        divergence = Some((action_index, what, checksum, actual));
+                        break;
+                    }
+                    checksum_count += 1;
+                }
+                trace::Action::FrameChecksum { surface, checksum } => {
+                    let actual = gfx_select!(device => surfaces.frame_checksum(&global, device, surface, &mut command_buffer_id_manager));
+                    if actual != checksum {
+                        let what = format!("The frame of {:?}", surface);
+                        divergence = Some((action_index, what, checksum, actual));
+                        break;
+                    }
+                    checksum_count += 1;
+                }
+                _ if headless::HeadlessSurfaces::handles(&action) => {
+                    gfx_select!(device => surfaces.process(&global, device, action, &mut command_buffer_id_manager));
+                }
+                _ => {
+                    gfx_select!(device => global.process(device, action, &dir, &mut command_buffer_id_manager));
+                }
+            }
         }
 
         gfx_select!(device => global.device_stop_capture(device));
         gfx_select!(device => global.device_poll(device, wgt::Maintain::Wait)).unwrap();
+
+        if let Some((index, what, expected, actual)) = divergence {
+            println!(
+                "First divergence at action {}: {} has checksum {:#x}, expected {:#x}",
+                index, what, actual, expected
+            );
+            std::process::exit(1);
+        }
+        if checksum_count != 0 {
+            println!("Verified {} checksums", checksum_count);
+        }
+        if surfaces.frame_count() != 0 {
+            println!("Presented {} frames", surfaces.frame_count());
+        }
     }
     #[cfg(feature = "winit")]
     {
@@ -139,6 +266,9 @@ fn main() {
                             gfx_select!(device => global.surface_present(id)).unwrap();
                             break;
                         }
+                        Some(trace::Action::FrameChecksum { .. }) => {
+                            log::debug!("Frame checksums are only verified without winit");
+                        }
                         Some(trace::Action::DiscardSurfaceTexture(id)) => {
                             log::debug!("Discarding frame {}", frame_count);
                             gfx_select!(device => global.surface_texture_discard(id)).unwrap();
@@ -188,3 +318,27 @@ fn main() {
         });
     }
 }
+
+/// Loads the trace actions, with all the ids moved to `backend` if it's given.
+fn load_actions(dir: &Path, backend: Option<wgt::Backend>) -> Vec<trace::Action<'static>> {
+    log::info!("Loading trace '{:?}'", dir);
+    let mut string = fs::read_to_string(dir.join(trace::FILE_NAME)).unwrap();
+    if let Some(backend) = backend {
+        log::info!("Retargeting the trace to {:?}", backend);
+        string = compat::retarget(&string, backend);
+    }
+    let actions: Vec<trace::Action> = ron::de::from_str(&string).unwrap();
+    log::info!("Found {} actions", actions.len());
+    actions
+}
+
+fn parse_backend(name: &str) -> wgt::Backend {
+    match name.to_lowercase().as_str() {
+        "vulkan" | "vk" => wgt::Backend::Vulkan,
+        "metal" | "mtl" => wgt::Backend::Metal,
+        "dx12" | "d3d12" => wgt::Backend::Dx12,
+        "dx11" | "d3d11" => wgt::Backend::Dx11,
+        "gl" | "gles" | "opengl" => wgt::Backend::Gl,
+        _ => panic!("Unknown backend '{}'", name),
+    }
+}
@@ -0,0 +1,578 @@
+/*! Compatibility checks for replaying a trace on an adapter
+ *  other than the one it was recorded with.
+ *
+ *  A trace captured on one backend may use features, limits
+ *  or texture formats that the target adapter lacks. `check` lists
+ *  all of them up front, and `remap` rewrites the actions where a
+ *  reasonable substitute exists. `retarget` moves the ids of a trace
+ *  to another backend.
+!*/
+
+use wgc::{
+    command::{ComputeCommand, RenderCommand},
+    device::trace::{Action, Command},
+};
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, mem,
+};
+
+/// Formats that may stand in for an unsupported render target format.
+pub const SUBSTITUTE_FORMATS: &[wgt::TextureFormat] = &[
+    wgt::TextureFormat::R8Unorm,
+    wgt::TextureFormat::Rg8Unorm,
+    wgt::TextureFormat::Rgba8Unorm,
+    wgt::TextureFormat::Rgba8UnormSrgb,
+    wgt::TextureFormat::Bgra8Unorm,
+    wgt::TextureFormat::Bgra8UnormSrgb,
+    wgt::TextureFormat::Rgb10a2Unorm,
+    wgt::TextureFormat::R16Float,
+    wgt::TextureFormat::Rg16Float,
+    wgt::TextureFormat::Rgba16Float,
+    wgt::TextureFormat::R32Float,
+    wgt::TextureFormat::Rg32Float,
+    wgt::TextureFormat::Rgba32Float,
+    wgt::TextureFormat::R8Uint,
+    wgt::TextureFormat::Rg8Uint,
+    wgt::TextureFormat::Rgba8Uint,
+    wgt::TextureFormat::R16Uint,
+    wgt::TextureFormat::Rg16Uint,
+    wgt::TextureFormat::Rgba16Uint,
+    wgt::TextureFormat::R32Uint,
+    wgt::TextureFormat::Rg32Uint,
+    wgt::TextureFormat::Rgba32Uint,
+    wgt::TextureFormat::R8Sint,
+    wgt::TextureFormat::Rg8Sint,
+    wgt::TextureFormat::Rgba8Sint,
+    wgt::TextureFormat::R16Sint,
+    wgt::TextureFormat::Rg16Sint,
+    wgt::TextureFormat::Rgba16Sint,
+    wgt::TextureFormat::R32Sint,
+    wgt::TextureFormat::Rg32Sint,
+    wgt::TextureFormat::Rgba32Sint,
+    wgt::TextureFormat::Depth32Float,
+    wgt::TextureFormat::Depth24Plus,
+    wgt::TextureFormat::Depth24PlusStencil8,
+    wgt::TextureFormat::Depth32FloatStencil8,
+];
+
+/// What the replaying adapter is able to do.
+#[derive(Clone, Debug)]
+pub struct Capabilities {
+    pub features: wgt::Features,
+    pub limits: wgt::Limits,
+    /// Features of every format used by the trace, as well as of the `SUBSTITUTE_FORMATS`.
+    pub formats: HashMap<wgt::TextureFormat, wgt::TextureFormatFeatures>,
+}
+
+impl Capabilities {
+    fn supports(
+        &self,
+        format: wgt::TextureFormat,
+        usage: wgt::TextureUsages,
+        sample_count: u32,
+    ) -> bool {
+        match self.formats.get(&format) {
+            Some(features) => {
+                features.allowed_usages.contains(usage)
+                    && (sample_count <= 1
+                        || features
+                            .flags
+                            .contains(wgt::TextureFormatFeatureFlags::MULTISAMPLE))
+            }
+            None => false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Issue {
+    MissingFeatures(wgt::Features),
+    Limit {
+        name: &'static str,
+        requested: u64,
+        supported: u64,
+    },
+    TextureFormat {
+        format: wgt::TextureFormat,
+        usage: wgt::TextureUsages,
+        sample_count: u32,
+    },
+    TargetFormat(wgt::TextureFormat),
+    TimestampQuery,
+}
+
+/// A single reason the trace can't be replayed as is.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Incompatibility {
+    /// Index of the offending action in the trace.
+    pub action_index: usize,
+    pub issue: Issue,
+}
+
+impl fmt::Display for Incompatibility {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "action {}: ", self.action_index)?;
+        match self.issue {
+            Issue::MissingFeatures(features) => write!(f, "missing features {:?}", features),
+            Issue::Limit {
+                name,
+                requested,
+                supported,
+            } => write!(
+                f,
+                "limit '{}' is {}, but the adapter only supports {}",
+                name, requested, supported
+            ),
+            Issue::TextureFormat {
+                format,
+                usage,
+                sample_count,
+            } => write!(
+                f,
+                "format {:?} doesn't support usage {:?} with {} samples",
+                format, usage, sample_count
+            ),
+            Issue::TargetFormat(format) => {
+                write!(f, "format {:?} can't be rendered to", format)
+            }
+            Issue::TimestampQuery => write!(f, "timestamp queries are not supported"),
+        }
+    }
+}
+
+/// Returns every texture format referenced by `actions`.
+pub fn used_formats(actions: &[Action]) -> HashSet<wgt::TextureFormat> {
+    let mut formats = HashSet::new();
+    for action in actions {
+        match *action {
+            Action::ConfigureSurface(_, ref config) => {
+                formats.insert(config.format);
+            }
+            Action::CreateTexture(_, ref desc) => {
+                formats.insert(desc.format);
+            }
+            Action::CreateTextureView { ref desc, .. } => {
+                formats.extend(desc.format);
+            }
+            Action::CreateRenderPipeline { ref desc, .. } => {
+                formats.extend(render_pipeline_formats(desc));
+            }
+            Action::CreateRenderBundle { ref desc, .. } => {
+                formats.extend(desc.color_formats.iter().flatten().cloned());
+                formats.extend(desc.depth_stencil.map(|ds| ds.format));
+            }
+            _ => {}
+        }
+    }
+    formats
+}
+
+fn render_pipeline_formats<'a>(
+    desc: &'a wgc::pipeline::RenderPipelineDescriptor,
+) -> impl Iterator<Item = wgt::TextureFormat> + 'a {
+    desc.fragment
+        .iter()
+        .flat_map(|fragment| {
+            fragment
+                .targets
+                .iter()
+                .flatten()
+                .map(|target| target.format)
+        })
+        .chain(desc.depth_stencil.as_ref().map(|ds| ds.format))
+}
+
+/// Lists every feature, limit and format used by `actions`
+/// that is beyond the given capabilities.
+pub fn check(actions: &[Action], caps: &Capabilities) -> Vec<Incompatibility> {
+    let mut incompatibilities = Vec::new();
+    let mut report = |action_index, issue| {
+        incompatibilities.push(Incompatibility {
+            action_index,
+            issue,
+        })
+    };
+
+    for (index, action) in actions.iter().enumerate() {
+        match *action {
+            Action::Init { ref desc, .. } => {
+                if !caps.features.contains(desc.features) {
+                    report(index, Issue::MissingFeatures(desc.features - caps.features));
+                }
+                desc.limits.check_limits_with_fail_fn(
+                    &caps.limits,
+                    false,
+                    |name, requested, supported| {
+                        report(
+                            index,
+                            Issue::Limit {
+                                name,
+                                requested,
+                                supported,
+                            },
+                        )
+                    },
+                );
+            }
+            Action::CreateTexture(_, ref desc)
+                if !caps.supports(desc.format, desc.usage, desc.sample_count) =>
+            {
+                report(
+                    index,
+                    Issue::TextureFormat {
+                        format: desc.format,
+                        usage: desc.usage,
+                        sample_count: desc.sample_count,
+                    },
+                );
+            }
+            Action::CreateRenderPipeline { ref desc, .. } => {
+                for format in render_pipeline_formats(desc) {
+                    if !caps.supports(format, wgt::TextureUsages::RENDER_ATTACHMENT, 1) {
+                        report(index, Issue::TargetFormat(format));
+                    }
+                }
+            }
+            Action::CreateQuerySet { ref desc, .. }
+                if matches!(desc.ty, wgt::QueryType::Timestamp)
+                    && !caps.features.contains(wgt::Features::TIMESTAMP_QUERY) =>
+            {
+                report(index, Issue::TimestampQuery);
+            }
+            Action::Submit(_, ref commands) => {
+                let inside_passes = commands.iter().any(|command| match *command {
+                    Command::RunComputePass { ref base } => base
+                        .commands
+                        .iter()
+                        .any(|c| matches!(*c, ComputeCommand::WriteTimestamp { .. })),
+                    Command::RunRenderPass { ref base, .. } => base
+                        .commands
+                        .iter()
+                        .any(|c| matches!(*c, RenderCommand::WriteTimestamp { .. })),
+                    _ => false,
+                });
+                if inside_passes
+                    && !caps
+                        .features
+                        .contains(wgt::Features::WRITE_TIMESTAMP_INSIDE_PASSES)
+                {
+                    report(index, Issue::TimestampQuery);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    incompatibilities
+}
+
+/// Which kinds of rewrites `remap` is allowed to do.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RemapOptions {
+    /// Clamp the requested device limits to the supported ones.
+    pub lower_limits: bool,
+    /// Replace unsupported render target formats with supported ones.
+    pub substitute_formats: bool,
+    /// Remove timestamp query sets, writes and resolves
+    /// if the adapter doesn't support them.
+    pub drop_timestamps: bool,
+}
+
+/// Summary of the rewrites done by `remap`.
+#[derive(Debug, Default)]
+pub struct RemapReport {
+    pub lowered_limits: Vec<&'static str>,
+    pub substituted_formats: HashMap<wgt::TextureFormat, wgt::TextureFormat>,
+    pub dropped_actions: usize,
+    pub dropped_commands: usize,
+}
+
+fn lower_limits(limits: &mut wgt::Limits, supported: &wgt::Limits) -> Vec<&'static str> {
+    let mut lowered = Vec::new();
+    limits.check_limits_with_fail_fn(supported, false, |name, _, _| lowered.push(name));
+
+    macro_rules! clamp {
+        ($($name:ident: $op:ident),* $(,)?) => {
+            $( limits.$name = limits.$name.$op(supported.$name); )*
+        };
+    }
+    clamp!(
+        max_texture_dimension_1d: min,
+        max_texture_dimension_2d: min,
+        max_texture_dimension_3d: min,
+        max_texture_array_layers: min,
+        max_bind_groups: min,
+        max_dynamic_uniform_buffers_per_pipeline_layout: min,
+        max_dynamic_storage_buffers_per_pipeline_layout: min,
+        max_sampled_textures_per_shader_stage: min,
+        max_samplers_per_shader_stage: min,
+        max_storage_buffers_per_shader_stage: min,
+        max_storage_textures_per_shader_stage: min,
+        max_uniform_buffers_per_shader_stage: min,
+        max_uniform_buffer_binding_size: min,
+        max_storage_buffer_binding_size: min,
+        max_vertex_buffers: min,
+        max_vertex_attributes: min,
+        max_vertex_buffer_array_stride: min,
+        max_push_constant_size: min,
+        min_uniform_buffer_offset_alignment: max,
+        min_storage_buffer_offset_alignment: max,
+        max_inter_stage_shader_components: min,
+        max_compute_workgroup_storage_size: min,
+        max_compute_invocations_per_workgroup: min,
+        max_compute_workgroup_size_x: min,
+        max_compute_workgroup_size_y: min,
+        max_compute_workgroup_size_z: min,
+        max_compute_workgroups_per_dimension: min,
+        max_buffer_size: min,
+    );
+    lowered
+}
+
+/// Picks a supported format for `format`, keeping the sample type, the texel
+/// size so that the layouts of copies and writes stay valid, and at least as
+/// many components, preferring the same color space.
+fn pick_substitute(
+    format: wgt::TextureFormat,
+    usage: wgt::TextureUsages,
+    sample_count: u32,
+    caps: &Capabilities,
+) -> Option<wgt::TextureFormat> {
+    let info = format.describe();
+    SUBSTITUTE_FORMATS
+        .iter()
+        .cloned()
+        .filter(|&candidate| {
+            let candidate_info = candidate.describe();
+            mem::discriminant(&candidate_info.sample_type) == mem::discriminant(&info.sample_type)
+                && candidate_info.block_size == info.block_size
+                && candidate_info.components >= info.components
+                && caps.supports(candidate, usage, sample_count)
+        })
+        .min_by_key(|candidate| {
+            let candidate_info = candidate.describe();
+            (
+                candidate_info.srgb != info.srgb,
+                candidate_info.components - info.components,
+            )
+        })
+}
+
+fn substitute(
+    format: &mut wgt::TextureFormat,
+    map: &HashMap<wgt::TextureFormat, wgt::TextureFormat>,
+) {
+    if let Some(&new) = map.get(format) {
+        *format = new;
+    }
+}
+
+/// Rewrites `actions` to fit into the given capabilities, where possible.
+///
+/// Incompatibilities that can't be remapped are left in place,
+/// so `check` should be called again on the result.
+pub fn remap(actions: &mut Vec<Action>, caps: &Capabilities, options: RemapOptions) -> RemapReport {
+    let mut report = RemapReport::default();
+    let mut substituted_formats = HashMap::new();
+
+    if options.substitute_formats {
+        // Formats are substituted for the whole trace, so that views,
+        // pipelines and bundles stay consistent with their textures.
+        let mut requirements = HashMap::<_, (wgt::TextureUsages, u32)>::new();
+        for action in actions.iter() {
+            if let Action::CreateTexture(_, ref desc) = *action {
+                let entry = requirements
+                    .entry(desc.format)
+                    .or_insert((wgt::TextureUsages::empty(), 1));
+                entry.0 |= desc.usage;
+                entry.1 = entry.1.max(desc.sample_count);
+            }
+        }
+        for (format, (usage, sample_count)) in requirements {
+            if !usage.contains(wgt::TextureUsages::RENDER_ATTACHMENT)
+                || caps.supports(format, usage, sample_count)
+            {
+                continue;
+            }
+            match pick_substitute(format, usage, sample_count, caps) {
+                Some(new) => {
+                    log::info!(
+                        "Substituting render target format {:?} with {:?}",
+                        format,
+                        new
+                    );
+                    substituted_formats.insert(format, new);
+                }
+                None => log::warn!("No substitute found for render target format {:?}", format),
+            }
+        }
+    }
+
+    let drop_timestamps =
+        options.drop_timestamps && !caps.features.contains(wgt::Features::TIMESTAMP_QUERY);
+    let drop_pass_timestamps = options.drop_timestamps
+        && !caps
+            .features
+            .contains(wgt::Features::WRITE_TIMESTAMP_INSIDE_PASSES);
+    let mut timestamp_sets = HashSet::new();
+    let map = &substituted_formats;
+
+    for action in actions.iter_mut() {
+        match *action {
+            Action::Init { ref mut desc, .. } => {
+                if options.lower_limits {
+                    report.lowered_limits = lower_limits(&mut desc.limits, &caps.limits);
+                }
+                if drop_timestamps {
+                    desc.features -= wgt::Features::TIMESTAMP_QUERY;
+                }
+                if drop_pass_timestamps {
+                    desc.features -= wgt::Features::WRITE_TIMESTAMP_INSIDE_PASSES;
+                }
+            }
+            Action::ConfigureSurface(_, ref mut config) => substitute(&mut config.format, map),
+            Action::CreateTexture(_, ref mut desc) => substitute(&mut desc.format, map),
+            Action::CreateTextureView { ref mut desc, .. } => {
+                if let Some(ref mut format) = desc.format {
+                    substitute(format, map);
+                }
+            }
+            Action::CreateRenderPipeline { ref mut desc, .. } => {
+                if let Some(ref mut fragment) = desc.fragment {
+                    for target in fragment.targets.to_mut().iter_mut().flatten() {
+                        substitute(&mut target.format, map);
+                    }
+                }
+                if let Some(ref mut ds) = desc.depth_stencil {
+                    substitute(&mut ds.format, map);
+                }
+            }
+            Action::CreateRenderBundle {
+                ref mut desc,
+                ref mut base,
+                ..
+            } => {
+                for format in desc.color_formats.to_mut().iter_mut().flatten() {
+                    substitute(format, map);
+                }
+                if let Some(ref mut ds) = desc.depth_stencil {
+                    substitute(&mut ds.format, map);
+                }
+                if drop_pass_timestamps {
+                    let count = base.commands.len();
+                    base.commands
+                        .retain(|c| !matches!(*c, RenderCommand::WriteTimestamp { .. }));
+                    report.dropped_commands += count - base.commands.len();
+                }
+            }
+            Action::CreateQuerySet { id, ref desc }
+                if drop_timestamps && matches!(desc.ty, wgt::QueryType::Timestamp) =>
+            {
+                timestamp_sets.insert(id);
+            }
+            Action::Submit(_, ref mut commands) => {
+                let count = commands.len();
+                commands.retain(|command| match *command {
+                    Command::WriteTimestamp { query_set_id, .. }
+                    | Command::ResolveQuerySet { query_set_id, .. } => {
+                        !timestamp_sets.contains(&query_set_id)
+                    }
+                    _ => true,
+                });
+                report.dropped_commands += count - commands.len();
+
+                if drop_pass_timestamps {
+                    for command in commands.iter_mut() {
+                        match *command {
+                            Command::RunComputePass { ref mut base } => {
+                                let count = base.commands.len();
+                                base.commands.retain(|c| {
+                                    !matches!(*c, ComputeCommand::WriteTimestamp { .. })
+                                });
+                                report.dropped_commands += count - base.commands.len();
+                            }
+                            Command::RunRenderPass { ref mut base, .. } => {
+                                let count = base.commands.len();
+                                base.commands.retain(|c| {
+                                    !matches!(*c, RenderCommand::WriteTimestamp { .. })
+                                });
+                                report.dropped_commands += count - base.commands.len();
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    report.substituted_formats = substituted_formats;
+
+    if !timestamp_sets.is_empty() {
+        let count = actions.len();
+        actions.retain(|action| match *action {
+            Action::CreateQuerySet { id, .. } | Action::DestroyQuerySet(id) => {
+                !timestamp_sets.contains(&id)
+            }
+            _ => true,
+        });
+        report.dropped_actions = count - actions.len();
+    }
+
+    report
+}
+
+/// Rewrites the backend of every `Id(index, epoch, backend)` in the RON
+/// text of a trace, so that it can be replayed on another backend.
+///
+/// Resource ids carry the backend they were created on, and all of them
+/// need to match the device that replays the trace: the ones in the
+/// actions, the descriptors, as well as in the recorded commands.
+/// String literals, such as labels, are left intact.
+pub fn retarget(trace: &str, backend: wgt::Backend) -> String {
+    const ID_START: &str = "Id(";
+    let mut output = String::with_capacity(trace.len());
+    let mut rest = trace;
+    let mut in_string = false;
+    let mut prev = ' ';
+    while let Some(c) = rest.chars().next() {
+        if in_string {
+            let len = match c {
+                '\\' => rest[1..].chars().next().map_or(1, |e| 1 + e.len_utf8()),
+                '"' => {
+                    in_string = false;
+                    1
+                }
+                _ => c.len_utf8(),
+            };
+            output.push_str(&rest[..len]);
+            rest = &rest[len..];
+        } else if c == '"' {
+            in_string = true;
+            output.push(c);
+            rest = &rest[1..];
+        } else if rest.starts_with(ID_START) && !(prev.is_alphanumeric() || prev == '_') {
+            let end = rest.find(')').expect("Unterminated id");
+            let fields = rest[ID_START.len()..end].split(',').collect::<Vec<_>>();
+            match fields[..] {
+                [index, epoch, _] => {
+                    output.push_str(&format!(
+                        "Id({}, {}, {:?})",
+                        index.trim(),
+                        epoch.trim(),
+                        backend
+                    ));
+                }
+                _ => output.push_str(&rest[..=end]),
+            }
+            rest = &rest[end + 1..];
+        } else {
+            output.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+        prev = c;
+    }
+    output
+}
@@ -0,0 +1,344 @@
+/*! Replaying of surface actions without a window.
+ *
+ *  Each traced surface texture is substituted with an offscreen texture
+ *  matching the traced `SurfaceConfiguration`. Presented frames can
+ *  be written out as a sequence of PNG images, or kept in memory, and
+ *  their traced checksums are verified.
+!*/
+
+use crate::{GlobalPlay as _, IdentityPassThroughFactory};
+use wgc::device::trace::{self, Action, Command};
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    slice,
+};
+
+type Global = wgc::hub::Global<IdentityPassThroughFactory>;
+
+/// Where the presented frames go.
+#[derive(Clone, Debug)]
+pub enum FrameOutput {
+    /// Frames are discarded.
+    None,
+    /// Frames are written as `frameNNNNN.png` into the directory.
+    Directory(PathBuf),
+    /// Frames are kept in memory, see `HeadlessSurfaces::frames`.
+    Memory,
+}
+
+/// Contents of a presented frame, in RGBA8 without row padding.
+#[derive(Clone, Debug)]
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct HeadlessSurfaces {
+    output: FrameOutput,
+    configs: HashMap<wgc::id::SurfaceId, wgt::SurfaceConfiguration>,
+    textures: HashMap<wgc::id::SurfaceId, wgc::id::TextureId>,
+    /// Index of the next buffer used for reading back a frame.
+    /// It's past any buffer index used by the trace.
+    next_buffer_index: u32,
+    frame_count: usize,
+    frames: Vec<Frame>,
+}
+
+impl HeadlessSurfaces {
+    /// Prepares for replaying `actions`, sending each presented frame
+    /// into the `output`.
+    pub fn new(actions: &[Action], output: FrameOutput) -> Self {
+        let next_buffer_index = actions
+            .iter()
+            .filter_map(|action| match *action {
+                Action::CreateBuffer(id, _) => Some(wgc::id::TypedId::unzip(id).0 + 1),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0);
+        Self {
+            output,
+            configs: HashMap::new(),
+            textures: HashMap::new(),
+            next_buffer_index,
+            frame_count: 0,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Returns `true` for actions that need to go through `process`.
+    pub fn handles(action: &Action) -> bool {
+        matches!(
+            *action,
+            Action::ConfigureSurface(..)
+                | Action::GetSurfaceTexture { .. }
+                | Action::Present(_)
+                | Action::DiscardSurfaceTexture(_)
+                | Action::FrameChecksum { .. }
+        )
+    }
+
+    /// Number of frames presented so far.
+    pub fn frame_count(&self) -> usize {
+        self.frame_count
+    }
+
+    /// Frames presented so far, if they are kept in memory.
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    /// Reads back the frame about to be presented on the `surface`, and
+    /// returns its checksum, as recorded by `Action::FrameChecksum`.
+    pub fn frame_checksum<A: wgc::hub::HalApi>(
+        &mut self,
+        global: &Global,
+        device: wgc::id::DeviceId,
+        surface: wgc::id::SurfaceId,
+        comb_manager: &mut wgc::hub::IdentityManager,
+    ) -> u64 {
+        let texture = *self
+            .textures
+            .get(&surface)
+            .expect("Checking a frame without a surface texture");
+        let buffer = self.next_buffer(device);
+        let texels = read_texels::<A>(
+            global,
+            device,
+            texture,
+            buffer,
+            &self.configs[&surface],
+            comb_manager,
+        );
+        trace::checksum(&texels)
+    }
+
+    fn next_buffer(&mut self, device: wgc::id::DeviceId) -> wgc::id::BufferId {
+        let buffer = wgc::id::TypedId::zip(self.next_buffer_index, 1, device.backend());
+        self.next_buffer_index += 1;
+        buffer
+    }
+
+    pub fn process<A: wgc::hub::HalApi>(
+        &mut self,
+        global: &Global,
+        device: wgc::id::DeviceId,
+        action: Action,
+        comb_manager: &mut wgc::hub::IdentityManager,
+    ) {
+        match action {
+            Action::ConfigureSurface(surface, config) => {
+                log::info!("Configuring the headless surface {:?}", surface);
+                self.configs.insert(surface, config);
+            }
+            Action::GetSurfaceTexture { id, parent_id } => {
+                let config = self
+                    .configs
+                    .get(&parent_id)
+                    .expect("Surface texture is acquired before the surface is configured");
+                let desc = wgc::resource::TextureDescriptor {
+                    label: Some(Cow::Borrowed("Headless Surface Texture")),
+                    size: wgt::Extent3d {
+                        width: config.width,
+                        height: config.height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgt::TextureDimension::D2,
+                    format: config.format,
+                    usage: config.usage | wgt::TextureUsages::COPY_SRC,
+                };
+                global.device_maintain_ids::<A>(device).unwrap();
+                let (_, error) = global.device_create_texture::<A>(device, &desc, id);
+                if let Some(e) = error {
+                    panic!("{:?}", e);
+                }
+                self.textures.insert(parent_id, id);
+            }
+            Action::Present(surface) => {
+                let texture = self
+                    .textures
+                    .remove(&surface)
+                    .expect("Presenting without a surface texture");
+                log::debug!("Presenting headless frame {}", self.frame_count);
+                if !matches!(self.output, FrameOutput::None) {
+                    let buffer = self.next_buffer(device);
+                    let config = &self.configs[&surface];
+                    match read_frame::<A>(global, device, texture, buffer, config, comb_manager) {
+                        Ok(frame) => match self.output {
+                            FrameOutput::None => unreachable!(),
+                            FrameOutput::Directory(ref dir) => {
+                                let path = dir.join(format!("frame{:05}.png", self.frame_count));
+                                if let Err(e) = write_png(&path, &frame) {
+                                    log::error!("Unable to write {:?}: {:?}", path, e);
+                                }
+                            }
+                            FrameOutput::Memory => self.frames.push(frame),
+                        },
+                        Err(format) => log::warn!(
+                            "Unable to read back frame {} of format {:?}",
+                            self.frame_count,
+                            format
+                        ),
+                    }
+                }
+                global.texture_drop::<A>(texture, true);
+                self.frame_count += 1;
+            }
+            Action::FrameChecksum { surface, checksum } => {
+                let actual = self.frame_checksum::<A>(global, device, surface, comb_manager);
+                if actual != checksum {
+                    panic!(
+                        "Checksum mismatch of the frame of {:?}: expected {:#x}, got {:#x}",
+                        surface, checksum, actual
+                    );
+                }
+            }
+            Action::DiscardSurfaceTexture(surface) => {
+                let texture = self
+                    .textures
+                    .remove(&surface)
+                    .expect("Discarding without a surface texture");
+                global.texture_drop::<A>(texture, true);
+            }
+            _ => panic!("Unexpected non-surface action {:?}", action),
+        }
+    }
+}
+
+fn read_frame<A: wgc::hub::HalApi>(
+    global: &Global,
+    device: wgc::id::DeviceId,
+    texture: wgc::id::TextureId,
+    buffer: wgc::id::BufferId,
+    config: &wgt::SurfaceConfiguration,
+    comb_manager: &mut wgc::hub::IdentityManager,
+) -> Result<Frame, wgt::TextureFormat> {
+    let swap_red_blue = match config.format {
+        wgt::TextureFormat::Rgba8Unorm | wgt::TextureFormat::Rgba8UnormSrgb => false,
+        wgt::TextureFormat::Bgra8Unorm | wgt::TextureFormat::Bgra8UnormSrgb => true,
+        other => return Err(other),
+    };
+    let mut pixels = read_texels::<A>(global, device, texture, buffer, config, comb_manager);
+    if swap_red_blue {
+        for texel in pixels.chunks_exact_mut(4) {
+            texel.swap(0, 2);
+        }
+    }
+
+    Ok(Frame {
+        width: config.width,
+        height: config.height,
+        pixels,
+    })
+}
+
+/// Reads back the texels of a surface `texture` through the `buffer`,
+/// row by row without padding.
+fn read_texels<A: wgc::hub::HalApi>(
+    global: &Global,
+    device: wgc::id::DeviceId,
+    texture: wgc::id::TextureId,
+    buffer: wgc::id::BufferId,
+    config: &wgt::SurfaceConfiguration,
+    comb_manager: &mut wgc::hub::IdentityManager,
+) -> Vec<u8> {
+    let align = wgt::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let unpadded_bytes_per_row = config.width * config.format.describe().block_size as u32;
+    let padding = (align - unpadded_bytes_per_row % align) % align;
+    let padded_bytes_per_row = unpadded_bytes_per_row + padding;
+    let size = (padded_bytes_per_row * config.height) as wgt::BufferAddress;
+
+    // neither of the actions refer to any files
+    let dir = Path::new("");
+    global.process::<A>(
+        device,
+        Action::CreateBuffer(
+            buffer,
+            wgc::resource::BufferDescriptor {
+                label: Some(Cow::Borrowed("Headless Frame Readback")),
+                size,
+                usage: wgt::BufferUsages::MAP_READ | wgt::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            },
+        ),
+        dir,
+        comb_manager,
+    );
+    global.process::<A>(
+        device,
+        Action::Submit(
+            0,
+            vec![Command::CopyTextureToBuffer {
+                src: wgt::ImageCopyTexture {
+                    texture,
+                    mip_level: 0,
+                    origin: wgt::Origin3d::ZERO,
+                    aspect: wgt::TextureAspect::All,
+                },
+                dst: wgt::ImageCopyBuffer {
+                    buffer,
+                    layout: wgt::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: std::num::NonZeroU32::new(padded_bytes_per_row),
+                        rows_per_image: None,
+                    },
+                },
+                size: wgt::Extent3d {
+                    width: config.width,
+                    height: config.height,
+                    depth_or_array_layers: 1,
+                },
+            }],
+        ),
+        dir,
+        comb_manager,
+    );
+
+    global
+        .buffer_map_async::<A>(
+            buffer,
+            0..size,
+            wgc::resource::BufferMapOperation {
+                host: wgc::device::HostMap::Read,
+                callback: wgc::resource::BufferMapCallback::from_rust(Box::new(|status| {
+                    match status {
+                        wgc::resource::BufferMapAsyncStatus::Success => (),
+                        _ => panic!("Unable to map: {:?}", status),
+                    }
+                })),
+            },
+        )
+        .unwrap();
+    global
+        .device_poll::<A>(device, wgt::Maintain::Wait)
+        .unwrap();
+    let (ptr, mapped_size) = global
+        .buffer_get_mapped_range::<A>(buffer, 0, Some(size))
+        .unwrap();
+    let contents = unsafe { slice::from_raw_parts(ptr, mapped_size as usize) };
+    let mut texels = Vec::with_capacity((unpadded_bytes_per_row * config.height) as usize);
+    for row in contents.chunks_exact(padded_bytes_per_row as usize) {
+        texels.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+    }
+    global.buffer_unmap::<A>(buffer).unwrap();
+    global.buffer_drop::<A>(buffer, false);
+    texels
+}
+
+fn write_png(path: &Path, frame: &Frame) -> io::Result<()> {
+    let file = io::BufWriter::new(fs::File::create(path)?);
+    let mut encoder = png::Encoder::new(file, frame.width, frame.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&frame.pixels)?;
+    Ok(())
+}
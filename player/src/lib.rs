@@ -8,7 +8,11 @@
 
 use wgc::device::trace;
 
-use std::{borrow::Cow, fmt::Debug, fs, marker::PhantomData, path::Path};
+pub mod compat;
+pub mod headless;
+pub mod stats;
+
+use std::{borrow::Cow, fmt::Debug, fs, marker::PhantomData, ops::Range, path::Path, slice};
 
 #[derive(Debug)]
 pub struct IdentityPassThrough<I>(PhantomData<I>);
@@ -47,6 +51,17 @@ pub trait GlobalPlay {
         dir: &Path,
         comb_manager: &mut wgc::hub::IdentityManager,
     );
+    fn query_capabilities<A: wgc::hub::HalApi>(
+        &self,
+        adapter: wgc::id::AdapterId,
+        actions: &[trace::Action],
+    ) -> compat::Capabilities;
+    fn buffer_checksum<A: wgc::hub::HalApi>(
+        &self,
+        device: wgc::id::DeviceId,
+        buffer: wgc::id::BufferId,
+        range: Range<wgt::BufferAddress>,
+    ) -> u64;
 }
 
 impl GlobalPlay for wgc::hub::Global<IdentityPassThroughFactory> {
@@ -160,8 +175,9 @@ impl GlobalPlay for wgc::hub::Global<IdentityPassThroughFactory> {
             }
             Action::ConfigureSurface { .. }
             | Action::Present(_)
-            | Action::DiscardSurfaceTexture(_) => {
-                panic!("Unexpected Surface action: winit feature is not enabled")
+            | Action::DiscardSurfaceTexture(_)
+            | Action::FrameChecksum { .. } => {
+                panic!("Unexpected Surface action: use `headless::HeadlessSurfaces` without winit")
             }
             Action::CreateBuffer(id, desc) => {
                 self.device_maintain_ids::<A>(device).unwrap();
@@ -251,12 +267,21 @@ impl GlobalPlay for wgc::hub::Global<IdentityPassThroughFactory> {
             }
             Action::CreateShaderModule { id, desc, data } => {
                 log::info!("Creating shader from {}", data);
-                let code = fs::read_to_string(dir.join(&data)).unwrap();
                 let source = if data.ends_with(".wgsl") {
+                    let code = fs::read_to_string(dir.join(&data)).unwrap();
                     wgc::pipeline::ShaderModuleSource::Wgsl(Cow::Owned(code))
                 } else if data.ends_with(".ron") {
+                    let code = fs::read_to_string(dir.join(&data)).unwrap();
                     let module = ron::de::from_str(&code).unwrap();
                     wgc::pipeline::ShaderModuleSource::Naga(module)
+                } else if cfg!(feature = "shader-bundle") && data.ends_with(".bundle") {
+                    #[cfg(feature = "shader-bundle")]
+                    {
+                        let bytes = fs::read(dir.join(&data)).unwrap();
+                        wgc::pipeline::ShaderModuleSource::Bundle(Cow::Owned(bytes))
+                    }
+                    #[cfg(not(feature = "shader-bundle"))]
+                    unreachable!()
                 } else {
                     panic!("Unknown shader {}", data);
                 };
@@ -379,6 +404,72 @@ impl GlobalPlay for wgc::hub::Global<IdentityPassThroughFactory> {
                 let cmdbuf = self.encode_commands::<A>(encoder, commands);
                 self.queue_submit::<A>(device, &[cmdbuf]).unwrap();
             }
+            Action::BufferChecksum {
+                id,
+                range,
+                checksum,
+            } => {
+                let actual = self.buffer_checksum::<A>(device, id, range.clone());
+                if actual != checksum {
+                    panic!(
+                        "Checksum mismatch of {:?} in {:?}: expected {:#x}, got {:#x}",
+                        id, range, checksum, actual
+                    );
+                }
+            }
+        }
+    }
+    fn query_capabilities<A: wgc::hub::HalApi>(
+        &self,
+        adapter: wgc::id::AdapterId,
+        actions: &[trace::Action],
+    ) -> compat::Capabilities {
+        let formats = compat::used_formats(actions)
+            .into_iter()
+            .chain(compat::SUBSTITUTE_FORMATS.iter().cloned())
+            .map(|format| {
+                let features = self
+                    .adapter_get_texture_format_features::<A>(adapter, format)
+                    .unwrap();
+                (format, features)
+            })
+            .collect();
+        compat::Capabilities {
+            features: self.adapter_features::<A>(adapter).unwrap(),
+            limits: self.adapter_limits::<A>(adapter).unwrap(),
+            formats,
+        }
+    }
+
+    fn buffer_checksum<A: wgc::hub::HalApi>(
+        &self,
+        device: wgc::id::DeviceId,
+        buffer: wgc::id::BufferId,
+        range: Range<wgt::BufferAddress>,
+    ) -> u64 {
+        if range.start == range.end {
+            return trace::checksum(&[]);
         }
+        self.buffer_map_async::<A>(
+            buffer,
+            range.clone(),
+            wgc::resource::BufferMapOperation {
+                host: wgc::device::HostMap::Read,
+                callback: wgc::resource::BufferMapCallback::from_rust(Box::new(
+                    |status| match status {
+                        wgc::resource::BufferMapAsyncStatus::Success => (),
+                        _ => panic!("Unable to map: {:?}", status),
+                    },
+                )),
+            },
+        )
+        .unwrap();
+        self.device_poll::<A>(device, wgt::Maintain::Wait).unwrap();
+        let (ptr, size) = self
+            .buffer_get_mapped_range::<A>(buffer, range.start, Some(range.end - range.start))
+            .unwrap();
+        let checksum = trace::checksum(unsafe { slice::from_raw_parts(ptr, size as usize) });
+        self.buffer_unmap::<A>(buffer).unwrap();
+        checksum
     }
 }
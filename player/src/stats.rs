@@ -0,0 +1,734 @@
+/*! Statistics of a trace, gathered without replaying it.
+ *
+ *  `TraceStats` summarizes the actions of a single trace,
+ *  and `TraceDiff` compares the summaries of two traces.
+!*/
+
+use wgc::{
+    binding_model::BindingResource,
+    command::{ComputeCommand, RenderCommand},
+    device::trace::{Action, Command},
+    id::TypedId,
+};
+
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap},
+    fmt::{self, Debug},
+    fs,
+    hash::{Hash, Hasher},
+    path::Path,
+};
+
+fn action_name(action: &Action) -> &'static str {
+    match *action {
+        Action::Init { .. } => "Init",
+        Action::ConfigureSurface(..) => "ConfigureSurface",
+        Action::CreateBuffer(..) => "CreateBuffer",
+        Action::FreeBuffer(..) => "FreeBuffer",
+        Action::DestroyBuffer(..) => "DestroyBuffer",
+        Action::CreateTexture(..) => "CreateTexture",
+        Action::FreeTexture(..) => "FreeTexture",
+        Action::DestroyTexture(..) => "DestroyTexture",
+        Action::CreateTextureView { .. } => "CreateTextureView",
+        Action::DestroyTextureView(..) => "DestroyTextureView",
+        Action::CreateSampler(..) => "CreateSampler",
+        Action::DestroySampler(..) => "DestroySampler",
+        Action::GetSurfaceTexture { .. } => "GetSurfaceTexture",
+        Action::Present(..) => "Present",
+        Action::DiscardSurfaceTexture(..) => "DiscardSurfaceTexture",
+        Action::CreateBindGroupLayout(..) => "CreateBindGroupLayout",
+        Action::DestroyBindGroupLayout(..) => "DestroyBindGroupLayout",
+        Action::CreatePipelineLayout(..) => "CreatePipelineLayout",
+        Action::DestroyPipelineLayout(..) => "DestroyPipelineLayout",
+        Action::CreateBindGroup(..) => "CreateBindGroup",
+        Action::DestroyBindGroup(..) => "DestroyBindGroup",
+        Action::CreateShaderModule { .. } => "CreateShaderModule",
+        Action::DestroyShaderModule(..) => "DestroyShaderModule",
+        Action::CreateComputePipeline { .. } => "CreateComputePipeline",
+        Action::DestroyComputePipeline(..) => "DestroyComputePipeline",
+        Action::CreateRenderPipeline { .. } => "CreateRenderPipeline",
+        Action::DestroyRenderPipeline(..) => "DestroyRenderPipeline",
+        Action::CreateRenderBundle { .. } => "CreateRenderBundle",
+        Action::DestroyRenderBundle(..) => "DestroyRenderBundle",
+        Action::CreateQuerySet { .. } => "CreateQuerySet",
+        Action::DestroyQuerySet(..) => "DestroyQuerySet",
+        Action::WriteBuffer { .. } => "WriteBuffer",
+        Action::WriteTexture { .. } => "WriteTexture",
+        Action::Submit(..) => "Submit",
+        Action::BufferChecksum { .. } => "BufferChecksum",
+        Action::FrameChecksum { .. } => "FrameChecksum",
+    }
+}
+
+/// Index and epoch of an ID, without the backend.
+type RawId = (u32, u32);
+
+fn raw_id<I: TypedId>(id: I) -> RawId {
+    let (index, epoch, _) = id.unzip();
+    (index, epoch)
+}
+
+/// Returns the resource kind, ID and label, if `action` creates a resource.
+fn created_resource<'a>(action: &'a Action) -> Option<(&'static str, RawId, Option<&'a str>)> {
+    Some(match *action {
+        Action::CreateBuffer(id, ref desc) => ("Buffer", raw_id(id), desc.label.as_deref()),
+        Action::CreateTexture(id, ref desc) => ("Texture", raw_id(id), desc.label.as_deref()),
+        Action::CreateTextureView { id, ref desc, .. } => {
+            ("TextureView", raw_id(id), desc.label.as_deref())
+        }
+        Action::CreateSampler(id, ref desc) => ("Sampler", raw_id(id), desc.label.as_deref()),
+        Action::CreateBindGroupLayout(id, ref desc) => {
+            ("BindGroupLayout", raw_id(id), desc.label.as_deref())
+        }
+        Action::CreatePipelineLayout(id, ref desc) => {
+            ("PipelineLayout", raw_id(id), desc.label.as_deref())
+        }
+        Action::CreateBindGroup(id, ref desc) => ("BindGroup", raw_id(id), desc.label.as_deref()),
+        Action::CreateShaderModule { id, ref desc, .. } => {
+            ("ShaderModule", raw_id(id), desc.label.as_deref())
+        }
+        Action::CreateComputePipeline { id, ref desc, .. } => {
+            ("ComputePipeline", raw_id(id), desc.label.as_deref())
+        }
+        Action::CreateRenderPipeline { id, ref desc, .. } => {
+            ("RenderPipeline", raw_id(id), desc.label.as_deref())
+        }
+        Action::CreateRenderBundle { id, ref desc, .. } => {
+            ("RenderBundle", raw_id(id), desc.label.as_deref())
+        }
+        Action::CreateQuerySet { id, ref desc } => ("QuerySet", raw_id(id), desc.label.as_deref()),
+        _ => return None,
+    })
+}
+
+/// Returns the resource kind, if `action` drops a resource.
+fn dropped_resource(action: &Action) -> Option<&'static str> {
+    Some(match *action {
+        Action::DestroyBuffer(_) => "Buffer",
+        Action::DestroyTexture(_) => "Texture",
+        Action::DestroyTextureView(_) => "TextureView",
+        Action::DestroySampler(_) => "Sampler",
+        Action::DestroyBindGroupLayout(_) => "BindGroupLayout",
+        Action::DestroyPipelineLayout(_) => "PipelineLayout",
+        Action::DestroyBindGroup(_) => "BindGroup",
+        Action::DestroyShaderModule(_) => "ShaderModule",
+        Action::DestroyComputePipeline(_) => "ComputePipeline",
+        Action::DestroyRenderPipeline(_) => "RenderPipeline",
+        Action::DestroyRenderBundle(_) => "RenderBundle",
+        Action::DestroyQuerySet(_) => "QuerySet",
+        _ => return None,
+    })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PassKind {
+    Render,
+    Compute,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PassStats {
+    pub kind: PassKind,
+    pub label: Option<String>,
+    pub commands: usize,
+    pub draws: usize,
+    pub dispatches: usize,
+    /// Hash of the recorded commands, to detect changes in their arguments.
+    pub contents_hash: u64,
+}
+
+fn hash_commands<'k, C: Clone + Debug>(
+    commands: &[C],
+    keys: &'k ResourceKeys,
+    unlink: impl Fn(&mut Unlinker<'k>, &mut C),
+) -> u64 {
+    let mut unlinker = Unlinker::new(keys);
+    let mut commands = commands.to_vec();
+    for command in commands.iter_mut() {
+        unlink(&mut unlinker, command);
+    }
+    let mut hasher = DefaultHasher::new();
+    unlinker.finish(format!("{:?}", commands)).hash(&mut hasher);
+    hasher.finish()
+}
+
+impl fmt::Display for PassStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:?} pass '{}': {} commands, {} draws, {} dispatches",
+            self.kind,
+            self.label.as_deref().unwrap_or(""),
+            self.commands,
+            self.draws,
+            self.dispatches
+        )
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SubmitStats {
+    /// Index of the `Submit` action in the trace.
+    pub action_index: usize,
+    pub passes: Vec<PassStats>,
+    pub copies: usize,
+    pub draws: usize,
+    pub dispatches: usize,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ResourceStats {
+    pub created: usize,
+    pub live: usize,
+    pub peak: usize,
+}
+
+/// Identifies a created resource across traces, since IDs differ between them.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ResourceKey {
+    pub kind: &'static str,
+    pub label: String,
+    /// Number of resources of the same kind and label created before it.
+    pub occurrence: usize,
+}
+
+impl fmt::Display for ResourceKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} '{}' #{}", self.kind, self.label, self.occurrence)
+    }
+}
+
+/// Keys of the resources created so far, by the type and raw value of their IDs.
+type ResourceKeys = HashMap<(&'static str, RawId), ResourceKey>;
+
+/// Replaces the IDs referenced by descriptors and commands with a placeholder,
+/// collecting the keys of the resources they refer to instead.
+struct Unlinker<'a> {
+    keys: &'a ResourceKeys,
+    references: Vec<String>,
+}
+
+impl<'a> Unlinker<'a> {
+    fn new(keys: &'a ResourceKeys) -> Self {
+        Self {
+            keys,
+            references: Vec::new(),
+        }
+    }
+
+    fn unlink<I: TypedId>(&mut self, kind: &'static str, id: &mut I) {
+        self.references
+            .push(match self.keys.get(&(kind, raw_id(*id))) {
+                Some(key) => key.to_string(),
+                None => format!("{} (unknown)", kind),
+            });
+        *id = I::zip(0, 1, wgt::Backend::Empty);
+    }
+
+    fn unlink_render(&mut self, command: &mut RenderCommand) {
+        match *command {
+            RenderCommand::SetBindGroup {
+                ref mut bind_group_id,
+                ..
+            } => self.unlink("BindGroup", bind_group_id),
+            RenderCommand::SetPipeline(ref mut id) => self.unlink("RenderPipeline", id),
+            RenderCommand::SetIndexBuffer {
+                ref mut buffer_id, ..
+            }
+            | RenderCommand::SetVertexBuffer {
+                ref mut buffer_id, ..
+            }
+            | RenderCommand::MultiDrawIndirect {
+                ref mut buffer_id, ..
+            } => self.unlink("Buffer", buffer_id),
+            RenderCommand::MultiDrawIndirectCount {
+                ref mut buffer_id,
+                ref mut count_buffer_id,
+                ..
+            } => {
+                self.unlink("Buffer", buffer_id);
+                self.unlink("Buffer", count_buffer_id);
+            }
+            RenderCommand::WriteTimestamp {
+                ref mut query_set_id,
+                ..
+            }
+            | RenderCommand::BeginPipelineStatisticsQuery {
+                ref mut query_set_id,
+                ..
+            } => self.unlink("QuerySet", query_set_id),
+            RenderCommand::ExecuteBundle(ref mut id) => self.unlink("RenderBundle", id),
+            _ => {}
+        }
+    }
+
+    fn unlink_compute(&mut self, command: &mut ComputeCommand) {
+        match *command {
+            ComputeCommand::SetBindGroup {
+                ref mut bind_group_id,
+                ..
+            } => self.unlink("BindGroup", bind_group_id),
+            ComputeCommand::SetPipeline(ref mut id) => self.unlink("ComputePipeline", id),
+            ComputeCommand::DispatchIndirect {
+                ref mut buffer_id, ..
+            } => self.unlink("Buffer", buffer_id),
+            ComputeCommand::WriteTimestamp {
+                ref mut query_set_id,
+                ..
+            }
+            | ComputeCommand::BeginPipelineStatisticsQuery {
+                ref mut query_set_id,
+                ..
+            } => self.unlink("QuerySet", query_set_id),
+            _ => {}
+        }
+    }
+
+    /// Appends the keys of the referenced resources to the `description`.
+    fn finish(self, description: String) -> String {
+        if self.references.is_empty() {
+            description
+        } else {
+            format!("{} -> [{}]", description, self.references.join(", "))
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TraceStats {
+    /// Number of actions of each kind.
+    pub actions: BTreeMap<&'static str, usize>,
+    /// Bytes uploaded with `WriteBuffer` actions.
+    pub buffer_bytes_written: u64,
+    /// Bytes uploaded with `WriteTexture` actions.
+    pub texture_bytes_written: u64,
+    pub submits: Vec<SubmitStats>,
+    /// Creation and liveness counts for each kind of resource.
+    pub resources: BTreeMap<&'static str, ResourceStats>,
+    /// Debug representation of every created resource descriptor.
+    pub descriptors: BTreeMap<ResourceKey, String>,
+}
+
+fn render_draws(
+    commands: &[RenderCommand],
+    bundles: &HashMap<wgc::id::RenderBundleId, usize>,
+) -> usize {
+    commands
+        .iter()
+        .map(|command| match *command {
+            RenderCommand::Draw { .. }
+            | RenderCommand::DrawIndexed { .. }
+            | RenderCommand::MultiDrawIndirectCount { .. } => 1,
+            RenderCommand::MultiDrawIndirect { count, .. } => count.map_or(1, |c| c.get() as usize),
+            RenderCommand::ExecuteBundle(id) => bundles.get(&id).cloned().unwrap_or(0),
+            _ => 0,
+        })
+        .sum()
+}
+
+impl TraceStats {
+    /// Gathers statistics of `actions`. The `dir` of the trace is used
+    /// to look up the sizes of texture uploads.
+    pub fn new(actions: &[Action], dir: &Path) -> Self {
+        let mut stats = Self::default();
+        let mut bundles = HashMap::new();
+        let mut labels = HashMap::<_, usize>::new();
+        let mut keys = ResourceKeys::new();
+        let mut next_key = |kind, label: &str| {
+            let occurrence = labels.entry((kind, label.to_string())).or_insert(0);
+            *occurrence += 1;
+            ResourceKey {
+                kind,
+                label: label.to_string(),
+                occurrence: *occurrence - 1,
+            }
+        };
+
+        for (index, action) in actions.iter().enumerate() {
+            *stats.actions.entry(action_name(action)).or_insert(0) += 1;
+
+            if let Some((kind, id, label)) = created_resource(action) {
+                let resource = stats.resources.entry(kind).or_default();
+                resource.created += 1;
+                resource.live += 1;
+                resource.peak = resource.peak.max(resource.live);
+
+                let key = next_key(kind, label.unwrap_or(""));
+                stats
+                    .descriptors
+                    .insert(key.clone(), describe(action, &keys));
+                keys.insert((kind, id), key);
+            }
+            if let Some(kind) = dropped_resource(action) {
+                if let Some(resource) = stats.resources.get_mut(kind) {
+                    resource.live = resource.live.saturating_sub(1);
+                }
+            }
+
+            let implicit = match *action {
+                Action::CreateComputePipeline {
+                    ref desc,
+                    implicit_context: Some(ref context),
+                    ..
+                } => Some((desc.label.as_deref(), context)),
+                Action::CreateRenderPipeline {
+                    ref desc,
+                    implicit_context: Some(ref context),
+                    ..
+                } => Some((desc.label.as_deref(), context)),
+                _ => None,
+            };
+            if let Some((label, context)) = implicit {
+                // implicit layouts are labeled after their pipeline
+                let label = label.unwrap_or("");
+                let key = next_key("PipelineLayout", label);
+                keys.insert(("PipelineLayout", raw_id(context.root_id)), key);
+                for &group_id in context.group_ids.iter() {
+                    let key = next_key("BindGroupLayout", label);
+                    keys.insert(("BindGroupLayout", raw_id(group_id)), key);
+                }
+            }
+
+            match *action {
+                Action::GetSurfaceTexture { id, .. } => {
+                    let key = next_key("SurfaceTexture", "");
+                    keys.insert(("Texture", raw_id(id)), key);
+                }
+                Action::WriteBuffer { ref range, .. } => {
+                    stats.buffer_bytes_written += range.end - range.start;
+                }
+                Action::WriteTexture { ref data, .. } => {
+                    stats.texture_bytes_written +=
+                        fs::metadata(dir.join(data)).map_or(0, |meta| meta.len());
+                }
+                Action::CreateRenderBundle { id, ref base, .. } => {
+                    bundles.insert(id, render_draws(&base.commands, &bundles));
+                }
+                Action::Submit(_, ref commands) => {
+                    let mut submit = SubmitStats {
+                        action_index: index,
+                        ..SubmitStats::default()
+                    };
+                    for command in commands {
+                        let pass = match *command {
+                            Command::CopyBufferToBuffer { .. }
+                            | Command::CopyBufferToTexture { .. }
+                            | Command::CopyTextureToBuffer { .. }
+                            | Command::CopyTextureToTexture { .. } => {
+                                submit.copies += 1;
+                                continue;
+                            }
+                            Command::RunComputePass { ref base } => PassStats {
+                                kind: PassKind::Compute,
+                                label: base.label.clone(),
+                                commands: base.commands.len(),
+                                draws: 0,
+                                dispatches: base
+                                    .commands
+                                    .iter()
+                                    .filter(|c| {
+                                        matches!(
+                                            **c,
+                                            ComputeCommand::Dispatch(_)
+                                                | ComputeCommand::DispatchIndirect { .. }
+                                        )
+                                    })
+                                    .count(),
+                                contents_hash: hash_commands(
+                                    &base.commands,
+                                    &keys,
+                                    Unlinker::unlink_compute,
+                                ),
+                            },
+                            Command::RunRenderPass { ref base, .. } => PassStats {
+                                kind: PassKind::Render,
+                                label: base.label.clone(),
+                                commands: base.commands.len(),
+                                draws: render_draws(&base.commands, &bundles),
+                                dispatches: 0,
+                                contents_hash: hash_commands(
+                                    &base.commands,
+                                    &keys,
+                                    Unlinker::unlink_render,
+                                ),
+                            },
+                            _ => continue,
+                        };
+                        submit.draws += pass.draws;
+                        submit.dispatches += pass.dispatches;
+                        submit.passes.push(pass);
+                    }
+                    stats.submits.push(submit);
+                }
+                _ => {}
+            }
+        }
+
+        stats
+    }
+
+    pub fn draws(&self) -> usize {
+        self.submits.iter().map(|submit| submit.draws).sum()
+    }
+
+    pub fn dispatches(&self) -> usize {
+        self.submits.iter().map(|submit| submit.dispatches).sum()
+    }
+}
+
+/// Debug representation of the descriptor of a created resource, with the IDs of
+/// the resources it refers to replaced by their keys, so that the same resource
+/// in different traces compares equal.
+fn describe(action: &Action, keys: &ResourceKeys) -> String {
+    let mut unlinker = Unlinker::new(keys);
+    let description = match *action {
+        Action::CreateBuffer(_, ref desc) => format!("{:?}", desc),
+        Action::CreateTexture(_, ref desc) => format!("{:?}", desc),
+        Action::CreateTextureView {
+            mut parent_id,
+            ref desc,
+            ..
+        } => {
+            unlinker.unlink("Texture", &mut parent_id);
+            format!("{:?}", desc)
+        }
+        Action::CreateSampler(_, ref desc) => format!("{:?}", desc),
+        Action::CreateBindGroupLayout(_, ref desc) => format!("{:?}", desc),
+        Action::CreatePipelineLayout(_, ref desc) => {
+            let mut desc = desc.clone();
+            for id in desc.bind_group_layouts.to_mut().iter_mut() {
+                unlinker.unlink("BindGroupLayout", id);
+            }
+            format!("{:?}", desc)
+        }
+        Action::CreateBindGroup(_, ref desc) => {
+            let mut desc = desc.clone();
+            unlinker.unlink("BindGroupLayout", &mut desc.layout);
+            for entry in desc.entries.to_mut().iter_mut() {
+                match entry.resource {
+                    BindingResource::Buffer(ref mut binding) => {
+                        unlinker.unlink("Buffer", &mut binding.buffer_id)
+                    }
+                    BindingResource::BufferArray(ref mut bindings) => {
+                        for binding in bindings.to_mut().iter_mut() {
+                            unlinker.unlink("Buffer", &mut binding.buffer_id);
+                        }
+                    }
+                    BindingResource::Sampler(ref mut id) => unlinker.unlink("Sampler", id),
+                    BindingResource::SamplerArray(ref mut ids) => {
+                        for id in ids.to_mut().iter_mut() {
+                            unlinker.unlink("Sampler", id);
+                        }
+                    }
+                    BindingResource::TextureView(ref mut id) => unlinker.unlink("TextureView", id),
+                    BindingResource::TextureViewArray(ref mut ids) => {
+                        for id in ids.to_mut().iter_mut() {
+                            unlinker.unlink("TextureView", id);
+                        }
+                    }
+                }
+            }
+            format!("{:?}", desc)
+        }
+        Action::CreateShaderModule { ref desc, .. } => format!("{:?}", desc),
+        Action::CreateComputePipeline { ref desc, .. } => {
+            let mut desc = desc.clone();
+            if let Some(ref mut id) = desc.layout {
+                unlinker.unlink("PipelineLayout", id);
+            }
+            unlinker.unlink("ShaderModule", &mut desc.stage.module);
+            format!("{:?}", desc)
+        }
+        Action::CreateRenderPipeline { ref desc, .. } => {
+            let mut desc = desc.clone();
+            if let Some(ref mut id) = desc.layout {
+                unlinker.unlink("PipelineLayout", id);
+            }
+            unlinker.unlink("ShaderModule", &mut desc.vertex.stage.module);
+            if let Some(ref mut fragment) = desc.fragment {
+                unlinker.unlink("ShaderModule", &mut fragment.stage.module);
+            }
+            format!("{:?}", desc)
+        }
+        Action::CreateRenderBundle { ref desc, .. } => format!("{:?}", desc),
+        Action::CreateQuerySet { ref desc, .. } => format!("{:?}", desc),
+        _ => unreachable!("{} doesn't create a resource", action_name(action)),
+    };
+    unlinker.finish(description)
+}
+
+impl fmt::Display for TraceStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Actions:")?;
+        for (name, count) in self.actions.iter() {
+            writeln!(f, "\t{}: {}", name, count)?;
+        }
+        writeln!(f, "Uploads:")?;
+        writeln!(f, "\tWriteBuffer: {} bytes", self.buffer_bytes_written)?;
+        writeln!(f, "\tWriteTexture: {} bytes", self.texture_bytes_written)?;
+        writeln!(f, "Resources (created / peak live):")?;
+        for (kind, resource) in self.resources.iter() {
+            writeln!(f, "\t{}: {} / {}", kind, resource.created, resource.peak)?;
+        }
+        writeln!(
+            f,
+            "Submits: {} with {} draws and {} dispatches",
+            self.submits.len(),
+            self.draws(),
+            self.dispatches()
+        )?;
+        for submit in self.submits.iter() {
+            writeln!(
+                f,
+                "\tAction {}: {} passes, {} copies, {} draws, {} dispatches",
+                submit.action_index,
+                submit.passes.len(),
+                submit.copies,
+                submit.draws,
+                submit.dispatches
+            )?;
+            for pass in submit.passes.iter() {
+                writeln!(f, "\t\t{}", pass)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change<T> {
+    Added(T),
+    Removed(T),
+    Changed { old: T, new: T },
+}
+
+/// Differences between two traces.
+#[derive(Clone, Debug, Default)]
+pub struct TraceDiff {
+    /// Action kinds whose counts differ.
+    pub actions: Vec<(&'static str, Change<usize>)>,
+    /// Resources that are only present in one of the traces,
+    /// or are created with a different descriptor.
+    pub resources: Vec<(ResourceKey, Change<String>)>,
+    /// Passes that differ, keyed by the submission and pass indices.
+    pub passes: Vec<((usize, usize), Change<PassStats>)>,
+}
+
+fn diff_maps<K: Clone + Ord, V: Clone + PartialEq>(
+    old: &BTreeMap<K, V>,
+    new: &BTreeMap<K, V>,
+) -> Vec<(K, Change<V>)> {
+    let mut changes = Vec::new();
+    for (key, old_value) in old.iter() {
+        match new.get(key) {
+            Some(new_value) if new_value == old_value => {}
+            Some(new_value) => changes.push((
+                key.clone(),
+                Change::Changed {
+                    old: old_value.clone(),
+                    new: new_value.clone(),
+                },
+            )),
+            None => changes.push((key.clone(), Change::Removed(old_value.clone()))),
+        }
+    }
+    for (key, new_value) in new.iter() {
+        if !old.contains_key(key) {
+            changes.push((key.clone(), Change::Added(new_value.clone())));
+        }
+    }
+    changes.sort_by(|a, b| a.0.cmp(&b.0));
+    changes
+}
+
+impl TraceDiff {
+    pub fn new(old: &TraceStats, new: &TraceStats) -> Self {
+        let mut passes = Vec::new();
+        let submit_count = old.submits.len().max(new.submits.len());
+        for submit in 0..submit_count {
+            let old_passes = old.submits.get(submit).map_or(&[][..], |s| &s.passes);
+            let new_passes = new.submits.get(submit).map_or(&[][..], |s| &s.passes);
+            for pass in 0..old_passes.len().max(new_passes.len()) {
+                let change = match (old_passes.get(pass), new_passes.get(pass)) {
+                    (Some(old), Some(new)) if old == new => continue,
+                    (Some(old), Some(new)) => Change::Changed {
+                        old: old.clone(),
+                        new: new.clone(),
+                    },
+                    (Some(old), None) => Change::Removed(old.clone()),
+                    (None, Some(new)) => Change::Added(new.clone()),
+                    (None, None) => unreachable!(),
+                };
+                passes.push(((submit, pass), change));
+            }
+        }
+
+        Self {
+            actions: diff_maps(&old.actions, &new.actions),
+            resources: diff_maps(&old.descriptors, &new.descriptors),
+            passes,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty() && self.resources.is_empty() && self.passes.is_empty()
+    }
+}
+
+impl fmt::Display for TraceDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "Traces are equivalent");
+        }
+        if !self.actions.is_empty() {
+            writeln!(f, "Action counts:")?;
+            for &(name, ref change) in self.actions.iter() {
+                match *change {
+                    Change::Added(count) => writeln!(f, "\t+ {}: {}", name, count)?,
+                    Change::Removed(count) => writeln!(f, "\t- {}: {}", name, count)?,
+                    Change::Changed { old, new } => {
+                        writeln!(f, "\t~ {}: {} -> {}", name, old, new)?
+                    }
+                }
+            }
+        }
+        if !self.resources.is_empty() {
+            writeln!(f, "Resources:")?;
+            for (name, change) in self.resources.iter() {
+                match *change {
+                    Change::Added(ref desc) => writeln!(f, "\t+ {}: {}", name, desc)?,
+                    Change::Removed(ref desc) => writeln!(f, "\t- {}: {}", name, desc)?,
+                    Change::Changed { ref old, ref new } => {
+                        writeln!(f, "\t~ {}:\n\t\t{}\n\t\t{}", name, old, new)?
+                    }
+                }
+            }
+        }
+        if !self.passes.is_empty() {
+            writeln!(f, "Passes:")?;
+            for &((submit, pass), ref change) in self.passes.iter() {
+                match *change {
+                    Change::Added(ref new) => {
+                        writeln!(f, "\t+ submit {} pass {}: {}", submit, pass, new)?
+                    }
+                    Change::Removed(ref old) => {
+                        writeln!(f, "\t- submit {} pass {}: {}", submit, pass, old)?
+                    }
+                    Change::Changed { ref old, ref new } if old.to_string() == new.to_string() => {
+                        writeln!(
+                            f,
+                            "\t~ submit {} pass {}: command arguments changed\n\t\t{}",
+                            submit, pass, new
+                        )?
+                    }
+                    Change::Changed { ref old, ref new } => writeln!(
+                        f,
+                        "\t~ submit {} pass {}:\n\t\t{}\n\t\t{}",
+                        submit, pass, old, new
+                    )?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -5,18 +5,24 @@
  *  Test requirements:
  *    - all IDs have the backend `Empty`
  *    - all expected buffers have `MAP_READ` usage
- *    - last action is `Submit`
- *    - no swapchain use
+ *    - all expected textures have `COPY_SRC` usage and an RGBA8/BGRA8 format
+ *    - last action is `Submit` or `Present`
+ *    - surface textures are substituted by offscreen ones, see `headless`
 !*/
 
-use player::{GlobalPlay, IdentityPassThroughFactory};
+use player::{headless, GlobalPlay, IdentityPassThroughFactory};
 use std::{
+    borrow::Cow,
+    collections::HashMap,
     fs::{read_to_string, File},
     io::{Read, Seek, SeekFrom},
     path::{Path, PathBuf},
     slice,
 };
 
+#[path = "../../wgpu/tests/common/image.rs"]
+mod image;
+
 #[derive(serde::Deserialize)]
 struct RawId {
     index: u32,
@@ -48,10 +54,52 @@ struct Expectation {
     data: ExpectedData,
 }
 
+#[derive(serde::Deserialize)]
+struct TextureExpectation {
+    name: String,
+    texture: RawId,
+    #[serde(default)]
+    mip_level: u32,
+    #[serde(default)]
+    array_layer: u32,
+    /// Reference PNG, relative to the test file. Written out if missing.
+    reference: String,
+    #[serde(default)]
+    tolerance: u8,
+    #[serde(default)]
+    max_outliers: usize,
+}
+
+#[derive(serde::Deserialize)]
+struct FrameExpectation {
+    name: String,
+    /// Index of the presented frame.
+    frame: usize,
+    /// Reference PNG, relative to the test file. Written out if missing.
+    reference: String,
+    #[serde(default)]
+    tolerance: u8,
+    #[serde(default)]
+    max_outliers: usize,
+}
+
+/// Staging buffer that a texture expectation is read back through.
+struct TextureReadback {
+    buffer: wgc::id::BufferId,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+    swap_red_blue: bool,
+}
+
 #[derive(serde::Deserialize)]
 struct Test<'a> {
     features: wgt::Features,
     expectations: Vec<Expectation>,
+    #[serde(default)]
+    texture_expectations: Vec<TextureExpectation>,
+    #[serde(default)]
+    frame_expectations: Vec<FrameExpectation>,
     actions: Vec<wgc::device::trace::Action<'a>>,
 }
 
@@ -64,15 +112,7 @@ fn map_callback(status: wgc::resource::BufferMapAsyncStatus) {
 
 impl Test<'_> {
     fn load(path: PathBuf, backend: wgt::Backend) -> Self {
-        let backend_name = match backend {
-            wgt::Backend::Vulkan => "Vulkan",
-            wgt::Backend::Metal => "Metal",
-            wgt::Backend::Dx12 => "Dx12",
-            wgt::Backend::Dx11 => "Dx11",
-            wgt::Backend::Gl => "Gl",
-            _ => unreachable!(),
-        };
-        let string = read_to_string(path).unwrap().replace("Empty", backend_name);
+        let string = player::compat::retarget(&read_to_string(path).unwrap(), backend);
         ron::de::from_str(&string).unwrap()
     }
 
@@ -99,10 +139,123 @@ impl Test<'_> {
             panic!("{:?}", e);
         }
 
+        let mut textures = HashMap::new();
+        let mut next_buffer_index = 0;
+        for action in &self.actions {
+            match *action {
+                wgc::device::trace::Action::CreateBuffer(id, _) => {
+                    next_buffer_index = next_buffer_index.max(wgc::id::TypedId::unzip(id).0 + 1);
+                }
+                wgc::device::trace::Action::CreateTexture(id, ref desc) => {
+                    let (index, epoch, _) = wgc::id::TypedId::unzip(id);
+                    textures.insert((index, epoch), (desc.size, desc.dimension, desc.format));
+                }
+                _ => {}
+            }
+        }
+
+        let frame_output = if self.frame_expectations.is_empty() {
+            headless::FrameOutput::None
+        } else {
+            headless::FrameOutput::Memory
+        };
+        let mut surfaces = headless::HeadlessSurfaces::new(&self.actions, frame_output);
+        // frame read-backs take buffer indices right after the trace ones
+        next_buffer_index += self
+            .actions
+            .iter()
+            .filter(|action| {
+                matches!(
+                    **action,
+                    wgc::device::trace::Action::Present(_)
+                        | wgc::device::trace::Action::FrameChecksum { .. }
+                )
+            })
+            .count() as u32;
+
         let mut command_buffer_id_manager = wgc::hub::IdentityManager::default();
         println!("\t\t\tRunning...");
         for action in self.actions {
-            wgc::gfx_select!(device => global.process(device, action, dir, &mut command_buffer_id_manager));
+            if headless::HeadlessSurfaces::handles(&action) {
+                wgc::gfx_select!(device => surfaces.process(global, device, action, &mut command_buffer_id_manager));
+            } else {
+                wgc::gfx_select!(device => global.process(device, action, dir, &mut command_buffer_id_manager));
+            }
+        }
+
+        let mut readbacks = Vec::with_capacity(self.texture_expectations.len());
+        if !self.texture_expectations.is_empty() {
+            println!("\t\t\tCopying textures...");
+            let mut commands = Vec::new();
+            for expect in &self.texture_expectations {
+                let key = (expect.texture.index, expect.texture.epoch);
+                let (size, dimension, format) = match textures.get(&key) {
+                    Some(&entry) => entry,
+                    None => panic!(
+                        "Texture {:?} of '{}' is not created by the test",
+                        key, expect.name
+                    ),
+                };
+                let swap_red_blue = match format {
+                    wgt::TextureFormat::Rgba8Unorm | wgt::TextureFormat::Rgba8UnormSrgb => false,
+                    wgt::TextureFormat::Bgra8Unorm | wgt::TextureFormat::Bgra8UnormSrgb => true,
+                    other => panic!("Unsupported format {:?} of '{}'", other, expect.name),
+                };
+                let mip_size =
+                    size.mip_level_size(expect.mip_level, dimension == wgt::TextureDimension::D3);
+                let align = wgt::COPY_BYTES_PER_ROW_ALIGNMENT;
+                let unpadded_bytes_per_row = mip_size.width * 4;
+                let padding = (align - unpadded_bytes_per_row % align) % align;
+                let padded_bytes_per_row = unpadded_bytes_per_row + padding;
+                let buffer = wgc::id::TypedId::zip(next_buffer_index, 1, backend);
+                next_buffer_index += 1;
+
+                let create = wgc::device::trace::Action::CreateBuffer(
+                    buffer,
+                    wgc::resource::BufferDescriptor {
+                        label: Some(Cow::Borrowed("Texture Expectation")),
+                        size: (padded_bytes_per_row * mip_size.height) as wgt::BufferAddress,
+                        usage: wgt::BufferUsages::MAP_READ | wgt::BufferUsages::COPY_DST,
+                        mapped_at_creation: false,
+                    },
+                );
+                wgc::gfx_select!(device => global.process(device, create, dir, &mut command_buffer_id_manager));
+
+                commands.push(wgc::device::trace::Command::CopyTextureToBuffer {
+                    src: wgt::ImageCopyTexture {
+                        texture: wgc::id::TypedId::zip(key.0, key.1, backend),
+                        mip_level: expect.mip_level,
+                        origin: wgt::Origin3d {
+                            x: 0,
+                            y: 0,
+                            z: expect.array_layer,
+                        },
+                        aspect: wgt::TextureAspect::All,
+                    },
+                    dst: wgt::ImageCopyBuffer {
+                        buffer,
+                        layout: wgt::ImageDataLayout {
+                            offset: 0,
+                            bytes_per_row: std::num::NonZeroU32::new(padded_bytes_per_row),
+                            rows_per_image: None,
+                        },
+                    },
+                    size: wgt::Extent3d {
+                        width: mip_size.width,
+                        height: mip_size.height,
+                        depth_or_array_layers: 1,
+                    },
+                });
+                readbacks.push(TextureReadback {
+                    buffer,
+                    width: mip_size.width,
+                    height: mip_size.height,
+                    padded_bytes_per_row,
+                    swap_red_blue,
+                });
+            }
+            let submit = wgc::device::trace::Action::Submit(0, commands);
+            wgc::gfx_select!(device => global.process(device, submit, dir, &mut command_buffer_id_manager));
         }
         println!("\t\t\tMapping...");
         for expect in &self.expectations {
@@ -119,6 +272,19 @@ impl Test<'_> {
             ))
             .unwrap();
         }
+        for readback in &readbacks {
+            wgc::gfx_select!(device => global.buffer_map_async(
+                readback.buffer,
+                0 .. (readback.padded_bytes_per_row * readback.height) as wgt::BufferAddress,
+                wgc::resource::BufferMapOperation {
+                    host: wgc::device::HostMap::Read,
+                    callback: wgc::resource::BufferMapCallback::from_rust(
+                        Box::new(map_callback)
+                    ),
+                }
+            ))
+            .unwrap();
+        }
 
         println!("\t\t\tWaiting...");
         wgc::gfx_select!(device => global.device_poll(device, wgt::Maintain::Wait)).unwrap();
@@ -155,6 +321,53 @@ impl Test<'_> {
             }
         }
 
+        for (expect, readback) in self.texture_expectations.iter().zip(readbacks) {
+            println!("\t\t\tChecking {}", expect.name);
+            let (ptr, size) =
+                wgc::gfx_select!(device => global.buffer_get_mapped_range(readback.buffer, 0, None))
+                    .unwrap();
+            let contents = unsafe { slice::from_raw_parts(ptr, size as usize) };
+            let row_size = readback.width as usize * 4;
+            let mut pixels = Vec::with_capacity(row_size * readback.height as usize);
+            for row in contents.chunks_exact(readback.padded_bytes_per_row as usize) {
+                pixels.extend_from_slice(&row[..row_size]);
+            }
+            if readback.swap_red_blue {
+                for texel in pixels.chunks_exact_mut(4) {
+                    texel.swap(0, 2);
+                }
+            }
+            image::compare_image_output(
+                dir.join(&expect.reference),
+                readback.width,
+                readback.height,
+                &pixels,
+                expect.tolerance,
+                expect.max_outliers,
+            );
+        }
+
+        for expect in &self.frame_expectations {
+            println!("\t\t\tChecking {}", expect.name);
+            let frame = match surfaces.frames().get(expect.frame) {
+                Some(frame) => frame,
+                None => panic!(
+                    "Frame {} of '{}' is not presented, only {} are",
+                    expect.frame,
+                    expect.name,
+                    surfaces.frame_count()
+                ),
+            };
+            image::compare_image_output(
+                dir.join(&expect.reference),
+                frame.width,
+                frame.height,
+                &frame.pixels,
+                expect.tolerance,
+                expect.max_outliers,
+            );
+        }
+
         wgc::gfx_select!(device => global.clear_backend(()));
     }
 }
@@ -226,3 +439,270 @@ fn test_api() {
 
     Corpus::run_from(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/data/all.ron"))
 }
+
+#[test]
+fn test_retarget() {
+    assert_eq!(
+        player::compat::retarget(
+            r#"[A(Id(0, 1, Empty)), B("Id(2, 1, Empty)")]"#,
+            wgt::Backend::Gl
+        ),
+        r#"[A(Id(0, 1, Gl)), B("Id(2, 1, Empty)")]"#,
+    );
+
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/data");
+    let backends = wgt::Backends::PRIMARY | wgt::Backends::SECONDARY;
+    let global = wgc::hub::Global::new("test", IdentityPassThroughFactory, backends);
+    let adapter = BACKENDS.iter().find_map(|&backend| {
+        global
+            .request_adapter(
+                &wgc::instance::RequestAdapterOptions {
+                    power_preference: wgt::PowerPreference::LowPower,
+                    force_fallback_adapter: false,
+                    compatible_surface: None,
+                },
+                wgc::instance::AdapterInputs::IdSet(
+                    &[wgc::id::TypedId::zip(0, 0, backend)],
+                    |id| id.backend(),
+                ),
+            )
+            .ok()
+    });
+    let adapter = match adapter {
+        Some(adapter) => adapter,
+        None => return,
+    };
+
+    // pretend the trace is recorded on another backend, then replay it on this one
+    let target = adapter.backend();
+    let recorded = *BACKENDS.iter().find(|&&backend| backend != target).unwrap();
+    let string = read_to_string(dir.join("zero-init-buffer.ron")).unwrap();
+    let recorded_string = player::compat::retarget(&string, recorded);
+    assert!(!recorded_string.contains("Empty"));
+    let test: Test =
+        ron::de::from_str(&player::compat::retarget(&recorded_string, target)).unwrap();
+    test.run(&dir, &global, adapter, 0);
+}
+
+#[test]
+fn test_stats() {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/data");
+    let load = |name: &str| -> Test<'static> {
+        ron::de::from_str(&read_to_string(dir.join(name)).unwrap()).unwrap()
+    };
+
+    let quad = load("quad.ron");
+    let quad_stats = player::stats::TraceStats::new(&quad.actions, &dir);
+    assert_eq!(quad_stats.actions["CreateTexture"], 1);
+    assert_eq!(quad_stats.resources["RenderPipeline"].peak, 1);
+    assert_eq!(quad_stats.submits.len(), 1);
+    assert_eq!(quad_stats.submits[0].copies, 1);
+    assert_eq!(quad_stats.draws(), 1);
+    assert_eq!(quad_stats.dispatches(), 0);
+    assert!(player::stats::TraceDiff::new(&quad_stats, &quad_stats).is_empty());
+
+    // the same trace, with every ID allocated differently
+    for name in ["quad.ron", "zero-init-buffer.ron"] {
+        let string = read_to_string(dir.join(name)).unwrap();
+        let mut parts = string.split("Id(");
+        let mut shifted = parts.next().unwrap().to_string();
+        for part in parts {
+            let (index, rest) = part.split_once(',').unwrap();
+            let index = index.parse::<u32>().unwrap() + 7;
+            shifted.push_str(&format!("Id({},{}", index, rest));
+        }
+        let original: Test = ron::de::from_str(&string).unwrap();
+        let shifted: Test = ron::de::from_str(&shifted).unwrap();
+        let diff = player::stats::TraceDiff::new(
+            &player::stats::TraceStats::new(&original.actions, &dir),
+            &player::stats::TraceStats::new(&shifted.actions, &dir),
+        );
+        assert!(diff.is_empty(), "{}", diff);
+    }
+
+    let clear = load("zero-init-texture-rendertarget.ron");
+    let clear_stats = player::stats::TraceStats::new(&clear.actions, &dir);
+    let diff = player::stats::TraceDiff::new(&quad_stats, &clear_stats);
+    assert_eq!(diff.passes.len(), 1);
+    assert!(diff
+        .resources
+        .iter()
+        .any(|(key, _)| key.kind == "RenderPipeline"));
+}
+
+#[test]
+fn test_compat() {
+    use player::compat::{self, Incompatibility, Issue};
+    use wgc::{
+        command::{BasePass, ComputeCommand},
+        device::trace::{Action, Command},
+        id::TypedId as _,
+    };
+
+    let texture_desc = |format, usage| wgc::resource::TextureDescriptor {
+        label: None,
+        size: wgt::Extent3d {
+            width: 4,
+            height: 4,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgt::TextureDimension::D2,
+        format,
+        usage,
+    };
+    let target_usage = wgt::TextureUsages::RENDER_ATTACHMENT | wgt::TextureUsages::COPY_SRC;
+    let query_set = wgc::id::QuerySetId::zip(0, 1, wgt::Backend::Empty);
+    let mut actions = vec![
+        Action::Init {
+            desc: wgt::DeviceDescriptor {
+                label: None,
+                features: wgt::Features::TIMESTAMP_QUERY
+                    | wgt::Features::WRITE_TIMESTAMP_INSIDE_PASSES,
+                limits: wgt::Limits::default(),
+            },
+            backend: wgt::Backend::Empty,
+        },
+        Action::CreateTexture(
+            wgc::id::TextureId::zip(0, 1, wgt::Backend::Empty),
+            texture_desc(wgt::TextureFormat::R16Float, target_usage),
+        ),
+        Action::CreateTexture(
+            wgc::id::TextureId::zip(1, 1, wgt::Backend::Empty),
+            texture_desc(wgt::TextureFormat::Rgba16Float, target_usage),
+        ),
+        Action::CreateQuerySet {
+            id: query_set,
+            desc: wgt::QuerySetDescriptor {
+                label: None,
+                ty: wgt::QueryType::Timestamp,
+                count: 2,
+            },
+        },
+        Action::Submit(
+            1,
+            vec![
+                Command::WriteTimestamp {
+                    query_set_id: query_set,
+                    query_index: 0,
+                },
+                Command::RunComputePass {
+                    base: BasePass {
+                        label: None,
+                        commands: vec![ComputeCommand::WriteTimestamp {
+                            query_set_id: query_set,
+                            query_index: 1,
+                        }],
+                        dynamic_offsets: Vec::new(),
+                        string_data: Vec::new(),
+                        push_constant_data: Vec::new(),
+                    },
+                },
+                Command::ResolveQuerySet {
+                    query_set_id: query_set,
+                    start_query: 0,
+                    query_count: 2,
+                    destination: wgc::id::BufferId::zip(0, 1, wgt::Backend::Empty),
+                    destination_offset: 0,
+                },
+            ],
+        ),
+        Action::DestroyQuerySet(query_set),
+    ];
+
+    // an adapter rendering only to 8-bit normalized formats, without timestamps
+    let renderable = wgt::TextureFormatFeatures {
+        allowed_usages: wgt::TextureUsages::all(),
+        flags: wgt::TextureFormatFeatureFlags::empty(),
+    };
+    let sampled = wgt::TextureFormatFeatures {
+        allowed_usages: wgt::TextureUsages::all() - wgt::TextureUsages::RENDER_ATTACHMENT,
+        flags: wgt::TextureFormatFeatureFlags::empty(),
+    };
+    let caps = compat::Capabilities {
+        features: wgt::Features::empty(),
+        limits: wgt::Limits::downlevel_webgl2_defaults(),
+        formats: [
+            (wgt::TextureFormat::R8Unorm, renderable),
+            (wgt::TextureFormat::Rg8Unorm, renderable),
+            (wgt::TextureFormat::Rgba8Unorm, renderable),
+            (wgt::TextureFormat::R16Float, sampled),
+            (wgt::TextureFormat::Rgba16Float, sampled),
+        ]
+        .into_iter()
+        .collect(),
+    };
+
+    let issues = compat::check(&actions, &caps);
+    let issues_of = |index| {
+        issues
+            .iter()
+            .filter(move |issue| issue.action_index == index)
+            .map(|issue| &issue.issue)
+    };
+    assert!(issues_of(0).any(|issue| *issue
+        == Issue::MissingFeatures(
+            wgt::Features::TIMESTAMP_QUERY | wgt::Features::WRITE_TIMESTAMP_INSIDE_PASSES
+        )));
+    assert!(issues_of(0).any(|issue| matches!(
+        *issue,
+        Issue::Limit {
+            name: "max_texture_dimension_2d",
+            requested: 8192,
+            supported: 2048,
+        }
+    )));
+    assert!(issues_of(1).any(|issue| matches!(*issue, Issue::TextureFormat { .. })));
+    assert!(issues_of(2).any(|issue| matches!(*issue, Issue::TextureFormat { .. })));
+    assert_eq!(issues_of(3).collect::<Vec<_>>(), [&Issue::TimestampQuery]);
+    assert_eq!(issues_of(4).collect::<Vec<_>>(), [&Issue::TimestampQuery]);
+
+    let report = compat::remap(
+        &mut actions,
+        &caps,
+        compat::RemapOptions {
+            lower_limits: true,
+            substitute_formats: true,
+            drop_timestamps: true,
+        },
+    );
+    assert!(report.lowered_limits.contains(&"max_texture_dimension_2d"));
+    // only a format of the same texel size can stand in, so that copies stay valid
+    assert_eq!(
+        report.substituted_formats,
+        [(wgt::TextureFormat::R16Float, wgt::TextureFormat::Rg8Unorm)]
+            .into_iter()
+            .collect()
+    );
+    assert_eq!(report.dropped_actions, 2);
+    assert_eq!(report.dropped_commands, 3);
+
+    match actions[0] {
+        Action::Init { ref desc, .. } => {
+            assert_eq!(desc.features, wgt::Features::empty());
+            assert_eq!(desc.limits.max_texture_dimension_2d, 2048);
+        }
+        ref other => panic!("Unexpected action {:?}", other),
+    }
+    match actions[3] {
+        Action::Submit(_, ref commands) => assert!(
+            matches!(commands[..], [Command::RunComputePass { ref base }] if base.commands.is_empty())
+        ),
+        ref other => panic!("Unexpected action {:?}", other),
+    }
+    assert_eq!(actions.len(), 4);
+
+    // the texture without a substitute is all that's left
+    assert_eq!(
+        compat::check(&actions, &caps),
+        [Incompatibility {
+            action_index: 2,
+            issue: Issue::TextureFormat {
+                format: wgt::TextureFormat::Rgba16Float,
+                usage: target_usage,
+                sample_count: 1,
+            },
+        }]
+    );
+}
@@ -840,6 +840,7 @@ impl<A: HalApi> LifetimeTracker<A> {
         hub: &Hub<A, G>,
         raw: &A::Device,
         trackers: &Mutex<Tracker<A>>,
+        #[cfg(feature = "trace")] trace: Option<&Mutex<trace::Trace>>,
         token: &mut Token<super::Device<A>>,
     ) -> Vec<super::BufferMapPendingClosure> {
         if self.ready_to_map.is_empty() {
@@ -883,6 +884,23 @@ impl<A: HalApi> LifetimeTracker<A> {
                     let size = mapping.range.end - mapping.range.start;
                     match super::map_buffer(raw, buffer, mapping.range.start, size, host) {
                         Ok(ptr) => {
+                            #[cfg(feature = "trace")]
+                            if let Some(t) = trace {
+                                let mut t = t.lock();
+                                if host == super::HostMap::Read && t.records_checksums() {
+                                    let data = unsafe {
+                                        std::slice::from_raw_parts(
+                                            ptr.as_ptr().offset(mapping.range.start as isize),
+                                            size as usize,
+                                        )
+                                    };
+                                    t.add(trace::Action::BufferChecksum {
+                                        id: buffer_id.0,
+                                        range: mapping.range.clone(),
+                                        checksum: trace::checksum(data),
+                                    });
+                                }
+                            }
                             buffer.map_state = resource::BufferMapState::Active {
                                 ptr,
                                 range: mapping.range.start..mapping.range.start + size,
@@ -493,7 +493,14 @@ impl<A: HalApi> Device<A> {
 
         let submission_closures =
             life_tracker.triage_submissions(last_done_index, &self.command_allocator);
-        let mapping_closures = life_tracker.handle_mapping(hub, &self.raw, &self.trackers, token);
+        let mapping_closures = life_tracker.handle_mapping(
+            hub,
+            &self.raw,
+            &self.trackers,
+            #[cfg(feature = "trace")]
+            self.trace.as_ref(),
+            token,
+        );
         life_tracker.cleanup(&self.raw);
 
         let closures = UserClosures {
@@ -1192,6 +1199,7 @@ impl<A: HalApi> Device<A> {
         desc: &pipeline::ShaderModuleDescriptor<'a>,
         source: pipeline::ShaderModuleSource<'a>,
     ) -> Result<pipeline::ShaderModule<A>, pipeline::CreateShaderModuleError> {
+        let mut precompiled = Vec::new();
         let (module, source) = match source {
             pipeline::ShaderModuleSource::Wgsl(code) => {
                 profiling::scope!("naga::wgsl::parse_str");
@@ -1205,6 +1213,12 @@ impl<A: HalApi> Device<A> {
                 (module, code.into_owned())
             }
             pipeline::ShaderModuleSource::Naga(module) => (module, String::new()),
+            #[cfg(feature = "shader-bundle")]
+            pipeline::ShaderModuleSource::Bundle(bytes) => {
+                let bundle = pipeline::ShaderBundle::from_bytes(&bytes)?;
+                precompiled = bundle.precompiled;
+                (bundle.module, String::new())
+            }
         };
         for (_, var) in module.global_variables.iter() {
             match var.binding {
@@ -1266,7 +1280,11 @@ impl<A: HalApi> Device<A> {
             })?;
         let interface =
             validation::Interface::new(&module, &info, self.features, self.limits.clone());
-        let hal_shader = hal::ShaderInput::Naga(hal::NagaShader { module, info });
+        let hal_shader = hal::ShaderInput::Naga(hal::NagaShader {
+            module,
+            info,
+            precompiled,
+        });
 
         let hal_desc = hal::ShaderModuleDescriptor {
             label: desc.label.borrow_option(),
@@ -4349,6 +4367,10 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                                 .unwrap();
                         trace.make_binary("ron", string.as_bytes())
                     }
+                    #[cfg(feature = "shader-bundle")]
+                    pipeline::ShaderModuleSource::Bundle(ref bytes) => {
+                        trace.make_binary("bundle", bytes)
+                    }
                 };
                 trace.add(trace::Action::CreateShaderModule {
                     id: fid.id(),
@@ -4425,6 +4447,24 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         A::hub(self).shader_modules.label_for_resource(id)
     }
 
+    /// Describe the entry points of a shader module and the resources they use.
+    ///
+    /// Returns `None` if the module is invalid, or was created without validation.
+    pub fn shader_module_reflect<A: HalApi>(
+        &self,
+        shader_module_id: id::ShaderModuleId,
+    ) -> Option<wgt::ShaderReflection> {
+        let hub = A::hub(self);
+        let mut token = Token::root();
+        let (_, mut token) = hub.devices.read(&mut token);
+        let (shader_module_guard, _) = hub.shader_modules.read(&mut token);
+        let module = shader_module_guard.get(shader_module_id).ok()?;
+        module
+            .interface
+            .as_ref()
+            .map(validation::Interface::reflect)
+    }
+
     pub fn shader_module_drop<A: HalApi>(&self, shader_module_id: id::ShaderModuleId) {
         profiling::scope!("ShaderModule::drop");
         log::debug!("shader module {:?} is dropped", shader_module_id);
@@ -5118,6 +5158,21 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 break error;
             }
 
+            // frames are copied out to record their checksums, if the surface allows it
+            #[cfg(feature = "trace")]
+            let record_checksums = match device.trace {
+                Some(ref trace) if trace.lock().records_checksums() => {
+                    if caps.usage.contains(hal::TextureUses::COPY_SRC) {
+                        hal_config.usage |= hal::TextureUses::COPY_SRC;
+                        true
+                    } else {
+                        log::warn!("Surface frames can't be read back for their checksums");
+                        false
+                    }
+                }
+                _ => false,
+            };
+
             match unsafe {
                 A::get_surface_mut(surface)
                     .raw
@@ -5150,6 +5205,8 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 config: config.clone(),
                 num_frames,
                 acquired_texture: None,
+                #[cfg(feature = "trace")]
+                record_checksums,
             });
 
             return None;
@@ -9,6 +9,25 @@ type FileName = String;
 
 pub const FILE_NAME: &str = "trace.ron";
 
+/// Environment variable that enables recording of `Action::BufferChecksum`
+/// and `Action::FrameChecksum`.
+///
+/// This is meant for debugging only: every present then stalls until the GPU
+/// is done with the frame, in order to read it back for the checksum.
+pub const CHECKSUMS_ENV_VAR: &str = "WGPU_TRACE_CHECKSUMS";
+
+/// Computes the 64-bit FNV-1a hash of `data`.
+///
+/// This is used to compare the contents read back by different replays,
+/// so it's stable across platforms and runs.
+pub fn checksum(data: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    data.iter().fold(OFFSET_BASIS, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(PRIME)
+    })
+}
+
 #[cfg(feature = "trace")]
 pub(crate) fn new_render_bundle_encoder_descriptor<'a>(
     label: crate::Label<'a>,
@@ -120,6 +139,18 @@ pub enum Action<'a> {
         size: wgt::Extent3d,
     },
     Submit(crate::SubmissionIndex, Vec<Command>),
+    /// Checksum of the buffer contents, as seen by a successful read mapping.
+    BufferChecksum {
+        id: id::BufferId,
+        range: Range<wgt::BufferAddress>,
+        checksum: u64,
+    },
+    /// Checksum of the texels of the frame about to be presented on a surface,
+    /// read row by row without padding.
+    FrameChecksum {
+        surface: id::SurfaceId,
+        checksum: u64,
+    },
 }
 
 #[derive(Debug)]
@@ -188,6 +219,7 @@ pub struct Trace {
     file: std::fs::File,
     config: ron::ser::PrettyConfig,
     binary_id: usize,
+    record_checksums: bool,
 }
 
 #[cfg(feature = "trace")]
@@ -201,9 +233,15 @@ impl Trace {
             file,
             config: ron::ser::PrettyConfig::default(),
             binary_id: 0,
+            record_checksums: std::env::var_os(CHECKSUMS_ENV_VAR).is_some(),
         })
     }
 
+    /// Returns `true` if the contents read back from the GPU are recorded.
+    pub fn records_checksums(&self) -> bool {
+        self.record_checksums
+    }
+
     pub fn make_binary(&mut self, kind: &str, data: &[u8]) -> String {
         self.binary_id += 1;
         let name = format!("data{}.{}", self.binary_id, kind);
@@ -22,6 +22,72 @@ pub(crate) struct LateSizedBufferGroup {
 pub enum ShaderModuleSource<'a> {
     Wgsl(Cow<'a, str>),
     Naga(naga::Module),
+    /// Encoded [`ShaderBundle`].
+    #[cfg(feature = "shader-bundle")]
+    Bundle(Cow<'a, [u8]>),
+}
+
+/// Shader module translated ahead of time.
+///
+/// Holds the IR of the module, which skips parsing its source, and backend outputs
+/// that are used instead of translating the IR when their options match those of the
+/// backend.
+///
+/// Bundles are encoded as a magic number and a version, followed by the RON of the
+/// module and of its outputs.
+#[cfg(feature = "shader-bundle")]
+#[derive(Debug)]
+pub struct ShaderBundle {
+    pub module: naga::Module,
+    pub precompiled: Vec<hal::PrecompiledShader>,
+}
+
+#[cfg(feature = "shader-bundle")]
+impl ShaderBundle {
+    const MAGIC: &'static [u8; 8] = b"WGPUSHDR";
+    /// Version of the encoding of bundles.
+    pub const VERSION: u32 = 2;
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Self::MAGIC.to_vec();
+        bytes.extend_from_slice(&Self::VERSION.to_le_bytes());
+        let body = ron::ser::to_string(&(&self.module, &self.precompiled)).unwrap();
+        bytes.extend_from_slice(body.as_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ShaderBundleError> {
+        let header = Self::MAGIC.len() + 4;
+        if bytes.len() < header || &bytes[..Self::MAGIC.len()] != Self::MAGIC {
+            return Err(ShaderBundleError::NotABundle);
+        }
+        let mut version = [0; 4];
+        version.copy_from_slice(&bytes[Self::MAGIC.len()..header]);
+        let version = u32::from_le_bytes(version);
+        if version != Self::VERSION {
+            return Err(ShaderBundleError::Version(version));
+        }
+        let (module, precompiled) = ron::de::from_bytes(&bytes[header..])
+            .map_err(|e| ShaderBundleError::Decoding(e.to_string()))?;
+        Ok(Self {
+            module,
+            precompiled,
+        })
+    }
+}
+
+#[cfg(feature = "shader-bundle")]
+#[derive(Clone, Debug, Error)]
+pub enum ShaderBundleError {
+    #[error("data is not a shader bundle")]
+    NotABundle,
+    #[error(
+        "shader bundle has version {0}, expected version {}",
+        ShaderBundle::VERSION
+    )]
+    Version(u32),
+    #[error("failed to decode the shader bundle: {0}")]
+    Decoding(String),
 }
 
 #[derive(Clone, Debug)]
@@ -132,6 +198,9 @@ pub enum CreateShaderModuleError {
         group: u32,
         limit: u32,
     },
+    #[cfg(feature = "shader-bundle")]
+    #[error(transparent)]
+    Bundle(#[from] ShaderBundleError),
 }
 
 impl CreateShaderModuleError {
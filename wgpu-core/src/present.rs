@@ -12,7 +12,7 @@ extract it from the hub.
 use std::borrow::Borrow;
 
 #[cfg(feature = "trace")]
-use crate::device::trace::Action;
+use crate::device::trace::{self, Action};
 use crate::{
     conv,
     device::DeviceError,
@@ -36,6 +36,9 @@ pub(crate) struct Presentation {
     #[allow(unused)]
     pub(crate) num_frames: u32,
     pub(crate) acquired_texture: Option<Stored<TextureId>>,
+    /// Whether the presented frames are read back to record their checksums.
+    #[cfg(feature = "trace")]
+    pub(crate) record_checksums: bool,
 }
 
 impl Presentation {
@@ -91,6 +94,106 @@ pub struct SurfaceOutput {
     pub texture_id: Option<TextureId>,
 }
 
+/// Reads back the contents of a surface `texture` that is in the `PRESENT` state,
+/// and returns their checksum.
+///
+/// The rows of texels are read without padding, the way the player reads them back.
+///
+/// The copy is submitted to the hal queue directly, bypassing the submission
+/// tracking of the device, and waited upon, so the GPU is idle afterwards.
+#[cfg(feature = "trace")]
+unsafe fn frame_checksum<A: HalApi>(
+    device: &A::Device,
+    queue: &mut A::Queue,
+    texture: &A::Texture,
+    config: &wgt::SurfaceConfiguration,
+) -> Result<u64, DeviceError> {
+    use hal::{CommandEncoder as _, Device as _};
+
+    let bytes_per_row = config.width * config.format.describe().block_size as u32;
+    let align = wgt::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let padded_bytes_per_row = bytes_per_row + (align - bytes_per_row % align) % align;
+    let size = padded_bytes_per_row as wgt::BufferAddress * config.height as wgt::BufferAddress;
+
+    let buffer = device.create_buffer(&hal::BufferDescriptor {
+        label: Some("(wgpu internal) frame checksum"),
+        size,
+        usage: hal::BufferUses::MAP_READ | hal::BufferUses::COPY_DST,
+        memory_flags: hal::MemoryFlags::empty(),
+    })?;
+    let mut encoder =
+        device.create_command_encoder(&hal::CommandEncoderDescriptor { label: None, queue })?;
+    let mut fence = device.create_fence()?;
+
+    encoder.begin_encoding(Some("(wgpu internal) frame checksum"))?;
+    encoder.transition_textures(std::iter::once(hal::TextureBarrier {
+        texture,
+        range: wgt::ImageSubresourceRange::default(),
+        usage: hal::TextureUses::PRESENT..hal::TextureUses::COPY_SRC,
+    }));
+    encoder.transition_buffers(std::iter::once(hal::BufferBarrier {
+        buffer: &buffer,
+        usage: hal::BufferUses::empty()..hal::BufferUses::COPY_DST,
+    }));
+    encoder.copy_texture_to_buffer(
+        texture,
+        hal::TextureUses::COPY_SRC,
+        &buffer,
+        std::iter::once(hal::BufferTextureCopy {
+            buffer_layout: wgt::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(padded_bytes_per_row),
+                rows_per_image: None,
+            },
+            texture_base: hal::TextureCopyBase {
+                mip_level: 0,
+                array_layer: 0,
+                origin: wgt::Origin3d::ZERO,
+                aspect: hal::FormatAspects::COLOR,
+            },
+            size: hal::CopyExtent {
+                width: config.width,
+                height: config.height,
+                depth: 1,
+            },
+        }),
+    );
+    encoder.transition_textures(std::iter::once(hal::TextureBarrier {
+        texture,
+        range: wgt::ImageSubresourceRange::default(),
+        usage: hal::TextureUses::COPY_SRC..hal::TextureUses::PRESENT,
+    }));
+    encoder.transition_buffers(std::iter::once(hal::BufferBarrier {
+        buffer: &buffer,
+        usage: hal::BufferUses::COPY_DST..hal::BufferUses::MAP_READ,
+    }));
+    let cmd_buf = encoder.end_encoding()?;
+
+    let result = queue
+        .submit(&[&cmd_buf], Some((&mut fence, 1)))
+        .and_then(|()| device.wait(&fence, 1, !0))
+        .and_then(|_| device.map_buffer(&buffer, 0..size))
+        .and_then(|mapping| {
+            if !mapping.is_coherent {
+                device.invalidate_mapped_ranges(&buffer, std::iter::once(0..size));
+            }
+            let data = std::slice::from_raw_parts(mapping.ptr.as_ptr(), size as usize);
+            let rows = data
+                .chunks_exact(padded_bytes_per_row as usize)
+                .flat_map(|row| &row[..bytes_per_row as usize])
+                .copied()
+                .collect::<Vec<u8>>();
+            device.unmap_buffer(&buffer)?;
+            Ok(trace::checksum(&rows))
+        });
+
+    encoder.reset_all(std::iter::once(cmd_buf));
+    device.destroy_command_encoder(encoder);
+    device.destroy_fence(fence);
+    device.destroy_buffer(buffer);
+    result.map_err(DeviceError::from)
+}
+
 impl<G: GlobalIdentityHandlerFactory> Global<G> {
     pub fn surface_get_current_texture<A: HalApi>(
         &self,
@@ -265,7 +368,39 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
 
         #[cfg(feature = "trace")]
         if let Some(ref trace) = device.trace {
-            trace.lock().add(Action::Present(surface_id));
+            // The frame is read back before locking the trace, so that the other
+            // threads recording into it don't wait for the GPU as well.
+            let mut checksum = None;
+            let acquired_texture = present.acquired_texture.as_ref();
+            if let Some(texture_id) = acquired_texture.filter(|_| present.record_checksums) {
+                let (texture_guard, _) = hub.textures.read(&mut token);
+                if let resource::TextureInner::Surface {
+                    ref raw,
+                    has_work: true,
+                    ..
+                } = texture_guard[texture_id.value].inner
+                {
+                    match unsafe {
+                        frame_checksum::<A>(
+                            &device.raw,
+                            &mut device.queue,
+                            raw.borrow(),
+                            &present.config,
+                        )
+                    } {
+                        Ok(value) => checksum = Some(value),
+                        Err(error) => log::error!("Unable to read back the frame: {}", error),
+                    }
+                }
+            }
+            let mut trace = trace.lock();
+            if let Some(checksum) = checksum {
+                trace.add(Action::FrameChecksum {
+                    surface: surface_id,
+                    checksum,
+                });
+            }
+            trace.add(Action::Present(surface_id));
         }
 
         let result = {
@@ -21,11 +21,12 @@ enum ResourceType {
 
 #[derive(Debug)]
 struct Resource {
-    #[allow(unused)]
     name: Option<String>,
     bind: naga::ResourceBinding,
     ty: ResourceType,
     class: naga::AddressSpace,
+    /// Count of elements of binding arrays.
+    count: Option<std::num::NonZeroU32>,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -667,6 +668,32 @@ impl NumericType {
         }
     }
 
+    /// Returns the vertex format with 32 or 64 bit components of this type.
+    fn to_vertex_format(self) -> Option<wgt::VertexFormat> {
+        use naga::{ScalarKind as Sk, VectorSize as Vs};
+        use wgt::VertexFormat as Vf;
+
+        Some(match (self.kind, self.width, self.dim) {
+            (Sk::Uint, 4, NumericDimension::Scalar) => Vf::Uint32,
+            (Sk::Uint, 4, NumericDimension::Vector(Vs::Bi)) => Vf::Uint32x2,
+            (Sk::Uint, 4, NumericDimension::Vector(Vs::Tri)) => Vf::Uint32x3,
+            (Sk::Uint, 4, NumericDimension::Vector(Vs::Quad)) => Vf::Uint32x4,
+            (Sk::Sint, 4, NumericDimension::Scalar) => Vf::Sint32,
+            (Sk::Sint, 4, NumericDimension::Vector(Vs::Bi)) => Vf::Sint32x2,
+            (Sk::Sint, 4, NumericDimension::Vector(Vs::Tri)) => Vf::Sint32x3,
+            (Sk::Sint, 4, NumericDimension::Vector(Vs::Quad)) => Vf::Sint32x4,
+            (Sk::Float, 4, NumericDimension::Scalar) => Vf::Float32,
+            (Sk::Float, 4, NumericDimension::Vector(Vs::Bi)) => Vf::Float32x2,
+            (Sk::Float, 4, NumericDimension::Vector(Vs::Tri)) => Vf::Float32x3,
+            (Sk::Float, 4, NumericDimension::Vector(Vs::Quad)) => Vf::Float32x4,
+            (Sk::Float, 8, NumericDimension::Scalar) => Vf::Float64,
+            (Sk::Float, 8, NumericDimension::Vector(Vs::Bi)) => Vf::Float64x2,
+            (Sk::Float, 8, NumericDimension::Vector(Vs::Tri)) => Vf::Float64x3,
+            (Sk::Float, 8, NumericDimension::Vector(Vs::Quad)) => Vf::Float64x4,
+            _ => return None,
+        })
+    }
+
     fn from_texture_format(format: wgt::TextureFormat) -> Self {
         use naga::{ScalarKind as Sk, VectorSize as Vs};
         use wgt::TextureFormat as Tf;
@@ -875,9 +902,27 @@ impl Interface {
             };
             let naga_ty = &module.types[var.ty].inner;
 
-            let inner_ty = match *naga_ty {
-                naga::TypeInner::BindingArray { base, .. } => &module.types[base].inner,
-                ref ty => ty,
+            let (inner_ty, count) = match *naga_ty {
+                naga::TypeInner::BindingArray { base, size } => {
+                    let count = match size {
+                        naga::ArraySize::Constant(constant) => {
+                            match module.constants[constant].inner {
+                                naga::ConstantInner::Scalar {
+                                    value: naga::ScalarValue::Uint(count),
+                                    ..
+                                } => std::num::NonZeroU32::new(count as u32),
+                                naga::ConstantInner::Scalar {
+                                    value: naga::ScalarValue::Sint(count),
+                                    ..
+                                } => std::num::NonZeroU32::new(count as u32),
+                                _ => None,
+                            }
+                        }
+                        naga::ArraySize::Dynamic => None,
+                    };
+                    (&module.types[base].inner, count)
+                }
+                ref ty => (ty, None),
             };
 
             let ty = match *inner_ty {
@@ -904,6 +949,7 @@ impl Interface {
                     bind,
                     ty,
                     class: var.space,
+                    count,
                 },
                 Default::default(),
             );
@@ -955,6 +1001,78 @@ impl Interface {
         }
     }
 
+    /// Describe the entry points of the module and the resources they use.
+    pub fn reflect(&self) -> wgt::ShaderReflection {
+        let unused = (GlobalUse::empty(), wgt::ShaderStages::empty());
+        let mut usages = FastHashMap::default();
+        let mut entry_points = Vec::with_capacity(self.entry_points.len());
+        for (&(naga_stage, ref name), entry_point) in self.entry_points.iter() {
+            let stage = match naga_stage {
+                naga::ShaderStage::Vertex => wgt::ShaderStages::VERTEX,
+                naga::ShaderStage::Fragment => wgt::ShaderStages::FRAGMENT,
+                naga::ShaderStage::Compute => wgt::ShaderStages::COMPUTE,
+            };
+            for &(handle, usage) in entry_point.resources.iter() {
+                let resource_usage = usages.entry(handle).or_insert(unused);
+                resource_usage.0 |= usage;
+                resource_usage.1 |= stage;
+            }
+            let reflect_varyings = |varyings: &[Varying]| {
+                varyings
+                    .iter()
+                    .filter_map(|varying| match *varying {
+                        Varying::Local { location, ref iv } => Some(wgt::VariableReflection {
+                            location,
+                            format: iv.ty.to_vertex_format()?,
+                        }),
+                        Varying::BuiltIn(_) => None,
+                    })
+                    .collect()
+            };
+            let mut bindings = entry_point
+                .resources
+                .iter()
+                .map(|&(handle, _)| {
+                    let bind = &self.resources[handle].bind;
+                    (bind.group, bind.binding)
+                })
+                .collect::<Vec<_>>();
+            bindings.sort_unstable();
+            entry_points.push(wgt::EntryPointReflection {
+                name: name.clone(),
+                stage,
+                workgroup_size: entry_point.workgroup_size,
+                inputs: reflect_varyings(&entry_point.inputs),
+                outputs: reflect_varyings(&entry_point.outputs),
+                bindings,
+            });
+        }
+        entry_points.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut bindings = self
+            .resources
+            .iter()
+            .filter_map(|(handle, resource)| {
+                let (usage, visibility) = usages.get(&handle).cloned().unwrap_or(unused);
+                let ty = resource.derive_binding_type(usage, self.features).ok()?;
+                Some(wgt::BindingReflection {
+                    name: resource.name.clone(),
+                    group: resource.bind.group,
+                    binding: resource.bind.binding,
+                    visibility,
+                    ty,
+                    count: resource.count,
+                })
+            })
+            .collect::<Vec<_>>();
+        bindings.sort_by_key(|binding| (binding.group, binding.binding));
+
+        wgt::ShaderReflection {
+            entry_points,
+            bindings,
+        }
+    }
+
     pub fn check_stage(
         &self,
         given_layouts: Option<&[&BindEntryMap]>,
@@ -143,7 +143,11 @@ impl<A: hal::Api> Example<A> {
             )
             .validate(&module)
             .unwrap();
-            hal::NagaShader { module, info }
+            hal::NagaShader {
+                module,
+                info,
+                precompiled: Vec::new(),
+            }
         };
         let shader_desc = hal::ShaderModuleDescriptor {
             label: None,
@@ -196,14 +196,48 @@ impl super::Device {
 
         let stage_bit = crate::auxil::map_naga_stage(naga_stage);
         let module = &stage.module.naga.module;
-        //TODO: reuse the writer
-        let mut source = String::new();
-        let mut writer = hlsl::Writer::new(&mut source, &layout.naga_options);
-        let reflection_info = {
-            profiling::scope!("naga::back::hlsl::write");
-            writer
-                .write(module, &stage.module.naga.info)
-                .map_err(|e| crate::PipelineError::Linkage(stage_bit, format!("HLSL: {:?}", e)))?
+        let ep_index = module
+            .entry_points
+            .iter()
+            .position(|ep| ep.stage == naga_stage && ep.name == stage.entry_point)
+            .ok_or(crate::PipelineError::EntryPoint(naga_stage))?;
+
+        let precompiled = stage
+            .module
+            .naga
+            .find_precompiled(|| crate::PrecompiledKey::hlsl(&layout.naga_options))
+            .and_then(|code| match *code {
+                crate::PrecompiledCode::Hlsl {
+                    ref source,
+                    ref entry_point_names,
+                } => Some((source, entry_point_names)),
+                _ => None,
+            });
+        let (source, raw_ep) = match precompiled {
+            Some((source, entry_point_names)) => {
+                let raw_ep = entry_point_names
+                    .get(ep_index)
+                    .and_then(|name| name.as_ref())
+                    .map(|name| ffi::CString::new(name.as_str()).unwrap())
+                    .ok_or(crate::PipelineError::EntryPoint(naga_stage))?;
+                (source.clone(), raw_ep)
+            }
+            None => {
+                //TODO: reuse the writer
+                let mut source = String::new();
+                let mut writer = hlsl::Writer::new(&mut source, &layout.naga_options);
+                let reflection_info = {
+                    profiling::scope!("naga::back::hlsl::write");
+                    writer.write(module, &stage.module.naga.info).map_err(|e| {
+                        crate::PipelineError::Linkage(stage_bit, format!("HLSL: {:?}", e))
+                    })?
+                };
+                let raw_ep = reflection_info.entry_point_names[ep_index]
+                    .as_ref()
+                    .map(|name| ffi::CString::new(name.as_str()).unwrap())
+                    .map_err(|e| crate::PipelineError::Linkage(stage_bit, format!("{}", e)))?;
+                (source, raw_ep)
+            }
         };
 
         let full_stage = format!(
@@ -211,15 +245,6 @@ impl super::Device {
             naga_stage.to_hlsl_str(),
             layout.naga_options.shader_model.to_str()
         );
-        let ep_index = module
-            .entry_points
-            .iter()
-            .position(|ep| ep.stage == naga_stage && ep.name == stage.entry_point)
-            .ok_or(crate::PipelineError::EntryPoint(naga_stage))?;
-        let raw_ep = reflection_info.entry_point_names[ep_index]
-            .as_ref()
-            .map(|name| ffi::CString::new(name.as_str()).unwrap())
-            .map_err(|e| crate::PipelineError::Linkage(stage_bit, format!("{}", e)))?;
 
         let mut shader_data = native::Blob::null();
         let mut error = native::Blob::null();
@@ -791,13 +816,13 @@ impl crate::Device<super::Api> for super::Device {
             desc.label.unwrap_or_default()
         );
 
-        let mut binding_map = hlsl::BindingMap::default();
-        let (mut bind_cbv, mut bind_srv, mut bind_uav, mut bind_sampler) = (
-            hlsl::BindTarget::default(),
-            hlsl::BindTarget::default(),
-            hlsl::BindTarget::default(),
-            hlsl::BindTarget::default(),
-        );
+        // the registers of the resources are assigned by `hlsl_options`
+        let bind_group_layouts = desc
+            .bind_group_layouts
+            .iter()
+            .map(|bgl| &bgl.entries[..])
+            .collect::<Vec<_>>();
+        let naga_options = crate::precompiled::hlsl_options(&bind_group_layouts, desc.flags);
         let mut parameters = Vec::new();
 
         // Collect the whole number of bindings we will create upfront.
@@ -848,33 +873,20 @@ impl crate::Device<super::Api> for super::Device {
                     wgt::BindingType::Buffer {
                         has_dynamic_offset: true,
                         ..
-                    } => continue,
+                    }
+                    | wgt::BindingType::Sampler { .. } => continue,
                     ref other => conv::map_binding_type(other),
                 };
-                let bt = match range_ty {
-                    native::DescriptorRangeType::CBV => &mut bind_cbv,
-                    native::DescriptorRangeType::SRV => &mut bind_srv,
-                    native::DescriptorRangeType::UAV => &mut bind_uav,
-                    native::DescriptorRangeType::Sampler => continue,
-                };
-
-                binding_map.insert(
-                    naga::ResourceBinding {
-                        group: index as u32,
-                        binding: entry.binding,
-                    },
-                    hlsl::BindTarget {
-                        binding_array_size: entry.count.map(NonZeroU32::get),
-                        ..bt.clone()
-                    },
-                );
+                let bt = &naga_options.binding_map[&naga::ResourceBinding {
+                    group: index as u32,
+                    binding: entry.binding,
+                }];
                 ranges.push(native::DescriptorRange::new(
                     range_ty,
                     entry.count.map_or(1, |count| count.get()),
                     native_binding(bt),
                     d3d12::D3D12_DESCRIPTOR_RANGE_OFFSET_APPEND,
                 ));
-                bt.register += entry.count.map(NonZeroU32::get).unwrap_or(1);
             }
             if ranges.len() > range_base {
                 log::debug!(
@@ -897,23 +909,16 @@ impl crate::Device<super::Api> for super::Device {
                     wgt::BindingType::Sampler { .. } => native::DescriptorRangeType::Sampler,
                     _ => continue,
                 };
-                binding_map.insert(
-                    naga::ResourceBinding {
-                        group: index as u32,
-                        binding: entry.binding,
-                    },
-                    hlsl::BindTarget {
-                        binding_array_size: entry.count.map(NonZeroU32::get),
-                        ..bind_sampler.clone()
-                    },
-                );
+                let bt = &naga_options.binding_map[&naga::ResourceBinding {
+                    group: index as u32,
+                    binding: entry.binding,
+                }];
                 ranges.push(native::DescriptorRange::new(
                     range_ty,
                     entry.count.map_or(1, |count| count.get()),
-                    native_binding(&bind_sampler),
+                    native_binding(bt),
                     d3d12::D3D12_DESCRIPTOR_RANGE_OFFSET_APPEND,
                 ));
-                bind_sampler.register += entry.count.map(NonZeroU32::get).unwrap_or(1);
             }
             if ranges.len() > range_base {
                 log::debug!(
@@ -941,34 +946,24 @@ impl crate::Device<super::Api> for super::Device {
                     _ => continue,
                 };
 
-                let (kind, parameter_ty, bt) = match buffer_ty {
+                let (kind, parameter_ty) = match buffer_ty {
                     wgt::BufferBindingType::Uniform => (
                         super::BufferViewKind::Constant,
                         d3d12::D3D12_ROOT_PARAMETER_TYPE_CBV,
-                        &mut bind_cbv,
                     ),
                     wgt::BufferBindingType::Storage { read_only: true } => (
                         super::BufferViewKind::ShaderResource,
                         d3d12::D3D12_ROOT_PARAMETER_TYPE_SRV,
-                        &mut bind_srv,
                     ),
                     wgt::BufferBindingType::Storage { read_only: false } => (
                         super::BufferViewKind::UnorderedAccess,
                         d3d12::D3D12_ROOT_PARAMETER_TYPE_UAV,
-                        &mut bind_uav,
                     ),
                 };
-
-                binding_map.insert(
-                    naga::ResourceBinding {
-                        group: index as u32,
-                        binding: entry.binding,
-                    },
-                    hlsl::BindTarget {
-                        binding_array_size: entry.count.map(NonZeroU32::get),
-                        ..bt.clone()
-                    },
-                );
+                let bt = &naga_options.binding_map[&naga::ResourceBinding {
+                    group: index as u32,
+                    binding: entry.binding,
+                }];
                 info.dynamic_buffers.push(kind);
 
                 log::debug!(
@@ -982,8 +977,6 @@ impl crate::Device<super::Api> for super::Device {
                     dynamic_buffers_visibility,
                     native_binding(bt),
                 ));
-
-                bt.register += entry.count.map_or(1, NonZeroU32::get);
             }
 
             bind_group_infos.push(info);
@@ -992,26 +985,22 @@ impl crate::Device<super::Api> for super::Device {
         // Ensure that we didn't reallocate!
         debug_assert_eq!(ranges.len(), total_non_dynamic_entries);
 
-        let (special_constants_root_index, special_constants_binding) = if desc.flags.intersects(
-            crate::PipelineLayoutFlags::BASE_VERTEX_INSTANCE
-                | crate::PipelineLayoutFlags::NUM_WORK_GROUPS,
-        ) {
-            let parameter_index = parameters.len();
-            log::debug!("\tParam[{}] = special", parameter_index);
-            parameters.push(native::RootParameter::constants(
-                native::ShaderVisibility::All, // really needed for VS and CS only
-                native_binding(&bind_cbv),
-                3, // 0 = base vertex, 1 = base instance, 2 = other
-            ));
-            let binding = bind_cbv.clone();
-            bind_cbv.register += 1;
-            (Some(parameter_index as u32), Some(binding))
-        } else {
-            (None, None)
-        };
+        let special_constants_root_index =
+            if let Some(ref binding) = naga_options.special_constants_binding {
+                let parameter_index = parameters.len();
+                log::debug!("\tParam[{}] = special", parameter_index);
+                parameters.push(native::RootParameter::constants(
+                    native::ShaderVisibility::All, // really needed for VS and CS only
+                    native_binding(binding),
+                    3, // 0 = base vertex, 1 = base instance, 2 = other
+                ));
+                Some(parameter_index as u32)
+            } else {
+                None
+            };
 
         log::trace!("{:#?}", parameters);
-        log::trace!("Bindings {:#?}", binding_map);
+        log::trace!("Bindings {:#?}", naga_options.binding_map);
 
         let (blob, error) = self
             .library
@@ -1056,12 +1045,7 @@ impl crate::Device<super::Api> for super::Device {
                 special_constants_root_index,
             },
             bind_group_infos,
-            naga_options: hlsl::Options {
-                shader_model: hlsl::ShaderModel::V5_1,
-                binding_map,
-                fake_missing_bindings: false,
-                special_constants_binding,
-            },
+            naga_options,
         })
     }
     unsafe fn destroy_pipeline_layout(&self, pipeline_layout: super::PipelineLayout) {
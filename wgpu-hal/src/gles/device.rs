@@ -162,24 +162,78 @@ impl super::Device {
             binding_array: BoundsCheckPolicy::Unchecked,
         };
 
-        let mut output = String::new();
-        let mut writer = glsl::Writer::new(
-            &mut output,
-            &shader.module,
-            &shader.info,
-            &context.layout.naga_options,
-            &pipeline_options,
-            policies,
-        )
-        .map_err(|e| {
-            let msg = format!("{}", e);
-            crate::PipelineError::Linkage(map_naga_stage(naga_stage), msg)
-        })?;
-
-        let reflection_info = writer.write().map_err(|e| {
-            let msg = format!("{}", e);
-            crate::PipelineError::Linkage(map_naga_stage(naga_stage), msg)
-        })?;
+        let precompiled = shader
+            .find_precompiled(|| {
+                crate::PrecompiledKey::glsl(
+                    &context.layout.naga_options,
+                    &pipeline_options,
+                    &policies,
+                )
+            })
+            .and_then(|code| match *code {
+                crate::PrecompiledCode::Glsl {
+                    ref source,
+                    ref texture_mapping,
+                    ref uniforms,
+                } => Some((source, texture_mapping, uniforms)),
+                _ => None,
+            });
+        let (output, reflection_info) = match precompiled {
+            Some((source, texture_mapping, uniforms)) => {
+                let globals = shader
+                    .module
+                    .global_variables
+                    .iter()
+                    .map(|(handle, _)| handle)
+                    .collect::<Vec<_>>();
+                let global = |index: u32| {
+                    globals.get(index as usize).copied().ok_or_else(|| {
+                        let msg = format!("Precompiled shader has no global variable {}", index);
+                        crate::PipelineError::Linkage(map_naga_stage(naga_stage), msg)
+                    })
+                };
+                let mut reflection_info = glsl::ReflectionInfo {
+                    texture_mapping: Default::default(),
+                    uniforms: Default::default(),
+                };
+                for &(ref name, texture, sampler) in texture_mapping {
+                    let mapping = glsl::TextureMapping {
+                        texture: global(texture)?,
+                        sampler: sampler.map(global).transpose()?,
+                    };
+                    reflection_info
+                        .texture_mapping
+                        .insert(name.clone(), mapping);
+                }
+                for &(index, ref name) in uniforms {
+                    reflection_info
+                        .uniforms
+                        .insert(global(index)?, name.clone());
+                }
+                (source.clone(), reflection_info)
+            }
+            None => {
+                let mut output = String::new();
+                let mut writer = glsl::Writer::new(
+                    &mut output,
+                    &shader.module,
+                    &shader.info,
+                    &context.layout.naga_options,
+                    &pipeline_options,
+                    policies,
+                )
+                .map_err(|e| {
+                    let msg = format!("{}", e);
+                    crate::PipelineError::Linkage(map_naga_stage(naga_stage), msg)
+                })?;
+
+                let reflection_info = writer.write().map_err(|e| {
+                    let msg = format!("{}", e);
+                    crate::PipelineError::Linkage(map_naga_stage(naga_stage), msg)
+                })?;
+                (output, reflection_info)
+            }
+        };
 
         log::debug!("Naga generated shader:\n{}", output);
 
@@ -821,13 +875,6 @@ impl crate::Device<super::Api> for super::Device {
     ) -> Result<super::PipelineLayout, crate::DeviceError> {
         use naga::back::glsl;
 
-        let mut group_infos = Vec::with_capacity(desc.bind_group_layouts.len());
-        let mut num_samplers = 0u8;
-        let mut num_textures = 0u8;
-        let mut num_images = 0u8;
-        let mut num_uniform_buffers = 0u8;
-        let mut num_storage_buffers = 0u8;
-
         let mut writer_flags = glsl::WriterFlags::ADJUST_COORDINATE_SPACE;
         writer_flags.set(
             glsl::WriterFlags::TEXTURE_SHADOW_LOD,
@@ -835,8 +882,14 @@ impl crate::Device<super::Api> for super::Device {
                 .private_caps
                 .contains(super::PrivateCapabilities::SHADER_TEXTURE_SHADOW_LOD),
         );
-        let mut binding_map = glsl::BindingMap::default();
+        let bind_group_layouts = desc
+            .bind_group_layouts
+            .iter()
+            .map(|bg_layout| &bg_layout.entries[..])
+            .collect::<Vec<_>>();
+        let binding_map = crate::precompiled::glsl_binding_map(&bind_group_layouts);
 
+        let mut group_infos = Vec::with_capacity(desc.bind_group_layouts.len());
         for (group_index, bg_layout) in desc.bind_group_layouts.iter().enumerate() {
             // create a vector with the size enough to hold all the bindings, filled with `!0`
             let mut binding_to_slot = vec![
@@ -849,27 +902,11 @@ impl crate::Device<super::Api> for super::Device {
             .into_boxed_slice();
 
             for entry in bg_layout.entries.iter() {
-                let counter = match entry.ty {
-                    wgt::BindingType::Sampler { .. } => &mut num_samplers,
-                    wgt::BindingType::Texture { .. } => &mut num_textures,
-                    wgt::BindingType::StorageTexture { .. } => &mut num_images,
-                    wgt::BindingType::Buffer {
-                        ty: wgt::BufferBindingType::Uniform,
-                        ..
-                    } => &mut num_uniform_buffers,
-                    wgt::BindingType::Buffer {
-                        ty: wgt::BufferBindingType::Storage { .. },
-                        ..
-                    } => &mut num_storage_buffers,
-                };
-
-                binding_to_slot[entry.binding as usize] = *counter;
                 let br = naga::ResourceBinding {
                     group: group_index as u32,
                     binding: entry.binding,
                 };
-                binding_map.insert(br, *counter);
-                *counter += entry.count.map_or(1, |c| c.get() as u8);
+                binding_to_slot[entry.binding as usize] = binding_map[&br];
             }
 
             group_infos.push(super::BindGroupLayoutInfo {
@@ -65,6 +65,7 @@ mod metal;
 mod vulkan;
 
 pub mod auxil;
+pub mod precompiled;
 pub mod api {
     #[cfg(feature = "dx11")]
     pub use super::dx11::Api as Dx11;
@@ -82,6 +83,8 @@ pub mod api {
 #[cfg(feature = "vulkan")]
 pub use vulkan::UpdateAfterBindTypes;
 
+pub use precompiled::{PrecompiledCode, PrecompiledKey, PrecompiledShader};
+
 use std::{
     borrow::Borrow,
     fmt,
@@ -942,6 +945,34 @@ pub struct NagaShader {
     pub module: naga::Module,
     /// Analysis information of the module.
     pub info: naga::valid::ModuleInfo,
+    /// Backend outputs translated ahead of time, used instead of translating the IR
+    /// when their options match.
+    pub precompiled: Vec<PrecompiledShader>,
+}
+
+impl NagaShader {
+    /// Returns the precompiled output accepting the translation options of the `key`.
+    ///
+    /// The key is only built if the shader has precompiled outputs.
+    pub fn find_precompiled(
+        &self,
+        key: impl FnOnce() -> PrecompiledKey,
+    ) -> Option<&PrecompiledCode> {
+        if self.precompiled.is_empty() {
+            return None;
+        }
+        let key = key();
+        let code = self
+            .precompiled
+            .iter()
+            .find(|shader| shader.key.accepts(&key))
+            .map(|shader| &shader.code);
+        match code {
+            Some(_) => log::debug!("Using precompiled shader"),
+            None => log::debug!("No precompiled shader for {:?}", key),
+        }
+        code
+    }
 }
 
 // Custom implementation avoids the need to generate Debug impl code
@@ -66,13 +66,48 @@ impl super::Device {
         };
 
         let module = &stage.module.naga.module;
-        let (source, info) = naga::back::msl::write_string(
-            module,
-            &stage.module.naga.info,
-            &layout.naga_options,
-            &pipeline_options,
-        )
-        .map_err(|e| crate::PipelineError::Linkage(stage_bit, format!("MSL: {:?}", e)))?;
+        let ep_index = module
+            .entry_points
+            .iter()
+            .position(|ep| ep.stage == naga_stage && ep.name == stage.entry_point)
+            .ok_or(crate::PipelineError::EntryPoint(naga_stage))?;
+
+        let precompiled = stage
+            .module
+            .naga
+            .find_precompiled(|| {
+                crate::PrecompiledKey::msl(&layout.naga_options, &pipeline_options)
+            })
+            .and_then(|code| match *code {
+                crate::PrecompiledCode::Msl {
+                    ref source,
+                    ref entry_point_names,
+                } => Some((source, entry_point_names)),
+                _ => None,
+            });
+        let (source, name) = match precompiled {
+            Some((source, entry_point_names)) => {
+                let name = entry_point_names
+                    .get(ep_index)
+                    .cloned()
+                    .flatten()
+                    .ok_or(crate::PipelineError::EntryPoint(naga_stage))?;
+                (source.clone(), name)
+            }
+            None => {
+                let (source, info) = naga::back::msl::write_string(
+                    module,
+                    &stage.module.naga.info,
+                    &layout.naga_options,
+                    &pipeline_options,
+                )
+                .map_err(|e| crate::PipelineError::Linkage(stage_bit, format!("MSL: {:?}", e)))?;
+                let name = info.entry_point_names[ep_index]
+                    .clone()
+                    .map_err(|e| crate::PipelineError::Linkage(stage_bit, format!("{}", e)))?;
+                (source, name)
+            }
+        };
 
         log::debug!(
             "Naga generated shader for entry point '{}' and stage {:?}\n{}",
@@ -98,22 +133,14 @@ impl super::Device {
                 crate::PipelineError::Linkage(stage_bit, format!("Metal: {}", err))
             })?;
 
-        let ep_index = module
-            .entry_points
-            .iter()
-            .position(|ep| ep.stage == naga_stage && ep.name == stage.entry_point)
-            .ok_or(crate::PipelineError::EntryPoint(naga_stage))?;
         let ep = &module.entry_points[ep_index];
-        let name = info.entry_point_names[ep_index]
-            .as_ref()
-            .map_err(|e| crate::PipelineError::Linkage(stage_bit, format!("{}", e)))?;
         let wg_size = mtl::MTLSize {
             width: ep.workgroup_size[0] as _,
             height: ep.workgroup_size[1] as _,
             depth: ep.workgroup_size[2] as _,
         };
 
-        let function = library.get_function(name, None).map_err(|e| {
+        let function = library.get_function(&name, None).map_err(|e| {
             log::error!("get_function: {:?}", e);
             crate::PipelineError::EntryPoint(naga_stage)
         })?;
@@ -486,9 +513,7 @@ impl crate::Device<super::Api> for super::Device {
             counters: super::ResourceData<super::ResourceIndex>,
             pc_buffer: Option<super::ResourceIndex>,
             pc_limit: u32,
-            sizes_buffer: Option<super::ResourceIndex>,
             sizes_count: u8,
-            resources: naga::back::msl::BindingMap,
         }
 
         let mut stage_data = super::NAGA_STAGES.map(|&stage| StageInfo {
@@ -496,9 +521,7 @@ impl crate::Device<super::Api> for super::Device {
             counters: super::ResourceData::default(),
             pc_buffer: None,
             pc_limit: 0,
-            sizes_buffer: None,
             sizes_count: 0,
-            resources: Default::default(),
         });
         let mut bind_group_infos = arrayvec::ArrayVec::new();
 
@@ -530,7 +553,7 @@ impl crate::Device<super::Api> for super::Device {
         }
 
         // Second, place the described resources
-        for (group_index, &bgl) in desc.bind_group_layouts.iter().enumerate() {
+        for &bgl in desc.bind_group_layouts.iter() {
             // remember where the resources for this set start at each shader stage
             let mut dynamic_buffers = Vec::new();
             let base_resource_indices = stage_data.map(|info| info.counters.clone());
@@ -565,43 +588,16 @@ impl crate::Device<super::Api> for super::Device {
                         continue;
                     }
 
-                    let mut target = naga::back::msl::BindTarget::default();
+                    // the slots of the resources are assigned by `msl_per_stage_map`
                     let count = entry.count.map_or(1, NonZeroU32::get);
-                    target.binding_array_size = entry.count.map(NonZeroU32::get);
                     match entry.ty {
-                        wgt::BindingType::Buffer { ty, .. } => {
-                            target.buffer = Some(info.counters.buffers as _);
-                            info.counters.buffers += count;
-                            if let wgt::BufferBindingType::Storage { read_only } = ty {
-                                target.mutable = !read_only;
-                            }
-                        }
-                        wgt::BindingType::Sampler { .. } => {
-                            target.sampler = Some(naga::back::msl::BindSamplerTarget::Resource(
-                                info.counters.samplers as _,
-                            ));
-                            info.counters.samplers += count;
-                        }
-                        wgt::BindingType::Texture { .. } => {
-                            target.texture = Some(info.counters.textures as _);
-                            info.counters.textures += count;
-                        }
-                        wgt::BindingType::StorageTexture { access, .. } => {
-                            target.texture = Some(info.counters.textures as _);
-                            info.counters.textures += count;
-                            target.mutable = match access {
-                                wgt::StorageTextureAccess::ReadOnly => false,
-                                wgt::StorageTextureAccess::WriteOnly => true,
-                                wgt::StorageTextureAccess::ReadWrite => true,
-                            };
+                        wgt::BindingType::Buffer { .. } => info.counters.buffers += count,
+                        wgt::BindingType::Sampler { .. } => info.counters.samplers += count,
+                        wgt::BindingType::Texture { .. }
+                        | wgt::BindingType::StorageTexture { .. } => {
+                            info.counters.textures += count
                         }
                     }
-
-                    let br = naga::ResourceBinding {
-                        group: group_index as u32,
-                        binding: entry.binding,
-                    };
-                    info.resources.insert(br, target);
                 }
             }
 
@@ -614,7 +610,6 @@ impl crate::Device<super::Api> for super::Device {
         for info in stage_data.iter_mut() {
             // handle the sizes buffer assignment and shader overrides
             if info.sizes_count != 0 {
-                info.sizes_buffer = Some(info.counters.buffers);
                 info.counters.buffers += 1;
             }
             if info.counters.buffers > self.shared.private_caps.max_buffers_per_stage
@@ -626,15 +621,13 @@ impl crate::Device<super::Api> for super::Device {
             }
         }
 
-        let per_stage_map = stage_data.map(|info| naga::back::msl::PerStageResources {
-            push_constant_buffer: info
-                .pc_buffer
-                .map(|buffer_index| buffer_index as naga::back::msl::Slot),
-            sizes_buffer: info
-                .sizes_buffer
-                .map(|buffer_index| buffer_index as naga::back::msl::Slot),
-            resources: Default::default(),
-        });
+        let bind_group_layouts = desc
+            .bind_group_layouts
+            .iter()
+            .map(|bgl| &bgl.entries[..])
+            .collect::<Vec<_>>();
+        let per_stage_map =
+            crate::precompiled::msl_per_stage_map(&bind_group_layouts, desc.push_constant_ranges);
 
         Ok(super::PipelineLayout {
             bind_group_infos,
@@ -659,20 +652,7 @@ impl crate::Device<super::Api> for super::Device {
                 inline_samplers: Default::default(),
                 spirv_cross_compatibility: false,
                 fake_missing_bindings: false,
-                per_stage_map: naga::back::msl::PerStageMap {
-                    vs: naga::back::msl::PerStageResources {
-                        resources: stage_data.vs.resources,
-                        ..per_stage_map.vs
-                    },
-                    fs: naga::back::msl::PerStageResources {
-                        resources: stage_data.fs.resources,
-                        ..per_stage_map.fs
-                    },
-                    cs: naga::back::msl::PerStageResources {
-                        resources: stage_data.cs.resources,
-                        ..per_stage_map.cs
-                    },
-                },
+                per_stage_map,
                 bounds_check_policies: naga::proc::BoundsCheckPolicies {
                     index: naga::proc::BoundsCheckPolicy::ReadZeroSkipWrite,
                     buffer: naga::proc::BoundsCheckPolicy::ReadZeroSkipWrite,
@@ -0,0 +1,738 @@
+/*! Backend outputs translated ahead of time.
+ *
+ *  Outputs are keyed by the subset of the translation options of their backend that
+ *  they depend on, encoded explicitly so that the keys don't depend on how naga
+ *  formats its options. The translation options derived from pipeline layouts are
+ *  computed here, so that the tools producing outputs can reproduce them.
+ */
+
+/// Output of a naga backend for a module, translated ahead of time.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PrecompiledShader {
+    /// Key of the translation options.
+    pub key: PrecompiledKey,
+    /// Backend output.
+    pub code: PrecompiledCode,
+}
+
+/// Precompiled backend output.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PrecompiledCode {
+    /// SPIR-V module.
+    SpirV(Vec<u32>),
+    /// GLSL source of an entry point.
+    Glsl {
+        source: String,
+        /// Names of the texture-sampler pairs of the source, with the indices of the
+        /// global variables of their texture and sampler.
+        texture_mapping: Vec<(String, u32, Option<u32>)>,
+        /// Names of the uniforms of the source, with the indices of their global variables.
+        uniforms: Vec<(u32, String)>,
+    },
+    /// MSL source of a module.
+    Msl {
+        source: String,
+        /// Names of the entry points in the source, in the order of the module.
+        entry_point_names: Vec<Option<String>>,
+    },
+    /// HLSL source of a module.
+    Hlsl {
+        source: String,
+        /// Names of the entry points in the source, in the order of the module.
+        entry_point_names: Vec<Option<String>>,
+    },
+}
+
+/// Key of a precompiled output, the translation options it depends on.
+///
+/// Keys are built from the options of the backends with the constructors of this type,
+/// and compared with [`PrecompiledKey::accepts`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PrecompiledKey {
+    /// Version of the encoding of the key. Outputs with keys of another version than
+    /// [`PrecompiledKey::VERSION`] are never used.
+    pub version: u32,
+    /// Translation options.
+    pub options: PrecompiledOptions,
+}
+
+/// Translation options of a backend that precompiled outputs depend on.
+///
+/// Resource bindings are sorted by group and binding.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PrecompiledOptions {
+    SpirV {
+        lang_version: (u8, u8),
+        /// Entry point of the output, or `None` for all of them.
+        entry_point: Option<EntryPointKey>,
+        adjust_coordinate_space: bool,
+        label_varyings: bool,
+        force_point_size: bool,
+        clamp_frag_depth: bool,
+        bounds_checks: BoundsChecksKey,
+        /// Sizes of the binding arrays, as `(group, binding, size)`.
+        binding_arrays: Vec<(u32, u32, Option<u32>)>,
+    },
+    Glsl {
+        version: u16,
+        embedded: bool,
+        webgl: bool,
+        entry_point: EntryPointKey,
+        multiview: Option<u32>,
+        adjust_coordinate_space: bool,
+        texture_shadow_lod: bool,
+        bounds_checks: BoundsChecksKey,
+        /// Slots of the resources, as `(group, binding, slot)`.
+        bindings: Vec<(u32, u32, u8)>,
+    },
+    Msl {
+        lang_version: (u8, u8),
+        allow_point_size: bool,
+        spirv_cross_compatibility: bool,
+        fake_missing_bindings: bool,
+        bounds_checks: BoundsChecksKey,
+        /// Resources of the vertex, fragment and compute stages.
+        stages: [MslStageKey; 3],
+    },
+    Hlsl {
+        /// Shader model, as `(major, minor)`.
+        shader_model: (u8, u8),
+        fake_missing_bindings: bool,
+        special_constants: Option<HlslBindingKey>,
+        bindings: Vec<HlslBindingKey>,
+    },
+}
+
+/// Entry point of a precompiled output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EntryPointKey {
+    /// Stage of the entry point, as the bits of its [`wgt::ShaderStages`].
+    pub stage: u32,
+    pub name: String,
+}
+
+/// Bounds checks of a precompiled output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BoundsChecksKey {
+    pub index: BoundsCheckKey,
+    pub buffer: BoundsCheckKey,
+    pub image: BoundsCheckKey,
+    pub binding_array: BoundsCheckKey,
+}
+
+/// Bounds check policy, see `naga::proc::BoundsCheckPolicy`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BoundsCheckKey {
+    Restrict,
+    ReadZeroSkipWrite,
+    Unchecked,
+}
+
+/// Resources of a stage of MSL outputs.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MslStageKey {
+    pub push_constant_buffer: Option<u8>,
+    pub sizes_buffer: Option<u8>,
+    pub resources: Vec<MslBindingKey>,
+}
+
+/// Slots of a resource of MSL outputs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MslBindingKey {
+    pub group: u32,
+    pub binding: u32,
+    pub buffer: Option<u8>,
+    pub texture: Option<u8>,
+    pub sampler: Option<u8>,
+    pub binding_array_size: Option<u32>,
+    pub mutable: bool,
+}
+
+/// Register of a resource of HLSL outputs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HlslBindingKey {
+    pub group: u32,
+    pub binding: u32,
+    pub space: u8,
+    pub register: u32,
+    pub binding_array_size: Option<u32>,
+}
+
+impl BoundsChecksKey {
+    fn accepts(&self, runtime: &Self) -> bool {
+        // outputs checking bounds are fine when the runtime doesn't need them to
+        let accepts = |precompiled, runtime| {
+            precompiled != BoundsCheckKey::Unchecked || runtime == BoundsCheckKey::Unchecked
+        };
+        accepts(self.index, runtime.index)
+            && accepts(self.buffer, runtime.buffer)
+            && accepts(self.image, runtime.image)
+            && accepts(self.binding_array, runtime.binding_array)
+    }
+}
+
+impl PrecompiledKey {
+    /// Version of the keys built by this crate.
+    pub const VERSION: u32 = 1;
+
+    fn new(options: PrecompiledOptions) -> Self {
+        Self {
+            version: Self::VERSION,
+            options,
+        }
+    }
+
+    /// Returns true if an output with this key can be used when translating with the
+    /// `runtime` options.
+    ///
+    /// Options have to be equal, except for those making the output more portable:
+    /// - bounds checks, when the runtime doesn't need them
+    /// - language versions older than the runtime one
+    /// - SPIR-V without varying labels, and GLSL without the shadow LOD extension
+    pub fn accepts(&self, runtime: &Self) -> bool {
+        if self.version != Self::VERSION || runtime.version != Self::VERSION {
+            return false;
+        }
+        match (&self.options, &runtime.options) {
+            (
+                &PrecompiledOptions::SpirV {
+                    lang_version,
+                    ref entry_point,
+                    adjust_coordinate_space,
+                    label_varyings,
+                    force_point_size,
+                    clamp_frag_depth,
+                    ref bounds_checks,
+                    ref binding_arrays,
+                },
+                &PrecompiledOptions::SpirV {
+                    lang_version: runtime_lang_version,
+                    entry_point: ref runtime_entry_point,
+                    adjust_coordinate_space: runtime_adjust_coordinate_space,
+                    label_varyings: runtime_label_varyings,
+                    force_point_size: runtime_force_point_size,
+                    clamp_frag_depth: runtime_clamp_frag_depth,
+                    bounds_checks: ref runtime_bounds_checks,
+                    binding_arrays: ref runtime_binding_arrays,
+                },
+            ) => {
+                lang_version == runtime_lang_version
+                    && entry_point == runtime_entry_point
+                    && adjust_coordinate_space == runtime_adjust_coordinate_space
+                    && (!label_varyings || runtime_label_varyings)
+                    && force_point_size == runtime_force_point_size
+                    && clamp_frag_depth == runtime_clamp_frag_depth
+                    && bounds_checks.accepts(runtime_bounds_checks)
+                    && binding_arrays == runtime_binding_arrays
+            }
+            (
+                &PrecompiledOptions::Glsl {
+                    version,
+                    embedded,
+                    webgl,
+                    ref entry_point,
+                    multiview,
+                    adjust_coordinate_space,
+                    texture_shadow_lod,
+                    ref bounds_checks,
+                    ref bindings,
+                },
+                &PrecompiledOptions::Glsl {
+                    version: runtime_version,
+                    embedded: runtime_embedded,
+                    webgl: runtime_webgl,
+                    entry_point: ref runtime_entry_point,
+                    multiview: runtime_multiview,
+                    adjust_coordinate_space: runtime_adjust_coordinate_space,
+                    texture_shadow_lod: runtime_texture_shadow_lod,
+                    bounds_checks: ref runtime_bounds_checks,
+                    bindings: ref runtime_bindings,
+                },
+            ) => {
+                version <= runtime_version
+                    && embedded == runtime_embedded
+                    && webgl == runtime_webgl
+                    && entry_point == runtime_entry_point
+                    && multiview == runtime_multiview
+                    && adjust_coordinate_space == runtime_adjust_coordinate_space
+                    && (!texture_shadow_lod || runtime_texture_shadow_lod)
+                    && bounds_checks.accepts(runtime_bounds_checks)
+                    && bindings == runtime_bindings
+            }
+            (
+                &PrecompiledOptions::Msl {
+                    lang_version,
+                    allow_point_size,
+                    spirv_cross_compatibility,
+                    fake_missing_bindings,
+                    ref bounds_checks,
+                    ref stages,
+                },
+                &PrecompiledOptions::Msl {
+                    lang_version: runtime_lang_version,
+                    allow_point_size: runtime_allow_point_size,
+                    spirv_cross_compatibility: runtime_spirv_cross_compatibility,
+                    fake_missing_bindings: runtime_fake_missing_bindings,
+                    bounds_checks: ref runtime_bounds_checks,
+                    stages: ref runtime_stages,
+                },
+            ) => {
+                lang_version <= runtime_lang_version
+                    && allow_point_size == runtime_allow_point_size
+                    && spirv_cross_compatibility == runtime_spirv_cross_compatibility
+                    && fake_missing_bindings == runtime_fake_missing_bindings
+                    && bounds_checks.accepts(runtime_bounds_checks)
+                    && stages == runtime_stages
+            }
+            (
+                &PrecompiledOptions::Hlsl {
+                    shader_model,
+                    fake_missing_bindings,
+                    ref special_constants,
+                    ref bindings,
+                },
+                &PrecompiledOptions::Hlsl {
+                    shader_model: runtime_shader_model,
+                    fake_missing_bindings: runtime_fake_missing_bindings,
+                    special_constants: ref runtime_special_constants,
+                    bindings: ref runtime_bindings,
+                },
+            ) => {
+                shader_model <= runtime_shader_model
+                    && fake_missing_bindings == runtime_fake_missing_bindings
+                    && special_constants == runtime_special_constants
+                    && bindings == runtime_bindings
+            }
+            _ => false,
+        }
+    }
+
+    /// Key of SPIR-V outputs, translated with `pipeline_options` if they are of a
+    /// single entry point.
+    ///
+    /// The capabilities of the options aren't part of the key, since outputs only
+    /// declare the capabilities they use.
+    #[cfg(any(feature = "vulkan", feature = "precompile"))]
+    pub fn spirv(
+        options: &naga::back::spv::Options,
+        pipeline_options: Option<&naga::back::spv::PipelineOptions>,
+    ) -> Self {
+        use naga::back::spv::WriterFlags as Wf;
+
+        Self::new(PrecompiledOptions::SpirV {
+            lang_version: options.lang_version,
+            entry_point: pipeline_options.map(|pipeline_options| EntryPointKey {
+                stage: crate::auxil::map_naga_stage(pipeline_options.shader_stage).bits(),
+                name: pipeline_options.entry_point.clone(),
+            }),
+            adjust_coordinate_space: options.flags.contains(Wf::ADJUST_COORDINATE_SPACE),
+            label_varyings: options.flags.contains(Wf::LABEL_VARYINGS),
+            force_point_size: options.flags.contains(Wf::FORCE_POINT_SIZE),
+            clamp_frag_depth: options.flags.contains(Wf::CLAMP_FRAG_DEPTH),
+            bounds_checks: bounds_checks_key(&options.bounds_check_policies),
+            binding_arrays: options
+                .binding_map
+                .iter()
+                .map(|(rb, info)| (rb.group, rb.binding, info.binding_array_size))
+                .collect(),
+        })
+    }
+
+    /// Key of GLSL outputs.
+    #[cfg(any(feature = "gles", feature = "precompile"))]
+    pub fn glsl(
+        options: &naga::back::glsl::Options,
+        pipeline_options: &naga::back::glsl::PipelineOptions,
+        policies: &naga::proc::BoundsCheckPolicies,
+    ) -> Self {
+        use naga::back::glsl::{Version, WriterFlags as Wf};
+
+        let (version, embedded, webgl) = match options.version {
+            Version::Desktop(version) => (version, false, false),
+            Version::Embedded { version, is_webgl } => (version, true, is_webgl),
+        };
+        Self::new(PrecompiledOptions::Glsl {
+            version,
+            embedded,
+            webgl,
+            entry_point: EntryPointKey {
+                stage: crate::auxil::map_naga_stage(pipeline_options.shader_stage).bits(),
+                name: pipeline_options.entry_point.clone(),
+            },
+            multiview: pipeline_options.multiview.map(std::num::NonZeroU32::get),
+            adjust_coordinate_space: options.writer_flags.contains(Wf::ADJUST_COORDINATE_SPACE),
+            texture_shadow_lod: options.writer_flags.contains(Wf::TEXTURE_SHADOW_LOD),
+            bounds_checks: bounds_checks_key(policies),
+            bindings: options
+                .binding_map
+                .iter()
+                .map(|(rb, &slot)| (rb.group, rb.binding, slot))
+                .collect(),
+        })
+    }
+
+    /// Key of MSL outputs.
+    ///
+    /// Inline samplers aren't part of the key, since the backend doesn't use them.
+    #[cfg(any(feature = "metal", feature = "precompile"))]
+    pub fn msl(
+        options: &naga::back::msl::Options,
+        pipeline_options: &naga::back::msl::PipelineOptions,
+    ) -> Self {
+        use naga::back::msl;
+
+        let stage = |resources: &msl::PerStageResources| MslStageKey {
+            push_constant_buffer: resources.push_constant_buffer,
+            sizes_buffer: resources.sizes_buffer,
+            resources: resources
+                .resources
+                .iter()
+                .map(|(rb, target)| MslBindingKey {
+                    group: rb.group,
+                    binding: rb.binding,
+                    buffer: target.buffer,
+                    texture: target.texture,
+                    sampler: match target.sampler {
+                        Some(msl::BindSamplerTarget::Resource(slot)) => Some(slot),
+                        _ => None,
+                    },
+                    binding_array_size: target.binding_array_size,
+                    mutable: target.mutable,
+                })
+                .collect(),
+        };
+        Self::new(PrecompiledOptions::Msl {
+            lang_version: options.lang_version,
+            allow_point_size: pipeline_options.allow_point_size,
+            spirv_cross_compatibility: options.spirv_cross_compatibility,
+            fake_missing_bindings: options.fake_missing_bindings,
+            bounds_checks: bounds_checks_key(&options.bounds_check_policies),
+            stages: [
+                stage(&options.per_stage_map.vs),
+                stage(&options.per_stage_map.fs),
+                stage(&options.per_stage_map.cs),
+            ],
+        })
+    }
+
+    /// Key of HLSL outputs.
+    #[cfg(any(feature = "dx11", feature = "dx12", feature = "precompile"))]
+    pub fn hlsl(options: &naga::back::hlsl::Options) -> Self {
+        use naga::back::hlsl;
+
+        let binding =
+            |rb: Option<&naga::ResourceBinding>, target: &hlsl::BindTarget| HlslBindingKey {
+                group: rb.map_or(0, |rb| rb.group),
+                binding: rb.map_or(0, |rb| rb.binding),
+                space: target.space,
+                register: target.register,
+                binding_array_size: target.binding_array_size,
+            };
+        Self::new(PrecompiledOptions::Hlsl {
+            shader_model: match options.shader_model {
+                hlsl::ShaderModel::V5_0 => (5, 0),
+                hlsl::ShaderModel::V5_1 => (5, 1),
+                hlsl::ShaderModel::V6_0 => (6, 0),
+            },
+            fake_missing_bindings: options.fake_missing_bindings,
+            special_constants: options
+                .special_constants_binding
+                .as_ref()
+                .map(|target| binding(None, target)),
+            bindings: options
+                .binding_map
+                .iter()
+                .map(|(rb, target)| binding(Some(rb), target))
+                .collect(),
+        })
+    }
+}
+
+#[cfg(any(
+    feature = "vulkan",
+    feature = "gles",
+    feature = "metal",
+    feature = "precompile"
+))]
+fn bounds_checks_key(policies: &naga::proc::BoundsCheckPolicies) -> BoundsChecksKey {
+    use naga::proc::BoundsCheckPolicy as Bcp;
+
+    let key = |policy| match policy {
+        Bcp::Restrict => BoundsCheckKey::Restrict,
+        Bcp::ReadZeroSkipWrite => BoundsCheckKey::ReadZeroSkipWrite,
+        Bcp::Unchecked => BoundsCheckKey::Unchecked,
+    };
+    BoundsChecksKey {
+        index: key(policies.index),
+        buffer: key(policies.buffer),
+        image: key(policies.image),
+        binding_array: key(policies.binding_array),
+    }
+}
+
+/// Sizes of the binding arrays of the bind group layouts, given as their sorted entries,
+/// as the Vulkan backend derives them.
+#[cfg(feature = "precompile")]
+pub fn spv_binding_map(
+    bind_group_layouts: &[&[wgt::BindGroupLayoutEntry]],
+) -> naga::back::spv::BindingMap {
+    let mut binding_map = naga::back::spv::BindingMap::default();
+    for (group, entries) in bind_group_layouts.iter().enumerate() {
+        for entry in entries.iter() {
+            if let Some(count) = entry.count {
+                binding_map.insert(
+                    naga::ResourceBinding {
+                        group: group as u32,
+                        binding: entry.binding,
+                    },
+                    naga::back::spv::BindingInfo {
+                        binding_array_size: Some(count.get()),
+                    },
+                );
+            }
+        }
+    }
+    binding_map
+}
+
+/// Slots of the resources of the bind group layouts, given as their sorted entries.
+///
+/// Each kind of resource has its own slots, assigned in order.
+#[cfg(any(feature = "gles", feature = "precompile"))]
+pub fn glsl_binding_map(
+    bind_group_layouts: &[&[wgt::BindGroupLayoutEntry]],
+) -> naga::back::glsl::BindingMap {
+    let mut num_samplers = 0u8;
+    let mut num_textures = 0u8;
+    let mut num_images = 0u8;
+    let mut num_uniform_buffers = 0u8;
+    let mut num_storage_buffers = 0u8;
+
+    let mut binding_map = naga::back::glsl::BindingMap::default();
+    for (group_index, entries) in bind_group_layouts.iter().enumerate() {
+        for entry in entries.iter() {
+            let counter = match entry.ty {
+                wgt::BindingType::Sampler { .. } => &mut num_samplers,
+                wgt::BindingType::Texture { .. } => &mut num_textures,
+                wgt::BindingType::StorageTexture { .. } => &mut num_images,
+                wgt::BindingType::Buffer {
+                    ty: wgt::BufferBindingType::Uniform,
+                    ..
+                } => &mut num_uniform_buffers,
+                wgt::BindingType::Buffer {
+                    ty: wgt::BufferBindingType::Storage { .. },
+                    ..
+                } => &mut num_storage_buffers,
+            };
+
+            let br = naga::ResourceBinding {
+                group: group_index as u32,
+                binding: entry.binding,
+            };
+            binding_map.insert(br, *counter);
+            *counter += entry.count.map_or(1, |c| c.get() as u8);
+        }
+    }
+    binding_map
+}
+
+/// Slots of the resources of the bind group layouts, given as their sorted entries, and
+/// of the push constants, for each stage.
+///
+/// Each stage has its own slots for each kind of resource, assigned in order after the
+/// push constant buffer. The buffer of the sizes of the storage buffers comes last.
+#[cfg(any(feature = "metal", feature = "precompile"))]
+pub fn msl_per_stage_map(
+    bind_group_layouts: &[&[wgt::BindGroupLayoutEntry]],
+    push_constant_ranges: &[wgt::PushConstantRange],
+) -> naga::back::msl::PerStageMap {
+    use naga::back::msl;
+
+    let stage_map = |stage: wgt::ShaderStages| {
+        let (mut buffers, mut textures, mut samplers) = (0u32, 0u32, 0u32);
+        let mut resources = msl::PerStageResources::default();
+
+        let pc_limit = push_constant_ranges
+            .iter()
+            .filter(|pcr| pcr.stages.contains(stage))
+            .map(|pcr| pcr.range.end / 4)
+            .max()
+            .unwrap_or(0);
+        if pc_limit != 0 {
+            resources.push_constant_buffer = Some(buffers as msl::Slot);
+            buffers += 1;
+        }
+
+        let mut sizes_count = 0;
+        for (group_index, entries) in bind_group_layouts.iter().enumerate() {
+            for entry in entries.iter() {
+                if !entry.visibility.contains(stage) {
+                    continue;
+                }
+
+                let mut target = msl::BindTarget::default();
+                let count = entry.count.map_or(1, std::num::NonZeroU32::get);
+                target.binding_array_size = entry.count.map(std::num::NonZeroU32::get);
+                match entry.ty {
+                    wgt::BindingType::Buffer { ty, .. } => {
+                        target.buffer = Some(buffers as msl::Slot);
+                        buffers += count;
+                        if let wgt::BufferBindingType::Storage { read_only } = ty {
+                            target.mutable = !read_only;
+                            sizes_count += 1;
+                        }
+                    }
+                    wgt::BindingType::Sampler { .. } => {
+                        target.sampler =
+                            Some(msl::BindSamplerTarget::Resource(samplers as msl::Slot));
+                        samplers += count;
+                    }
+                    wgt::BindingType::Texture { .. } => {
+                        target.texture = Some(textures as msl::Slot);
+                        textures += count;
+                    }
+                    wgt::BindingType::StorageTexture { access, .. } => {
+                        target.texture = Some(textures as msl::Slot);
+                        textures += count;
+                        target.mutable = match access {
+                            wgt::StorageTextureAccess::ReadOnly => false,
+                            wgt::StorageTextureAccess::WriteOnly => true,
+                            wgt::StorageTextureAccess::ReadWrite => true,
+                        };
+                    }
+                }
+
+                let br = naga::ResourceBinding {
+                    group: group_index as u32,
+                    binding: entry.binding,
+                };
+                resources.resources.insert(br, target);
+            }
+        }
+
+        if sizes_count != 0 {
+            resources.sizes_buffer = Some(buffers as msl::Slot);
+        }
+        resources
+    };
+
+    msl::PerStageMap {
+        vs: stage_map(wgt::ShaderStages::VERTEX),
+        fs: stage_map(wgt::ShaderStages::FRAGMENT),
+        cs: stage_map(wgt::ShaderStages::COMPUTE),
+    }
+}
+
+/// Registers of the resources of the bind group layouts, given as their sorted entries,
+/// and of the special constants if the `flags` need them.
+///
+/// Uniform buffers, read-only resources, writable resources and samplers have their own
+/// registers. The registers of each bind group are assigned to its resources in order,
+/// the buffers with dynamic offsets coming last.
+#[cfg(any(feature = "dx12", feature = "precompile"))]
+pub fn hlsl_options(
+    bind_group_layouts: &[&[wgt::BindGroupLayoutEntry]],
+    flags: crate::PipelineLayoutFlags,
+) -> naga::back::hlsl::Options {
+    use naga::back::hlsl;
+
+    let mut binding_map = hlsl::BindingMap::default();
+    let (mut bind_cbv, mut bind_srv, mut bind_uav, mut bind_sampler) = (
+        hlsl::BindTarget::default(),
+        hlsl::BindTarget::default(),
+        hlsl::BindTarget::default(),
+        hlsl::BindTarget::default(),
+    );
+
+    for (index, entries) in bind_group_layouts.iter().enumerate() {
+        let mut bind = |entry: &wgt::BindGroupLayoutEntry, bt: &mut hlsl::BindTarget| {
+            binding_map.insert(
+                naga::ResourceBinding {
+                    group: index as u32,
+                    binding: entry.binding,
+                },
+                hlsl::BindTarget {
+                    binding_array_size: entry.count.map(std::num::NonZeroU32::get),
+                    ..bt.clone()
+                },
+            );
+            bt.register += entry.count.map_or(1, std::num::NonZeroU32::get);
+        };
+
+        // descriptor table of the views
+        for entry in entries.iter() {
+            let bt = match entry.ty {
+                wgt::BindingType::Buffer {
+                    has_dynamic_offset: true,
+                    ..
+                }
+                | wgt::BindingType::Sampler { .. } => continue,
+                wgt::BindingType::Buffer {
+                    ty: wgt::BufferBindingType::Uniform,
+                    ..
+                } => &mut bind_cbv,
+                wgt::BindingType::Buffer {
+                    ty: wgt::BufferBindingType::Storage { read_only: true },
+                    ..
+                }
+                | wgt::BindingType::Texture { .. } => &mut bind_srv,
+                wgt::BindingType::Buffer {
+                    ty: wgt::BufferBindingType::Storage { read_only: false },
+                    ..
+                }
+                | wgt::BindingType::StorageTexture { .. } => &mut bind_uav,
+            };
+            bind(entry, bt);
+        }
+
+        // descriptor table of the samplers
+        for entry in entries.iter() {
+            if let wgt::BindingType::Sampler { .. } = entry.ty {
+                bind(entry, &mut bind_sampler);
+            }
+        }
+
+        // root descriptors of the buffers with dynamic offsets
+        for entry in entries.iter() {
+            let bt = match entry.ty {
+                wgt::BindingType::Buffer {
+                    has_dynamic_offset: true,
+                    ty,
+                    ..
+                } => match ty {
+                    wgt::BufferBindingType::Uniform => &mut bind_cbv,
+                    wgt::BufferBindingType::Storage { read_only: true } => &mut bind_srv,
+                    wgt::BufferBindingType::Storage { read_only: false } => &mut bind_uav,
+                },
+                _ => continue,
+            };
+            bind(entry, bt);
+        }
+    }
+
+    let special_constants_binding = if flags.intersects(
+        crate::PipelineLayoutFlags::BASE_VERTEX_INSTANCE
+            | crate::PipelineLayoutFlags::NUM_WORK_GROUPS,
+    ) {
+        Some(bind_cbv)
+    } else {
+        None
+    };
+
+    hlsl::Options {
+        shader_model: hlsl::ShaderModel::V5_1,
+        binding_map,
+        fake_missing_bindings: false,
+        special_constants_binding,
+    }
+}
@@ -671,16 +671,31 @@ impl super::Device {
                 } else {
                     &self.naga_options
                 };
-                let spv = {
-                    profiling::scope!("naga::spv::write_vec");
-                    naga::back::spv::write_vec(
-                        &naga_shader.module,
-                        &naga_shader.info,
-                        options,
-                        Some(&pipeline_options),
-                    )
-                }
-                .map_err(|e| crate::PipelineError::Linkage(stage_flags, format!("{}", e)))?;
+                let precompiled = naga_shader
+                    .find_precompiled(|| {
+                        crate::PrecompiledKey::spirv(options, Some(&pipeline_options))
+                    })
+                    .and_then(|code| match *code {
+                        crate::PrecompiledCode::SpirV(ref spv) => Some(spv),
+                        _ => None,
+                    });
+                let spv = match precompiled {
+                    Some(spv) => Cow::Borrowed(&spv[..]),
+                    None => {
+                        profiling::scope!("naga::spv::write_vec");
+                        Cow::Owned(
+                            naga::back::spv::write_vec(
+                                &naga_shader.module,
+                                &naga_shader.info,
+                                options,
+                                Some(&pipeline_options),
+                            )
+                            .map_err(|e| {
+                                crate::PipelineError::Linkage(stage_flags, format!("{}", e))
+                            })?,
+                        )
+                    }
+                };
                 self.create_shader_module_impl(&spv)?
             }
         };
@@ -1415,15 +1430,24 @@ impl crate::Device<super::Api> for super::Device {
                         binding_array: naga::proc::BoundsCheckPolicy::Unchecked,
                     };
                 }
-                Cow::Owned(
-                    naga::back::spv::write_vec(
-                        &naga_shader.module,
-                        &naga_shader.info,
-                        &naga_options,
-                        None,
-                    )
-                    .map_err(|e| crate::ShaderError::Compilation(format!("{}", e)))?,
-                )
+                let precompiled = naga_shader
+                    .find_precompiled(|| crate::PrecompiledKey::spirv(&naga_options, None))
+                    .and_then(|code| match *code {
+                        crate::PrecompiledCode::SpirV(ref spv) => Some(spv),
+                        _ => None,
+                    });
+                match precompiled {
+                    Some(spv) => Cow::Owned(spv.clone()),
+                    None => Cow::Owned(
+                        naga::back::spv::write_vec(
+                            &naga_shader.module,
+                            &naga_shader.info,
+                            &naga_options,
+                            None,
+                        )
+                        .map_err(|e| crate::ShaderError::Compilation(format!("{}", e)))?,
+                    ),
+                }
             }
             crate::ShaderInput::SpirV(spv) => Cow::Borrowed(spv),
         };
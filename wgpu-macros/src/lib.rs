@@ -0,0 +1,189 @@
+/*! Procedural macros of wgpu, re-exported by `wgpu` with the `macros` feature.
+ */
+
+#![warn(missing_docs)]
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Fields};
+
+mod vertex;
+mod wgsl;
+
+/// Derive `wgpu::util::WgslLayout` for a struct, laying out its fields with the
+/// WGSL memory layout rules.
+///
+/// Every field has to implement `WgslLayout`, and is placed at the next offset that is a
+/// multiple of its alignment. The struct is aligned to the largest alignment of its fields,
+/// and padded to a multiple of it.
+#[proc_macro_derive(WgslLayout)]
+pub fn derive_wgsl_layout(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match wgsl_layout(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+/// Derive `wgpu::util::VertexLayout` for a `#[repr(C)]` struct, with a vertex attribute
+/// per field.
+///
+/// The format of each field is given by its `wgpu::util::VertexAttributeType`, and fields
+/// are assigned consecutive shader locations starting at 0. Fields accept these
+/// attributes:
+///
+/// - `#[unorm]` and `#[snorm]` read arrays of 8 or 16 bit integers as normalized floats.
+/// - `#[vertex(location = N)]` sets the location of the field, the next fields following it.
+/// - `#[vertex(format = "Float16x2")]` overrides the format of the field.
+/// - `#[vertex(skip)]` leaves the field out of the attributes, like padding.
+#[proc_macro_derive(VertexLayout, attributes(unorm, snorm, vertex))]
+pub fn derive_vertex_layout(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match vertex::vertex_layout(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+/// Load a WGSL module statically like `wgpu::include_wgsl!`, parsing and validating it
+/// with naga at build time.
+///
+/// The path is relative to the directory of the manifest of the crate being built, and
+/// shader errors are reported as compile errors pointing to their location in the file:
+///
+/// ```ignore
+/// let descriptor = wgpu::include_wgsl_checked!("src/shader.wgsl");
+/// ```
+///
+/// The capabilities the shader may rely on are given by the names of the
+/// `naga::valid::Capabilities` flags, and default to none:
+///
+/// ```ignore
+/// let descriptor = wgpu::include_wgsl_checked!(
+///     "src/shader.wgsl",
+///     capabilities = [PUSH_CONSTANT, FLOAT64],
+/// );
+/// ```
+///
+/// Instead of a descriptor, the macro can also emit a module with the bindings of the
+/// shader:
+///
+/// ```ignore
+/// wgpu::include_wgsl_checked!(pub mod shader = "src/shader.wgsl");
+///
+/// let module = device.create_shader_module(shader::descriptor());
+/// let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+///     label: None,
+///     entries: shader::GROUP_0,
+/// });
+/// ```
+///
+/// The module has a `SOURCE` constant and a `descriptor()` function, and for each named
+/// resource of the shader a `wgpu::BindGroupLayoutEntry` constant with the upper snake
+/// case name of its variable, along with a `_GROUP` constant of its bind group. `GROUP_n`
+/// constants list the entries of each bind group. An entry is visible from the stages of
+/// the entry points that use its resource, storage bindings are read-only unless they're
+/// declared writable, and float textures are filterable.
+#[proc_macro]
+pub fn include_wgsl_checked(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as wgsl::Input);
+    match wgsl::include_wgsl_checked(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+fn wgsl_layout(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "WgslLayout can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "WgslLayout can only be derived for structs",
+            ))
+        }
+    };
+    if fields.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "WgslLayout can't be derived for structs without fields",
+        ));
+    }
+
+    let layout = quote!(::wgpu::util::WgslLayout);
+    let round_up = quote!(::wgpu::util::wgsl_round_up);
+    let names = fields
+        .iter()
+        .map(|field| field.ident.clone().unwrap())
+        .collect::<Vec<_>>();
+    let types = fields.iter().map(|field| &field.ty).collect::<Vec<_>>();
+    let offsets = (0..fields.len())
+        .map(|i| format_ident!("offset_{}", i))
+        .collect::<Vec<_>>();
+
+    // `let offset_i = ...;` for each field, placing it after the previous one
+    let mut compute_offsets = quote!(let offset_0: u64 = 0;);
+    for i in 1..fields.len() {
+        let (previous, previous_type) = (&offsets[i - 1], types[i - 1]);
+        let (offset, ty) = (&offsets[i], types[i]);
+        compute_offsets.extend(quote! {
+            let #offset: u64 = #round_up(
+                #previous + <#previous_type as #layout>::SIZE,
+                <#ty as #layout>::ALIGNMENT,
+            );
+        });
+    }
+    let (last, last_type) = (offsets.last().unwrap(), types.last().unwrap());
+    let member_names = names.iter().map(|name| name.to_string());
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #layout for #ident #ty_generics #where_clause {
+            const ALIGNMENT: u64 = {
+                let alignment = 1;
+                #(let alignment = ::wgpu::util::wgsl_max(alignment, <#types as #layout>::ALIGNMENT);)*
+                alignment
+            };
+            const SIZE: u64 = {
+                #compute_offsets
+                #round_up(#last + <#last_type as #layout>::SIZE, Self::ALIGNMENT)
+            };
+
+            fn write_bytes(&self, bytes: &mut [u8]) {
+                #compute_offsets
+                #(#layout::write_bytes(&self.#names, &mut bytes[#offsets as usize..]);)*
+            }
+
+            fn read_bytes(bytes: &[u8]) -> Self {
+                #compute_offsets
+                Self {
+                    #(#names: #layout::read_bytes(&bytes[#offsets as usize..]),)*
+                }
+            }
+
+            fn layout() -> ::wgpu::util::WgslTypeLayout {
+                #compute_offsets
+                ::wgpu::util::WgslTypeLayout::Struct {
+                    size: <Self as #layout>::SIZE,
+                    members: vec![
+                        #(::wgpu::util::WgslMemberLayout {
+                            name: #member_names,
+                            offset: #offsets,
+                            layout: <#types as #layout>::layout(),
+                        },)*
+                    ],
+                }
+            }
+        }
+    })
+}
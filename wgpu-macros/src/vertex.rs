@@ -0,0 +1,210 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{Data, DeriveInput, Fields, Ident, Lit, Meta, NestedMeta};
+
+/// Attributes of a field of a `VertexLayout` struct.
+#[derive(Default)]
+struct FieldOptions {
+    unorm: bool,
+    snorm: bool,
+    skip: bool,
+    location: Option<u32>,
+    format: Option<Ident>,
+}
+
+fn field_options(field: &syn::Field) -> syn::Result<FieldOptions> {
+    let mut options = FieldOptions::default();
+    for attribute in field.attrs.iter() {
+        if attribute.path.is_ident("unorm") || attribute.path.is_ident("snorm") {
+            if !attribute.tokens.is_empty() {
+                return Err(syn::Error::new_spanned(attribute, "Expected no arguments"));
+            }
+            if attribute.path.is_ident("unorm") {
+                options.unorm = true;
+            } else {
+                options.snorm = true;
+            }
+        } else if attribute.path.is_ident("vertex") {
+            let list = match attribute.parse_meta()? {
+                Meta::List(list) => list,
+                meta => {
+                    return Err(syn::Error::new_spanned(
+                        meta,
+                        "Expected #[vertex(location = N)], #[vertex(format = \"F\")] or #[vertex(skip)]",
+                    ))
+                }
+            };
+            for nested in list.nested.iter() {
+                match *nested {
+                    NestedMeta::Meta(Meta::Path(ref path)) if path.is_ident("skip") => {
+                        options.skip = true;
+                    }
+                    NestedMeta::Meta(Meta::NameValue(ref pair))
+                        if pair.path.is_ident("location") =>
+                    {
+                        match pair.lit {
+                            Lit::Int(ref location) => {
+                                options.location = Some(location.base10_parse()?)
+                            }
+                            ref lit => {
+                                return Err(syn::Error::new_spanned(lit, "Expected a location"))
+                            }
+                        }
+                    }
+                    NestedMeta::Meta(Meta::NameValue(ref pair)) if pair.path.is_ident("format") => {
+                        match pair.lit {
+                            Lit::Str(ref format) => {
+                                options.format = Some(Ident::new(&format.value(), format.span()))
+                            }
+                            ref lit => {
+                                return Err(syn::Error::new_spanned(lit, "Expected a format name"))
+                            }
+                        }
+                    }
+                    ref nested => {
+                        return Err(syn::Error::new_spanned(
+                            nested,
+                            "Expected location = N, format = \"F\" or skip",
+                        ))
+                    }
+                }
+            }
+        }
+    }
+    if [options.unorm, options.snorm, options.format.is_some()]
+        .iter()
+        .filter(|&&set| set)
+        .count()
+        > 1
+    {
+        return Err(syn::Error::new_spanned(
+            field,
+            "Only one of #[unorm], #[snorm] and #[vertex(format)] can be used",
+        ));
+    }
+    Ok(options)
+}
+
+/// Whether the struct has the C representation, and isn't packed.
+fn is_repr_c(input: &DeriveInput) -> bool {
+    let mut repr_c = false;
+    for attribute in input
+        .attrs
+        .iter()
+        .filter(|attribute| attribute.path.is_ident("repr"))
+    {
+        if let Ok(Meta::List(list)) = attribute.parse_meta() {
+            for nested in list.nested.iter() {
+                match *nested {
+                    NestedMeta::Meta(Meta::Path(ref path)) if path.is_ident("C") => repr_c = true,
+                    NestedMeta::Meta(ref meta) if meta.path().is_ident("packed") => return false,
+                    _ => {}
+                }
+            }
+        }
+    }
+    repr_c
+}
+
+pub fn vertex_layout(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let fields = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => &fields.named,
+            Fields::Unnamed(ref fields) => &fields.unnamed,
+            Fields::Unit => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "VertexLayout can't be derived for structs without fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "VertexLayout can only be derived for structs",
+            ))
+        }
+    };
+    if fields.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "VertexLayout can't be derived for structs without fields",
+        ));
+    }
+    if !is_repr_c(input) {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "VertexLayout can only be derived for #[repr(C)] structs",
+        ));
+    }
+
+    let attribute_type = quote!(::wgpu::util::VertexAttributeType);
+    let types = fields.iter().map(|field| &field.ty).collect::<Vec<_>>();
+    let offsets = (0..fields.len())
+        .map(|i| format_ident!("offset_{}", i))
+        .collect::<Vec<_>>();
+
+    // `let offset_i = ...;` for each field, placing it after the previous one like
+    // the C representation does
+    let mut compute_offsets = quote!(let offset_0: usize = 0;);
+    for i in 1..fields.len() {
+        let (previous, previous_type) = (&offsets[i - 1], types[i - 1]);
+        let (offset, ty) = (&offsets[i], types[i]);
+        compute_offsets.extend(quote! {
+            let #offset: usize = ::wgpu::util::vertex_round_up(
+                #previous + ::std::mem::size_of::<#previous_type>(),
+                ::std::mem::align_of::<#ty>(),
+            );
+        });
+    }
+
+    let mut location = 0u32;
+    let mut attributes = Vec::new();
+    let mut checks = Vec::new();
+    for (i, field) in fields.iter().enumerate() {
+        let options = field_options(field)?;
+        if options.skip {
+            continue;
+        }
+        let (ty, offset) = (types[i], &offsets[i]);
+        location = options.location.unwrap_or(location);
+        let format = if options.unorm {
+            quote!(::wgpu::util::vertex_normalized_format(<#ty as #attribute_type>::UNORM))
+        } else if options.snorm {
+            quote!(::wgpu::util::vertex_normalized_format(<#ty as #attribute_type>::SNORM))
+        } else if let Some(ref format) = options.format {
+            checks.push(quote! {
+                assert!(
+                    ::wgpu::VertexFormat::#format.size() <= ::std::mem::size_of::<#ty>() as u64,
+                    "The format is larger than the field type",
+                );
+            });
+            quote!(::wgpu::VertexFormat::#format)
+        } else {
+            quote!(<#ty as #attribute_type>::FORMAT)
+        };
+        attributes.push(quote! {
+            ::wgpu::VertexAttribute {
+                format: #format,
+                offset: #offset as u64,
+                shader_location: #location,
+            }
+        });
+        location += 1;
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let unused = Ident::new("unused_variables", Span::call_site());
+    Ok(quote! {
+        impl #impl_generics ::wgpu::util::VertexLayout for #ident #ty_generics #where_clause {
+            #[allow(#unused)]
+            const ATTRIBUTES: &'static [::wgpu::VertexAttribute] = {
+                #compute_offsets
+                #(#checks)*
+                &[#(#attributes,)*]
+            };
+            const STRIDE: ::wgpu::BufferAddress = ::std::mem::size_of::<Self>() as u64;
+        }
+    })
+}
@@ -0,0 +1,387 @@
+//! Compile time validation of WGSL shaders with naga.
+
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use std::{error::Error, fmt::Write, path::PathBuf};
+use syn::{
+    bracketed,
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    Ident, LitStr, Token, Visibility,
+};
+
+/// Input of `include_wgsl_checked!`.
+pub struct Input {
+    /// Visibility and name of the module to emit the bindings in.
+    module: Option<(Visibility, Ident)>,
+    path: LitStr,
+    capabilities: Vec<Ident>,
+}
+
+impl Parse for Input {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let module = if input.peek(LitStr) {
+            None
+        } else {
+            let visibility = input.parse::<Visibility>()?;
+            input.parse::<Token![mod]>()?;
+            let name = input.parse::<Ident>()?;
+            input.parse::<Token![=]>()?;
+            Some((visibility, name))
+        };
+        let path = input.parse::<LitStr>()?;
+
+        let mut capabilities = Vec::new();
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            let key = input.parse::<Ident>()?;
+            if key != "capabilities" {
+                return Err(syn::Error::new_spanned(
+                    key,
+                    "expected `capabilities = [...]`",
+                ));
+            }
+            input.parse::<Token![=]>()?;
+            let content;
+            bracketed!(content in input);
+            capabilities.extend(Punctuated::<Ident, Token![,]>::parse_terminated(&content)?);
+        }
+
+        Ok(Input {
+            module,
+            path,
+            capabilities,
+        })
+    }
+}
+
+fn capability(ident: &Ident) -> syn::Result<naga::valid::Capabilities> {
+    use naga::valid::Capabilities as C;
+
+    Ok(match ident.to_string().as_str() {
+        "PUSH_CONSTANT" => C::PUSH_CONSTANT,
+        "FLOAT64" => C::FLOAT64,
+        "PRIMITIVE_INDEX" => C::PRIMITIVE_INDEX,
+        "SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING" => {
+            C::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING
+        }
+        "UNIFORM_BUFFER_AND_STORAGE_TEXTURE_ARRAY_NON_UNIFORM_INDEXING" => {
+            C::UNIFORM_BUFFER_AND_STORAGE_TEXTURE_ARRAY_NON_UNIFORM_INDEXING
+        }
+        "SAMPLER_NON_UNIFORM_INDEXING" => C::SAMPLER_NON_UNIFORM_INDEXING,
+        "CLIP_DISTANCE" => C::CLIP_DISTANCE,
+        "CULL_DISTANCE" => C::CULL_DISTANCE,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                ident,
+                format!("unknown shader capability `{}`", ident),
+            ))
+        }
+    })
+}
+
+/// Format a naga error like rustc does, as `path:line:column: message` followed by the
+/// offending line of the shader.
+fn shader_error(
+    path: &str,
+    source: &str,
+    location: Option<naga::SourceLocation>,
+    message: String,
+) -> String {
+    let location = match location {
+        Some(location) => location,
+        None => return format!("{}: {}", path, message),
+    };
+    let line = source
+        .lines()
+        .nth(location.line_number as usize - 1)
+        .unwrap_or_default();
+    format!(
+        "{}:{}:{}: {}\n{}\n{}^",
+        path,
+        location.line_number,
+        location.line_position,
+        message,
+        line,
+        " ".repeat(location.line_position as usize - 1),
+    )
+}
+
+/// Convert `camelCase` and `snake_case` names to `UPPER_SNAKE_CASE`.
+fn constant_name(name: &str) -> String {
+    let mut constant = String::with_capacity(name.len() + 4);
+    let mut previous_lowercase = false;
+    for c in name.chars() {
+        if c.is_uppercase() && previous_lowercase {
+            constant.push('_');
+        }
+        previous_lowercase = c.is_lowercase() || c.is_ascii_digit();
+        constant.extend(c.to_uppercase());
+    }
+    constant
+}
+
+/// `wgpu::BindingType` of a resource, and its count for binding arrays.
+fn binding_type(
+    module: &naga::Module,
+    var: &naga::GlobalVariable,
+) -> Result<(TokenStream2, TokenStream2), String> {
+    let (ty, count) = match module.types[var.ty].inner {
+        naga::TypeInner::BindingArray { base, size } => {
+            let count = match size {
+                naga::ArraySize::Constant(constant) => match module.constants[constant].inner {
+                    naga::ConstantInner::Scalar {
+                        value: naga::ScalarValue::Uint(count),
+                        ..
+                    } => count as u32,
+                    naga::ConstantInner::Scalar {
+                        value: naga::ScalarValue::Sint(count),
+                        ..
+                    } => count as u32,
+                    _ => 0,
+                },
+                naga::ArraySize::Dynamic => 0,
+            };
+            (
+                &module.types[base].inner,
+                quote!(::core::num::NonZeroU32::new(#count)),
+            )
+        }
+        ref ty => (ty, quote!(None)),
+    };
+
+    let binding_type = match *ty {
+        naga::TypeInner::Image {
+            dim,
+            arrayed,
+            class,
+        } => {
+            let view_dimension = match dim {
+                naga::ImageDimension::D1 => quote!(D1),
+                naga::ImageDimension::D2 if arrayed => quote!(D2Array),
+                naga::ImageDimension::D2 => quote!(D2),
+                naga::ImageDimension::D3 => quote!(D3),
+                naga::ImageDimension::Cube if arrayed => quote!(CubeArray),
+                naga::ImageDimension::Cube => quote!(Cube),
+            };
+            let view_dimension = quote!(::wgpu::TextureViewDimension::#view_dimension);
+            match class {
+                naga::ImageClass::Sampled { kind, multi } => {
+                    let sample_type = match kind {
+                        naga::ScalarKind::Float => quote!(Float { filterable: true }),
+                        naga::ScalarKind::Sint => quote!(Sint),
+                        naga::ScalarKind::Uint => quote!(Uint),
+                        naga::ScalarKind::Bool => unreachable!(),
+                    };
+                    quote!(::wgpu::BindingType::Texture {
+                        sample_type: ::wgpu::TextureSampleType::#sample_type,
+                        view_dimension: #view_dimension,
+                        multisampled: #multi,
+                    })
+                }
+                naga::ImageClass::Depth { multi } => quote!(::wgpu::BindingType::Texture {
+                    sample_type: ::wgpu::TextureSampleType::Depth,
+                    view_dimension: #view_dimension,
+                    multisampled: #multi,
+                }),
+                naga::ImageClass::Storage { format, access } => {
+                    let access = if !access.contains(naga::StorageAccess::LOAD) {
+                        quote!(WriteOnly)
+                    } else if access.contains(naga::StorageAccess::STORE) {
+                        quote!(ReadWrite)
+                    } else {
+                        quote!(ReadOnly)
+                    };
+                    // naga names its storage formats like wgpu
+                    let format = Ident::new(&format!("{:?}", format), Span::call_site());
+                    quote!(::wgpu::BindingType::StorageTexture {
+                        access: ::wgpu::StorageTextureAccess::#access,
+                        format: ::wgpu::TextureFormat::#format,
+                        view_dimension: #view_dimension,
+                    })
+                }
+            }
+        }
+        naga::TypeInner::Sampler { comparison } => {
+            if comparison {
+                quote!(::wgpu::BindingType::Sampler(
+                    ::wgpu::SamplerBindingType::Comparison
+                ))
+            } else {
+                quote!(::wgpu::BindingType::Sampler(
+                    ::wgpu::SamplerBindingType::Filtering
+                ))
+            }
+        }
+        ref other => {
+            let ty = match var.space {
+                naga::AddressSpace::Uniform => quote!(Uniform),
+                naga::AddressSpace::Storage { access } => {
+                    let read_only = !access.contains(naga::StorageAccess::STORE);
+                    quote!(Storage {
+                        read_only: #read_only
+                    })
+                }
+                space => return Err(format!("unexpected address space {:?}", space)),
+            };
+            // runtime sized arrays need at least one element
+            let size = match *other {
+                naga::TypeInner::Array { stride, .. } => stride as u64,
+                ref other => other.size(&module.constants) as u64,
+            };
+            quote!(::wgpu::BindingType::Buffer {
+                ty: ::wgpu::BufferBindingType::#ty,
+                has_dynamic_offset: false,
+                min_binding_size: ::core::num::NonZeroU64::new(#size),
+            })
+        }
+    };
+    Ok((binding_type, count))
+}
+
+/// Constants of the bind group layout entries of the resources of a module.
+fn binding_constants(
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+    span: Span,
+) -> Result<TokenStream2, String> {
+    let mut constants = TokenStream2::new();
+    let mut groups = Vec::<(u32, Vec<(u32, Ident)>)>::new();
+    for (handle, var) in module.global_variables.iter() {
+        let (binding, name) = match (var.binding.as_ref(), var.name.as_ref()) {
+            (Some(binding), Some(name)) => (binding, name),
+            _ => continue,
+        };
+        let (ty, count) = binding_type(module, var)?;
+
+        let mut visibility = 0u32;
+        for (index, entry_point) in module.entry_points.iter().enumerate() {
+            if !info.get_entry_point(index)[handle].is_empty() {
+                visibility |= match entry_point.stage {
+                    naga::ShaderStage::Vertex => 1,
+                    naga::ShaderStage::Fragment => 2,
+                    naga::ShaderStage::Compute => 4,
+                };
+            }
+        }
+
+        let constant = constant_name(name);
+        let ident = Ident::new(&constant, span);
+        let group_ident = format_ident!("{}_GROUP", constant, span = span);
+        let (group, index) = (binding.group, binding.binding);
+        let entry_doc = format!(
+            "Layout entry of `{}`, at binding {} of group {}.",
+            name, index, group
+        );
+        let group_doc = format!("Bind group of `{}`.", name);
+        constants.extend(quote! {
+            #[doc = #entry_doc]
+            pub const #ident: ::wgpu::BindGroupLayoutEntry = ::wgpu::BindGroupLayoutEntry {
+                binding: #index,
+                visibility: ::wgpu::ShaderStages::from_bits_truncate(#visibility),
+                ty: #ty,
+                count: #count,
+            };
+            #[doc = #group_doc]
+            pub const #group_ident: u32 = #group;
+        });
+
+        match groups.iter_mut().find(|(index, _)| *index == group) {
+            Some((_, entries)) => entries.push((index, ident)),
+            None => groups.push((group, vec![(index, ident)])),
+        }
+    }
+
+    for (group, mut entries) in groups {
+        entries.sort_by_key(|&(binding, _)| binding);
+        let entries = entries.into_iter().map(|(_, ident)| ident);
+        let ident = format_ident!("GROUP_{}", group, span = span);
+        let doc = format!("Layout entries of bind group {}, sorted by binding.", group);
+        constants.extend(quote! {
+            #[doc = #doc]
+            pub const #ident: &[::wgpu::BindGroupLayoutEntry] = &[#(#entries),*];
+        });
+    }
+    Ok(constants)
+}
+
+pub fn include_wgsl_checked(input: &Input) -> syn::Result<TokenStream2> {
+    let mut capabilities = naga::valid::Capabilities::empty();
+    for ident in &input.capabilities {
+        capabilities |= capability(ident)?;
+    }
+
+    let path = input.path.value();
+    let manifest_dir = std::env::var_os("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_default();
+    let full_path = manifest_dir.join(&path);
+    let source = std::fs::read_to_string(&full_path).map_err(|error| {
+        syn::Error::new_spanned(
+            &input.path,
+            format!("couldn't read {}: {}", full_path.display(), error),
+        )
+    })?;
+
+    let module = naga::front::wgsl::parse_str(&source).map_err(|error| {
+        let message = shader_error(
+            &path,
+            &source,
+            error.location(&source),
+            error.message().to_string(),
+        );
+        syn::Error::new_spanned(&input.path, message)
+    })?;
+    let info = naga::valid::Validator::new(naga::valid::ValidationFlags::all(), capabilities)
+        .validate(&module)
+        .map_err(|error| {
+            let mut message = error.as_inner().to_string();
+            let mut source_error = error.as_inner().source();
+            while let Some(inner) = source_error {
+                let _ = write!(message, ": {}", inner);
+                source_error = inner.source();
+            }
+            let message = shader_error(&path, &source, error.location(&source), message);
+            syn::Error::new_spanned(&input.path, message)
+        })?;
+
+    // including the file makes cargo rebuild the crate when it changes
+    let full_path = full_path.to_string_lossy();
+    let (visibility, name) = match input.module {
+        Some((ref visibility, ref name)) => (visibility, name),
+        None => {
+            return Ok(quote! {
+                ::wgpu::ShaderModuleDescriptor {
+                    label: Some(#path),
+                    source: ::wgpu::ShaderSource::Wgsl(::std::borrow::Cow::Borrowed(
+                        include_str!(#full_path)
+                    )),
+                }
+            })
+        }
+    };
+    let constants = binding_constants(&module, &info, input.path.span())
+        .map_err(|message| syn::Error::new_spanned(&input.path, message))?;
+    let doc = format!("Bindings of the `{}` shader.", path);
+    Ok(quote! {
+        #[doc = #doc]
+        #[allow(dead_code)]
+        #visibility mod #name {
+            /// Source of the shader.
+            pub const SOURCE: &str = include_str!(#full_path);
+
+            /// Descriptor of the shader module.
+            pub fn descriptor() -> ::wgpu::ShaderModuleDescriptor<'static> {
+                ::wgpu::ShaderModuleDescriptor {
+                    label: Some(#path),
+                    source: ::wgpu::ShaderSource::Wgsl(::std::borrow::Cow::Borrowed(SOURCE)),
+                }
+            }
+
+            #constants
+        }
+    })
+}
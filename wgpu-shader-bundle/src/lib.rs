@@ -0,0 +1,341 @@
+/*! Ahead of time translation of shaders, into bundles loaded with
+ *  `wgpu::ShaderSource::Bundle`.
+ *
+ *  A bundle holds the naga IR of a module, so that loading it doesn't parse any source,
+ *  and the outputs of naga backends. When a backend translates the module with options
+ *  accepted by the key of one of its outputs, that output is used instead.
+ *  The options of the backends are derived from the adapter and the pipeline layout,
+ *  so the outputs are translated here for a given [`Layout`], with the options the
+ *  backends of `wgpu-hal` use on any adapter supporting the target.
+ */
+
+#![warn(missing_docs)]
+
+use naga::back::{glsl, hlsl, msl, spv};
+use std::{error, fmt};
+
+pub use hal::{PrecompiledCode, PrecompiledKey, PrecompiledShader};
+pub use naga;
+pub use wgc::pipeline::{ShaderBundle, ShaderBundleError};
+pub use wgt;
+
+/// Pipeline layout the outputs are translated for.
+///
+/// Outputs are only used by pipelines with an equivalent layout, including the layouts
+/// derived from the module.
+#[derive(Clone, Debug, Default, serde::Deserialize)]
+#[serde(default)]
+pub struct Layout {
+    /// Entries of the bind group layouts, in the order of the groups.
+    pub bind_group_layouts: Vec<Vec<wgt::BindGroupLayoutEntry>>,
+    /// Push constant ranges.
+    pub push_constant_ranges: Vec<wgt::PushConstantRange>,
+}
+
+impl Layout {
+    /// Entries of the bind group layouts sorted by binding, like `wgpu-core` gives them
+    /// to the backends.
+    fn sorted_entries(&self) -> Vec<Vec<wgt::BindGroupLayoutEntry>> {
+        self.bind_group_layouts
+            .iter()
+            .map(|entries| {
+                let mut entries = entries.clone();
+                entries.sort_by_key(|entry| entry.binding);
+                entries
+            })
+            .collect()
+    }
+}
+
+/// Backend output to precompile.
+#[derive(Clone, Debug)]
+pub enum Target {
+    /// SPIR-V of each entry point, for Vulkan.
+    SpirV,
+    /// GLSL of each entry point, for GL.
+    Glsl {
+        /// Version of the output, used on contexts supporting it.
+        version: glsl::Version,
+    },
+    /// MSL of the module, for Metal.
+    ///
+    /// Pipelines drawing points translate the module themselves.
+    Msl {
+        /// Language version of the output, used on devices supporting it.
+        lang_version: (u8, u8),
+    },
+    /// HLSL of the module, for DX12.
+    Hlsl {
+        /// Shader model of the output, used on devices supporting it.
+        shader_model: hlsl::ShaderModel,
+    },
+}
+
+impl Target {
+    fn name(&self) -> &'static str {
+        match *self {
+            Target::SpirV => "SPIR-V",
+            Target::Glsl { .. } => "GLSL",
+            Target::Msl { .. } => "MSL",
+            Target::Hlsl { .. } => "HLSL",
+        }
+    }
+
+    fn translate(
+        &self,
+        module: &naga::Module,
+        info: &naga::valid::ModuleInfo,
+        bind_group_layouts: &[&[wgt::BindGroupLayoutEntry]],
+        push_constant_ranges: &[wgt::PushConstantRange],
+    ) -> Result<Vec<PrecompiledShader>, String> {
+        use naga::proc::{BoundsCheckPolicies, BoundsCheckPolicy as Bcp};
+
+        // the options have to be built like the backends of wgpu-hal do
+        Ok(match *self {
+            Target::SpirV => {
+                // the bounds checks needed without robust access
+                let options = spv::Options {
+                    lang_version: (1, 0),
+                    flags: spv::WriterFlags::FORCE_POINT_SIZE,
+                    binding_map: hal::precompiled::spv_binding_map(bind_group_layouts),
+                    capabilities: None,
+                    bounds_check_policies: BoundsCheckPolicies {
+                        index: Bcp::Restrict,
+                        buffer: Bcp::Restrict,
+                        image: Bcp::Restrict,
+                        binding_array: Bcp::Unchecked,
+                    },
+                };
+                module
+                    .entry_points
+                    .iter()
+                    .map(|entry_point| {
+                        let pipeline_options = spv::PipelineOptions {
+                            shader_stage: entry_point.stage,
+                            entry_point: entry_point.name.clone(),
+                        };
+                        let spv = spv::write_vec(module, info, &options, Some(&pipeline_options))
+                            .map_err(|e| e.to_string())?;
+                        Ok(PrecompiledShader {
+                            key: PrecompiledKey::spirv(&options, Some(&pipeline_options)),
+                            code: PrecompiledCode::SpirV(spv),
+                        })
+                    })
+                    .collect::<Result<_, String>>()?
+            }
+            Target::Glsl { version } => {
+                let options = glsl::Options {
+                    version,
+                    writer_flags: glsl::WriterFlags::ADJUST_COORDINATE_SPACE,
+                    binding_map: hal::precompiled::glsl_binding_map(bind_group_layouts),
+                };
+                // image bounds checks are only available on desktop contexts
+                let policies = BoundsCheckPolicies {
+                    index: Bcp::Unchecked,
+                    buffer: Bcp::Unchecked,
+                    image: match version {
+                        glsl::Version::Desktop(_) => Bcp::ReadZeroSkipWrite,
+                        glsl::Version::Embedded { .. } => Bcp::Unchecked,
+                    },
+                    binding_array: Bcp::Unchecked,
+                };
+                module
+                    .entry_points
+                    .iter()
+                    .map(|entry_point| {
+                        let pipeline_options = glsl::PipelineOptions {
+                            shader_stage: entry_point.stage,
+                            entry_point: entry_point.name.clone(),
+                            multiview: None,
+                        };
+                        translate_glsl(module, info, &options, &pipeline_options, policies)
+                    })
+                    .collect::<Result<_, String>>()?
+            }
+            Target::Msl { lang_version } => {
+                let options = msl::Options {
+                    lang_version,
+                    per_stage_map: hal::precompiled::msl_per_stage_map(
+                        bind_group_layouts,
+                        push_constant_ranges,
+                    ),
+                    inline_samplers: Vec::new(),
+                    spirv_cross_compatibility: false,
+                    fake_missing_bindings: false,
+                    bounds_check_policies: BoundsCheckPolicies {
+                        index: Bcp::ReadZeroSkipWrite,
+                        buffer: Bcp::ReadZeroSkipWrite,
+                        image: Bcp::ReadZeroSkipWrite,
+                        binding_array: Bcp::Unchecked,
+                    },
+                };
+                let pipeline_options = msl::PipelineOptions {
+                    allow_point_size: false,
+                };
+                let (source, info) = msl::write_string(module, info, &options, &pipeline_options)
+                    .map_err(|e| e.to_string())?;
+                vec![PrecompiledShader {
+                    key: PrecompiledKey::msl(&options, &pipeline_options),
+                    code: PrecompiledCode::Msl {
+                        source,
+                        entry_point_names: info
+                            .entry_point_names
+                            .into_iter()
+                            .map(Result::ok)
+                            .collect(),
+                    },
+                }]
+            }
+            Target::Hlsl { shader_model } => {
+                let options = hlsl::Options {
+                    shader_model,
+                    ..hal::precompiled::hlsl_options(
+                        bind_group_layouts,
+                        hal::PipelineLayoutFlags::BASE_VERTEX_INSTANCE,
+                    )
+                };
+                let mut source = String::new();
+                let reflection_info = hlsl::Writer::new(&mut source, &options)
+                    .write(module, info)
+                    .map_err(|e| e.to_string())?;
+                vec![PrecompiledShader {
+                    key: PrecompiledKey::hlsl(&options),
+                    code: PrecompiledCode::Hlsl {
+                        source,
+                        entry_point_names: reflection_info
+                            .entry_point_names
+                            .into_iter()
+                            .map(Result::ok)
+                            .collect(),
+                    },
+                }]
+            }
+        })
+    }
+}
+
+fn translate_glsl(
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+    options: &glsl::Options,
+    pipeline_options: &glsl::PipelineOptions,
+    policies: naga::proc::BoundsCheckPolicies,
+) -> Result<PrecompiledShader, String> {
+    let mut source = String::new();
+    let reflection_info = glsl::Writer::new(
+        &mut source,
+        module,
+        info,
+        options,
+        pipeline_options,
+        policies,
+    )
+    .and_then(|mut writer| writer.write())
+    .map_err(|e| e.to_string())?;
+
+    let mut texture_mapping = reflection_info
+        .texture_mapping
+        .into_iter()
+        .map(|(name, mapping)| {
+            (
+                name,
+                mapping.texture.index() as u32,
+                mapping.sampler.map(|sampler| sampler.index() as u32),
+            )
+        })
+        .collect::<Vec<_>>();
+    texture_mapping.sort();
+    let mut uniforms = reflection_info
+        .uniforms
+        .into_iter()
+        .map(|(handle, name)| (handle.index() as u32, name))
+        .collect::<Vec<_>>();
+    uniforms.sort();
+    Ok(PrecompiledShader {
+        key: PrecompiledKey::glsl(options, pipeline_options, &policies),
+        code: PrecompiledCode::Glsl {
+            source,
+            texture_mapping,
+            uniforms,
+        },
+    })
+}
+
+/// Error returned when a bundle can't be compiled.
+#[derive(Clone, Debug)]
+pub enum BundleError {
+    /// The WGSL source doesn't parse.
+    Parsing(String),
+    /// The module isn't valid with the given capabilities.
+    Validation(String),
+    /// A backend failed to translate the module.
+    Translation {
+        /// Name of the backend output.
+        target: &'static str,
+        /// Error of the backend.
+        message: String,
+    },
+}
+
+impl fmt::Display for BundleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            BundleError::Parsing(ref message) => write!(f, "Parsing failed: {}", message),
+            BundleError::Validation(ref message) => write!(f, "Validation failed: {}", message),
+            BundleError::Translation {
+                target,
+                ref message,
+            } => write!(f, "{} translation failed: {}", target, message),
+        }
+    }
+}
+
+impl error::Error for BundleError {}
+
+/// Compile a bundle of the WGSL `source`, validated with the shader `capabilities`,
+/// with the outputs of each of the `targets` for pipelines of the `layout`.
+pub fn compile_wgsl(
+    source: &str,
+    capabilities: naga::valid::Capabilities,
+    layout: &Layout,
+    targets: &[Target],
+) -> Result<ShaderBundle, BundleError> {
+    let module = naga::front::wgsl::parse_str(source)
+        .map_err(|e| BundleError::Parsing(e.emit_to_string(source)))?;
+    compile(module, capabilities, layout, targets)
+}
+
+/// Compile a bundle of the `module`, validated with the shader `capabilities`, with the
+/// outputs of each of the `targets` for pipelines of the `layout`.
+pub fn compile(
+    module: naga::Module,
+    capabilities: naga::valid::Capabilities,
+    layout: &Layout,
+    targets: &[Target],
+) -> Result<ShaderBundle, BundleError> {
+    let info = naga::valid::Validator::new(naga::valid::ValidationFlags::all(), capabilities)
+        .validate(&module)
+        .map_err(|e| BundleError::Validation(e.as_inner().to_string()))?;
+    let entries = layout.sorted_entries();
+    let bind_group_layouts = entries.iter().map(Vec::as_slice).collect::<Vec<_>>();
+
+    let mut precompiled = Vec::new();
+    for target in targets {
+        let outputs = target
+            .translate(
+                &module,
+                &info,
+                &bind_group_layouts,
+                &layout.push_constant_ranges,
+            )
+            .map_err(|message| BundleError::Translation {
+                target: target.name(),
+                message,
+            })?;
+        precompiled.extend(outputs);
+    }
+    Ok(ShaderBundle {
+        module,
+        precompiled,
+    })
+}
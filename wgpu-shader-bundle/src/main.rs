@@ -0,0 +1,177 @@
+/*! Command line tool compiling WGSL shaders into bundles.
+ */
+
+use naga::back::{glsl, hlsl};
+use std::{fs, process::exit};
+use wgpu_shader_bundle::{compile, naga, Layout, Target};
+
+const USAGE: &str = "\
+Usage: wgpu-shader-bundle [OPTIONS] <INPUT.wgsl> <OUTPUT>
+
+Options:
+    --spv                   Precompile SPIR-V for Vulkan
+    --glsl <VERSION>        Precompile GLSL for GL, e.g. 300es or 330
+    --msl <VERSION>         Precompile MSL for Metal, e.g. 2.1
+    --hlsl <MODEL>          Precompile HLSL for DX12, e.g. 5.1
+    --capabilities <LIST>   Comma separated shader capabilities, e.g. PUSH_CONSTANT,FLOAT64
+    --layout <LAYOUT.ron>   Pipeline layout of the outputs, required if the shader uses resources
+
+Outputs are only used by pipelines with the given layout, or the layout derived from
+the shader. A layout lists the entries of the bind group layouts and the push constant
+ranges, e.g.
+    (
+        bind_group_layouts: [[(
+            binding: 0,
+            visibility: 4, // COMPUTE
+            ty: Buffer(
+                ty: Storage(read_only: false),
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            ),
+            count: None,
+        )]],
+        push_constant_ranges: [],
+    )";
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}\n\n{}", message, USAGE);
+    exit(1)
+}
+
+fn parse_capabilities(list: &str) -> naga::valid::Capabilities {
+    use naga::valid::Capabilities as C;
+
+    list.split(',')
+        .filter(|name| !name.is_empty())
+        .map(|name| match name.trim() {
+            "PUSH_CONSTANT" => C::PUSH_CONSTANT,
+            "FLOAT64" => C::FLOAT64,
+            "PRIMITIVE_INDEX" => C::PRIMITIVE_INDEX,
+            "SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING" => {
+                C::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING
+            }
+            "UNIFORM_BUFFER_AND_STORAGE_TEXTURE_ARRAY_NON_UNIFORM_INDEXING" => {
+                C::UNIFORM_BUFFER_AND_STORAGE_TEXTURE_ARRAY_NON_UNIFORM_INDEXING
+            }
+            "SAMPLER_NON_UNIFORM_INDEXING" => C::SAMPLER_NON_UNIFORM_INDEXING,
+            "CLIP_DISTANCE" => C::CLIP_DISTANCE,
+            "CULL_DISTANCE" => C::CULL_DISTANCE,
+            other => fail(&format!("Unknown capability {}", other)),
+        })
+        .fold(C::empty(), |capabilities, capability| {
+            capabilities | capability
+        })
+}
+
+fn parse_glsl_version(version: &str) -> glsl::Version {
+    let (number, embedded) = match version.strip_suffix("es") {
+        Some(number) => (number, true),
+        None => (version, false),
+    };
+    let number = number
+        .parse()
+        .unwrap_or_else(|_| fail(&format!("Invalid GLSL version {}", version)));
+    if embedded {
+        glsl::Version::new_gles(number)
+    } else {
+        glsl::Version::Desktop(number)
+    }
+}
+
+fn parse_msl_version(version: &str) -> (u8, u8) {
+    let mut parts = version.split('.').map(str::parse);
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(Ok(major)), Some(Ok(minor)), None) => (major, minor),
+        _ => fail(&format!("Invalid MSL version {}", version)),
+    }
+}
+
+fn parse_shader_model(model: &str) -> hlsl::ShaderModel {
+    match model {
+        "5.0" => hlsl::ShaderModel::V5_0,
+        "5.1" => hlsl::ShaderModel::V5_1,
+        "6.0" => hlsl::ShaderModel::V6_0,
+        _ => fail(&format!("Invalid shader model {}", model)),
+    }
+}
+
+fn main() {
+    let mut spv = false;
+    let mut glsl_version = None;
+    let mut msl_version = None;
+    let mut shader_model = None;
+    let mut capabilities = naga::valid::Capabilities::empty();
+    let mut layout_path = None;
+    let mut paths = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .unwrap_or_else(|| fail(&format!("Missing value of {}", arg)))
+        };
+        match arg.as_str() {
+            "--spv" => spv = true,
+            "--glsl" => glsl_version = Some(parse_glsl_version(&value())),
+            "--msl" => msl_version = Some(parse_msl_version(&value())),
+            "--hlsl" => shader_model = Some(parse_shader_model(&value())),
+            "--capabilities" => capabilities = parse_capabilities(&value()),
+            "--layout" => layout_path = Some(value()),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if arg.starts_with("--") => fail(&format!("Unknown option {}", arg)),
+            _ => paths.push(arg),
+        }
+    }
+    let (input, output) = match paths.as_slice() {
+        [input, output] => (input, output),
+        _ => fail("Expected an input and an output path"),
+    };
+
+    let source = fs::read_to_string(input)
+        .unwrap_or_else(|e| fail(&format!("Couldn't read {}: {}", input, e)));
+    let module = naga::front::wgsl::parse_str(&source).unwrap_or_else(|e| {
+        eprint!("{}", e.emit_to_string(&source));
+        exit(1)
+    });
+
+    let uses_resources = module
+        .global_variables
+        .iter()
+        .any(|(_, var)| var.binding.is_some() || var.space == naga::AddressSpace::PushConstant);
+    let layout = match layout_path {
+        Some(path) => {
+            let layout = fs::read_to_string(&path)
+                .unwrap_or_else(|e| fail(&format!("Couldn't read {}: {}", path, e)));
+            ron::de::from_str::<Layout>(&layout)
+                .unwrap_or_else(|e| fail(&format!("Invalid layout {}: {}", path, e)))
+        }
+        None if uses_resources => fail("The shader uses resources, a layout is required"),
+        None => Layout::default(),
+    };
+
+    let mut targets = Vec::new();
+    if spv {
+        targets.push(Target::SpirV);
+    }
+    if let Some(version) = glsl_version {
+        targets.push(Target::Glsl { version });
+    }
+    if let Some(lang_version) = msl_version {
+        targets.push(Target::Msl { lang_version });
+    }
+    if let Some(shader_model) = shader_model {
+        targets.push(Target::Hlsl { shader_model });
+    }
+
+    let bundle = compile(module, capabilities, &layout, &targets).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        exit(1)
+    });
+    if let Err(e) = fs::write(output, bundle.to_bytes()) {
+        eprintln!("Couldn't write {}: {}", output, e);
+        exit(1);
+    }
+}
@@ -3977,6 +3977,124 @@ pub struct BindGroupLayoutEntry {
     pub count: Option<NonZeroU32>,
 }
 
+/// Description of the entry points of a shader module and of the resources they use.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "trace", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct ShaderReflection {
+    /// Entry points of the module, sorted by name.
+    pub entry_points: Vec<EntryPointReflection>,
+    /// Resources of the module bound to a group, sorted by group and binding.
+    pub bindings: Vec<BindingReflection>,
+}
+
+impl ShaderReflection {
+    /// Returns the entry point named `name`.
+    pub fn entry_point(&self, name: &str) -> Option<&EntryPointReflection> {
+        self.entry_points
+            .iter()
+            .find(|entry_point| entry_point.name == name)
+    }
+
+    /// Returns the layout entries of the resources bound to `group`, to create a bind group
+    /// layout for the module.
+    pub fn bind_group_layout_entries(&self, group: u32) -> Vec<BindGroupLayoutEntry> {
+        self.bindings
+            .iter()
+            .filter(|binding| binding.group == group)
+            .map(BindingReflection::layout_entry)
+            .collect()
+    }
+}
+
+/// Description of an entry point of a shader module.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "trace", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct EntryPointReflection {
+    /// Name of the entry point.
+    pub name: String,
+    /// Stage of the entry point, as a single flag.
+    pub stage: ShaderStages,
+    /// Size of the workgroups of compute entry points, and zeros for other stages.
+    pub workgroup_size: [u32; 3],
+    /// Inputs of the entry point with a location, which are vertex attributes for
+    /// vertex entry points. Built-in inputs are left out.
+    pub inputs: Vec<VariableReflection>,
+    /// Outputs of the entry point with a location, which are color targets for
+    /// fragment entry points. Built-in outputs are left out.
+    pub outputs: Vec<VariableReflection>,
+    /// Group and binding indices of the resources used by the entry point, sorted.
+    pub bindings: Vec<(u32, u32)>,
+}
+
+impl EntryPointReflection {
+    /// Returns the vertex attributes of the inputs, tightly packed in a single buffer
+    /// in the order of their locations.
+    pub fn vertex_attributes(&self) -> Vec<VertexAttribute> {
+        let mut inputs = self.inputs.clone();
+        inputs.sort_by_key(|input| input.location);
+        let mut offset = 0;
+        inputs
+            .iter()
+            .map(|input| {
+                let attribute = VertexAttribute {
+                    format: input.format,
+                    offset,
+                    shader_location: input.location,
+                };
+                offset += input.format.size();
+                attribute
+            })
+            .collect()
+    }
+}
+
+/// Description of an input or output of an entry point with a location.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "trace", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct VariableReflection {
+    /// Location of the variable.
+    pub location: ShaderLocation,
+    /// Type of the variable, as the vertex format with 32 or 64 bit components the shader
+    /// sees it as.
+    pub format: VertexFormat,
+}
+
+/// Description of a resource of a shader module bound to a group.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "trace", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct BindingReflection {
+    /// Name of the resource variable, if any.
+    pub name: Option<String>,
+    /// Bind group index.
+    pub group: u32,
+    /// Binding index in the group.
+    pub binding: u32,
+    /// Stages of the entry points using the resource.
+    pub visibility: ShaderStages,
+    /// Type of the binding. The size of buffers is their `min_binding_size`, or the size of
+    /// an element for runtime-sized arrays, and their access is read-only unless an entry
+    /// point writes to them.
+    pub ty: BindingType,
+    /// Count of elements of binding arrays.
+    pub count: Option<NonZeroU32>,
+}
+
+impl BindingReflection {
+    /// Returns the bind group layout entry of the resource.
+    pub fn layout_entry(&self) -> BindGroupLayoutEntry {
+        BindGroupLayoutEntry {
+            binding: self.binding,
+            visibility: self.visibility,
+            ty: self.ty,
+            count: self.count,
+        }
+    }
+}
+
 /// View of a buffer which can be used to copy to/from a texture.
 ///
 /// Corresponds to [WebGPU `GPUImageCopyBuffer`](
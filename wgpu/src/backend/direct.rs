@@ -1125,6 +1125,8 @@ impl crate::Context for Context {
             ShaderSource::Wgsl(ref code) => wgc::pipeline::ShaderModuleSource::Wgsl(Borrowed(code)),
             #[cfg(feature = "naga")]
             ShaderSource::Naga(module) => wgc::pipeline::ShaderModuleSource::Naga(module),
+            #[cfg(feature = "shader-bundle")]
+            ShaderSource::Bundle(bundle) => wgc::pipeline::ShaderModuleSource::Bundle(bundle),
         };
         let (id, error) = wgc::gfx_select!(
             device.id => global.device_create_shader_module(device.id, &descriptor, source, PhantomData)
@@ -1168,6 +1170,14 @@ impl crate::Context for Context {
         id
     }
 
+    fn shader_module_reflect(
+        &self,
+        shader_module: &Self::ShaderModuleId,
+    ) -> Option<wgt::ShaderReflection> {
+        let global = &self.0;
+        wgc::gfx_select!(*shader_module => global.shader_module_reflect(*shader_module))
+    }
+
     fn device_create_bind_group_layout(
         &self,
         device: &Self::DeviceId,
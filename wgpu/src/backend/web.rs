@@ -1347,6 +1347,25 @@ impl crate::Context for Context {
                 );
                 let module_info = validator.validate(&module).unwrap();
 
+                let writer_flags = naga::back::wgsl::WriterFlags::empty();
+                let wgsl_text =
+                    back::wgsl::write_string(&module, &module_info, writer_flags).unwrap();
+                web_sys::GpuShaderModuleDescriptor::new(wgsl_text.as_str())
+            }
+            #[cfg(feature = "shader-bundle")]
+            crate::ShaderSource::Bundle(ref bundle) => {
+                use naga::{back, valid};
+
+                // The precompiled outputs are of no use to WebGPU.
+                let module = wgc::pipeline::ShaderBundle::from_bytes(bundle)
+                    .unwrap()
+                    .module;
+                let mut validator = valid::Validator::new(
+                    valid::ValidationFlags::all(),
+                    valid::Capabilities::all(),
+                );
+                let module_info = validator.validate(&module).unwrap();
+
                 let writer_flags = naga::back::wgsl::WriterFlags::empty();
                 let wgsl_text =
                     back::wgsl::write_string(&module, &module_info, writer_flags).unwrap();
@@ -1465,6 +1484,14 @@ impl crate::Context for Context {
         unreachable!("SPIRV_SHADER_PASSTHROUGH is not enabled for this backend")
     }
 
+    fn shader_module_reflect(
+        &self,
+        _shader_module: &Self::ShaderModuleId,
+    ) -> Option<wgt::ShaderReflection> {
+        // The browser doesn't expose the interface of shader modules
+        None
+    }
+
     fn device_create_bind_group(
         &self,
         device: &Self::DeviceId,
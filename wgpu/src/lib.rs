@@ -27,21 +27,25 @@ use parking_lot::Mutex;
 
 pub use wgt::{
     AdapterInfo, AddressMode, AstcBlock, AstcChannel, Backend, Backends, BindGroupLayoutEntry,
-    BindingType, BlendComponent, BlendFactor, BlendOperation, BlendState, BufferAddress,
-    BufferBindingType, BufferSize, BufferUsages, Color, ColorTargetState, ColorWrites,
-    CommandBufferDescriptor, CompareFunction, DepthBiasState, DepthStencilState, DeviceType,
-    DownlevelCapabilities, DownlevelFlags, DynamicOffset, Extent3d, Face, Features, FilterMode,
-    FrontFace, ImageDataLayout, ImageSubresourceRange, IndexFormat, Limits, MultisampleState,
-    Origin3d, PipelineStatisticsTypes, PolygonMode, PowerPreference, PresentMode, PrimitiveState,
-    PrimitiveTopology, PushConstantRange, QueryType, RenderBundleDepthStencil, SamplerBindingType,
-    SamplerBorderColor, ShaderLocation, ShaderModel, ShaderStages, StencilFaceState,
-    StencilOperation, StencilState, StorageTextureAccess, SurfaceConfiguration, SurfaceStatus,
-    TextureAspect, TextureDimension, TextureFormat, TextureFormatFeatureFlags,
-    TextureFormatFeatures, TextureSampleType, TextureUsages, TextureViewDimension, VertexAttribute,
-    VertexFormat, VertexStepMode, COPY_BUFFER_ALIGNMENT, COPY_BYTES_PER_ROW_ALIGNMENT,
-    MAP_ALIGNMENT, PUSH_CONSTANT_ALIGNMENT, QUERY_RESOLVE_BUFFER_ALIGNMENT, QUERY_SET_MAX_QUERIES,
-    QUERY_SIZE, VERTEX_STRIDE_ALIGNMENT,
+    BindingReflection, BindingType, BlendComponent, BlendFactor, BlendOperation, BlendState,
+    BufferAddress, BufferBindingType, BufferSize, BufferUsages, Color, ColorTargetState,
+    ColorWrites, CommandBufferDescriptor, CompareFunction, DepthBiasState, DepthStencilState,
+    DeviceType, DownlevelCapabilities, DownlevelFlags, DynamicOffset, EntryPointReflection,
+    Extent3d, Face, Features, FilterMode, FrontFace, ImageDataLayout, ImageSubresourceRange,
+    IndexFormat, Limits, MultisampleState, Origin3d, PipelineStatisticsTypes, PolygonMode,
+    PowerPreference, PresentMode, PrimitiveState, PrimitiveTopology, PushConstantRange, QueryType,
+    RenderBundleDepthStencil, SamplerBindingType, SamplerBorderColor, ShaderLocation, ShaderModel,
+    ShaderReflection, ShaderStages, StencilFaceState, StencilOperation, StencilState,
+    StorageTextureAccess, SurfaceConfiguration, SurfaceStatus, TextureAspect, TextureDimension,
+    TextureFormat, TextureFormatFeatureFlags, TextureFormatFeatures, TextureSampleType,
+    TextureUsages, TextureViewDimension, VariableReflection, VertexAttribute, VertexFormat,
+    VertexStepMode, COPY_BUFFER_ALIGNMENT, COPY_BYTES_PER_ROW_ALIGNMENT, MAP_ALIGNMENT,
+    PUSH_CONSTANT_ALIGNMENT, QUERY_RESOLVE_BUFFER_ALIGNMENT, QUERY_SET_MAX_QUERIES, QUERY_SIZE,
+    VERTEX_STRIDE_ALIGNMENT,
 };
+#[cfg(feature = "macros")]
+#[cfg_attr(docsrs, doc(cfg(feature = "macros")))]
+pub use wgpu_macros::include_wgsl_checked;
 
 use backend::{BufferMappedRange, Context as C, QueueWriteBuffer};
 
@@ -270,6 +274,10 @@ trait Context: Debug + Send + Sized + Sync {
         device: &Self::DeviceId,
         desc: &ShaderModuleDescriptorSpirV,
     ) -> Self::ShaderModuleId;
+    fn shader_module_reflect(
+        &self,
+        shader_module: &Self::ShaderModuleId,
+    ) -> Option<ShaderReflection>;
     fn device_create_bind_group_layout(
         &self,
         device: &Self::DeviceId,
@@ -808,6 +816,19 @@ impl Drop for ShaderModule {
     }
 }
 
+impl ShaderModule {
+    /// Describe the entry points of the module, with their stages, workgroup sizes, inputs
+    /// and outputs, and the resources they use, with their bindings, types, sizes and access.
+    ///
+    /// This can be used to create bind group layouts and vertex buffer layouts from shaders.
+    ///
+    /// Returns `None` for invalid modules, for modules created with
+    /// [`Device::create_shader_module_spirv`], which aren't validated, and on the web.
+    pub fn reflect(&self) -> Option<ShaderReflection> {
+        self.context.shader_module_reflect(&self.id)
+    }
+}
+
 /// Source of a shader module.
 ///
 /// The source will be parsed and validated.
@@ -841,6 +862,14 @@ pub enum ShaderSource<'a> {
     #[cfg(feature = "naga")]
     #[cfg_attr(docsrs, doc(cfg(feature = "naga")))]
     Naga(naga::Module),
+    /// Shader bundle, as written by `wgpu-shader-bundle`.
+    ///
+    /// The naga IR of the bundle is used without parsing any source, and its precompiled
+    /// outputs are used instead of translating the IR when their options match those of
+    /// the backend.
+    #[cfg(feature = "shader-bundle")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "shader-bundle")))]
+    Bundle(Cow<'a, [u8]>),
 }
 
 /// Descriptor for use with [`Device::create_shader_module`].
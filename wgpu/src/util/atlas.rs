@@ -0,0 +1,491 @@
+use crate::{
+    CommandEncoderDescriptor, Device, Extent3d, ImageCopyTexture, ImageDataLayout, Origin3d, Queue,
+    Texture, TextureAspect, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+    TextureView, TextureViewDescriptor, TextureViewDimension,
+};
+use std::{error, fmt, num::NonZeroU32};
+
+/// Error returned when a [`TextureAtlas`] can't allocate a region.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TextureAtlasError {
+    /// The region, with its padding, is larger than a layer of the atlas.
+    TooLarge {
+        /// Width of the region.
+        width: u32,
+        /// Height of the region.
+        height: u32,
+    },
+    /// No layer has room for the region, and the atlas has `max_texture_array_layers`
+    /// layers.
+    Full,
+}
+
+impl fmt::Display for TextureAtlasError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            TextureAtlasError::TooLarge { width, height } => write!(
+                f,
+                "Region of {}x{} doesn't fit in a layer of the atlas",
+                width, height
+            ),
+            TextureAtlasError::Full => write!(f, "Atlas has no room left"),
+        }
+    }
+}
+
+impl error::Error for TextureAtlasError {}
+
+/// Algorithm packing the regions of a [`TextureAtlas`] in its layers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AtlasPacking {
+    /// Rows of regions, each as high as its first region. Space of deallocated regions is
+    /// reused by regions no higher than their row.
+    Shelf,
+    /// Regions are placed as low as possible on the outline of the regions already
+    /// placed. Packs regions of varied sizes tighter than shelves, but only reuses the
+    /// space of a layer once all its regions are deallocated.
+    Skyline,
+}
+
+/// Describes a [`TextureAtlas`].
+#[derive(Clone, Debug)]
+pub struct TextureAtlasDescriptor<'a> {
+    /// Debug label of the texture.
+    pub label: Option<&'a str>,
+    /// Width of the layers.
+    pub width: u32,
+    /// Height of the layers.
+    pub height: u32,
+    /// Initial number of layers.
+    pub layers: u32,
+    /// Format of the texture.
+    pub format: TextureFormat,
+    /// Usages of the texture, on top of `TEXTURE_BINDING`, `COPY_SRC` and `COPY_DST`.
+    pub usage: TextureUsages,
+    /// Texels left between regions, and between regions and the right and bottom edges
+    /// of the layers.
+    pub padding: u32,
+    /// Packing algorithm.
+    pub packing: AtlasPacking,
+}
+
+/// Region of a [`TextureAtlas`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AtlasAllocation {
+    /// Layer of the region.
+    pub layer: u32,
+    /// Position of the top left corner of the region in its layer.
+    pub origin: [u32; 2],
+    /// Size of the region.
+    pub size: [u32; 2],
+}
+
+#[derive(Debug)]
+struct Shelf {
+    y: u32,
+    height: u32,
+    /// Free spans, as x and width, sorted by x.
+    free: Vec<(u32, u32)>,
+}
+
+#[derive(Debug)]
+struct Skyline {
+    /// Top of the regions, as x, y and width, sorted by x.
+    nodes: Vec<(u32, u32, u32)>,
+}
+
+#[derive(Debug)]
+enum Packer {
+    Shelf(Vec<Shelf>),
+    Skyline(Skyline),
+}
+
+impl Packer {
+    fn new(packing: AtlasPacking, width: u32) -> Self {
+        match packing {
+            AtlasPacking::Shelf => Packer::Shelf(Vec::new()),
+            AtlasPacking::Skyline => Packer::Skyline(Skyline {
+                nodes: vec![(0, 0, width)],
+            }),
+        }
+    }
+
+    /// Place a padded region in a layer of `layer_width` by `layer_height` texels.
+    fn allocate(
+        &mut self,
+        width: u32,
+        height: u32,
+        layer_width: u32,
+        layer_height: u32,
+    ) -> Option<[u32; 2]> {
+        match *self {
+            Packer::Shelf(ref mut shelves) => {
+                // the shortest shelf with a wide enough span
+                let best = shelves
+                    .iter_mut()
+                    .filter(|shelf| shelf.height >= height)
+                    .filter_map(|shelf| {
+                        let span = shelf.free.iter().position(|&(_, span)| span >= width)?;
+                        Some((shelf, span))
+                    })
+                    .min_by_key(|(shelf, _)| shelf.height);
+                if let Some((shelf, span)) = best {
+                    let (x, span_width) = shelf.free[span];
+                    if span_width == width {
+                        shelf.free.remove(span);
+                    } else {
+                        shelf.free[span] = (x + width, span_width - width);
+                    }
+                    return Some([x, shelf.y]);
+                }
+
+                let y = shelves.last().map_or(0, |shelf| shelf.y + shelf.height);
+                if y + height > layer_height {
+                    return None;
+                }
+                let mut free = Vec::new();
+                if width < layer_width {
+                    free.push((width, layer_width - width));
+                }
+                shelves.push(Shelf { y, height, free });
+                Some([0, y])
+            }
+            Packer::Skyline(ref mut skyline) => {
+                let nodes = &mut skyline.nodes;
+                // lowest bottom, then leftmost
+                let mut best = None;
+                for (index, &(x, _, _)) in nodes.iter().enumerate() {
+                    if x + width > layer_width {
+                        break;
+                    }
+                    let mut top = 0;
+                    for &(node_x, node_y, _) in nodes[index..].iter() {
+                        if node_x >= x + width {
+                            break;
+                        }
+                        top = top.max(node_y);
+                    }
+                    let lower = match best {
+                        Some((_, best_top)) => top < best_top,
+                        None => true,
+                    };
+                    if top + height <= layer_height && lower {
+                        best = Some((index, top));
+                    }
+                }
+                let (index, y) = best?;
+                let x = nodes[index].0;
+
+                nodes.insert(index, (x, y + height, width));
+                let right = x + width;
+                while index + 1 < nodes.len() {
+                    let (node_x, node_y, node_width) = nodes[index + 1];
+                    if node_x >= right {
+                        break;
+                    }
+                    if node_x + node_width <= right {
+                        nodes.remove(index + 1);
+                    } else {
+                        nodes[index + 1] = (right, node_y, node_x + node_width - right);
+                        break;
+                    }
+                }
+                let mut index = 0;
+                while index + 1 < nodes.len() {
+                    if nodes[index].1 == nodes[index + 1].1 {
+                        nodes[index].2 += nodes[index + 1].2;
+                        nodes.remove(index + 1);
+                    } else {
+                        index += 1;
+                    }
+                }
+                Some([x, y])
+            }
+        }
+    }
+
+    /// Free a padded region placed by `allocate`.
+    fn deallocate(&mut self, origin: [u32; 2], width: u32, layer_width: u32) {
+        match *self {
+            Packer::Shelf(ref mut shelves) => {
+                let shelf = match shelves.iter_mut().find(|shelf| shelf.y == origin[1]) {
+                    Some(shelf) => shelf,
+                    None => return,
+                };
+                let index = shelf.free.partition_point(|&(x, _)| x < origin[0]);
+                shelf.free.insert(index, (origin[0], width));
+                let mut index = 0;
+                while index + 1 < shelf.free.len() {
+                    let (x, span_width) = shelf.free[index];
+                    if x + span_width == shelf.free[index + 1].0 {
+                        shelf.free[index].1 += shelf.free[index + 1].1;
+                        shelf.free.remove(index + 1);
+                    } else {
+                        index += 1;
+                    }
+                }
+                while matches!(shelves.last(), Some(shelf) if shelf.free[..] == [(0, layer_width)])
+                {
+                    shelves.pop();
+                }
+            }
+            // the space is reclaimed once the layer is empty
+            Packer::Skyline(_) => {}
+        }
+    }
+}
+
+#[derive(Debug)]
+struct AtlasLayer {
+    packer: Packer,
+    allocations: u32,
+}
+
+/// Regions of a 2D array texture, allocated and deallocated at runtime, for sprites,
+/// glyphs, lightmaps and the like.
+///
+/// When no layer has room for a region, the texture is replaced by one with twice as
+/// many layers, up to `max_texture_array_layers`, and the contents of the previous
+/// layers are copied into it. Bind groups using the texture have to be recreated when
+/// [`TextureAtlas::generation`] changes.
+pub struct TextureAtlas {
+    label: Option<String>,
+    texture: Texture,
+    view: TextureView,
+    width: u32,
+    height: u32,
+    format: TextureFormat,
+    usage: TextureUsages,
+    padding: u32,
+    packing: AtlasPacking,
+    layers: Vec<AtlasLayer>,
+    max_layers: u32,
+    generation: u32,
+}
+
+impl TextureAtlas {
+    /// Create an atlas with empty layers.
+    pub fn new(device: &Device, desc: &TextureAtlasDescriptor) -> Self {
+        let usage = desc.usage
+            | TextureUsages::TEXTURE_BINDING
+            | TextureUsages::COPY_SRC
+            | TextureUsages::COPY_DST;
+        let layers = desc.layers.max(1);
+        let (texture, view) = create_texture(
+            device,
+            desc.label,
+            desc.width,
+            desc.height,
+            layers,
+            desc.format,
+            usage,
+        );
+        Self {
+            label: desc.label.map(str::to_string),
+            texture,
+            view,
+            width: desc.width,
+            height: desc.height,
+            format: desc.format,
+            usage,
+            padding: desc.padding,
+            packing: desc.packing,
+            layers: (0..layers)
+                .map(|_| AtlasLayer {
+                    packer: Packer::new(desc.packing, desc.width),
+                    allocations: 0,
+                })
+                .collect(),
+            max_layers: device.limits().max_texture_array_layers,
+            generation: 0,
+        }
+    }
+
+    /// Texture of the atlas.
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    /// View of all the layers of the texture, with the `D2Array` dimension.
+    pub fn view(&self) -> &TextureView {
+        &self.view
+    }
+
+    /// Number of times the texture was replaced to add layers.
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    /// Number of layers of the texture.
+    pub fn layer_count(&self) -> u32 {
+        self.layers.len() as u32
+    }
+
+    /// Allocate a region of `width` by `height` texels, adding layers if needed.
+    pub fn allocate(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        width: u32,
+        height: u32,
+    ) -> Result<AtlasAllocation, TextureAtlasError> {
+        let padded = [width + self.padding, height + self.padding];
+        if width == 0 || height == 0 || padded[0] > self.width || padded[1] > self.height {
+            return Err(TextureAtlasError::TooLarge { width, height });
+        }
+
+        loop {
+            for (index, layer) in self.layers.iter_mut().enumerate() {
+                let origin = layer
+                    .packer
+                    .allocate(padded[0], padded[1], self.width, self.height);
+                if let Some(origin) = origin {
+                    layer.allocations += 1;
+                    return Ok(AtlasAllocation {
+                        layer: index as u32,
+                        origin,
+                        size: [width, height],
+                    });
+                }
+            }
+            self.grow(device, queue)?;
+        }
+    }
+
+    /// Free a region allocated by [`TextureAtlas::allocate`], so that it can be reused.
+    pub fn deallocate(&mut self, allocation: AtlasAllocation) {
+        let layer = &mut self.layers[allocation.layer as usize];
+        layer.packer.deallocate(
+            allocation.origin,
+            allocation.size[0] + self.padding,
+            self.width,
+        );
+        layer.allocations -= 1;
+        if layer.allocations == 0 {
+            layer.packer = Packer::new(self.packing, self.width);
+        }
+    }
+
+    /// Write tightly packed texels to the region, with `Queue::write_texture`.
+    pub fn write(&self, queue: &Queue, allocation: &AtlasAllocation, data: &[u8]) {
+        let info = self.format.describe();
+        let blocks_per_row = allocation.size[0] / info.block_dimensions.0 as u32;
+        queue.write_texture(
+            ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: Origin3d {
+                    x: allocation.origin[0],
+                    y: allocation.origin[1],
+                    z: allocation.layer,
+                },
+                aspect: TextureAspect::All,
+            },
+            data,
+            ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(blocks_per_row * info.block_size as u32),
+                rows_per_image: None,
+            },
+            Extent3d {
+                width: allocation.size[0],
+                height: allocation.size[1],
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    /// Texture coordinates of the region in its layer, as `[u_min, v_min, u_max, v_max]`.
+    pub fn uv_rect(&self, allocation: &AtlasAllocation) -> [f32; 4] {
+        let [x, y] = allocation.origin;
+        let [width, height] = allocation.size;
+        [
+            x as f32 / self.width as f32,
+            y as f32 / self.height as f32,
+            (x + width) as f32 / self.width as f32,
+            (y + height) as f32 / self.height as f32,
+        ]
+    }
+
+    fn grow(&mut self, device: &Device, queue: &Queue) -> Result<(), TextureAtlasError> {
+        let layers = self.layers.len() as u32;
+        if layers >= self.max_layers {
+            return Err(TextureAtlasError::Full);
+        }
+        let new_layers = (layers * 2).min(self.max_layers);
+        let (texture, view) = create_texture(
+            device,
+            self.label.as_deref(),
+            self.width,
+            self.height,
+            new_layers,
+            self.format,
+            self.usage,
+        );
+
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("(wgpu internal) TextureAtlas"),
+        });
+        encoder.copy_texture_to_texture(
+            self.texture.as_image_copy(),
+            texture.as_image_copy(),
+            Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: layers,
+            },
+        );
+        queue.submit(Some(encoder.finish()));
+
+        self.texture = texture;
+        self.view = view;
+        self.layers.extend((layers..new_layers).map(|_| AtlasLayer {
+            packer: Packer::new(self.packing, self.width),
+            allocations: 0,
+        }));
+        self.generation += 1;
+        Ok(())
+    }
+}
+
+impl fmt::Debug for TextureAtlas {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TextureAtlas")
+            .field("label", &self.label)
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("format", &self.format)
+            .field("layers", &self.layers.len())
+            .field("generation", &self.generation)
+            .finish_non_exhaustive()
+    }
+}
+
+fn create_texture(
+    device: &Device,
+    label: Option<&str>,
+    width: u32,
+    height: u32,
+    layers: u32,
+    format: TextureFormat,
+    usage: TextureUsages,
+) -> (Texture, TextureView) {
+    let texture = device.create_texture(&TextureDescriptor {
+        label,
+        size: Extent3d {
+            width,
+            height,
+            depth_or_array_layers: layers,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format,
+        usage,
+    });
+    let view = texture.create_view(&TextureViewDescriptor {
+        dimension: Some(TextureViewDimension::D2Array),
+        ..Default::default()
+    });
+    (texture, view)
+}
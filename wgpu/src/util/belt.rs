@@ -1,9 +1,13 @@
 use crate::{
-    util::align_to, Buffer, BufferAddress, BufferDescriptor, BufferSize, BufferUsages,
-    BufferViewMut, CommandEncoder, Device, MapMode,
+    util::align_to, Buffer, BufferAddress, BufferAsyncError, BufferDescriptor, BufferSize,
+    BufferUsages, BufferViewMut, CommandEncoder, Device, MapMode,
 };
 use std::fmt;
-use std::sync::{mpsc, Arc};
+use std::future::Future;
+use std::ops::Range;
+use std::pin::Pin;
+use std::sync::{mpsc, Arc, Mutex};
+use std::task::{Context, Poll, Waker};
 
 struct Chunk {
     buffer: Arc<Buffer>,
@@ -150,3 +154,256 @@ impl fmt::Debug for StagingBelt {
             .finish_non_exhaustive()
     }
 }
+
+type ReadbackCallback = Box<dyn FnOnce(Result<&[u8], BufferAsyncError>) + Send>;
+
+/// Part of a read-back chunk that is delivered to a single callback.
+struct Region {
+    range: Range<BufferAddress>,
+    callback: ReadbackCallback,
+}
+
+struct ReadbackChunk {
+    chunk: Chunk,
+    regions: Vec<Region>,
+}
+
+/// Read-back belt is a machine that downloads data.
+///
+/// It's the counterpart of [`StagingBelt`]: internally it uses a ring-buffer of
+/// `MAP_READ` buffers that are sub-allocated, so many small reads share a few buffers
+/// instead of creating a new one each, like [`DownloadBuffer`] does.
+///
+/// Using a read-back belt generally goes as follows:
+/// - Schedule reads of buffers using [`ReadbackBelt::read_buffer`] or
+///   [`ReadbackBelt::read_buffer_async`].
+/// - Call `finish`.
+/// - Submit all command encoders used with `ReadbackBelt::read_buffer`.
+/// - Call `recall`.
+///
+/// Each region is delivered to its callback when the submission is done,
+/// as the device is polled.
+///
+/// [`DownloadBuffer`]: crate::util::DownloadBuffer
+pub struct ReadbackBelt {
+    chunk_size: BufferAddress,
+    /// Chunks that we are actively using for pending transfers at this moment.
+    active_chunks: Vec<ReadbackChunk>,
+    /// Chunks that have scheduled transfers already.
+    closed_chunks: Vec<ReadbackChunk>,
+    /// Chunks that are back from the GPU and ready to be used.
+    free_chunks: Vec<Chunk>,
+    /// Count of chunks allocated and not dropped yet.
+    chunk_count: usize,
+    /// Chunks delivered to their callbacks, or `None` for chunks which failed to map,
+    /// which are dropped instead of being reused.
+    sender: mpsc::Sender<Option<Chunk>>,
+    receiver: mpsc::Receiver<Option<Chunk>>,
+}
+
+impl ReadbackBelt {
+    /// Create a new read-back belt.
+    ///
+    /// The `chunk_size` is the unit of internal buffer allocation.
+    /// It's better when it's big, but ideally still 1-4 times less than
+    /// the total amount of data downloaded per submission.
+    pub fn new(chunk_size: BufferAddress) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        ReadbackBelt {
+            chunk_size,
+            active_chunks: Vec::new(),
+            closed_chunks: Vec::new(),
+            free_chunks: Vec::new(),
+            chunk_count: 0,
+            sender,
+            receiver,
+        }
+    }
+
+    /// Count of buffers the belt owns, whether they are in use or ready to be reused.
+    pub fn chunk_count(&self) -> usize {
+        self.chunk_count
+    }
+
+    /// Schedule a read of `size` bytes from the `source` buffer at the specified offset.
+    ///
+    /// The copy will be placed into the provided command encoder. This encoder
+    /// must be submitted after `finish` is called and before `recall` is called.
+    /// The `callback` receives the data once the submission is done.
+    ///
+    /// The `offset` and `size` have to follow the rules of
+    /// [`CommandEncoder::copy_buffer_to_buffer`].
+    pub fn read_buffer(
+        &mut self,
+        encoder: &mut CommandEncoder,
+        source: &Buffer,
+        offset: BufferAddress,
+        size: BufferSize,
+        device: &Device,
+        callback: impl FnOnce(Result<&[u8], BufferAsyncError>) + Send + 'static,
+    ) {
+        self.receive_chunks();
+        let index = if let Some(index) = self
+            .active_chunks
+            .iter()
+            .position(|active| active.chunk.offset + size.get() <= active.chunk.size)
+        {
+            index
+        } else {
+            let chunk = if let Some(index) = self
+                .free_chunks
+                .iter()
+                .position(|chunk| size.get() <= chunk.size)
+            {
+                self.free_chunks.swap_remove(index)
+            } else {
+                let size = self.chunk_size.max(size.get());
+                self.chunk_count += 1;
+                Chunk {
+                    buffer: Arc::new(device.create_buffer(&BufferDescriptor {
+                        label: Some("(wgpu internal) ReadbackBelt read-back buffer"),
+                        size,
+                        usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                        mapped_at_creation: false,
+                    })),
+                    size,
+                    offset: 0,
+                }
+            };
+            self.active_chunks.push(ReadbackChunk {
+                chunk,
+                regions: Vec::new(),
+            });
+            self.active_chunks.len() - 1
+        };
+
+        let active = &mut self.active_chunks[index];
+        let chunk = &mut active.chunk;
+        encoder.copy_buffer_to_buffer(source, offset, &chunk.buffer, chunk.offset, size.get());
+        active.regions.push(Region {
+            range: chunk.offset..chunk.offset + size.get(),
+            callback: Box::new(callback),
+        });
+        chunk.offset = align_to(chunk.offset + size.get(), crate::MAP_ALIGNMENT);
+    }
+
+    /// Schedule a read of `size` bytes from the `source` buffer at the specified offset,
+    /// returning a future that resolves with a copy of the data.
+    ///
+    /// See [`ReadbackBelt::read_buffer`] for the requirements.
+    pub fn read_buffer_async(
+        &mut self,
+        encoder: &mut CommandEncoder,
+        source: &Buffer,
+        offset: BufferAddress,
+        size: BufferSize,
+        device: &Device,
+    ) -> impl Future<Output = Result<Vec<u8>, BufferAsyncError>> + Send {
+        let future = ReadbackFuture::default();
+        let shared = Arc::clone(&future.shared);
+        self.read_buffer(encoder, source, offset, size, device, move |result| {
+            let mut shared = shared.lock().unwrap();
+            shared.result = Some(result.map(|data| data.to_vec()));
+            if let Some(waker) = shared.waker.take() {
+                waker.wake();
+            }
+        });
+        future
+    }
+
+    /// Close the currently active buffers for use in a submission.
+    ///
+    /// No more reads are placed into them until they are recalled and delivered.
+    pub fn finish(&mut self) {
+        self.closed_chunks.append(&mut self.active_chunks);
+    }
+
+    /// Start mapping all of the closed buffers, delivering the regions to their callbacks
+    /// and making the buffers ready to be reused. Buffers which fail to map are dropped.
+    ///
+    /// This has to be called after the command encoders written to `read_buffer` are submitted!
+    pub fn recall(&mut self) {
+        self.receive_chunks();
+
+        let sender = &self.sender;
+        for ReadbackChunk { chunk, regions } in self.closed_chunks.drain(..) {
+            let sender = sender.clone();
+            chunk
+                .buffer
+                .clone()
+                .slice(..)
+                .map_async(MapMode::Read, move |result| {
+                    match result {
+                        Ok(()) => {
+                            for region in regions {
+                                let view = chunk.buffer.slice(region.range).get_mapped_range();
+                                (region.callback)(Ok(&view));
+                            }
+                            chunk.buffer.unmap();
+                            let _ = sender.send(Some(chunk));
+                        }
+                        Err(e) => {
+                            for region in regions {
+                                (region.callback)(Err(e.clone()));
+                            }
+                            // the failed mapping can't be unmapped, and keeps the buffer
+                            // from being mapped again
+                            drop(chunk);
+                            let _ = sender.send(None);
+                        }
+                    }
+                });
+        }
+    }
+
+    /// Move the chunks delivered to their callbacks to the free chunks.
+    fn receive_chunks(&mut self) {
+        while let Ok(chunk) = self.receiver.try_recv() {
+            match chunk {
+                Some(mut chunk) => {
+                    chunk.offset = 0;
+                    self.free_chunks.push(chunk);
+                }
+                None => self.chunk_count -= 1,
+            }
+        }
+    }
+}
+
+impl fmt::Debug for ReadbackBelt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReadbackBelt")
+            .field("chunk_size", &self.chunk_size)
+            .field("active_chunks", &self.active_chunks.len())
+            .field("closed_chunks", &self.closed_chunks.len())
+            .field("free_chunks", &self.free_chunks.len())
+            .field("chunk_count", &self.chunk_count)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Default)]
+struct ReadbackShared {
+    result: Option<Result<Vec<u8>, BufferAsyncError>>,
+    waker: Option<Waker>,
+}
+
+#[derive(Default)]
+struct ReadbackFuture {
+    shared: Arc<Mutex<ReadbackShared>>,
+}
+
+impl Future for ReadbackFuture {
+    type Output = Result<Vec<u8>, BufferAsyncError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut shared = self.shared.lock().unwrap();
+        match shared.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                shared.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
@@ -0,0 +1,350 @@
+use crate::{
+    util::DeviceExt, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType,
+    BufferBindingType, BufferUsages, CommandEncoder, Device, FilterMode, FragmentState, LoadOp,
+    Operations, PipelineLayout, PipelineLayoutDescriptor, RenderPassColorAttachment,
+    RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, Sampler, SamplerBindingType,
+    SamplerDescriptor, ShaderModule, ShaderModuleDescriptor, ShaderSource, ShaderStages,
+    TextureFormat, TextureSampleType, TextureView, TextureViewDimension, VertexState,
+};
+use std::{borrow::Cow, collections::HashMap, error, fmt};
+
+const SHADER: &str = include_str!("blit.wgsl");
+
+/// Rectangle of a texture, in texels.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct BlitRect {
+    /// Left edge of the rectangle.
+    pub x: u32,
+    /// Top edge of the rectangle.
+    pub y: u32,
+    /// Width of the rectangle.
+    pub width: u32,
+    /// Height of the rectangle.
+    pub height: u32,
+}
+
+impl BlitRect {
+    /// Rectangle covering the whole texture of the given size.
+    pub fn full(width: u32, height: u32) -> Self {
+        BlitRect {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        }
+    }
+}
+
+/// Source of a destination channel of a blit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Swizzle {
+    /// The red channel of the source.
+    R,
+    /// The green channel of the source.
+    G,
+    /// The blue channel of the source.
+    B,
+    /// The alpha channel of the source.
+    A,
+    /// Constant 0.
+    Zero,
+    /// Constant 1.
+    One,
+}
+
+/// Options of [`Blitter::blit`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BlitOptions {
+    /// Filtering of the source texture. `Linear` requires a filterable format.
+    pub filter: FilterMode,
+    /// Mirror the source rectangle horizontally.
+    pub flip_x: bool,
+    /// Mirror the source rectangle vertically.
+    pub flip_y: bool,
+    /// Source of each of the red, green, blue and alpha destination channels.
+    pub swizzle: [Swizzle; 4],
+}
+
+impl Default for BlitOptions {
+    fn default() -> Self {
+        BlitOptions {
+            filter: FilterMode::Nearest,
+            flip_x: false,
+            flip_y: false,
+            swizzle: [Swizzle::R, Swizzle::G, Swizzle::B, Swizzle::A],
+        }
+    }
+}
+
+/// Error of [`Blitter::blit`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlitError {
+    /// The source format isn't a float format, or isn't filterable with [`FilterMode::Linear`].
+    UnsupportedSourceFormat(TextureFormat),
+    /// The destination format isn't a float format.
+    UnsupportedDestinationFormat(TextureFormat),
+}
+
+impl fmt::Display for BlitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            BlitError::UnsupportedSourceFormat(format) => {
+                write!(f, "Source format {:?} can't be sampled by the blit", format)
+            }
+            BlitError::UnsupportedDestinationFormat(format) => {
+                write!(f, "Destination format {:?} can't be blitted into", format)
+            }
+        }
+    }
+}
+
+impl error::Error for BlitError {}
+
+/// Layout of the bindings for one of the filter modes.
+struct FilterLayout {
+    bind_group_layout: BindGroupLayout,
+    pipeline_layout: PipelineLayout,
+    sampler: Sampler,
+}
+
+/// Copies rectangles between textures of different formats and sizes.
+///
+/// Unlike [`CommandEncoder::copy_texture_to_texture`], the source can be any
+/// 2D view of a float texture, filterable if it's filtered linearly, and the
+/// destination any renderable float color format. Integer, depth and stencil
+/// formats aren't supported. The source rectangle is scaled to the destination
+/// one, and can be flipped and swizzled on the way, see [`BlitOptions`].
+///
+/// Pipelines are created once per destination format and filter mode, and reused across calls.
+pub struct Blitter {
+    shader: ShaderModule,
+    nearest: FilterLayout,
+    linear: FilterLayout,
+    pipelines: HashMap<(TextureFormat, FilterMode), RenderPipeline>,
+}
+
+impl Blitter {
+    /// Create a new blitter.
+    pub fn new(device: &Device) -> Self {
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("(wgpu internal) Blitter shader"),
+            source: ShaderSource::Wgsl(Cow::Borrowed(SHADER)),
+        });
+        Blitter {
+            shader,
+            nearest: FilterLayout::new(device, FilterMode::Nearest),
+            linear: FilterLayout::new(device, FilterMode::Linear),
+            pipelines: HashMap::new(),
+        }
+    }
+
+    /// Record a blit of `src_rect` of the `src` view into `dst_rect` of the `dst` view.
+    ///
+    /// The `src` view has to be a 2D view with a single mip level, and `src_format`
+    /// and `dst_format` have to be the formats of the `src` and `dst` views.
+    /// The texels of `dst` outside of `dst_rect` are preserved.
+    ///
+    /// Returns an error, without recording anything, if either format isn't supported.
+    #[allow(clippy::too_many_arguments)]
+    pub fn blit(
+        &mut self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        src: &TextureView,
+        src_format: TextureFormat,
+        src_rect: BlitRect,
+        dst: &TextureView,
+        dst_format: TextureFormat,
+        dst_rect: BlitRect,
+        options: &BlitOptions,
+    ) -> Result<(), BlitError> {
+        match src_format.describe().sample_type {
+            TextureSampleType::Float { filterable }
+                if filterable || options.filter == FilterMode::Nearest => {}
+            _ => return Err(BlitError::UnsupportedSourceFormat(src_format)),
+        }
+        match dst_format.describe().sample_type {
+            TextureSampleType::Float { .. } => {}
+            _ => return Err(BlitError::UnsupportedDestinationFormat(dst_format)),
+        }
+
+        let layout = match options.filter {
+            FilterMode::Nearest => &self.nearest,
+            FilterMode::Linear => &self.linear,
+        };
+        let shader = &self.shader;
+        let pipeline = self
+            .pipelines
+            .entry((dst_format, options.filter))
+            .or_insert_with(|| {
+                device.create_render_pipeline(&RenderPipelineDescriptor {
+                    label: Some("(wgpu internal) Blitter pipeline"),
+                    layout: Some(&layout.pipeline_layout),
+                    vertex: VertexState {
+                        module: shader,
+                        entry_point: "vs_main",
+                        buffers: &[],
+                    },
+                    fragment: Some(FragmentState {
+                        module: shader,
+                        entry_point: "fs_main",
+                        targets: &[Some(dst_format.into())],
+                    }),
+                    primitive: Default::default(),
+                    depth_stencil: None,
+                    multisample: Default::default(),
+                    multiview: None,
+                })
+            });
+
+        let params = device.create_buffer_init(&super::BufferInitDescriptor {
+            label: Some("(wgpu internal) Blitter parameters"),
+            contents: &params_data(src_rect, options)
+                .iter()
+                .flat_map(|value| value.to_le_bytes())
+                .collect::<Vec<u8>>(),
+            usage: BufferUsages::UNIFORM,
+        });
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: None,
+            layout: &layout.bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(src),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&layout.sampler),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: params.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("(wgpu internal) Blitter"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: dst,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.set_viewport(
+            dst_rect.x as f32,
+            dst_rect.y as f32,
+            dst_rect.width as f32,
+            dst_rect.height as f32,
+            0.0,
+            1.0,
+        );
+        pass.set_scissor_rect(dst_rect.x, dst_rect.y, dst_rect.width, dst_rect.height);
+        pass.draw(0..3, 0..1);
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Blitter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Blitter")
+            .field("pipelines", &self.pipelines.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl FilterLayout {
+    fn new(device: &Device, filter: FilterMode) -> Self {
+        let filterable = filter == FilterMode::Linear;
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("(wgpu internal) Blitter bind group layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(if filterable {
+                        SamplerBindingType::Filtering
+                    } else {
+                        SamplerBindingType::NonFiltering
+                    }),
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("(wgpu internal) Blitter pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("(wgpu internal) Blitter sampler"),
+            mag_filter: filter,
+            min_filter: filter,
+            ..Default::default()
+        });
+        FilterLayout {
+            bind_group_layout,
+            pipeline_layout,
+            sampler,
+        }
+    }
+}
+
+/// Returns the contents of the `Params` uniform of the shader.
+fn params_data(src_rect: BlitRect, options: &BlitOptions) -> [f32; 28] {
+    let mut data = [0.0; 28];
+    data[..4].copy_from_slice(&[
+        src_rect.x as f32,
+        src_rect.y as f32,
+        src_rect.width as f32,
+        src_rect.height as f32,
+    ]);
+    data[4] = options.flip_x as u32 as f32;
+    data[5] = options.flip_y as u32 as f32;
+    // the matrix is column-major: the column is the source channel,
+    // and the row is the destination channel
+    let (matrix, offset) = data[8..].split_at_mut(16);
+    for (dst, &swizzle) in options.swizzle.iter().enumerate() {
+        let src = match swizzle {
+            Swizzle::R => 0,
+            Swizzle::G => 1,
+            Swizzle::B => 2,
+            Swizzle::A => 3,
+            Swizzle::Zero => continue,
+            Swizzle::One => {
+                offset[dst] = 1.0;
+                continue;
+            }
+        };
+        matrix[src * 4 + dst] = 1.0;
+    }
+    data
+}
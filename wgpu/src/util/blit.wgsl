@@ -0,0 +1,49 @@
+// Copies a rectangle of the source texture into the viewport, used by `Blitter`.
+
+struct Params {
+    // origin and size of the source rectangle, in texels
+    src_rect: vec4<f32>,
+    // non-zero x and y mirror the respective axis
+    flip: vec4<f32>,
+    swizzle: mat4x4<f32>,
+    swizzle_offset: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+@group(0) @binding(0)
+var src_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var src_sampler: sampler;
+@group(0) @binding(2)
+var<uniform> params: Params;
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var result: VertexOutput;
+    let x = i32(vertex_index) / 2;
+    let y = i32(vertex_index) & 1;
+    let tc = vec2<f32>(
+        f32(x) * 2.0,
+        f32(y) * 2.0
+    );
+    result.position = vec4<f32>(
+        tc.x * 2.0 - 1.0,
+        1.0 - tc.y * 2.0,
+        0.0, 1.0
+    );
+    result.tex_coords = tc;
+    return result;
+}
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    let flipped = abs(params.flip.xy - vertex.tex_coords);
+    let texel = params.src_rect.xy + flipped * params.src_rect.zw;
+    let uv = texel / vec2<f32>(textureDimensions(src_texture));
+    let color = textureSampleLevel(src_texture, src_sampler, uv, 0.0);
+    return params.swizzle * color + params.swizzle_offset;
+}
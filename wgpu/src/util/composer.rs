@@ -0,0 +1,603 @@
+use crate::ShaderSource;
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    error, fmt,
+    ops::Range,
+};
+
+/// Keywords starting a named declaration, followed by the name.
+const DECLARATION_KEYWORDS: &[&str] = &[
+    "alias", "const", "fn", "let", "override", "struct", "type", "var",
+];
+
+/// Location in a source given to a [`ShaderComposer`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ComposerLocation {
+    /// Path of the module, or name of the composed shader.
+    pub file: String,
+    /// 1-based line number.
+    pub line: u32,
+    /// 1-based column, in characters.
+    pub column: u32,
+}
+
+impl fmt::Display for ComposerLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+/// Error returned when a shader can't be composed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ComposerError {
+    /// No module is registered under the path of an import.
+    UnknownModule {
+        /// Imported path.
+        path: String,
+        /// Location of the import.
+        location: ComposerLocation,
+    },
+    /// The imported module has no declaration with this name.
+    UnknownItem {
+        /// Path of the module.
+        path: String,
+        /// Name of the declaration.
+        item: String,
+        /// Location of the import.
+        location: ComposerLocation,
+    },
+    /// A module imports itself, directly or through other modules.
+    ImportCycle {
+        /// Path of the module.
+        path: String,
+        /// Location of the import.
+        location: ComposerLocation,
+    },
+    /// A directive is unknown, malformed, or has no matching `#ifdef`.
+    InvalidDirective {
+        /// Line of the directive.
+        directive: String,
+        /// Location of the directive.
+        location: ComposerLocation,
+    },
+    /// An `#ifdef` or `#ifndef` has no matching `#endif`.
+    UnclosedConditional {
+        /// Location of the `#ifdef` or `#ifndef`.
+        location: ComposerLocation,
+    },
+    /// The composed shader failed to parse or validate.
+    Shader {
+        /// Description of the error.
+        message: String,
+        /// Location of the error in the original sources, if known.
+        location: Option<ComposerLocation>,
+    },
+}
+
+impl fmt::Display for ComposerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ComposerError::UnknownModule {
+                ref path,
+                ref location,
+            } => write!(f, "{}: no module registered as {:?}", location, path),
+            ComposerError::UnknownItem {
+                ref path,
+                ref item,
+                ref location,
+            } => write!(f, "{}: module {:?} has no item {:?}", location, path, item),
+            ComposerError::ImportCycle {
+                ref path,
+                ref location,
+            } => write!(f, "{}: module {:?} imports itself", location, path),
+            ComposerError::InvalidDirective {
+                ref directive,
+                ref location,
+            } => write!(f, "{}: invalid directive {:?}", location, directive),
+            ComposerError::UnclosedConditional { ref location } => {
+                write!(f, "{}: conditional has no matching #endif", location)
+            }
+            ComposerError::Shader {
+                ref message,
+                location: Some(ref location),
+            } => write!(f, "{}: {}", location, message),
+            ComposerError::Shader {
+                ref message,
+                location: None,
+            } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl error::Error for ComposerError {}
+
+/// Composes WGSL shaders out of registered modules.
+///
+/// Sources can contain the following directives, each on its own line:
+///
+/// - `#import path::item` includes the declaration named `item` of the module registered
+///   as `path`, along with the declarations of the module it uses.
+/// - `#import path` includes all the declarations of the module.
+/// - `#define NAME` defines `NAME` for the rest of the source, and for the modules imported
+///   for the first time after it.
+/// - `#ifdef NAME`, `#ifndef NAME`, `#else` and `#endif` keep or remove the lines between
+///   them, depending on whether `NAME` is defined.
+///
+/// Each module is processed once, with the definitions of its first import, and each
+/// declaration is included once, before the declarations using it. Declarations aren't
+/// renamed, so the names of all the included declarations have to be distinct.
+#[derive(Clone, Debug, Default)]
+pub struct ShaderComposer {
+    modules: HashMap<String, String>,
+}
+
+impl ShaderComposer {
+    /// Create a composer without modules.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the WGSL `source` of a module as `path`, such as `"lighting::pbr"`,
+    /// replacing the module previously registered as `path`.
+    pub fn add_module(&mut self, path: &str, source: &str) {
+        self.modules.insert(path.to_string(), source.to_string());
+    }
+
+    /// Compose the WGSL `source` of a shader named `name`, with the `defs` defined.
+    ///
+    /// All the declarations of the shader are kept, after the imported ones.
+    pub fn compose(
+        &self,
+        name: &str,
+        source: &str,
+        defs: &[&str],
+    ) -> Result<ComposedShader, ComposerError> {
+        let mut composition = Composition {
+            modules: &self.modules,
+            defs: defs.iter().map(|def| def.to_string()).collect(),
+            files: vec![name.to_string()],
+            parsed: vec![None],
+            loaded: HashMap::new(),
+            emitted: HashSet::new(),
+            source: String::new(),
+            lines: Vec::new(),
+        };
+        let preprocessed = composition.preprocess(0, source)?;
+        let items = scan_items(&preprocessed);
+        let count = items.len();
+        composition.parsed[0] = Some(ParsedModule {
+            source: preprocessed,
+            items,
+        });
+        for index in 0..count {
+            composition.emit_item(0, index);
+        }
+        Ok(ComposedShader {
+            source: composition.source,
+            files: composition.files,
+            lines: composition.lines,
+        })
+    }
+}
+
+/// WGSL shader composed by a [`ShaderComposer`].
+#[derive(Clone, Debug)]
+pub struct ComposedShader {
+    source: String,
+    files: Vec<String>,
+    lines: Vec<LineOrigin>,
+}
+
+impl ComposedShader {
+    /// Composed WGSL source.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Composed WGSL source, to create a shader module with.
+    pub fn shader_source(&self) -> ShaderSource<'_> {
+        ShaderSource::Wgsl(Cow::Borrowed(&self.source))
+    }
+
+    /// Returns the location in the original sources of the 1-based `line` and `column`
+    /// of the composed source.
+    pub fn location(&self, line: u32, column: u32) -> Option<ComposerLocation> {
+        let origin = self.lines.get(line.checked_sub(1)? as usize)?;
+        Some(ComposerLocation {
+            file: self.files[origin.file].clone(),
+            line: origin.line,
+            column: column + origin.column_offset,
+        })
+    }
+
+    /// Parse and validate the composed source, with all the capabilities enabled.
+    ///
+    /// Errors are located in the original sources. The module is validated again against
+    /// the capabilities of the device when creating a shader module out of it.
+    #[cfg(feature = "naga")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "naga")))]
+    pub fn parse(&self) -> Result<naga::Module, ComposerError> {
+        let module =
+            naga::front::wgsl::parse_str(&self.source).map_err(|error| ComposerError::Shader {
+                message: error.message().to_string(),
+                location: error.location(&self.source).and_then(|location| {
+                    self.location(location.line_number, location.line_position)
+                }),
+            })?;
+        naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::all(),
+        )
+        .validate(&module)
+        .map_err(|error| {
+            let mut message = error.to_string();
+            let mut source = error::Error::source(error.as_inner());
+            while let Some(inner) = source {
+                message = format!("{}: {}", message, inner);
+                source = inner.source();
+            }
+            ComposerError::Shader {
+                message,
+                location: error.location(&self.source).and_then(|location| {
+                    self.location(location.line_number, location.line_position)
+                }),
+            }
+        })?;
+        Ok(module)
+    }
+}
+
+/// Location in the original sources of a line of the composed source.
+#[derive(Clone, Copy, Debug)]
+struct LineOrigin {
+    file: usize,
+    line: u32,
+    /// Columns before the composed line in the original line.
+    column_offset: u32,
+}
+
+/// Top-level declaration of a source.
+#[derive(Debug)]
+struct Item {
+    name: Option<String>,
+    /// Byte range in the preprocessed source.
+    range: Range<usize>,
+    /// Indices of the declarations of the same source it uses.
+    dependencies: Vec<usize>,
+}
+
+/// Source with its directives processed, and split into declarations.
+struct ParsedModule {
+    source: String,
+    items: Vec<Item>,
+}
+
+/// State of a [`ShaderComposer::compose`] call.
+struct Composition<'a> {
+    modules: &'a HashMap<String, String>,
+    defs: HashSet<String>,
+    /// Names of the sources, the composed shader being the first one.
+    files: Vec<String>,
+    /// Parsed sources, `None` while their directives are processed.
+    parsed: Vec<Option<ParsedModule>>,
+    /// Index in `files` of the modules, by path.
+    loaded: HashMap<String, usize>,
+    emitted: HashSet<(usize, usize)>,
+    source: String,
+    lines: Vec<LineOrigin>,
+}
+
+/// Block of lines between `#ifdef` or `#ifndef` and `#endif`.
+struct Conditional {
+    active: bool,
+    has_else: bool,
+    line: u32,
+}
+
+impl Composition<'_> {
+    fn location(&self, file: usize, line: u32) -> ComposerLocation {
+        ComposerLocation {
+            file: self.files[file].clone(),
+            line,
+            column: 1,
+        }
+    }
+
+    /// Process the directives of the source of a file, resolving its imports, and replace
+    /// them and the removed lines with empty lines.
+    fn preprocess(&mut self, file: usize, source: &str) -> Result<String, ComposerError> {
+        let mut output = String::with_capacity(source.len());
+        let mut conditionals: Vec<Conditional> = Vec::new();
+        for (index, line) in source.lines().enumerate() {
+            let line_number = index as u32 + 1;
+            let active = conditionals.iter().all(|conditional| conditional.active);
+            let directive = match line.trim().strip_prefix('#') {
+                Some(directive) => directive,
+                None => {
+                    if active {
+                        output.push_str(line);
+                    }
+                    output.push('\n');
+                    continue;
+                }
+            };
+            let mut words = directive.split_whitespace();
+            match (words.next(), words.next(), words.next()) {
+                (Some(keyword @ ("ifdef" | "ifndef")), Some(def), None) => {
+                    conditionals.push(Conditional {
+                        active: self.defs.contains(def) == (keyword == "ifdef"),
+                        has_else: false,
+                        line: line_number,
+                    });
+                }
+                (Some("else"), None, None) if matches!(conditionals.last(), Some(conditional) if !conditional.has_else) =>
+                {
+                    let conditional = conditionals.last_mut().unwrap();
+                    conditional.active = !conditional.active;
+                    conditional.has_else = true;
+                }
+                (Some("endif"), None, None) if !conditionals.is_empty() => {
+                    conditionals.pop();
+                }
+                (Some("define"), Some(def), None) => {
+                    if active {
+                        self.defs.insert(def.to_string());
+                    }
+                }
+                (Some("import"), Some(path), None) => {
+                    if active {
+                        self.import(file, line_number, path)?;
+                    }
+                }
+                _ => {
+                    return Err(ComposerError::InvalidDirective {
+                        directive: line.trim().to_string(),
+                        location: self.location(file, line_number),
+                    })
+                }
+            }
+            output.push('\n');
+        }
+        match conditionals.last() {
+            Some(conditional) => Err(ComposerError::UnclosedConditional {
+                location: self.location(file, conditional.line),
+            }),
+            None => Ok(output),
+        }
+    }
+
+    fn import(&mut self, file: usize, line: u32, path: &str) -> Result<(), ComposerError> {
+        if self.modules.contains_key(path) {
+            let module = self.load(file, line, path)?;
+            let count = self.parsed[module].as_ref().unwrap().items.len();
+            for index in 0..count {
+                self.emit_item(module, index);
+            }
+            return Ok(());
+        }
+
+        let (module_path, item) = match path.rfind("::") {
+            Some(position) if self.modules.contains_key(&path[..position]) => {
+                (&path[..position], &path[position + 2..])
+            }
+            _ => {
+                return Err(ComposerError::UnknownModule {
+                    path: path.to_string(),
+                    location: self.location(file, line),
+                })
+            }
+        };
+        let module = self.load(file, line, module_path)?;
+        let index = self.parsed[module]
+            .as_ref()
+            .unwrap()
+            .items
+            .iter()
+            .position(|candidate| candidate.name.as_deref() == Some(item))
+            .ok_or_else(|| ComposerError::UnknownItem {
+                path: module_path.to_string(),
+                item: item.to_string(),
+                location: self.location(file, line),
+            })?;
+        self.emit_item(module, index);
+        Ok(())
+    }
+
+    /// Returns the index in `files` of the module registered as `path`, processing it
+    /// the first time.
+    fn load(&mut self, file: usize, line: u32, path: &str) -> Result<usize, ComposerError> {
+        if let Some(&module) = self.loaded.get(path) {
+            if self.parsed[module].is_none() {
+                return Err(ComposerError::ImportCycle {
+                    path: path.to_string(),
+                    location: self.location(file, line),
+                });
+            }
+            return Ok(module);
+        }
+
+        let module = self.files.len();
+        self.files.push(path.to_string());
+        self.parsed.push(None);
+        self.loaded.insert(path.to_string(), module);
+        let modules = self.modules;
+        let source = self.preprocess(module, &modules[path])?;
+        self.parsed[module] = Some(ParsedModule {
+            items: scan_items(&source),
+            source,
+        });
+        Ok(module)
+    }
+
+    /// Append a declaration to the composed source, after the ones it uses.
+    fn emit_item(&mut self, file: usize, index: usize) {
+        if !self.emitted.insert((file, index)) {
+            return;
+        }
+        let dependencies = self.parsed[file].as_ref().unwrap().items[index]
+            .dependencies
+            .clone();
+        for dependency in dependencies {
+            self.emit_item(file, dependency);
+        }
+
+        let module = self.parsed[file].as_ref().unwrap();
+        let range = module.items[index].range.clone();
+        let prefix = &module.source[..range.start];
+        let line = prefix.matches('\n').count() as u32 + 1;
+        let line_start = prefix.rfind('\n').map_or(0, |position| position + 1);
+        let column_offset = prefix[line_start..].chars().count() as u32;
+        let text = &module.source[range];
+        for (index, _) in text.split('\n').enumerate() {
+            self.lines.push(LineOrigin {
+                file,
+                line: line + index as u32,
+                column_offset: if index == 0 { column_offset } else { 0 },
+            });
+        }
+        self.source.push_str(text);
+        self.source.push('\n');
+    }
+}
+
+/// Declaration being scanned.
+struct PendingItem<'a> {
+    start: usize,
+    name: Option<&'a str>,
+    /// Whether the keyword preceding the name was found.
+    declaration: bool,
+    /// Depth of the template list of a `var` before its name.
+    template_depth: u32,
+    identifiers: Vec<&'a str>,
+}
+
+/// Split a preprocessed source into its top-level declarations.
+fn scan_items(source: &str) -> Vec<Item> {
+    let bytes = source.as_bytes();
+    let mut scanned = Vec::new();
+    let mut pending: Option<PendingItem> = None;
+    let mut depth = 0u32;
+    let mut position = 0;
+    while position < bytes.len() {
+        let start = position;
+        let byte = bytes[position];
+        if byte.is_ascii_whitespace() {
+            position += 1;
+            continue;
+        }
+        if source[position..].starts_with("//") {
+            position = source[position..]
+                .find('\n')
+                .map_or(bytes.len(), |end| position + end);
+            continue;
+        }
+        if source[position..].starts_with("/*") {
+            let mut comment_depth = 0;
+            while position < bytes.len() {
+                if source[position..].starts_with("/*") {
+                    comment_depth += 1;
+                    position += 2;
+                } else if source[position..].starts_with("*/") {
+                    comment_depth -= 1;
+                    position += 2;
+                    if comment_depth == 0 {
+                        break;
+                    }
+                } else {
+                    position += 1;
+                }
+            }
+            continue;
+        }
+
+        let item = pending.get_or_insert_with(|| PendingItem {
+            start,
+            name: None,
+            declaration: false,
+            template_depth: 0,
+            identifiers: Vec::new(),
+        });
+        if byte == b'_' || byte.is_ascii_alphanumeric() {
+            while position < bytes.len()
+                && (bytes[position] == b'_' || bytes[position].is_ascii_alphanumeric())
+            {
+                position += 1;
+            }
+            if byte.is_ascii_digit() {
+                continue;
+            }
+            let identifier = &source[start..position];
+            if item.name.is_none() {
+                if !item.declaration {
+                    item.declaration = DECLARATION_KEYWORDS.contains(&identifier);
+                } else if item.template_depth == 0 {
+                    item.name = Some(identifier);
+                }
+            }
+            item.identifiers.push(identifier);
+            continue;
+        }
+
+        // punctuation, possibly the start of a multi-byte character
+        position += source[position..].chars().next().map_or(1, char::len_utf8);
+        let end = match byte {
+            b'<' if item.declaration && item.name.is_none() => {
+                item.template_depth += 1;
+                false
+            }
+            b'>' if item.declaration && item.name.is_none() => {
+                item.template_depth = item.template_depth.saturating_sub(1);
+                false
+            }
+            b'{' => {
+                depth += 1;
+                false
+            }
+            b'}' => {
+                depth = depth.saturating_sub(1);
+                depth == 0
+            }
+            b';' => depth == 0,
+            _ => false,
+        };
+        if end {
+            let item = pending.take().unwrap();
+            // skip the optional semicolon after structures
+            if item.start != start {
+                scanned.push((item, position));
+            }
+        }
+    }
+    if let Some(item) = pending {
+        scanned.push((item, bytes.len()));
+    }
+
+    let names = scanned
+        .iter()
+        .enumerate()
+        .filter_map(|(index, (item, _))| Some((item.name?, index)))
+        .collect::<HashMap<_, _>>();
+    scanned
+        .iter()
+        .enumerate()
+        .map(|(index, &(ref item, end))| {
+            let mut dependencies = Vec::new();
+            for identifier in item.identifiers.iter() {
+                match names.get(identifier) {
+                    Some(&dependency)
+                        if dependency != index && !dependencies.contains(&dependency) =>
+                    {
+                        dependencies.push(dependency)
+                    }
+                    _ => {}
+                }
+            }
+            Item {
+                name: item.name.map(str::to_string),
+                range: item.start..end,
+                dependencies,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,57 @@
+// Stream compaction passes of `ParallelPrimitives`, with `{workgroup_size}` replaced
+// by the workgroup size.
+
+struct Params {
+    len: u32,
+    blocks: u32,
+    blocks_x: u32,
+    _padding: u32,
+}
+
+@group(0) @binding(0)
+var<uniform> params: Params;
+@group(0) @binding(1)
+var<storage, read> flags: array<u32>;
+@group(0) @binding(2)
+var<storage, read_write> kept: array<u32>;
+@group(0) @binding(3)
+var<storage, read> input: array<u32>;
+@group(0) @binding(4)
+var<storage, read> positions: array<u32>;
+@group(0) @binding(5)
+var<storage, read_write> output: array<u32>;
+
+fn element_index(workgroup_id: vec3<u32>, local_index: u32) -> u32 {
+    return (workgroup_id.x + workgroup_id.y * params.blocks_x) * {workgroup_size}u + local_index;
+}
+
+// Turns the flags into ones and zeros, to be scanned into the positions.
+@compute @workgroup_size({workgroup_size})
+fn normalize_flags(
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    let index = element_index(workgroup_id, local_index);
+    if (index < params.len) {
+        kept[index] = select(0u, 1u, flags[index] != 0u);
+    }
+}
+
+// Moves the kept elements to their position, which is the inclusive scan of the
+// kept elements minus one.
+@compute @workgroup_size({workgroup_size})
+fn scatter(
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    let index = element_index(workgroup_id, local_index);
+    if (index < params.len) {
+        var position = 0u;
+        if (index > 0u) {
+            position = positions[index - 1u];
+        }
+        if (positions[index] != position) {
+            output[position] = input[index];
+        }
+    }
+}
@@ -0,0 +1,493 @@
+//! Parallel primitives running on the GPU: scans, reductions, stream compaction
+//! and radix sort.
+//!
+//! They are recorded into a [`CommandEncoder`] by [`ParallelPrimitives`], and operate
+//! on storage buffers of 32-bit elements.
+
+use crate::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroupDescriptor, BindGroupEntry, Buffer, BufferDescriptor, BufferSize, BufferUsages,
+    CommandEncoder, ComputePassDescriptor, ComputePipeline, ComputePipelineDescriptor, Device,
+    ShaderModuleDescriptor, ShaderSource,
+};
+use std::{borrow::Cow, collections::HashMap, fmt};
+
+const SCAN_SHADER: &str = include_str!("scan.wgsl");
+const SORT_SHADER: &str = include_str!("sort.wgsl");
+const COMPACT_SHADER: &str = include_str!("compact.wgsl");
+
+/// Number of bits of the keys sorted by each radix sort pass.
+const RADIX_BITS: u32 = 4;
+/// Number of digits of a radix sort pass.
+const RADIX_DIGITS: u32 = 1 << RADIX_BITS;
+/// Workgroup memory used by the radix sort, on top of an element per invocation.
+const HISTOGRAM_SIZE: u32 = RADIX_DIGITS * 4;
+
+/// Types of the elements scanned and reduced by [`ParallelPrimitives`].
+///
+/// Implemented for `u32`, `i32` and `f32`.
+pub trait Element: Copy {
+    /// Name of the type in WGSL.
+    const WGSL_TYPE: &'static str;
+    #[doc(hidden)]
+    const ZERO: &'static str;
+    #[doc(hidden)]
+    const MIN: &'static str;
+    #[doc(hidden)]
+    const MAX: &'static str;
+}
+
+impl Element for u32 {
+    const WGSL_TYPE: &'static str = "u32";
+    const ZERO: &'static str = "0u";
+    const MIN: &'static str = "0u";
+    const MAX: &'static str = "0xffffffffu";
+}
+
+impl Element for i32 {
+    const WGSL_TYPE: &'static str = "i32";
+    const ZERO: &'static str = "0";
+    const MIN: &'static str = "bitcast<i32>(0x80000000u)";
+    const MAX: &'static str = "2147483647";
+}
+
+impl Element for f32 {
+    const WGSL_TYPE: &'static str = "f32";
+    const ZERO: &'static str = "0.0";
+    const MIN: &'static str = "bitcast<f32>(0xff800000u)";
+    const MAX: &'static str = "bitcast<f32>(0x7f800000u)";
+}
+
+/// Operation combining the elements of scans and reductions.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ReduceOp {
+    /// Sum of the elements, wrapping around for integers.
+    Sum,
+    /// Smallest element.
+    Min,
+    /// Largest element.
+    Max,
+}
+
+/// Kind of scan, also known as prefix sum.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ScanKind {
+    /// Each output element combines the input elements before it, the first one
+    /// being the identity of the operation.
+    Exclusive,
+    /// Each output element combines the input elements up to and including it.
+    Inclusive,
+}
+
+/// Shader module of a set of primitives.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Shader {
+    Scan {
+        ty: &'static str,
+        identity: &'static str,
+        op: ReduceOp,
+    },
+    Sort,
+    Compact,
+}
+
+impl Shader {
+    fn scan<T: Element>(op: ReduceOp) -> Self {
+        let identity = match op {
+            ReduceOp::Sum => T::ZERO,
+            ReduceOp::Min => T::MAX,
+            ReduceOp::Max => T::MIN,
+        };
+        Shader::Scan {
+            ty: T::WGSL_TYPE,
+            identity,
+            op,
+        }
+    }
+
+    fn entry_points(self) -> &'static [&'static str] {
+        match self {
+            Shader::Scan { .. } => &["scan_blocks", "add_block_sums", "reduce_blocks"],
+            Shader::Sort => &["count_digits", "scatter"],
+            Shader::Compact => &["normalize_flags", "scatter"],
+        }
+    }
+
+    fn source(self, workgroup_size: u32) -> String {
+        let source = match self {
+            Shader::Scan { ty, identity, op } => {
+                let combine = match op {
+                    ReduceOp::Sum => "a + b",
+                    ReduceOp::Min => "min(a, b)",
+                    ReduceOp::Max => "max(a, b)",
+                };
+                SCAN_SHADER
+                    .replace("{T}", ty)
+                    .replace("{identity}", identity)
+                    .replace("{combine}", combine)
+            }
+            Shader::Sort => SORT_SHADER.to_string(),
+            Shader::Compact => COMPACT_SHADER.to_string(),
+        };
+        source.replace("{workgroup_size}", &workgroup_size.to_string())
+    }
+}
+
+/// Records scans, reductions, stream compactions and radix sorts of buffers.
+///
+/// The workgroup size is the largest power of two up to 256 allowed by the
+/// `max_compute_workgroup_size_x`, `max_compute_invocations_per_workgroup` and
+/// `max_compute_workgroup_storage_size` limits of the device. Buffers longer than
+/// a workgroup are processed in blocks, whose results are combined by further passes,
+/// and the blocks are spread over two dimensions when there are more of them than
+/// `max_compute_workgroups_per_dimension`.
+///
+/// Buffers passed to the primitives need the `STORAGE` usage, and to be at least as
+/// long as the processed elements. Temporary buffers are created for each call.
+/// Pipelines are created on first use and reused across calls.
+pub struct ParallelPrimitives {
+    workgroup_size: u32,
+    max_workgroups: u32,
+    pipelines: HashMap<(Shader, &'static str), ComputePipeline>,
+}
+
+impl ParallelPrimitives {
+    /// Create the primitives, with a workgroup size fitting the limits of the `device`.
+    pub fn new(device: &Device) -> Self {
+        let limits = device.limits();
+        let size = 256
+            .min(limits.max_compute_workgroup_size_x)
+            .min(limits.max_compute_invocations_per_workgroup)
+            .min(
+                limits
+                    .max_compute_workgroup_storage_size
+                    .saturating_sub(HISTOGRAM_SIZE)
+                    / 4,
+            )
+            .max(1);
+        ParallelPrimitives {
+            // reductions halve the workgroup size at each step
+            workgroup_size: 1 << (31 - size.leading_zeros()),
+            max_workgroups: limits.max_compute_workgroups_per_dimension.max(1),
+            pipelines: HashMap::new(),
+        }
+    }
+
+    /// Number of invocations of the workgroups, and number of elements of each block.
+    pub fn workgroup_size(&self) -> u32 {
+        self.workgroup_size
+    }
+
+    /// Record the scan of the first `len` elements of `input` into `output`.
+    ///
+    /// `input` and `output` have to be different buffers.
+    #[allow(clippy::too_many_arguments)]
+    pub fn scan<T: Element>(
+        &mut self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        input: &Buffer,
+        output: &Buffer,
+        len: u32,
+        op: ReduceOp,
+        kind: ScanKind,
+    ) {
+        let shader = Shader::scan::<T>(op);
+        self.prepare(device, shader);
+        self.record_scan(
+            device,
+            encoder,
+            shader,
+            input,
+            output,
+            len,
+            kind == ScanKind::Inclusive,
+        );
+    }
+
+    /// Record the reduction of the first `len` elements of `input` into the first
+    /// element of `output`.
+    ///
+    /// The reduction of no elements is the identity of the operation.
+    pub fn reduce<T: Element>(
+        &mut self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        input: &Buffer,
+        output: &Buffer,
+        len: u32,
+        op: ReduceOp,
+    ) {
+        let shader = Shader::scan::<T>(op);
+        self.prepare(device, shader);
+        self.record_reduce(device, encoder, shader, input, output, len);
+    }
+
+    /// Record the compaction of the first `len` elements of `input` whose `u32` flag in
+    /// `flags` isn't zero into `output`, keeping their order.
+    ///
+    /// The number of kept elements is written as a `u32` at the start of `count`,
+    /// which needs the `COPY_DST` usage.
+    #[allow(clippy::too_many_arguments)]
+    pub fn compact(
+        &mut self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        input: &Buffer,
+        flags: &Buffer,
+        output: &Buffer,
+        count: &Buffer,
+        len: u32,
+    ) {
+        if len == 0 {
+            encoder.clear_buffer(count, 0, BufferSize::new(4));
+            return;
+        }
+        let scan = Shader::scan::<u32>(ReduceOp::Sum);
+        self.prepare(device, scan);
+        self.prepare(device, Shader::Compact);
+
+        let kept = scratch_buffer(device, len, BufferUsages::empty());
+        let positions = scratch_buffer(device, len, BufferUsages::COPY_SRC);
+        self.dispatch(
+            device,
+            encoder,
+            (Shader::Compact, "normalize_flags"),
+            len,
+            0,
+            &[(1, flags), (2, &kept)],
+        );
+        self.record_scan(device, encoder, scan, &kept, &positions, len, true);
+        self.dispatch(
+            device,
+            encoder,
+            (Shader::Compact, "scatter"),
+            len,
+            0,
+            &[(3, input), (4, &positions), (5, output)],
+        );
+        encoder.copy_buffer_to_buffer(&positions, (len as u64 - 1) * 4, count, 0, 4);
+    }
+
+    /// Record the sort of the first `len` `u32` elements of `keys` in ascending order,
+    /// moving the 32-bit elements of `values` along with them.
+    ///
+    /// The sort is stable: values of equal keys keep their order.
+    pub fn sort(
+        &mut self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        keys: &Buffer,
+        values: &Buffer,
+        len: u32,
+    ) {
+        if len < 2 {
+            return;
+        }
+        let scan = Shader::scan::<u32>(ReduceOp::Sum);
+        self.prepare(device, scan);
+        self.prepare(device, Shader::Sort);
+
+        let digit_count = RADIX_DIGITS * self.block_count(len);
+        let counts = scratch_buffer(device, digit_count, BufferUsages::empty());
+        let offsets = scratch_buffer(device, digit_count, BufferUsages::empty());
+        let temp_keys = scratch_buffer(device, len, BufferUsages::empty());
+        let temp_values = scratch_buffer(device, len, BufferUsages::empty());
+        // there is an even number of passes, so the last one writes to `keys` and `values`
+        let buffers = [(keys, values), (&temp_keys, &temp_values)];
+        for pass in 0..32 / RADIX_BITS {
+            let (src_keys, src_values) = buffers[pass as usize % 2];
+            let (dst_keys, dst_values) = buffers[1 - pass as usize % 2];
+            let shift = pass * RADIX_BITS;
+            self.dispatch(
+                device,
+                encoder,
+                (Shader::Sort, "count_digits"),
+                len,
+                shift,
+                &[(1, src_keys), (2, &counts)],
+            );
+            self.record_scan(device, encoder, scan, &counts, &offsets, digit_count, false);
+            for (src, dst) in [(src_keys, dst_keys), (src_values, dst_values)] {
+                self.dispatch(
+                    device,
+                    encoder,
+                    (Shader::Sort, "scatter"),
+                    len,
+                    shift,
+                    &[(1, src_keys), (3, &offsets), (4, src), (5, dst)],
+                );
+            }
+        }
+    }
+
+    fn prepare(&mut self, device: &Device, shader: Shader) {
+        if self
+            .pipelines
+            .contains_key(&(shader, shader.entry_points()[0]))
+        {
+            return;
+        }
+        let module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("(wgpu internal) ParallelPrimitives shader"),
+            source: ShaderSource::Wgsl(Cow::Owned(shader.source(self.workgroup_size))),
+        });
+        for &entry_point in shader.entry_points() {
+            let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+                label: Some("(wgpu internal) ParallelPrimitives pipeline"),
+                layout: None,
+                module: &module,
+                entry_point,
+            });
+            self.pipelines.insert((shader, entry_point), pipeline);
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn record_scan(
+        &self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        shader: Shader,
+        input: &Buffer,
+        output: &Buffer,
+        len: u32,
+        inclusive: bool,
+    ) {
+        if len == 0 {
+            return;
+        }
+        let blocks = self.block_count(len);
+        let block_sums = scratch_buffer(device, blocks, BufferUsages::empty());
+        self.dispatch(
+            device,
+            encoder,
+            (shader, "scan_blocks"),
+            len,
+            inclusive as u32,
+            &[(1, input), (2, output), (3, &block_sums)],
+        );
+        if blocks > 1 {
+            let block_prefixes = scratch_buffer(device, blocks, BufferUsages::empty());
+            self.record_scan(
+                device,
+                encoder,
+                shader,
+                &block_sums,
+                &block_prefixes,
+                blocks,
+                false,
+            );
+            self.dispatch(
+                device,
+                encoder,
+                (shader, "add_block_sums"),
+                len,
+                0,
+                &[(2, output), (4, &block_prefixes)],
+            );
+        }
+    }
+
+    fn record_reduce(
+        &self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        shader: Shader,
+        input: &Buffer,
+        output: &Buffer,
+        len: u32,
+    ) {
+        let blocks = self.block_count(len);
+        if blocks == 1 {
+            self.dispatch(
+                device,
+                encoder,
+                (shader, "reduce_blocks"),
+                len,
+                0,
+                &[(1, input), (2, output)],
+            );
+        } else {
+            let partials = scratch_buffer(device, blocks, BufferUsages::empty());
+            self.dispatch(
+                device,
+                encoder,
+                (shader, "reduce_blocks"),
+                len,
+                0,
+                &[(1, input), (2, &partials)],
+            );
+            self.record_reduce(device, encoder, shader, &partials, output, blocks);
+        }
+    }
+
+    /// Number of blocks of `len` elements, at least one.
+    fn block_count(&self, len: u32) -> u32 {
+        let size = self.workgroup_size as u64;
+        ((len as u64 + size - 1) / size).max(1) as u32
+    }
+
+    /// Record a compute pass running the `kernel` on a workgroup per block of `len`
+    /// elements, with the parameters at binding 0 and the `buffers` at their bindings.
+    fn dispatch(
+        &self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        kernel: (Shader, &'static str),
+        len: u32,
+        extra: u32,
+        buffers: &[(u32, &Buffer)],
+    ) {
+        let blocks = self.block_count(len);
+        let blocks_x = blocks.min(self.max_workgroups);
+        let blocks_y = (blocks + blocks_x - 1) / blocks_x;
+        let params = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("(wgpu internal) ParallelPrimitives parameters"),
+            contents: &[len, blocks, blocks_x, extra]
+                .iter()
+                .flat_map(|value| value.to_le_bytes())
+                .collect::<Vec<u8>>(),
+            usage: BufferUsages::UNIFORM,
+        });
+
+        let pipeline = &self.pipelines[&kernel];
+        let mut entries = vec![BindGroupEntry {
+            binding: 0,
+            resource: params.as_entire_binding(),
+        }];
+        entries.extend(buffers.iter().map(|&(binding, buffer)| BindGroupEntry {
+            binding,
+            resource: buffer.as_entire_binding(),
+        }));
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: None,
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &entries,
+        });
+        let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some("(wgpu internal) ParallelPrimitives"),
+        });
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.dispatch_workgroups(blocks_x, blocks_y, 1);
+    }
+}
+
+impl fmt::Debug for ParallelPrimitives {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ParallelPrimitives")
+            .field("workgroup_size", &self.workgroup_size)
+            .field("pipelines", &self.pipelines.len())
+            .finish_non_exhaustive()
+    }
+}
+
+fn scratch_buffer(device: &Device, len: u32, usage: BufferUsages) -> Buffer {
+    device.create_buffer(&BufferDescriptor {
+        label: Some("(wgpu internal) ParallelPrimitives scratch"),
+        size: len.max(1) as u64 * 4,
+        usage: BufferUsages::STORAGE | usage,
+        mapped_at_creation: false,
+    })
+}
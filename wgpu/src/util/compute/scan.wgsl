@@ -0,0 +1,105 @@
+// Scans and reductions of `ParallelPrimitives`, with `{T}` replaced by the element
+// type, `{identity}` by the identity of the operation, `{combine}` by the operation
+// applied to `a` and `b`, and `{workgroup_size}` by the workgroup size, a power of two.
+
+struct Params {
+    len: u32,
+    blocks: u32,
+    blocks_x: u32,
+    inclusive: u32,
+}
+
+@group(0) @binding(0)
+var<uniform> params: Params;
+@group(0) @binding(1)
+var<storage, read> input: array<{T}>;
+@group(0) @binding(2)
+var<storage, read_write> output: array<{T}>;
+@group(0) @binding(3)
+var<storage, read_write> block_sums: array<{T}>;
+@group(0) @binding(4)
+var<storage, read> block_prefixes: array<{T}>;
+
+var<workgroup> scratch: array<{T}, {workgroup_size}>;
+
+fn combine(a: {T}, b: {T}) -> {T} {
+    return {combine};
+}
+
+// Index of the block, the grid being 2D when there are too many blocks for one dimension.
+fn block_index(workgroup_id: vec3<u32>) -> u32 {
+    return workgroup_id.x + workgroup_id.y * params.blocks_x;
+}
+
+fn load_input(index: u32) -> {T} {
+    if (index < params.len) {
+        return input[index];
+    }
+    return {identity};
+}
+
+// Scans each block, and writes the total of each block to `block_sums`.
+@compute @workgroup_size({workgroup_size})
+fn scan_blocks(
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    let block = block_index(workgroup_id);
+    let index = block * {workgroup_size}u + local_index;
+    scratch[local_index] = load_input(index);
+    for (var offset = 1u; offset < {workgroup_size}u; offset = offset * 2u) {
+        workgroupBarrier();
+        var other = {identity};
+        if (local_index >= offset) {
+            other = scratch[local_index - offset];
+        }
+        workgroupBarrier();
+        scratch[local_index] = combine(other, scratch[local_index]);
+    }
+    workgroupBarrier();
+
+    if (index < params.len) {
+        if (params.inclusive != 0u) {
+            output[index] = scratch[local_index];
+        } else if (local_index == 0u) {
+            output[index] = {identity};
+        } else {
+            output[index] = scratch[local_index - 1u];
+        }
+    }
+    if (local_index == 0u && block < params.blocks) {
+        block_sums[block] = scratch[{workgroup_size}u - 1u];
+    }
+}
+
+// Combines each block of a scan with the exclusive scan of the totals of the blocks.
+@compute @workgroup_size({workgroup_size})
+fn add_block_sums(
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    let block = block_index(workgroup_id);
+    let index = block * {workgroup_size}u + local_index;
+    if (index < params.len) {
+        output[index] = combine(block_prefixes[block], output[index]);
+    }
+}
+
+// Reduces each block to a single element of `output`.
+@compute @workgroup_size({workgroup_size})
+fn reduce_blocks(
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    let block = block_index(workgroup_id);
+    scratch[local_index] = load_input(block * {workgroup_size}u + local_index);
+    for (var stride = {workgroup_size}u / 2u; stride > 0u; stride = stride / 2u) {
+        workgroupBarrier();
+        if (local_index < stride) {
+            scratch[local_index] = combine(scratch[local_index], scratch[local_index + stride]);
+        }
+    }
+    if (local_index == 0u && block < params.blocks) {
+        output[block] = scratch[0];
+    }
+}
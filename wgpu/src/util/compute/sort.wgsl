@@ -0,0 +1,81 @@
+// Radix sort passes of `ParallelPrimitives`, with `{workgroup_size}` replaced by the
+// workgroup size. Each pass sorts by the 4 bits of the keys starting at `params.shift`.
+
+struct Params {
+    len: u32,
+    blocks: u32,
+    blocks_x: u32,
+    shift: u32,
+}
+
+@group(0) @binding(0)
+var<uniform> params: Params;
+@group(0) @binding(1)
+var<storage, read> keys: array<u32>;
+@group(0) @binding(2)
+var<storage, read_write> counts: array<u32>;
+@group(0) @binding(3)
+var<storage, read> offsets: array<u32>;
+@group(0) @binding(4)
+var<storage, read> payload_in: array<u32>;
+@group(0) @binding(5)
+var<storage, read_write> payload_out: array<u32>;
+
+var<workgroup> histogram: array<atomic<u32>, 16>;
+var<workgroup> digits: array<u32, {workgroup_size}>;
+
+fn block_index(workgroup_id: vec3<u32>) -> u32 {
+    return workgroup_id.x + workgroup_id.y * params.blocks_x;
+}
+
+// Counts the digits of each block into `counts[digit * blocks + block]`, so that
+// their exclusive scan is the first sorted position of each digit of each block.
+@compute @workgroup_size({workgroup_size})
+fn count_digits(
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    let block = block_index(workgroup_id);
+    for (var digit = local_index; digit < 16u; digit = digit + {workgroup_size}u) {
+        atomicStore(&histogram[digit], 0u);
+    }
+    workgroupBarrier();
+    let index = block * {workgroup_size}u + local_index;
+    if (index < params.len) {
+        atomicAdd(&histogram[(keys[index] >> params.shift) & 15u], 1u);
+    }
+    workgroupBarrier();
+    if (block < params.blocks) {
+        for (var digit = local_index; digit < 16u; digit = digit + {workgroup_size}u) {
+            counts[digit * params.blocks + block] = atomicLoad(&histogram[digit]);
+        }
+    }
+}
+
+// Moves the payload of each key to its sorted position, keeping the order of keys
+// with the same digit.
+@compute @workgroup_size({workgroup_size})
+fn scatter(
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    let block = block_index(workgroup_id);
+    let index = block * {workgroup_size}u + local_index;
+    // keys past the end get a digit which never matches
+    var digit = 16u;
+    if (index < params.len) {
+        digit = (keys[index] >> params.shift) & 15u;
+    }
+    digits[local_index] = digit;
+    workgroupBarrier();
+
+    if (index < params.len) {
+        var rank = 0u;
+        for (var i = 0u; i < local_index; i = i + 1u) {
+            if (digits[i] == digit) {
+                rank = rank + 1u;
+            }
+        }
+        payload_out[offsets[digit * params.blocks + block] + rank] = payload_in[index];
+    }
+}
@@ -0,0 +1,591 @@
+//! Decoder of the LDR profile of ASTC.
+//!
+//! Blocks which can't be decoded, including those using HDR endpoints, decode to magenta
+//! as the specification requires.
+
+const ERROR_COLOR: [u8; 4] = [255, 0, 255, 255];
+
+/// Ranges of the integer sequence encoding, as the kind of their trits or quints, or 0
+/// when there are neither, followed by their count of bits.
+///
+/// The index of a range is its quantization level.
+const RANGES: [(u32, u32); 21] = [
+    (0, 1),
+    (3, 0),
+    (0, 2),
+    (5, 0),
+    (3, 1),
+    (0, 3),
+    (5, 1),
+    (3, 2),
+    (0, 4),
+    (5, 2),
+    (3, 3),
+    (0, 5),
+    (5, 3),
+    (3, 4),
+    (0, 6),
+    (5, 4),
+    (3, 5),
+    (0, 7),
+    (5, 5),
+    (3, 6),
+    (0, 8),
+];
+
+/// Lowest quantization level of the color endpoints, with 6 values.
+const MIN_COLOR_LEVEL: usize = 4;
+
+/// Reads the bits of a 128 bit block, from the least significant one.
+///
+/// The bits from `end` on read as zeros.
+struct Bits {
+    value: u128,
+    position: u32,
+    end: u32,
+}
+
+impl Bits {
+    fn read(&mut self, count: u32) -> u32 {
+        let available = self.end.saturating_sub(self.position).min(count);
+        let value = if available == 0 {
+            0
+        } else {
+            field(self.value, self.position, available)
+        };
+        self.position += count;
+        value
+    }
+}
+
+/// Extracts `count` bits of `block`, starting from the bit `lowest`.
+fn field(block: u128, lowest: u32, count: u32) -> u32 {
+    (block >> lowest) as u32 & (u32::MAX >> (32 - count))
+}
+
+/// Returns the count of bits of `count` values encoded in the range of the given level.
+fn ise_bits(count: u32, level: usize) -> u32 {
+    let (kind, bits) = RANGES[level];
+    match kind {
+        3 => (8 * count + 4) / 5 + count * bits,
+        5 => (7 * count + 2) / 3 + count * bits,
+        _ => count * bits,
+    }
+}
+
+fn bit(value: u32, index: u32) -> u32 {
+    (value >> index) & 1
+}
+
+fn decode_trits(t: u32) -> [u32; 5] {
+    let (c, t3, t4) = if (t >> 2) & 7 == 7 {
+        ((t >> 5) << 2 | (t & 3), 2, 2)
+    } else if (t >> 5) & 3 == 3 {
+        (t & 0x1F, bit(t, 7), 2)
+    } else {
+        (t & 0x1F, (t >> 5) & 3, bit(t, 7))
+    };
+    let (t0, t1, t2) = if c & 3 == 3 {
+        (bit(c, 3) << 1 | (bit(c, 2) & !bit(c, 3) & 1), bit(c, 4), 2)
+    } else if (c >> 2) & 3 == 3 {
+        (c & 3, 2, 2)
+    } else {
+        (
+            bit(c, 1) << 1 | (bit(c, 0) & !bit(c, 1) & 1),
+            (c >> 2) & 3,
+            bit(c, 4),
+        )
+    };
+    [t0, t1, t2, t3, t4]
+}
+
+fn decode_quints(q: u32) -> [u32; 3] {
+    if (q >> 1) & 3 == 3 && (q >> 5) & 3 == 0 {
+        let not_q0 = !q & 1;
+        let q2 = bit(q, 0) << 2 | (bit(q, 4) & not_q0) << 1 | (bit(q, 3) & not_q0);
+        return [4, 4, q2];
+    }
+    let (c, q2) = if (q >> 1) & 3 == 3 {
+        (((q >> 3) & 3) << 3 | (!(q >> 5) & 3) << 1 | (q & 1), 4)
+    } else {
+        (q & 0x1F, (q >> 5) & 3)
+    };
+    if c & 7 == 5 {
+        [(c >> 3) & 3, 4, q2]
+    } else {
+        [c & 7, (c >> 3) & 3, q2]
+    }
+}
+
+/// Decodes `count` values of the integer sequence encoding, starting from the current position.
+///
+/// Each value is returned as its trit or quint, followed by its bits.
+fn decode_ise(bits: &mut Bits, count: usize, level: usize) -> Vec<u32> {
+    // the bits of the trits and quints are spread between the bits of a group of values
+    const TRIT_BITS: &[u32] = &[2, 2, 1, 2, 1];
+    const QUINT_BITS: &[u32] = &[3, 2, 2];
+
+    let (kind, bit_count) = RANGES[level];
+    let mut values = Vec::with_capacity(count + 4);
+    while values.len() < count {
+        let group = match kind {
+            3 => TRIT_BITS,
+            5 => QUINT_BITS,
+            _ => {
+                values.push(bits.read(bit_count));
+                continue;
+            }
+        };
+        let mut low = [0; 5];
+        let (mut packed, mut shift) = (0, 0);
+        for (low, &count) in low.iter_mut().zip(group) {
+            *low = bits.read(bit_count);
+            packed |= bits.read(count) << shift;
+            shift += count;
+        }
+        let high = if kind == 3 {
+            decode_trits(packed).to_vec()
+        } else {
+            decode_quints(packed).to_vec()
+        };
+        for (high, low) in high.into_iter().zip(low) {
+            values.push(high << bit_count | low);
+        }
+    }
+    values.truncate(count);
+    values
+}
+
+/// Replicates the lowest `bits` bits of `value` to fill `target` bits.
+fn replicate(value: u32, bits: u32, target: u32) -> u32 {
+    let mut result = 0;
+    let mut filled = 0;
+    while filled < target {
+        result = result << bits | value;
+        filled += bits;
+    }
+    result >> (filled - target)
+}
+
+/// Unquantizes a color endpoint value to `0..=255`.
+fn unquantize_color(value: u32, level: usize) -> i32 {
+    let (kind, bits) = RANGES[level];
+    if kind == 0 {
+        return replicate(value, bits, 8) as i32;
+    }
+    let d = value >> bits;
+    let m = value & ((1 << bits) - 1);
+    // the bits of `m` above the lowest one
+    let high = m >> 1;
+    let a = if m & 1 != 0 { 0x1FF } else { 0 };
+    let (b, c) = match (kind, bits) {
+        (3, 1) => (0, 204),
+        (3, 2) => (high * 0b1_0001_0110, 93),
+        (3, 3) => (high * 0b1000_0101, 44),
+        (3, 4) => (high * 0b100_0001, 22),
+        (3, 5) => (high << 5 | high >> 2, 11),
+        (3, 6) => (high << 4 | high >> 4, 5),
+        (5, 1) => (0, 113),
+        (5, 2) => (high * 0b1_0000_1100, 54),
+        (5, 3) => (high << 7 | high << 1 | high >> 1, 26),
+        (5, 4) => (high << 6 | high >> 1, 13),
+        (5, 5) => (high << 5 | high >> 3, 6),
+        _ => unreachable!(),
+    };
+    let t = (d * c + b) ^ a;
+    ((a & 0x80) | (t >> 2)) as i32
+}
+
+/// Unquantizes a weight to `0..=64`.
+fn unquantize_weight(value: u32, level: usize) -> u32 {
+    let (kind, bits) = RANGES[level];
+    let result = match (kind, bits) {
+        (0, _) => replicate(value, bits, 6),
+        (3, 0) => return value * 32,
+        (5, 0) => return value * 16,
+        _ => {
+            let d = value >> bits;
+            let m = value & ((1 << bits) - 1);
+            let high = m >> 1;
+            let a = if m & 1 != 0 { 0x7F } else { 0 };
+            let (b, c) = match (kind, bits) {
+                (3, 1) => (0, 50),
+                (3, 2) => (high * 0b100_0101, 23),
+                (3, 3) => (high * 0b10_0001, 11),
+                (5, 1) => (0, 28),
+                (5, 2) => (high * 0b100_0010, 13),
+                _ => unreachable!(),
+            };
+            let t = (d * c + b) ^ a;
+            (a & 0x20) | (t >> 2)
+        }
+    };
+    if result > 32 {
+        result + 1
+    } else {
+        result
+    }
+}
+
+fn hash52(mut p: u32) -> u32 {
+    p ^= p >> 15;
+    p = p.wrapping_sub(p << 17);
+    p = p.wrapping_add(p << 7);
+    p = p.wrapping_add(p << 4);
+    p ^= p >> 5;
+    p = p.wrapping_add(p << 16);
+    p ^= p >> 7;
+    p ^= p >> 3;
+    p ^= p << 6;
+    p ^= p >> 17;
+    p
+}
+
+/// Selects the partition of a texel of a 2D block.
+fn select_partition(seed: u32, x: u32, y: u32, partition_count: u32, small_block: bool) -> usize {
+    let (x, y) = if small_block {
+        (x << 1, y << 1)
+    } else {
+        (x, y)
+    };
+    let seed = seed + (partition_count - 1) * 1024;
+    let rnum = hash52(seed);
+    let (sh1, sh2) = if seed & 1 != 0 {
+        (
+            if seed & 2 != 0 { 4 } else { 5 },
+            if partition_count == 3 { 6 } else { 5 },
+        )
+    } else {
+        (
+            if partition_count == 3 { 6 } else { 5 },
+            if seed & 2 != 0 { 4 } else { 5 },
+        )
+    };
+    // the seeds of the z coordinate aren't needed in 2D
+    let mut seeds = [0u32; 8];
+    for (i, s) in seeds.iter_mut().enumerate() {
+        let value = (rnum >> (4 * i)) & 0xF;
+        *s = (value * value) >> if i % 2 == 0 { sh1 } else { sh2 };
+    }
+    let a = (seeds[0] * x + seeds[1] * y + (rnum >> 14)) & 0x3F;
+    let b = (seeds[2] * x + seeds[3] * y + (rnum >> 10)) & 0x3F;
+    let c = if partition_count >= 3 {
+        (seeds[4] * x + seeds[5] * y + (rnum >> 6)) & 0x3F
+    } else {
+        0
+    };
+    let d = if partition_count >= 4 {
+        (seeds[6] * x + seeds[7] * y + (rnum >> 2)) & 0x3F
+    } else {
+        0
+    };
+    if a >= b && a >= c && a >= d {
+        0
+    } else if b >= c && b >= d {
+        1
+    } else if c >= d {
+        2
+    } else {
+        3
+    }
+}
+
+/// Turns a pair of values into a signed offset and a base value.
+fn bit_transfer_signed(offset: i32, base: i32) -> (i32, i32) {
+    let base = (base >> 1) | (offset & 0x80);
+    let offset = (offset >> 1) & 0x3F;
+    if offset & 0x20 != 0 {
+        (offset - 0x40, base)
+    } else {
+        (offset, base)
+    }
+}
+
+fn blue_contract(color: [i32; 4]) -> [i32; 4] {
+    [
+        (color[0] + color[2]) >> 1,
+        (color[1] + color[2]) >> 1,
+        color[2],
+        color[3],
+    ]
+}
+
+/// Decodes the endpoints of a LDR color endpoint mode, or `None` for HDR modes.
+fn decode_endpoints(mode: u32, values: &[i32]) -> Option<[[i32; 4]; 2]> {
+    let mut v = [0; 8];
+    v[..values.len()].copy_from_slice(values);
+    let endpoints = match mode {
+        0 => [[v[0], v[0], v[0], 255], [v[1], v[1], v[1], 255]],
+        1 => {
+            let l0 = (v[0] >> 2) | (v[1] & 0xC0);
+            let l1 = (l0 + (v[1] & 0x3F)).min(255);
+            [[l0, l0, l0, 255], [l1, l1, l1, 255]]
+        }
+        4 => [[v[0], v[0], v[0], v[2]], [v[1], v[1], v[1], v[3]]],
+        5 => {
+            let (l1, l0) = bit_transfer_signed(v[1], v[0]);
+            let (a1, a0) = bit_transfer_signed(v[3], v[2]);
+            [[l0, l0, l0, a0], [l0 + l1, l0 + l1, l0 + l1, a0 + a1]]
+        }
+        6 | 10 => {
+            let (a0, a1) = if mode == 10 { (v[4], v[5]) } else { (255, 255) };
+            [
+                [
+                    (v[0] * v[3]) >> 8,
+                    (v[1] * v[3]) >> 8,
+                    (v[2] * v[3]) >> 8,
+                    a0,
+                ],
+                [v[0], v[1], v[2], a1],
+            ]
+        }
+        8 | 12 => {
+            let (a0, a1) = if mode == 12 { (v[6], v[7]) } else { (255, 255) };
+            if v[1] + v[3] + v[5] >= v[0] + v[2] + v[4] {
+                [[v[0], v[2], v[4], a0], [v[1], v[3], v[5], a1]]
+            } else {
+                [
+                    blue_contract([v[1], v[3], v[5], a1]),
+                    blue_contract([v[0], v[2], v[4], a0]),
+                ]
+            }
+        }
+        9 | 13 => {
+            let (r1, r0) = bit_transfer_signed(v[1], v[0]);
+            let (g1, g0) = bit_transfer_signed(v[3], v[2]);
+            let (b1, b0) = bit_transfer_signed(v[5], v[4]);
+            let (a1, a0) = if mode == 13 {
+                bit_transfer_signed(v[7], v[6])
+            } else {
+                (0, 255)
+            };
+            if r1 + g1 + b1 >= 0 {
+                [[r0, g0, b0, a0], [r0 + r1, g0 + g1, b0 + b1, a0 + a1]]
+            } else {
+                [
+                    blue_contract([r0 + r1, g0 + g1, b0 + b1, a0 + a1]),
+                    blue_contract([r0, g0, b0, a0]),
+                ]
+            }
+        }
+        _ => return None,
+    };
+    Some(endpoints.map(|endpoint| endpoint.map(|value| value.clamp(0, 255))))
+}
+
+/// Describes the weights of a block.
+struct WeightGrid {
+    width: u32,
+    height: u32,
+    level: usize,
+    dual_plane: bool,
+}
+
+/// Decodes the block mode, or returns `None` if it is reserved.
+fn decode_block_mode(mode: u32) -> Option<WeightGrid> {
+    let a = (mode >> 5) & 3;
+    let (mut high_precision, mut dual_plane) = (bit(mode, 9), bit(mode, 10));
+    let (range, width, height);
+    if mode & 3 != 0 {
+        range = (mode & 3) << 1 | bit(mode, 4);
+        let b = (mode >> 7) & 3;
+        let size = match (mode >> 2) & 3 {
+            0 => (b + 4, a + 2),
+            1 => (b + 8, a + 2),
+            2 => (a + 2, b + 8),
+            _ if bit(mode, 8) != 0 => (bit(mode, 7) + 2, a + 2),
+            _ => (a + 2, bit(mode, 7) + 6),
+        };
+        width = size.0;
+        height = size.1;
+    } else {
+        range = ((mode >> 2) & 3) << 1 | bit(mode, 4);
+        if (mode >> 2) & 3 == 0 {
+            return None;
+        }
+        let b = (mode >> 9) & 3;
+        let size = match (mode >> 7) & 3 {
+            0 => (12, a + 2),
+            1 => (a + 2, 12),
+            2 => {
+                high_precision = 0;
+                dual_plane = 0;
+                (a + 6, b + 6)
+            }
+            _ => match a {
+                0 => (6, 10),
+                1 => (10, 6),
+                _ => return None,
+            },
+        };
+        width = size.0;
+        height = size.1;
+    }
+    Some(WeightGrid {
+        width,
+        height,
+        level: (range - 2 + 6 * high_precision) as usize,
+        dual_plane: dual_plane != 0,
+    })
+}
+
+/// Decodes a block of `width` by `height` texels into RGBA8 texels, in row-major order.
+pub(super) fn decode_block(block: &[u8], out: &mut [u8], width: u32, height: u32, srgb: bool) {
+    let mut bytes = [0; 16];
+    bytes.copy_from_slice(&block[..16]);
+    if decode(u128::from_le_bytes(bytes), out, width, height, srgb).is_none() {
+        for texel in out.chunks_exact_mut(4) {
+            texel.copy_from_slice(&ERROR_COLOR);
+        }
+    }
+}
+
+fn decode(block: u128, out: &mut [u8], width: u32, height: u32, srgb: bool) -> Option<()> {
+    let mode = field(block, 0, 11);
+    if mode & 0x1FF == 0x1FC {
+        // void extent blocks have a single color, which can't be HDR in the LDR profile
+        if bit(mode, 9) != 0 {
+            return None;
+        }
+        let mut color = [0; 4];
+        for (channel, value) in color.iter_mut().enumerate() {
+            *value = (field(block, 64 + 16 * channel as u32, 16) >> 8) as u8;
+        }
+        for texel in out.chunks_exact_mut(4) {
+            texel.copy_from_slice(&color);
+        }
+        return Some(());
+    }
+
+    let grid = decode_block_mode(mode)?;
+    let plane_count = if grid.dual_plane { 2 } else { 1 };
+    let weight_count = grid.width * grid.height * plane_count;
+    if grid.width > width || grid.height > height || weight_count > 64 {
+        return None;
+    }
+    let weight_bits = ise_bits(weight_count, grid.level);
+    if !(24..=96).contains(&weight_bits) {
+        return None;
+    }
+
+    let partition_count = field(block, 11, 2) + 1;
+    if grid.dual_plane && partition_count == 4 {
+        return None;
+    }
+    let mut below_weights = 128 - weight_bits;
+    let mut modes = [0; 4];
+    let (color_start, seed) = if partition_count == 1 {
+        modes[0] = field(block, 13, 4);
+        (17, 0)
+    } else {
+        let selector = field(block, 23, 2);
+        if selector == 0 {
+            modes = [field(block, 25, 4); 4];
+        } else {
+            // the modes of each partition are a class offset and 2 bits, partially stored
+            // below the weights
+            let extra_bits = 3 * partition_count - 4;
+            below_weights -= extra_bits;
+            let encoded = field(block, 25, 4) | field(block, below_weights, extra_bits) << 4;
+            for (i, mode) in modes[..partition_count as usize].iter_mut().enumerate() {
+                let class = selector - 1 + bit(encoded, i as u32);
+                *mode = class << 2 | (encoded >> (partition_count + 2 * i as u32)) & 3;
+            }
+        }
+        (29, field(block, 13, 10))
+    };
+    let modes = &modes[..partition_count as usize];
+    let ccs = if grid.dual_plane {
+        below_weights -= 2;
+        Some(field(block, below_weights, 2) as usize)
+    } else {
+        None
+    };
+
+    let color_count: u32 = modes.iter().map(|mode| (mode / 4 + 1) * 2).sum();
+    if color_count > 18 {
+        return None;
+    }
+    let color_bits = below_weights.checked_sub(color_start)?;
+    let color_level = (0..RANGES.len())
+        .rev()
+        .find(|&level| ise_bits(color_count, level) <= color_bits)?;
+    if color_level < MIN_COLOR_LEVEL {
+        return None;
+    }
+    let mut bits = Bits {
+        value: block,
+        position: color_start,
+        end: color_start + ise_bits(color_count, color_level),
+    };
+    let colors = decode_ise(&mut bits, color_count as usize, color_level);
+    let mut endpoints = [[[0; 4]; 2]; 4];
+    let mut offset = 0;
+    for (endpoints, &mode) in endpoints.iter_mut().zip(modes) {
+        let count = (mode as usize / 4 + 1) * 2;
+        let values = colors[offset..offset + count]
+            .iter()
+            .map(|&value| unquantize_color(value, color_level))
+            .collect::<Vec<_>>();
+        *endpoints = decode_endpoints(mode, &values)?;
+        offset += count;
+    }
+
+    // the weights are stored from the highest bit down
+    let mut bits = Bits {
+        value: block.reverse_bits(),
+        position: 0,
+        end: weight_bits,
+    };
+    let weights = decode_ise(&mut bits, weight_count as usize, grid.level)
+        .into_iter()
+        .map(|weight| unquantize_weight(weight, grid.level))
+        .collect::<Vec<_>>();
+
+    let ds = (1024 + width / 2) / (width - 1);
+    let dt = (1024 + height / 2) / (height - 1);
+    let small_block = width * height < 31;
+    for y in 0..height {
+        for x in 0..width {
+            // bilinear infill of the weight grid
+            let gs = (ds * x * (grid.width - 1) + 32) >> 6;
+            let gt = (dt * y * (grid.height - 1) + 32) >> 6;
+            let (js, fs, jt, ft) = (gs >> 4, gs & 0xF, gt >> 4, gt & 0xF);
+            let w11 = (fs * ft + 8) >> 4;
+            let factors = [16 - fs - ft + w11, fs - w11, ft - w11, w11];
+            let base = (js + jt * grid.width) as usize;
+            let neighbours = [
+                base,
+                base + 1,
+                base + grid.width as usize,
+                base + grid.width as usize + 1,
+            ];
+            let mut plane_weights = [0; 2];
+            for (plane, weight) in plane_weights[..plane_count as usize].iter_mut().enumerate() {
+                let sum: u32 = factors
+                    .iter()
+                    .zip(neighbours)
+                    .filter(|&(&factor, _)| factor != 0)
+                    .map(|(&factor, index)| factor * weights[index * plane_count as usize + plane])
+                    .sum();
+                *weight = (sum + 8) >> 4;
+            }
+
+            let partition = if partition_count > 1 {
+                select_partition(seed, x, y, partition_count, small_block)
+            } else {
+                0
+            };
+            let [e0, e1] = endpoints[partition];
+            let texel = &mut out[((y * width + x) * 4) as usize..][..4];
+            for (channel, value) in texel.iter_mut().enumerate() {
+                let weight = plane_weights[(ccs == Some(channel)) as usize] as i32;
+                let expand = |e: i32| if srgb { e << 8 | 0x80 } else { e << 8 | e };
+                let c0 = expand(e0[channel]);
+                let c1 = expand(e1[channel]);
+                *value = (((c0 * (64 - weight) + c1 * weight + 32) >> 6) >> 8) as u8;
+            }
+        }
+    }
+    Some(())
+}
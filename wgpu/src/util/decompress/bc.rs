@@ -0,0 +1,1006 @@
+//! Decoders of the BC1 to BC7 formats.
+//!
+//! Each decoder writes the 4x4 texels of a block in row-major order.
+
+/// Expands a 565 color to 8 bits per channel.
+fn rgb565(color: u16) -> [u8; 3] {
+    let r = (color >> 11) as u8 & 0x1F;
+    let g = (color >> 5) as u8 & 0x3F;
+    let b = color as u8 & 0x1F;
+    [r << 3 | r >> 2, g << 2 | g >> 4, b << 3 | b >> 2]
+}
+
+/// Decodes the color part of BC1, BC2 and BC3 into RGBA8 texels.
+///
+/// The three color mode with a transparent texel is only available to BC1.
+fn decode_color(block: &[u8], out: &mut [u8], allow_transparent: bool) {
+    let c0 = u16::from_le_bytes([block[0], block[1]]);
+    let c1 = u16::from_le_bytes([block[2], block[3]]);
+    let (e0, e1) = (rgb565(c0), rgb565(c1));
+    let mut palette = [[0u8; 4]; 4];
+    for i in 0..3 {
+        let (a, b) = (e0[i] as u32, e1[i] as u32);
+        palette[0][i] = a as u8;
+        palette[1][i] = b as u8;
+        if c0 > c1 || !allow_transparent {
+            palette[2][i] = ((2 * a + b) / 3) as u8;
+            palette[3][i] = ((a + 2 * b) / 3) as u8;
+        } else {
+            palette[2][i] = ((a + b) / 2) as u8;
+        }
+    }
+    palette[0][3] = 255;
+    palette[1][3] = 255;
+    palette[2][3] = 255;
+    palette[3][3] = if c0 > c1 || !allow_transparent {
+        255
+    } else {
+        0
+    };
+
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+    for (i, texel) in out.chunks_exact_mut(4).enumerate() {
+        texel.copy_from_slice(&palette[(indices >> (2 * i)) as usize & 3]);
+    }
+}
+
+/// Decodes a BC4 block into the bytes of one channel, at `stride` bytes from each other.
+fn decode_channel(block: &[u8], out: &mut [u8], stride: usize, signed: bool) {
+    let mut palette = [0i32; 8];
+    let (a, b) = if signed {
+        // -128 is decoded as -127
+        (
+            (block[0] as i8).max(-127) as i32,
+            (block[1] as i8).max(-127) as i32,
+        )
+    } else {
+        (block[0] as i32, block[1] as i32)
+    };
+    palette[0] = a;
+    palette[1] = b;
+    if a > b {
+        for i in 1..7 {
+            palette[i + 1] = ((7 - i as i32) * a + i as i32 * b) / 7;
+        }
+    } else {
+        for i in 1..5 {
+            palette[i + 1] = ((5 - i as i32) * a + i as i32 * b) / 5;
+        }
+        palette[6] = if signed { -127 } else { 0 };
+        palette[7] = if signed { 127 } else { 255 };
+    }
+
+    let mut indices = 0u64;
+    for (i, &byte) in block[2..8].iter().enumerate() {
+        indices |= (byte as u64) << (8 * i);
+    }
+    for i in 0..16 {
+        out[i * stride] = palette[(indices >> (3 * i)) as usize & 7] as u8;
+    }
+}
+
+pub(super) fn decode_bc1(block: &[u8], out: &mut [u8]) {
+    decode_color(block, out, true);
+}
+
+pub(super) fn decode_bc2(block: &[u8], out: &mut [u8]) {
+    decode_color(&block[8..], out, false);
+    for i in 0..16 {
+        let alpha = (block[i / 2] >> (4 * (i % 2))) & 0xF;
+        out[i * 4 + 3] = alpha * 17;
+    }
+}
+
+pub(super) fn decode_bc3(block: &[u8], out: &mut [u8]) {
+    decode_color(&block[8..], out, false);
+    decode_channel(block, &mut out[3..], 4, false);
+}
+
+pub(super) fn decode_bc4(block: &[u8], out: &mut [u8], signed: bool) {
+    decode_channel(block, out, 1, signed);
+}
+
+pub(super) fn decode_bc5(block: &[u8], out: &mut [u8], signed: bool) {
+    decode_channel(block, out, 2, signed);
+    decode_channel(&block[8..], &mut out[1..], 2, signed);
+}
+
+/// Reads the bits of a 128 bit block, from the least significant one.
+struct Bits {
+    value: u128,
+    position: u32,
+}
+
+impl Bits {
+    fn new(block: &[u8]) -> Self {
+        let mut bytes = [0; 16];
+        bytes.copy_from_slice(&block[..16]);
+        Bits {
+            value: u128::from_le_bytes(bytes),
+            position: 0,
+        }
+    }
+
+    fn read(&mut self, count: u32) -> u32 {
+        let value = (self.value >> self.position) as u32 & ((1u64 << count) - 1) as u32;
+        self.position += count;
+        value
+    }
+
+    /// Reads a single bit, and moves it to the bit `shift` of the result.
+    fn bit(&mut self, shift: u32) -> u32 {
+        self.read(1) << shift
+    }
+}
+
+/// Bits of 1.0 as a half float.
+const HALF_ONE: u16 = 0x3C00;
+
+const WEIGHTS2: [u32; 4] = [0, 21, 43, 64];
+const WEIGHTS3: [u32; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+const WEIGHTS4: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+fn weights(index_bits: u32) -> &'static [u32] {
+    match index_bits {
+        2 => &WEIGHTS2,
+        3 => &WEIGHTS3,
+        _ => &WEIGHTS4,
+    }
+}
+
+/// Subsets of the texels of the partitions of two subsets, one row of 4 texels per nibble.
+const PARTITIONS2: [[u8; 16]; 64] = [
+    [0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1],
+    [0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1],
+    [0, 1, 1, 1, 0, 1, 1, 1, 0, 1, 1, 1, 0, 1, 1, 1],
+    [0, 0, 0, 1, 0, 0, 1, 1, 0, 0, 1, 1, 0, 1, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 1, 1],
+    [0, 0, 1, 1, 0, 1, 1, 1, 0, 1, 1, 1, 1, 1, 1, 1],
+    [0, 0, 0, 1, 0, 0, 1, 1, 0, 1, 1, 1, 1, 1, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 1, 1, 0, 1, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 1, 1],
+    [0, 0, 1, 1, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 1, 0, 1, 1, 1, 1, 1, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 1, 1, 1],
+    [0, 0, 0, 1, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1],
+    [0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1],
+    [0, 0, 0, 0, 1, 0, 0, 0, 1, 1, 1, 0, 1, 1, 1, 1],
+    [0, 1, 1, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 1, 1, 0],
+    [0, 1, 1, 1, 0, 0, 1, 1, 0, 0, 0, 1, 0, 0, 0, 0],
+    [0, 0, 1, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 0, 0, 1, 0, 0, 0, 1, 1, 0, 0, 1, 1, 1, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 1, 0, 0],
+    [0, 1, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 0, 1],
+    [0, 0, 1, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 0],
+    [0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 1, 0, 0],
+    [0, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1, 0],
+    [0, 0, 1, 1, 0, 1, 1, 0, 0, 1, 1, 0, 1, 1, 0, 0],
+    [0, 0, 0, 1, 0, 1, 1, 1, 1, 1, 1, 0, 1, 0, 0, 0],
+    [0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0],
+    [0, 1, 1, 1, 0, 0, 0, 1, 1, 0, 0, 0, 1, 1, 1, 0],
+    [0, 0, 1, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1, 1, 0, 0],
+    [0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1],
+    [0, 0, 0, 0, 1, 1, 1, 1, 0, 0, 0, 0, 1, 1, 1, 1],
+    [0, 1, 0, 1, 1, 0, 1, 0, 0, 1, 0, 1, 1, 0, 1, 0],
+    [0, 0, 1, 1, 0, 0, 1, 1, 1, 1, 0, 0, 1, 1, 0, 0],
+    [0, 0, 1, 1, 1, 1, 0, 0, 0, 0, 1, 1, 1, 1, 0, 0],
+    [0, 1, 0, 1, 0, 1, 0, 1, 1, 0, 1, 0, 1, 0, 1, 0],
+    [0, 1, 1, 0, 1, 0, 0, 1, 0, 1, 1, 0, 1, 0, 0, 1],
+    [0, 1, 0, 1, 1, 0, 1, 0, 1, 0, 1, 0, 0, 1, 0, 1],
+    [0, 1, 1, 1, 0, 0, 1, 1, 1, 1, 0, 0, 1, 1, 1, 0],
+    [0, 0, 0, 1, 0, 0, 1, 1, 1, 1, 0, 0, 1, 0, 0, 0],
+    [0, 0, 1, 1, 0, 0, 1, 0, 0, 1, 0, 0, 1, 1, 0, 0],
+    [0, 0, 1, 1, 1, 0, 1, 1, 1, 1, 0, 1, 1, 1, 0, 0],
+    [0, 1, 1, 0, 1, 0, 0, 1, 1, 0, 0, 1, 0, 1, 1, 0],
+    [0, 0, 1, 1, 1, 1, 0, 0, 1, 1, 0, 0, 0, 0, 1, 1],
+    [0, 1, 1, 0, 0, 1, 1, 0, 1, 0, 0, 1, 1, 0, 0, 1],
+    [0, 0, 0, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 0, 0, 0],
+    [0, 1, 0, 0, 1, 1, 1, 0, 0, 1, 0, 0, 0, 0, 0, 0],
+    [0, 0, 1, 0, 0, 1, 1, 1, 0, 0, 1, 0, 0, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 1, 0, 0, 1, 1, 1, 0, 0, 1, 0],
+    [0, 0, 0, 0, 0, 1, 0, 0, 1, 1, 1, 0, 0, 1, 0, 0],
+    [0, 1, 1, 0, 1, 1, 0, 0, 1, 0, 0, 1, 0, 0, 1, 1],
+    [0, 0, 1, 1, 0, 1, 1, 0, 1, 1, 0, 0, 1, 0, 0, 1],
+    [0, 1, 1, 0, 0, 0, 1, 1, 1, 0, 0, 1, 1, 1, 0, 0],
+    [0, 0, 1, 1, 1, 0, 0, 1, 1, 1, 0, 0, 0, 1, 1, 0],
+    [0, 1, 1, 0, 1, 1, 0, 0, 1, 1, 0, 0, 1, 0, 0, 1],
+    [0, 1, 1, 0, 0, 0, 1, 1, 0, 0, 1, 1, 1, 0, 0, 1],
+    [0, 1, 1, 1, 1, 1, 1, 0, 1, 0, 0, 0, 0, 0, 0, 1],
+    [0, 0, 0, 1, 1, 0, 0, 0, 1, 1, 1, 0, 0, 1, 1, 1],
+    [0, 0, 0, 0, 1, 1, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1],
+    [0, 0, 1, 1, 0, 0, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0],
+    [0, 0, 1, 0, 0, 0, 1, 0, 1, 1, 1, 0, 1, 1, 1, 0],
+    [0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 1, 1, 0, 1, 1, 1],
+];
+
+const PARTITIONS3: [[u8; 16]; 64] = [
+    [0, 0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 1, 2, 2, 2, 2],
+    [0, 0, 0, 1, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 2, 1],
+    [0, 0, 0, 0, 2, 0, 0, 1, 2, 2, 1, 1, 2, 2, 1, 1],
+    [0, 2, 2, 2, 0, 0, 2, 2, 0, 0, 1, 1, 0, 1, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2],
+    [0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 2, 2, 0, 0, 2, 2],
+    [0, 0, 2, 2, 0, 0, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1],
+    [0, 0, 1, 1, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2],
+    [0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2],
+    [0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2],
+    [0, 0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2],
+    [0, 1, 1, 2, 0, 1, 1, 2, 0, 1, 1, 2, 0, 1, 1, 2],
+    [0, 1, 2, 2, 0, 1, 2, 2, 0, 1, 2, 2, 0, 1, 2, 2],
+    [0, 0, 1, 1, 0, 1, 1, 2, 1, 1, 2, 2, 1, 2, 2, 2],
+    [0, 0, 1, 1, 2, 0, 0, 1, 2, 2, 0, 0, 2, 2, 2, 0],
+    [0, 0, 0, 1, 0, 0, 1, 1, 0, 1, 1, 2, 1, 1, 2, 2],
+    [0, 1, 1, 1, 0, 0, 1, 1, 2, 0, 0, 1, 2, 2, 0, 0],
+    [0, 0, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 1, 1, 2, 2],
+    [0, 0, 2, 2, 0, 0, 2, 2, 0, 0, 2, 2, 1, 1, 1, 1],
+    [0, 1, 1, 1, 0, 1, 1, 1, 0, 2, 2, 2, 0, 2, 2, 2],
+    [0, 0, 0, 1, 0, 0, 0, 1, 2, 2, 2, 1, 2, 2, 2, 1],
+    [0, 0, 0, 0, 0, 0, 1, 1, 0, 1, 2, 2, 0, 1, 2, 2],
+    [0, 0, 0, 0, 1, 1, 0, 0, 2, 2, 1, 0, 2, 2, 1, 0],
+    [0, 1, 2, 2, 0, 1, 2, 2, 0, 0, 1, 1, 0, 0, 0, 0],
+    [0, 0, 1, 2, 0, 0, 1, 2, 1, 1, 2, 2, 2, 2, 2, 2],
+    [0, 1, 1, 0, 1, 2, 2, 1, 1, 2, 2, 1, 0, 1, 1, 0],
+    [0, 0, 0, 0, 0, 1, 1, 0, 1, 2, 2, 1, 1, 2, 2, 1],
+    [0, 0, 2, 2, 1, 1, 0, 2, 1, 1, 0, 2, 0, 0, 2, 2],
+    [0, 1, 1, 0, 0, 1, 1, 0, 2, 0, 0, 2, 2, 2, 2, 2],
+    [0, 0, 1, 1, 0, 1, 2, 2, 0, 1, 2, 2, 0, 0, 1, 1],
+    [0, 0, 0, 0, 2, 0, 0, 0, 2, 2, 1, 1, 2, 2, 2, 1],
+    [0, 0, 0, 0, 0, 0, 0, 2, 1, 1, 2, 2, 1, 2, 2, 2],
+    [0, 2, 2, 2, 0, 0, 2, 2, 0, 0, 1, 2, 0, 0, 1, 1],
+    [0, 0, 1, 1, 0, 0, 1, 2, 0, 0, 2, 2, 0, 2, 2, 2],
+    [0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2, 0],
+    [0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 0, 0, 0, 0],
+    [0, 1, 2, 0, 1, 2, 0, 1, 2, 0, 1, 2, 0, 1, 2, 0],
+    [0, 1, 2, 0, 2, 0, 1, 2, 1, 2, 0, 1, 0, 1, 2, 0],
+    [0, 0, 1, 1, 2, 2, 0, 0, 1, 1, 2, 2, 0, 0, 1, 1],
+    [0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 0, 0, 0, 0, 1, 1],
+    [0, 1, 0, 1, 0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2],
+    [0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 2, 1, 2, 1, 2, 1],
+    [0, 0, 2, 2, 1, 1, 2, 2, 0, 0, 2, 2, 1, 1, 2, 2],
+    [0, 0, 2, 2, 0, 0, 1, 1, 0, 0, 2, 2, 0, 0, 1, 1],
+    [0, 2, 2, 0, 1, 2, 2, 1, 0, 2, 2, 0, 1, 2, 2, 1],
+    [0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2, 0, 1, 0, 1],
+    [0, 0, 0, 0, 2, 1, 2, 1, 2, 1, 2, 1, 2, 1, 2, 1],
+    [0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 2, 2, 2, 2],
+    [0, 2, 2, 2, 0, 1, 1, 1, 0, 2, 2, 2, 0, 1, 1, 1],
+    [0, 0, 0, 2, 1, 1, 1, 2, 0, 0, 0, 2, 1, 1, 1, 2],
+    [0, 0, 0, 0, 2, 1, 1, 2, 2, 1, 1, 2, 2, 1, 1, 2],
+    [0, 2, 2, 2, 0, 1, 1, 1, 0, 1, 1, 1, 0, 2, 2, 2],
+    [0, 0, 0, 2, 1, 1, 1, 2, 1, 1, 1, 2, 0, 0, 0, 2],
+    [0, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 2, 2],
+    [0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 1, 2, 2, 1, 1, 2],
+    [0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 2, 2, 2, 2, 2, 2],
+    [0, 0, 2, 2, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 2, 2],
+    [0, 0, 2, 2, 1, 1, 2, 2, 1, 1, 2, 2, 0, 0, 2, 2],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 1, 2],
+    [0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 1],
+    [0, 2, 2, 2, 1, 2, 2, 2, 0, 2, 2, 2, 1, 2, 2, 2],
+    [0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2],
+    [0, 1, 1, 1, 2, 0, 1, 1, 2, 2, 0, 1, 2, 2, 2, 0],
+];
+
+/// Anchor texel of the second subset of the partitions of two subsets.
+const ANCHORS2: [u8; 64] = [
+    15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, //
+    15, 2, 8, 2, 2, 8, 8, 15, 2, 8, 2, 2, 8, 8, 2, 2, //
+    15, 15, 6, 8, 2, 8, 15, 15, 2, 8, 2, 2, 2, 15, 15, 6, //
+    6, 2, 6, 8, 15, 15, 2, 2, 15, 15, 15, 15, 15, 2, 2, 15,
+];
+
+/// Anchor texels of the second and third subsets of the partitions of three subsets.
+const ANCHORS3: [[u8; 64]; 2] = [
+    [
+        3, 3, 15, 15, 8, 3, 15, 15, 8, 8, 6, 6, 6, 5, 3, 3, //
+        3, 3, 8, 15, 3, 3, 6, 10, 5, 8, 8, 6, 8, 5, 15, 15, //
+        8, 15, 3, 5, 6, 10, 8, 15, 15, 3, 15, 5, 15, 15, 15, 15, //
+        3, 15, 5, 5, 5, 8, 5, 10, 5, 10, 8, 13, 15, 12, 3, 3,
+    ],
+    [
+        15, 8, 8, 3, 15, 15, 3, 8, 15, 15, 15, 15, 15, 15, 15, 8, //
+        15, 8, 15, 3, 15, 8, 15, 8, 3, 15, 6, 10, 15, 15, 10, 8, //
+        15, 3, 15, 10, 10, 8, 9, 10, 6, 15, 8, 15, 3, 6, 6, 8, //
+        15, 3, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 3, 15, 15, 8,
+    ],
+];
+
+/// Returns true if the texel is the anchor of its subset, whose index has one bit less.
+fn is_anchor(subsets: usize, partition: usize, texel: usize) -> bool {
+    texel == 0
+        || match subsets {
+            2 => texel == ANCHORS2[partition] as usize,
+            3 => {
+                texel == ANCHORS3[0][partition] as usize || texel == ANCHORS3[1][partition] as usize
+            }
+            _ => false,
+        }
+}
+
+fn subset(subsets: usize, partition: usize, texel: usize) -> usize {
+    match subsets {
+        2 => PARTITIONS2[partition][texel] as usize,
+        3 => PARTITIONS3[partition][texel] as usize,
+        _ => 0,
+    }
+}
+
+struct Bc7Mode {
+    subsets: usize,
+    partition_bits: u32,
+    rotation_bits: u32,
+    index_selection_bits: u32,
+    color_bits: u32,
+    alpha_bits: u32,
+    endpoint_pbits: bool,
+    shared_pbits: bool,
+    index_bits: u32,
+    secondary_index_bits: u32,
+}
+
+const BC7_MODES: [Bc7Mode; 8] = [
+    Bc7Mode {
+        subsets: 3,
+        partition_bits: 4,
+        rotation_bits: 0,
+        index_selection_bits: 0,
+        color_bits: 4,
+        alpha_bits: 0,
+        endpoint_pbits: true,
+        shared_pbits: false,
+        index_bits: 3,
+        secondary_index_bits: 0,
+    },
+    Bc7Mode {
+        subsets: 2,
+        partition_bits: 6,
+        rotation_bits: 0,
+        index_selection_bits: 0,
+        color_bits: 6,
+        alpha_bits: 0,
+        endpoint_pbits: false,
+        shared_pbits: true,
+        index_bits: 3,
+        secondary_index_bits: 0,
+    },
+    Bc7Mode {
+        subsets: 3,
+        partition_bits: 6,
+        rotation_bits: 0,
+        index_selection_bits: 0,
+        color_bits: 5,
+        alpha_bits: 0,
+        endpoint_pbits: false,
+        shared_pbits: false,
+        index_bits: 2,
+        secondary_index_bits: 0,
+    },
+    Bc7Mode {
+        subsets: 2,
+        partition_bits: 6,
+        rotation_bits: 0,
+        index_selection_bits: 0,
+        color_bits: 7,
+        alpha_bits: 0,
+        endpoint_pbits: true,
+        shared_pbits: false,
+        index_bits: 2,
+        secondary_index_bits: 0,
+    },
+    Bc7Mode {
+        subsets: 1,
+        partition_bits: 0,
+        rotation_bits: 2,
+        index_selection_bits: 1,
+        color_bits: 5,
+        alpha_bits: 6,
+        endpoint_pbits: false,
+        shared_pbits: false,
+        index_bits: 2,
+        secondary_index_bits: 3,
+    },
+    Bc7Mode {
+        subsets: 1,
+        partition_bits: 0,
+        rotation_bits: 2,
+        index_selection_bits: 0,
+        color_bits: 7,
+        alpha_bits: 8,
+        endpoint_pbits: false,
+        shared_pbits: false,
+        index_bits: 2,
+        secondary_index_bits: 2,
+    },
+    Bc7Mode {
+        subsets: 1,
+        partition_bits: 0,
+        rotation_bits: 0,
+        index_selection_bits: 0,
+        color_bits: 7,
+        alpha_bits: 7,
+        endpoint_pbits: true,
+        shared_pbits: false,
+        index_bits: 4,
+        secondary_index_bits: 0,
+    },
+    Bc7Mode {
+        subsets: 2,
+        partition_bits: 6,
+        rotation_bits: 0,
+        index_selection_bits: 0,
+        color_bits: 5,
+        alpha_bits: 5,
+        endpoint_pbits: true,
+        shared_pbits: false,
+        index_bits: 2,
+        secondary_index_bits: 0,
+    },
+];
+
+/// Expands a value of `bits` bits to 8 bits, by replicating its highest bits.
+fn expand_bits(value: u32, bits: u32) -> u32 {
+    let value = value << (8 - bits);
+    value | value >> bits
+}
+
+fn interpolate(e0: u32, e1: u32, weight: u32) -> u32 {
+    ((64 - weight) * e0 + weight * e1 + 32) >> 6
+}
+
+pub(super) fn decode_bc7(block: &[u8], out: &mut [u8]) {
+    let mode_index = block[0].trailing_zeros() as usize;
+    let mode = match BC7_MODES.get(mode_index) {
+        Some(mode) => mode,
+        None => {
+            // reserved mode
+            out.fill(0);
+            return;
+        }
+    };
+    let mut bits = Bits::new(block);
+    bits.read(mode_index as u32 + 1);
+    let partition = bits.read(mode.partition_bits) as usize;
+    let rotation = bits.read(mode.rotation_bits);
+    let index_selection = bits.read(mode.index_selection_bits);
+
+    // endpoints[subset * 2 + i][channel]
+    let mut endpoints = [[0u32; 4]; 6];
+    let endpoint_count = mode.subsets * 2;
+    for channel in 0..3 {
+        for endpoint in endpoints[..endpoint_count].iter_mut() {
+            endpoint[channel] = bits.read(mode.color_bits);
+        }
+    }
+    for endpoint in endpoints[..endpoint_count].iter_mut() {
+        endpoint[3] = bits.read(mode.alpha_bits);
+    }
+
+    let (mut color_bits, mut alpha_bits) = (mode.color_bits, mode.alpha_bits);
+    if mode.endpoint_pbits || mode.shared_pbits {
+        let mut pbits = [0; 6];
+        if mode.endpoint_pbits {
+            for pbit in pbits[..endpoint_count].iter_mut() {
+                *pbit = bits.read(1);
+            }
+        } else {
+            for subset in 0..mode.subsets {
+                let pbit = bits.read(1);
+                pbits[subset * 2] = pbit;
+                pbits[subset * 2 + 1] = pbit;
+            }
+        }
+        for (endpoint, pbit) in endpoints[..endpoint_count].iter_mut().zip(pbits) {
+            for (channel, value) in endpoint.iter_mut().enumerate() {
+                if channel < 3 || mode.alpha_bits != 0 {
+                    *value = *value << 1 | pbit;
+                }
+            }
+        }
+        color_bits += 1;
+        if alpha_bits != 0 {
+            alpha_bits += 1;
+        }
+    }
+    for endpoint in endpoints[..endpoint_count].iter_mut() {
+        for value in endpoint[..3].iter_mut() {
+            *value = expand_bits(*value, color_bits);
+        }
+        endpoint[3] = if alpha_bits == 0 {
+            255
+        } else {
+            expand_bits(endpoint[3], alpha_bits)
+        };
+    }
+
+    let mut indices = [0u32; 16];
+    for (texel, index) in indices.iter_mut().enumerate() {
+        let anchor = is_anchor(mode.subsets, partition, texel);
+        *index = bits.read(mode.index_bits - anchor as u32);
+    }
+    let mut secondary_indices = [0u32; 16];
+    if mode.secondary_index_bits != 0 {
+        for (texel, index) in secondary_indices.iter_mut().enumerate() {
+            *index = bits.read(mode.secondary_index_bits - (texel == 0) as u32);
+        }
+    }
+
+    for (texel, out) in out.chunks_exact_mut(4).enumerate() {
+        let subset = subset(mode.subsets, partition, texel);
+        let (e0, e1) = (endpoints[subset * 2], endpoints[subset * 2 + 1]);
+        let (color_weight, alpha_weight) = if mode.secondary_index_bits == 0 {
+            let weight = weights(mode.index_bits)[indices[texel] as usize];
+            (weight, weight)
+        } else if index_selection == 0 {
+            (
+                weights(mode.index_bits)[indices[texel] as usize],
+                weights(mode.secondary_index_bits)[secondary_indices[texel] as usize],
+            )
+        } else {
+            (
+                weights(mode.secondary_index_bits)[secondary_indices[texel] as usize],
+                weights(mode.index_bits)[indices[texel] as usize],
+            )
+        };
+        let mut color = [0u8; 4];
+        for channel in 0..3 {
+            color[channel] = interpolate(e0[channel], e1[channel], color_weight) as u8;
+        }
+        color[3] = interpolate(e0[3], e1[3], alpha_weight) as u8;
+        match rotation {
+            1 => color.swap(0, 3),
+            2 => color.swap(1, 3),
+            3 => color.swap(2, 3),
+            _ => {}
+        }
+        out.copy_from_slice(&color);
+    }
+}
+
+/// Field of a BC6H block: an endpoint component, or the partition.
+#[derive(Clone, Copy)]
+enum Field {
+    R(usize),
+    G(usize),
+    B(usize),
+    D,
+}
+
+/// Runs of bits of a BC6H block, each as the field, its lowest bit and the count of bits.
+///
+/// A negative count stores the bits in reverse order, from the highest one.
+type Layout = &'static [(Field, u32, i32)];
+
+use Field::{B, D, G, R};
+
+const BC6H_LAYOUTS: [Layout; 14] = [
+    &[
+        (G(2), 4, 1),
+        (B(2), 4, 1),
+        (B(3), 4, 1),
+        (R(0), 0, 10),
+        (G(0), 0, 10),
+        (B(0), 0, 10),
+        (R(1), 0, 5),
+        (G(3), 4, 1),
+        (G(2), 0, 4),
+        (G(1), 0, 5),
+        (B(3), 0, 1),
+        (G(3), 0, 4),
+        (B(1), 0, 5),
+        (B(3), 1, 1),
+        (B(2), 0, 4),
+        (R(2), 0, 5),
+        (B(3), 2, 1),
+        (R(3), 0, 5),
+        (B(3), 3, 1),
+        (D, 0, 5),
+    ],
+    &[
+        (G(2), 5, 1),
+        (G(3), 4, 1),
+        (G(3), 5, 1),
+        (R(0), 0, 7),
+        (B(3), 0, 1),
+        (B(3), 1, 1),
+        (B(2), 4, 1),
+        (G(0), 0, 7),
+        (B(2), 5, 1),
+        (B(3), 2, 1),
+        (G(2), 4, 1),
+        (B(0), 0, 7),
+        (B(3), 3, 1),
+        (B(3), 5, 1),
+        (B(3), 4, 1),
+        (R(1), 0, 6),
+        (G(2), 0, 4),
+        (G(1), 0, 6),
+        (G(3), 0, 4),
+        (B(1), 0, 6),
+        (B(2), 0, 4),
+        (R(2), 0, 6),
+        (R(3), 0, 6),
+        (D, 0, 5),
+    ],
+    &[
+        (R(0), 0, 10),
+        (G(0), 0, 10),
+        (B(0), 0, 10),
+        (R(1), 0, 5),
+        (R(0), 10, 1),
+        (G(2), 0, 4),
+        (G(1), 0, 4),
+        (G(0), 10, 1),
+        (B(3), 0, 1),
+        (G(3), 0, 4),
+        (B(1), 0, 4),
+        (B(0), 10, 1),
+        (B(3), 1, 1),
+        (B(2), 0, 4),
+        (R(2), 0, 5),
+        (B(3), 2, 1),
+        (R(3), 0, 5),
+        (B(3), 3, 1),
+        (D, 0, 5),
+    ],
+    &[
+        (R(0), 0, 10),
+        (G(0), 0, 10),
+        (B(0), 0, 10),
+        (R(1), 0, 4),
+        (R(0), 10, 1),
+        (G(3), 4, 1),
+        (G(2), 0, 4),
+        (G(1), 0, 5),
+        (G(0), 10, 1),
+        (G(3), 0, 4),
+        (B(1), 0, 4),
+        (B(0), 10, 1),
+        (B(3), 1, 1),
+        (B(2), 0, 4),
+        (R(2), 0, 4),
+        (B(3), 0, 1),
+        (B(3), 2, 1),
+        (R(3), 0, 4),
+        (G(2), 4, 1),
+        (B(3), 3, 1),
+        (D, 0, 5),
+    ],
+    &[
+        (R(0), 0, 10),
+        (G(0), 0, 10),
+        (B(0), 0, 10),
+        (R(1), 0, 4),
+        (R(0), 10, 1),
+        (B(2), 4, 1),
+        (G(2), 0, 4),
+        (G(1), 0, 4),
+        (G(0), 10, 1),
+        (B(3), 0, 1),
+        (G(3), 0, 4),
+        (B(1), 0, 5),
+        (B(0), 10, 1),
+        (B(2), 0, 4),
+        (R(2), 0, 4),
+        (B(3), 1, 1),
+        (B(3), 2, 1),
+        (R(3), 0, 4),
+        (B(3), 4, 1),
+        (B(3), 3, 1),
+        (D, 0, 5),
+    ],
+    &[
+        (R(0), 0, 9),
+        (B(2), 4, 1),
+        (G(0), 0, 9),
+        (G(2), 4, 1),
+        (B(0), 0, 9),
+        (B(3), 4, 1),
+        (R(1), 0, 5),
+        (G(3), 4, 1),
+        (G(2), 0, 4),
+        (G(1), 0, 5),
+        (B(3), 0, 1),
+        (G(3), 0, 4),
+        (B(1), 0, 5),
+        (B(3), 1, 1),
+        (B(2), 0, 4),
+        (R(2), 0, 5),
+        (B(3), 2, 1),
+        (R(3), 0, 5),
+        (B(3), 3, 1),
+        (D, 0, 5),
+    ],
+    &[
+        (R(0), 0, 8),
+        (G(3), 4, 1),
+        (B(2), 4, 1),
+        (G(0), 0, 8),
+        (B(3), 2, 1),
+        (G(2), 4, 1),
+        (B(0), 0, 8),
+        (B(3), 3, 1),
+        (B(3), 4, 1),
+        (R(1), 0, 6),
+        (G(2), 0, 4),
+        (G(1), 0, 5),
+        (B(3), 0, 1),
+        (G(3), 0, 4),
+        (B(1), 0, 5),
+        (B(3), 1, 1),
+        (B(2), 0, 4),
+        (R(2), 0, 6),
+        (R(3), 0, 6),
+        (D, 0, 5),
+    ],
+    &[
+        (R(0), 0, 8),
+        (B(3), 0, 1),
+        (B(2), 4, 1),
+        (G(0), 0, 8),
+        (G(2), 5, 1),
+        (G(2), 4, 1),
+        (B(0), 0, 8),
+        (G(3), 5, 1),
+        (B(3), 4, 1),
+        (R(1), 0, 5),
+        (G(3), 4, 1),
+        (G(2), 0, 4),
+        (G(1), 0, 6),
+        (G(3), 0, 4),
+        (B(1), 0, 5),
+        (B(3), 1, 1),
+        (B(2), 0, 4),
+        (R(2), 0, 5),
+        (B(3), 2, 1),
+        (R(3), 0, 5),
+        (B(3), 3, 1),
+        (D, 0, 5),
+    ],
+    &[
+        (R(0), 0, 8),
+        (B(3), 1, 1),
+        (B(2), 4, 1),
+        (G(0), 0, 8),
+        (B(2), 5, 1),
+        (G(2), 4, 1),
+        (B(0), 0, 8),
+        (B(3), 5, 1),
+        (B(3), 4, 1),
+        (R(1), 0, 5),
+        (G(3), 4, 1),
+        (G(2), 0, 4),
+        (G(1), 0, 5),
+        (B(3), 0, 1),
+        (G(3), 0, 4),
+        (B(1), 0, 6),
+        (B(2), 0, 4),
+        (R(2), 0, 5),
+        (B(3), 2, 1),
+        (R(3), 0, 5),
+        (B(3), 3, 1),
+        (D, 0, 5),
+    ],
+    &[
+        (R(0), 0, 6),
+        (G(3), 4, 1),
+        (B(3), 0, 1),
+        (B(3), 1, 1),
+        (B(2), 4, 1),
+        (G(0), 0, 6),
+        (G(2), 5, 1),
+        (B(2), 5, 1),
+        (B(3), 2, 1),
+        (G(2), 4, 1),
+        (B(0), 0, 6),
+        (G(3), 5, 1),
+        (B(3), 3, 1),
+        (B(3), 5, 1),
+        (B(3), 4, 1),
+        (R(1), 0, 6),
+        (G(2), 0, 4),
+        (G(1), 0, 6),
+        (G(3), 0, 4),
+        (B(1), 0, 6),
+        (B(2), 0, 4),
+        (R(2), 0, 6),
+        (R(3), 0, 6),
+        (D, 0, 5),
+    ],
+    &[
+        (R(0), 0, 10),
+        (G(0), 0, 10),
+        (B(0), 0, 10),
+        (R(1), 0, 10),
+        (G(1), 0, 10),
+        (B(1), 0, 10),
+    ],
+    &[
+        (R(0), 0, 10),
+        (G(0), 0, 10),
+        (B(0), 0, 10),
+        (R(1), 0, 9),
+        (R(0), 10, 1),
+        (G(1), 0, 9),
+        (G(0), 10, 1),
+        (B(1), 0, 9),
+        (B(0), 10, 1),
+    ],
+    &[
+        (R(0), 0, 10),
+        (G(0), 0, 10),
+        (B(0), 0, 10),
+        (R(1), 0, 8),
+        (R(0), 10, -2),
+        (G(1), 0, 8),
+        (G(0), 10, -2),
+        (B(1), 0, 8),
+        (B(0), 10, -2),
+    ],
+    &[
+        (R(0), 0, 10),
+        (G(0), 0, 10),
+        (B(0), 0, 10),
+        (R(1), 0, 4),
+        (R(0), 10, -6),
+        (G(1), 0, 4),
+        (G(0), 10, -6),
+        (B(1), 0, 4),
+        (B(0), 10, -6),
+    ],
+];
+
+/// Endpoint precision, delta precisions and whether the endpoints are deltas, of each BC6H mode.
+const BC6H_PRECISIONS: [(u32, [u32; 3], bool); 14] = [
+    (10, [5, 5, 5], true),
+    (7, [6, 6, 6], true),
+    (11, [5, 4, 4], true),
+    (11, [4, 5, 4], true),
+    (11, [4, 4, 5], true),
+    (9, [5, 5, 5], true),
+    (8, [6, 5, 5], true),
+    (8, [5, 6, 5], true),
+    (8, [5, 5, 6], true),
+    (6, [6, 6, 6], false),
+    (10, [10, 10, 10], false),
+    (11, [9, 9, 9], true),
+    (12, [8, 8, 8], true),
+    (16, [4, 4, 4], true),
+];
+
+fn sign_extend(value: i32, bits: u32) -> i32 {
+    let shift = 32 - bits;
+    (value << shift) >> shift
+}
+
+fn unquantize_bc6h(value: i32, bits: u32, signed: bool) -> i32 {
+    if signed {
+        if bits >= 16 {
+            return value;
+        }
+        let (negative, magnitude) = (value < 0, value.abs());
+        let unquantized = if magnitude == 0 {
+            0
+        } else if magnitude >= (1 << (bits - 1)) - 1 {
+            0x7FFF
+        } else {
+            ((magnitude << 15) + 0x4000) >> (bits - 1)
+        };
+        if negative {
+            -unquantized
+        } else {
+            unquantized
+        }
+    } else if bits >= 15 {
+        value
+    } else if value == 0 {
+        0
+    } else if value == (1 << bits) - 1 {
+        0xFFFF
+    } else {
+        ((value << 16) + 0x8000) >> bits
+    }
+}
+
+/// Scales an interpolated value to the bits of a half float.
+fn finish_unquantize_bc6h(value: i32, signed: bool) -> u16 {
+    if signed {
+        if value < 0 {
+            0x8000 | ((-value * 31) >> 5) as u16
+        } else {
+            ((value * 31) >> 5) as u16
+        }
+    } else {
+        ((value * 31) >> 6) as u16
+    }
+}
+
+/// Decodes a BC6H block into RGBA16Float texels.
+pub(super) fn decode_bc6h(block: &[u8], out: &mut [u8], signed: bool) {
+    let mut bits = Bits::new(block);
+    let mode = match bits.read(2) {
+        0 => 0,
+        1 => 1,
+        low => match bits.read(3) << 2 | low {
+            0x02 => 2,
+            0x06 => 3,
+            0x0A => 4,
+            0x0E => 5,
+            0x12 => 6,
+            0x16 => 7,
+            0x1A => 8,
+            0x1E => 9,
+            0x03 => 10,
+            0x07 => 11,
+            0x0B => 12,
+            0x0F => 13,
+            _ => {
+                // reserved mode
+                out.fill(0);
+                return;
+            }
+        },
+    };
+
+    // endpoints[endpoint][channel]
+    let mut endpoints = [[0i32; 3]; 4];
+    let mut partition = 0;
+    for &(field, lowest, count) in BC6H_LAYOUTS[mode] {
+        let target = match field {
+            R(endpoint) => &mut endpoints[endpoint][0],
+            G(endpoint) => &mut endpoints[endpoint][1],
+            B(endpoint) => &mut endpoints[endpoint][2],
+            D => &mut partition,
+        };
+        if count > 0 {
+            *target |= (bits.read(count as u32) << lowest) as i32;
+        } else {
+            for bit in (0..-count as u32).rev() {
+                *target |= bits.bit(lowest + bit) as i32;
+            }
+        }
+    }
+
+    let (precision, delta_precision, transformed) = BC6H_PRECISIONS[mode];
+    let subsets = if mode < 10 { 2 } else { 1 };
+    let endpoint_count = subsets * 2;
+    if signed {
+        for value in endpoints[0].iter_mut() {
+            *value = sign_extend(*value, precision);
+        }
+    }
+    let base = endpoints[0];
+    for endpoint in endpoints[1..endpoint_count].iter_mut() {
+        for channel in 0..3 {
+            if transformed {
+                let delta = sign_extend(endpoint[channel], delta_precision[channel]);
+                endpoint[channel] = (base[channel] + delta) & ((1 << precision) - 1);
+            }
+            if signed {
+                endpoint[channel] = sign_extend(endpoint[channel], precision);
+            }
+        }
+    }
+    for endpoint in endpoints[..endpoint_count].iter_mut() {
+        for value in endpoint.iter_mut() {
+            *value = unquantize_bc6h(*value, precision, signed);
+        }
+    }
+
+    let partition = partition as usize;
+    let index_bits = if subsets == 2 { 3 } else { 4 };
+    for (texel, out) in out.chunks_exact_mut(8).enumerate() {
+        let anchor = is_anchor(subsets, partition, texel);
+        let weight = weights(index_bits)[bits.read(index_bits - anchor as u32) as usize];
+        let subset = subset(subsets, partition, texel);
+        let (e0, e1) = (endpoints[subset * 2], endpoints[subset * 2 + 1]);
+        for channel in 0..3 {
+            let value =
+                (e0[channel] * (64 - weight as i32) + e1[channel] * weight as i32 + 32) >> 6;
+            let half = finish_unquantize_bc6h(value, signed);
+            out[channel * 2..channel * 2 + 2].copy_from_slice(&half.to_le_bytes());
+        }
+        out[6..8].copy_from_slice(&HALF_ONE.to_le_bytes());
+    }
+}
@@ -0,0 +1,311 @@
+//! Decoders of the ETC2 and EAC formats.
+//!
+//! Blocks are stored big endian, and their texels are indexed column by column.
+//! Each decoder writes the 4x4 texels of a block in row-major order.
+
+const MODIFIERS: [[i32; 2]; 8] = [
+    [2, 8],
+    [5, 17],
+    [9, 29],
+    [13, 42],
+    [18, 60],
+    [24, 80],
+    [33, 106],
+    [47, 183],
+];
+
+const DISTANCES: [i32; 8] = [3, 6, 11, 16, 23, 32, 41, 64];
+
+const EAC_MODIFIERS: [[i32; 8]; 16] = [
+    [-3, -6, -9, -15, 2, 5, 8, 14],
+    [-3, -7, -10, -13, 2, 6, 9, 12],
+    [-2, -5, -8, -13, 1, 4, 7, 12],
+    [-2, -4, -6, -13, 1, 3, 5, 12],
+    [-3, -6, -8, -12, 2, 5, 7, 11],
+    [-3, -7, -9, -11, 2, 6, 8, 10],
+    [-4, -7, -8, -11, 3, 6, 7, 10],
+    [-3, -5, -8, -11, 2, 4, 7, 10],
+    [-2, -6, -8, -10, 1, 5, 7, 9],
+    [-2, -5, -8, -10, 1, 4, 7, 9],
+    [-2, -4, -8, -10, 1, 3, 7, 9],
+    [-2, -5, -7, -10, 1, 4, 6, 9],
+    [-3, -4, -7, -10, 2, 3, 6, 9],
+    [-1, -2, -3, -10, 0, 1, 2, 9],
+    [-4, -6, -8, -9, 3, 5, 7, 8],
+    [-3, -5, -7, -9, 2, 4, 6, 8],
+];
+
+fn read_u64(block: &[u8]) -> u64 {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&block[..8]);
+    u64::from_be_bytes(bytes)
+}
+
+/// Extracts `count` bits of `block`, starting from the bit `lowest`.
+fn field(block: u64, lowest: u32, count: u32) -> i32 {
+    ((block >> lowest) & ((1 << count) - 1)) as i32
+}
+
+fn extend4(value: i32) -> i32 {
+    value << 4 | value
+}
+
+fn extend5(value: i32) -> i32 {
+    value << 3 | value >> 2
+}
+
+fn extend6(value: i32) -> i32 {
+    value << 2 | value >> 4
+}
+
+fn extend7(value: i32) -> i32 {
+    value << 1 | value >> 6
+}
+
+fn clamp(value: i32) -> u8 {
+    value.clamp(0, 255) as u8
+}
+
+fn offset(color: [i32; 3], offset: i32) -> [u8; 4] {
+    [
+        clamp(color[0] + offset),
+        clamp(color[1] + offset),
+        clamp(color[2] + offset),
+        255,
+    ]
+}
+
+/// Writes the texels of a block selecting one of 4 paint colors, with the index 2 transparent
+/// in punchthrough blocks.
+fn write_paint_colors(block: u64, paint: [[u8; 4]; 4], transparent: bool, out: &mut [u8]) {
+    for i in 0..16 {
+        let index = field(block, i + 16, 1) << 1 | field(block, i, 1);
+        let (x, y) = (i as usize / 4, i as usize % 4);
+        let texel = &mut out[(y * 4 + x) * 4..][..4];
+        if transparent && index == 2 {
+            texel.copy_from_slice(&[0; 4]);
+        } else {
+            texel.copy_from_slice(&paint[index as usize]);
+        }
+    }
+}
+
+/// Decodes an ETC2 RGB block into RGBA8 texels.
+///
+/// With `punchthrough`, the bit selecting the differential mode instead tells if the block is opaque.
+fn decode_color(block: &[u8], out: &mut [u8], punchthrough: bool) {
+    let block = read_u64(block);
+    let diff_bit = field(block, 33, 1) != 0;
+    let (differential, transparent) = if punchthrough {
+        (true, !diff_bit)
+    } else {
+        (diff_bit, false)
+    };
+
+    let (base0, base1) = if differential {
+        let r = field(block, 59, 5);
+        let g = field(block, 51, 5);
+        let b = field(block, 43, 5);
+        let dr = r + (field(block, 56, 3) << 29 >> 29);
+        let dg = g + (field(block, 48, 3) << 29 >> 29);
+        let db = b + (field(block, 40, 3) << 29 >> 29);
+        if !(0..32).contains(&dr) {
+            return decode_t(block, out, transparent);
+        }
+        if !(0..32).contains(&dg) {
+            return decode_h(block, out, transparent);
+        }
+        if !(0..32).contains(&db) {
+            return decode_planar(block, out);
+        }
+        (
+            [extend5(r), extend5(g), extend5(b)],
+            [extend5(dr), extend5(dg), extend5(db)],
+        )
+    } else {
+        (
+            [
+                extend4(field(block, 60, 4)),
+                extend4(field(block, 52, 4)),
+                extend4(field(block, 44, 4)),
+            ],
+            [
+                extend4(field(block, 56, 4)),
+                extend4(field(block, 48, 4)),
+                extend4(field(block, 40, 4)),
+            ],
+        )
+    };
+
+    let flip = field(block, 32, 1) != 0;
+    let tables = [
+        MODIFIERS[field(block, 37, 3) as usize],
+        MODIFIERS[field(block, 34, 3) as usize],
+    ];
+    for i in 0..16 {
+        let (x, y) = (i as usize / 4, i as usize % 4);
+        let second = if flip { y >= 2 } else { x >= 2 };
+        let (base, table) = if second {
+            (base1, tables[1])
+        } else {
+            (base0, tables[0])
+        };
+        let index = field(block, i + 16, 1) << 1 | field(block, i, 1);
+        let texel = &mut out[(y * 4 + x) * 4..][..4];
+        if transparent && index == 2 {
+            texel.copy_from_slice(&[0; 4]);
+            continue;
+        }
+        let modifier = match index {
+            // punchthrough blocks use the base color instead of the smaller modifier
+            0 if transparent => 0,
+            0 => table[0],
+            1 => table[1],
+            2 => -table[0],
+            _ => -table[1],
+        };
+        texel.copy_from_slice(&offset(base, modifier));
+    }
+}
+
+fn decode_t(block: u64, out: &mut [u8], transparent: bool) {
+    let color0 = [
+        extend4(field(block, 59, 2) << 2 | field(block, 56, 2)),
+        extend4(field(block, 52, 4)),
+        extend4(field(block, 48, 4)),
+    ];
+    let color1 = [
+        extend4(field(block, 44, 4)),
+        extend4(field(block, 40, 4)),
+        extend4(field(block, 36, 4)),
+    ];
+    let distance = DISTANCES[(field(block, 34, 2) << 1 | field(block, 32, 1)) as usize];
+    let paint = [
+        offset(color0, 0),
+        offset(color1, distance),
+        offset(color1, 0),
+        offset(color1, -distance),
+    ];
+    write_paint_colors(block, paint, transparent, out);
+}
+
+fn decode_h(block: u64, out: &mut [u8], transparent: bool) {
+    let raw0 = [
+        field(block, 59, 4),
+        field(block, 56, 3) << 1 | field(block, 52, 1),
+        field(block, 51, 1) << 3 | field(block, 47, 3),
+    ];
+    let raw1 = [
+        field(block, 43, 4),
+        field(block, 39, 4),
+        field(block, 35, 4),
+    ];
+    let value = |c: [i32; 3]| c[0] << 8 | c[1] << 4 | c[2];
+    let index =
+        field(block, 34, 1) << 2 | field(block, 32, 1) << 1 | (value(raw0) >= value(raw1)) as i32;
+    let distance = DISTANCES[index as usize];
+    let color0 = raw0.map(extend4);
+    let color1 = raw1.map(extend4);
+    let paint = [
+        offset(color0, distance),
+        offset(color0, -distance),
+        offset(color1, distance),
+        offset(color1, -distance),
+    ];
+    write_paint_colors(block, paint, transparent, out);
+}
+
+fn decode_planar(block: u64, out: &mut [u8]) {
+    let origin = [
+        extend6(field(block, 57, 6)),
+        extend7(field(block, 56, 1) << 6 | field(block, 49, 6)),
+        extend6(field(block, 48, 1) << 5 | field(block, 43, 2) << 3 | field(block, 39, 3)),
+    ];
+    let horizontal = [
+        extend6(field(block, 34, 5) << 1 | field(block, 32, 1)),
+        extend7(field(block, 25, 7)),
+        extend6(field(block, 19, 6)),
+    ];
+    let vertical = [
+        extend6(field(block, 13, 6)),
+        extend7(field(block, 6, 7)),
+        extend6(field(block, 0, 6)),
+    ];
+    for y in 0..4 {
+        for x in 0..4 {
+            let texel = &mut out[(y * 4 + x) * 4..][..4];
+            for c in 0..3 {
+                texel[c] = clamp(
+                    (x as i32 * (horizontal[c] - origin[c])
+                        + y as i32 * (vertical[c] - origin[c])
+                        + 4 * origin[c]
+                        + 2)
+                        >> 2,
+                );
+            }
+            texel[3] = 255;
+        }
+    }
+}
+
+/// Decodes an EAC block into 16 values, in row-major order.
+///
+/// `eleven_bits` decodes the values of the R11 and RG11 formats, in `-1023..=1023` when
+/// `signed` and `0..=2047` otherwise. The alpha of ETC2 is decoded in `0..=255`.
+fn decode_eac(block: &[u8], eleven_bits: bool, signed: bool) -> [i32; 16] {
+    let block = read_u64(block);
+    let base = if signed {
+        (field(block, 56, 8) as u8 as i8).max(-127) as i32
+    } else {
+        field(block, 56, 8)
+    };
+    let multiplier = field(block, 52, 4);
+    let table = EAC_MODIFIERS[field(block, 48, 4) as usize];
+    let mut values = [0; 16];
+    for i in 0..16u32 {
+        let modifier = table[field(block, 45 - 3 * i, 3) as usize];
+        let (x, y) = (i as usize / 4, i as usize % 4);
+        values[y * 4 + x] = if !eleven_bits {
+            (base + modifier * multiplier).clamp(0, 255)
+        } else {
+            let scale = if multiplier == 0 { 1 } else { multiplier * 8 };
+            if signed {
+                (base * 8 + modifier * scale).clamp(-1023, 1023)
+            } else {
+                (base * 8 + 4 + modifier * scale).clamp(0, 2047)
+            }
+        };
+    }
+    values
+}
+
+pub(super) fn decode_etc2_rgb(block: &[u8], out: &mut [u8]) {
+    decode_color(block, out, false);
+}
+
+pub(super) fn decode_etc2_rgb_a1(block: &[u8], out: &mut [u8]) {
+    decode_color(block, out, true);
+}
+
+pub(super) fn decode_etc2_rgba(block: &[u8], out: &mut [u8]) {
+    decode_color(&block[8..], out, false);
+    for (texel, alpha) in decode_eac(block, false, false).iter().enumerate() {
+        out[texel * 4 + 3] = *alpha as u8;
+    }
+}
+
+/// Decodes the EAC R11 and RG11 formats into half float channels.
+pub(super) fn decode_eac_r11(block: &[u8], out: &mut [u8], channels: usize, signed: bool) {
+    for channel in 0..channels {
+        let values = decode_eac(&block[channel * 8..], true, signed);
+        for (texel, &value) in values.iter().enumerate() {
+            let value = if signed {
+                value as f32 / 1023.0
+            } else {
+                value as f32 / 2047.0
+            };
+            let offset = (texel * channels + channel) * 2;
+            out[offset..offset + 2].copy_from_slice(&super::f32_to_f16(value).to_le_bytes());
+        }
+    }
+}
@@ -0,0 +1,190 @@
+//! CPU decoding of block compressed textures, for devices which can't sample their format.
+
+mod astc;
+mod bc;
+mod etc;
+
+use crate::{AstcChannel, Extent3d, TextureDescriptor, TextureDimension, TextureFormat as Tf};
+
+/// Decodes the texels of a block, in row-major order.
+type BlockDecoder = Box<dyn Fn(&[u8], &mut [u8])>;
+
+/// Returns the uncompressed format [`decompress`] decodes `format` into.
+///
+/// The BC1 to BC3, BC7, ETC2 and LDR ASTC formats decode into 8 bit RGBA with the same color
+/// space, BC4 and BC5 into 8 bit normalized formats, BC6H into [`Tf::Rgba16Float`] and the EAC
+/// formats into [`Tf::R16Float`] or [`Tf::Rg16Float`].
+///
+/// Returns `None` for uncompressed formats and for HDR ASTC, which can't be decoded.
+pub fn decompressed_format(format: Tf) -> Option<Tf> {
+    Some(match format {
+        Tf::Bc1RgbaUnorm
+        | Tf::Bc2RgbaUnorm
+        | Tf::Bc3RgbaUnorm
+        | Tf::Bc7RgbaUnorm
+        | Tf::Etc2Rgb8Unorm
+        | Tf::Etc2Rgb8A1Unorm
+        | Tf::Etc2Rgba8Unorm
+        | Tf::Astc {
+            channel: AstcChannel::Unorm,
+            ..
+        } => Tf::Rgba8Unorm,
+        Tf::Bc1RgbaUnormSrgb
+        | Tf::Bc2RgbaUnormSrgb
+        | Tf::Bc3RgbaUnormSrgb
+        | Tf::Bc7RgbaUnormSrgb
+        | Tf::Etc2Rgb8UnormSrgb
+        | Tf::Etc2Rgb8A1UnormSrgb
+        | Tf::Etc2Rgba8UnormSrgb
+        | Tf::Astc {
+            channel: AstcChannel::UnormSrgb,
+            ..
+        } => Tf::Rgba8UnormSrgb,
+        Tf::Bc4RUnorm => Tf::R8Unorm,
+        Tf::Bc4RSnorm => Tf::R8Snorm,
+        Tf::Bc5RgUnorm => Tf::Rg8Unorm,
+        Tf::Bc5RgSnorm => Tf::Rg8Snorm,
+        Tf::Bc6hRgbUfloat | Tf::Bc6hRgbSfloat => Tf::Rgba16Float,
+        Tf::EacR11Unorm | Tf::EacR11Snorm => Tf::R16Float,
+        Tf::EacRg11Unorm | Tf::EacRg11Snorm => Tf::Rg16Float,
+        _ => return None,
+    })
+}
+
+/// Decodes an image of block compressed texels on the CPU, into [`decompressed_format`].
+///
+/// `size` is the size of the image in texels, which doesn't need to be a multiple of the
+/// block size. Each of its `depth_or_array_layers` is decoded as a separate image, with
+/// tightly packed rows of blocks. The decoded texels are tightly packed, with the parts of
+/// the blocks outside of `size` left out.
+///
+/// Returns `None` if the format can't be decoded.
+///
+/// # Panics
+///
+/// - If `data` is shorter than the blocks covering `size`.
+pub fn decompress(format: Tf, size: Extent3d, data: &[u8]) -> Option<Vec<u8>> {
+    let target = decompressed_format(format)?;
+    let info = format.describe();
+    let (block_width, block_height) = (
+        info.block_dimensions.0 as usize,
+        info.block_dimensions.1 as usize,
+    );
+    let block_size = info.block_size as usize;
+    let texel_size = target.describe().block_size as usize;
+
+    let decode: BlockDecoder = match format {
+        Tf::Bc1RgbaUnorm | Tf::Bc1RgbaUnormSrgb => Box::new(bc::decode_bc1),
+        Tf::Bc2RgbaUnorm | Tf::Bc2RgbaUnormSrgb => Box::new(bc::decode_bc2),
+        Tf::Bc3RgbaUnorm | Tf::Bc3RgbaUnormSrgb => Box::new(bc::decode_bc3),
+        Tf::Bc4RUnorm => Box::new(|block, out| bc::decode_bc4(block, out, false)),
+        Tf::Bc4RSnorm => Box::new(|block, out| bc::decode_bc4(block, out, true)),
+        Tf::Bc5RgUnorm => Box::new(|block, out| bc::decode_bc5(block, out, false)),
+        Tf::Bc5RgSnorm => Box::new(|block, out| bc::decode_bc5(block, out, true)),
+        Tf::Bc6hRgbUfloat => Box::new(|block, out| bc::decode_bc6h(block, out, false)),
+        Tf::Bc6hRgbSfloat => Box::new(|block, out| bc::decode_bc6h(block, out, true)),
+        Tf::Bc7RgbaUnorm | Tf::Bc7RgbaUnormSrgb => Box::new(bc::decode_bc7),
+        Tf::Etc2Rgb8Unorm | Tf::Etc2Rgb8UnormSrgb => Box::new(etc::decode_etc2_rgb),
+        Tf::Etc2Rgb8A1Unorm | Tf::Etc2Rgb8A1UnormSrgb => Box::new(etc::decode_etc2_rgb_a1),
+        Tf::Etc2Rgba8Unorm | Tf::Etc2Rgba8UnormSrgb => Box::new(etc::decode_etc2_rgba),
+        Tf::EacR11Unorm => Box::new(|block, out| etc::decode_eac_r11(block, out, 1, false)),
+        Tf::EacR11Snorm => Box::new(|block, out| etc::decode_eac_r11(block, out, 1, true)),
+        Tf::EacRg11Unorm => Box::new(|block, out| etc::decode_eac_r11(block, out, 2, false)),
+        Tf::EacRg11Snorm => Box::new(|block, out| etc::decode_eac_r11(block, out, 2, true)),
+        Tf::Astc { channel, .. } => {
+            let srgb = channel == AstcChannel::UnormSrgb;
+            Box::new(move |block, out| {
+                astc::decode_block(block, out, block_width as u32, block_height as u32, srgb)
+            })
+        }
+        _ => unreachable!(),
+    };
+
+    let (width, height) = (size.width as usize, size.height as usize);
+    let blocks_x = (width + block_width - 1) / block_width;
+    let blocks_y = (height + block_height - 1) / block_height;
+    let row_size = block_width * texel_size;
+    let mut texels = vec![0; width * height * size.depth_or_array_layers as usize * texel_size];
+    let mut block_texels = vec![0; block_height * row_size];
+    let mut offset = 0;
+    for layer in 0..size.depth_or_array_layers as usize {
+        for block_y in 0..blocks_y {
+            for block_x in 0..blocks_x {
+                decode(&data[offset..offset + block_size], &mut block_texels);
+                offset += block_size;
+
+                let x = block_x * block_width;
+                let copy_size = block_width.min(width - x) * texel_size;
+                for (row, block_row) in block_texels.chunks_exact(row_size).enumerate() {
+                    let y = block_y * block_height + row;
+                    if y >= height {
+                        break;
+                    }
+                    let start = ((layer * height + y) * width + x) * texel_size;
+                    texels[start..start + copy_size].copy_from_slice(&block_row[..copy_size]);
+                }
+            }
+        }
+    }
+    Some(texels)
+}
+
+/// Decodes all the layers and mip levels of a texture, laid out as
+/// [`DeviceExt::create_texture_with_data`] expects them.
+///
+/// Returns the decoded format and texels, or `None` if the format can't be decoded.
+///
+/// [`DeviceExt::create_texture_with_data`]: super::DeviceExt::create_texture_with_data
+pub(super) fn decompress_texture(desc: &TextureDescriptor, data: &[u8]) -> Option<(Tf, Vec<u8>)> {
+    let format = decompressed_format(desc.format)?;
+    let info = desc.format.describe();
+    let mut texels = Vec::new();
+    let mut offset = 0;
+    for _ in 0..desc.array_layer_count() {
+        for mip in 0..desc.mip_level_count {
+            let mut mip_size = desc.mip_level_size(mip).unwrap();
+            if desc.dimension != TextureDimension::D3 {
+                mip_size.depth_or_array_layers = 1;
+            }
+            let mip_physical = mip_size.physical_size(desc.format);
+            let data_size = (mip_physical.width / info.block_dimensions.0 as u32) as usize
+                * (mip_physical.height / info.block_dimensions.1 as u32) as usize
+                * mip_size.depth_or_array_layers as usize
+                * info.block_size as usize;
+            texels.extend(decompress(
+                desc.format,
+                mip_size,
+                &data[offset..offset + data_size],
+            )?);
+            offset += data_size;
+        }
+    }
+    Some((format, texels))
+}
+
+/// Converts a `f32` to the bits of a half-precision float, rounding to the nearest even.
+fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    let mantissa = bits & 0x7f_ffff;
+    if (bits >> 23) & 0xff == 0xff {
+        return sign | 0x7c00 | if mantissa != 0 { 0x200 } else { 0 };
+    }
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if exponent <= 0 {
+        // subnormal, or too small for a half float
+        if exponent < -10 {
+            return sign;
+        }
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (14 - exponent) as u32;
+        let rounded = (mantissa + (1 << (shift - 1)) - 1 + ((mantissa >> shift) & 1)) >> shift;
+        return sign | rounded as u16;
+    }
+    // a carry out of the mantissa correctly increments the exponent
+    let rounded = (mantissa + 0xfff + ((mantissa >> 13) & 1)) >> 13;
+    sign | (((exponent as u32) << 10) + rounded) as u16
+}
@@ -35,6 +35,36 @@ pub trait DeviceExt {
         desc: &crate::TextureDescriptor,
         data: &[u8],
     ) -> crate::Texture;
+
+    /// Upload an entire texture like [`DeviceExt::create_texture_with_data`], decoding it
+    /// on the CPU if the device doesn't support its block compressed format.
+    ///
+    /// The texels are decoded with [`decompress`] when the features required by the format
+    /// are missing, and the texture is created with the [`decompressed_format`] instead.
+    /// Returns the texture, and the format it fell back to if any.
+    ///
+    /// [`decompress`]: crate::util::decompress
+    /// [`decompressed_format`]: crate::util::decompressed_format
+    fn create_texture_with_fallback(
+        &self,
+        queue: &crate::Queue,
+        desc: &crate::TextureDescriptor,
+        data: &[u8],
+    ) -> (crate::Texture, Option<crate::TextureFormat>);
+
+    /// Generates all mip levels of a texture from its first mip level, and submits the work.
+    ///
+    /// `desc` has to be the descriptor the texture was created with.
+    /// This creates a new [`MipmapGenerator`] every time; keep one around instead
+    /// when generating mipmaps repeatedly, so that its pipelines are reused.
+    ///
+    /// [`MipmapGenerator`]: crate::util::MipmapGenerator
+    fn generate_mipmaps(
+        &self,
+        queue: &crate::Queue,
+        texture: &crate::Texture,
+        desc: &crate::TextureDescriptor,
+    ) -> Result<(), super::MipmapError>;
 }
 
 impl DeviceExt for crate::Device {
@@ -144,4 +174,38 @@ impl DeviceExt for crate::Device {
 
         texture
     }
+
+    fn create_texture_with_fallback(
+        &self,
+        queue: &crate::Queue,
+        desc: &crate::TextureDescriptor,
+        data: &[u8],
+    ) -> (crate::Texture, Option<crate::TextureFormat>) {
+        let missing_features = desc.format.describe().required_features - self.features();
+        if !missing_features.is_empty() {
+            if let Some((format, texels)) = super::decompress::decompress_texture(desc, data) {
+                let mut desc = desc.to_owned();
+                desc.format = format;
+                return (
+                    self.create_texture_with_data(queue, &desc, &texels),
+                    Some(format),
+                );
+            }
+        }
+        (self.create_texture_with_data(queue, desc, data), None)
+    }
+
+    fn generate_mipmaps(
+        &self,
+        queue: &crate::Queue,
+        texture: &crate::Texture,
+        desc: &crate::TextureDescriptor,
+    ) -> Result<(), super::MipmapError> {
+        let mut encoder = self.create_command_encoder(&crate::CommandEncoderDescriptor {
+            label: Some("(wgpu internal) generate_mipmaps"),
+        });
+        super::MipmapGenerator::new(self).generate(self, &mut encoder, texture, desc)?;
+        queue.submit(Some(encoder.finish()));
+        Ok(())
+    }
 }
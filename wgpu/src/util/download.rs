@@ -0,0 +1,296 @@
+use crate::{
+    BufferAsyncError, BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Device, Extent3d,
+    ImageCopyBuffer, ImageCopyTexture, ImageDataLayout, MapMode, Queue, TextureAspect,
+    TextureFormat, COPY_BYTES_PER_ROW_ALIGNMENT,
+};
+use std::{num::NonZeroU32, sync::Arc};
+
+/// Tightly packed contents of a texture subresource, downloaded from the GPU.
+///
+/// Unlike a plain copy into a buffer, the rows have no padding:
+/// each row of blocks takes exactly [`TextureDownload::bytes_per_row`] bytes,
+/// and each image (layer or depth slice) takes [`TextureDownload::rows_per_image`] rows.
+pub struct TextureDownload {
+    format: TextureFormat,
+    aspect: TextureAspect,
+    size: Extent3d,
+    bytes_per_row: u32,
+    rows_per_image: u32,
+    data: Vec<u8>,
+}
+
+impl TextureDownload {
+    /// Asynchronously read a region of a texture.
+    ///
+    /// `format` has to be the format of the texture, and `size` is the extent of
+    /// the region in texels. For compressed formats, it's rounded up to whole blocks.
+    /// The texture needs the `COPY_SRC` usage.
+    pub fn read_texture(
+        device: &Device,
+        queue: &Queue,
+        texture: &ImageCopyTexture,
+        format: TextureFormat,
+        size: Extent3d,
+        callback: impl FnOnce(Result<Self, BufferAsyncError>) + Send + 'static,
+    ) {
+        let size = size.physical_size(format);
+        let info = format.describe();
+        let (block_width, block_height) = info.block_dimensions;
+        let blocks_per_row = size.width / block_width as u32;
+        let rows_per_image = size.height / block_height as u32;
+        let bytes_per_row = blocks_per_row * aspect_block_size(format, texture.aspect);
+
+        // Validation uses the block size of the whole format,
+        // even if only a single aspect is copied.
+        let align = COPY_BYTES_PER_ROW_ALIGNMENT;
+        let unpadded_bytes_per_row = blocks_per_row * info.block_size as u32;
+        let padding = (align - unpadded_bytes_per_row % align) % align;
+        let padded_bytes_per_row = unpadded_bytes_per_row + padding;
+        let buffer_size =
+            padded_bytes_per_row as u64 * rows_per_image as u64 * size.depth_or_array_layers as u64;
+
+        let download = Arc::new(device.create_buffer(&BufferDescriptor {
+            size: buffer_size,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+            label: Some("TextureDownload"),
+        }));
+
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor { label: None });
+        encoder.copy_texture_to_buffer(
+            ImageCopyTexture {
+                texture: texture.texture,
+                mip_level: texture.mip_level,
+                origin: texture.origin,
+                aspect: texture.aspect,
+            },
+            ImageCopyBuffer {
+                buffer: &download,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(padded_bytes_per_row),
+                    rows_per_image: NonZeroU32::new(rows_per_image),
+                },
+            },
+            size,
+        );
+        queue.submit(Some(encoder.finish()));
+
+        let aspect = texture.aspect;
+        download
+            .clone()
+            .slice(..)
+            .map_async(MapMode::Read, move |result| {
+                if let Err(e) = result {
+                    callback(Err(e));
+                    return;
+                }
+
+                let mapped_range = crate::Context::buffer_get_mapped_range(
+                    &*download.context,
+                    &download.id,
+                    0..buffer_size,
+                );
+                let padded = crate::BufferMappedRangeSlice::slice(&mapped_range);
+                let mut data = Vec::with_capacity(
+                    (bytes_per_row * rows_per_image * size.depth_or_array_layers) as usize,
+                );
+                for row in padded.chunks_exact(padded_bytes_per_row as usize) {
+                    data.extend_from_slice(&row[..bytes_per_row as usize]);
+                }
+                drop(mapped_range);
+                download.unmap();
+
+                callback(Ok(Self {
+                    format,
+                    aspect,
+                    size,
+                    bytes_per_row,
+                    rows_per_image,
+                    data,
+                }));
+            });
+    }
+
+    /// Format of the downloaded texture.
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    /// Aspect of the texture that was downloaded.
+    pub fn aspect(&self) -> TextureAspect {
+        self.aspect
+    }
+
+    /// Extent of the downloaded region, rounded up to whole blocks.
+    pub fn size(&self) -> Extent3d {
+        self.size
+    }
+
+    /// Number of bytes in a row of blocks.
+    pub fn bytes_per_row(&self) -> u32 {
+        self.bytes_per_row
+    }
+
+    /// Number of rows of blocks in each image.
+    pub fn rows_per_image(&self) -> u32 {
+        self.rows_per_image
+    }
+
+    /// Converts the data to RGBA8, with 4 bytes per texel.
+    ///
+    /// 8-bit normalized formats keep their values, so sRGB data stays sRGB encoded.
+    /// Other formats are clamped to the `[0, 1]` range.
+    /// Returns `None` for compressed and opaque formats.
+    pub fn to_rgba8(&self) -> Option<Vec<u8>> {
+        match (self.format, self.aspect) {
+            (TextureFormat::Rgba8Unorm, _) | (TextureFormat::Rgba8UnormSrgb, _) => {
+                Some(self.data.clone())
+            }
+            (TextureFormat::Bgra8Unorm, _) | (TextureFormat::Bgra8UnormSrgb, _) => {
+                let mut data = self.data.clone();
+                for texel in data.chunks_exact_mut(4) {
+                    texel.swap(0, 2);
+                }
+                Some(data)
+            }
+            _ => {
+                let data = self.to_rgba32f()?;
+                Some(
+                    data.into_iter()
+                        .map(|value| (value.clamp(0.0, 1.0) * 255.0).round() as u8)
+                        .collect(),
+                )
+            }
+        }
+    }
+
+    /// Converts the data to RGBA32F, with 4 floats per texel.
+    ///
+    /// Missing color channels are filled with 0, and missing alpha with 1.
+    /// Normalized values are not converted from sRGB.
+    /// Depth is put into the color channels, and so is stencil, normalized to `[0, 1]`.
+    /// Returns `None` for compressed and opaque formats.
+    pub fn to_rgba32f(&self) -> Option<Vec<f32>> {
+        let texel_size = aspect_block_size(self.format, self.aspect) as usize;
+        let convert = texel_converter(self.format, self.aspect)?;
+        let mut result = Vec::with_capacity(self.data.len() / texel_size * 4);
+        for texel in self.data.chunks_exact(texel_size) {
+            result.extend_from_slice(&convert(texel));
+        }
+        Some(result)
+    }
+
+    /// Writes the data into a PNG file, converting it to RGBA8 first.
+    ///
+    /// Only the first image is written out for arrays and 3D textures.
+    #[cfg(feature = "png")]
+    pub fn write_png(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        let data = self.to_rgba8().ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("format {:?} can't be converted to RGBA8", self.format),
+            )
+        })?;
+        let image_size = (self.size.width * self.size.height * 4) as usize;
+
+        let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        let mut encoder = png::Encoder::new(file, self.size.width, self.size.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&data[..image_size])?;
+        Ok(())
+    }
+}
+
+impl std::ops::Deref for TextureDownload {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// Returns the number of bytes a block of `aspect` takes in a copy.
+fn aspect_block_size(format: TextureFormat, aspect: TextureAspect) -> u32 {
+    match (format, aspect) {
+        (_, TextureAspect::StencilOnly) => 1,
+        (TextureFormat::Depth32FloatStencil8, TextureAspect::DepthOnly) => 4,
+        _ => format.describe().block_size as u32,
+    }
+}
+
+type TexelConverter = fn(&[u8]) -> [f32; 4];
+
+fn texel_converter(format: TextureFormat, aspect: TextureAspect) -> Option<TexelConverter> {
+    fn unorm8(value: u8) -> f32 {
+        value as f32 / 255.0
+    }
+    fn snorm8(value: u8) -> f32 {
+        (value as i8 as f32 / 127.0).max(-1.0)
+    }
+    fn half(bytes: &[u8]) -> f32 {
+        f16_to_f32(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+    fn float(bytes: &[u8]) -> f32 {
+        f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    }
+    fn depth(bytes: &[u8]) -> [f32; 4] {
+        let d = float(bytes);
+        [d, d, d, 1.0]
+    }
+
+    if aspect == TextureAspect::StencilOnly {
+        return Some(|t| {
+            let s = unorm8(t[0]);
+            [s, s, s, 1.0]
+        });
+    }
+
+    Some(match format {
+        TextureFormat::R8Unorm => |t| [unorm8(t[0]), 0.0, 0.0, 1.0],
+        TextureFormat::Rg8Unorm => |t| [unorm8(t[0]), unorm8(t[1]), 0.0, 1.0],
+        TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => {
+            |t| [unorm8(t[0]), unorm8(t[1]), unorm8(t[2]), unorm8(t[3])]
+        }
+        TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => {
+            |t| [unorm8(t[2]), unorm8(t[1]), unorm8(t[0]), unorm8(t[3])]
+        }
+        TextureFormat::R8Snorm => |t| [snorm8(t[0]), 0.0, 0.0, 1.0],
+        TextureFormat::Rg8Snorm => |t| [snorm8(t[0]), snorm8(t[1]), 0.0, 1.0],
+        TextureFormat::Rgba8Snorm => |t| [snorm8(t[0]), snorm8(t[1]), snorm8(t[2]), snorm8(t[3])],
+        TextureFormat::Rgb10a2Unorm => |t| {
+            let bits = u32::from_le_bytes([t[0], t[1], t[2], t[3]]);
+            [
+                (bits & 0x3ff) as f32 / 1023.0,
+                ((bits >> 10) & 0x3ff) as f32 / 1023.0,
+                ((bits >> 20) & 0x3ff) as f32 / 1023.0,
+                (bits >> 30) as f32 / 3.0,
+            ]
+        },
+        TextureFormat::R16Float => |t| [half(t), 0.0, 0.0, 1.0],
+        TextureFormat::Rg16Float => |t| [half(t), half(&t[2..]), 0.0, 1.0],
+        TextureFormat::Rgba16Float => |t| [half(t), half(&t[2..]), half(&t[4..]), half(&t[6..])],
+        TextureFormat::R32Float => |t| [float(t), 0.0, 0.0, 1.0],
+        TextureFormat::Rg32Float => |t| [float(t), float(&t[4..]), 0.0, 1.0],
+        TextureFormat::Rgba32Float => {
+            |t| [float(t), float(&t[4..]), float(&t[8..]), float(&t[12..])]
+        }
+        TextureFormat::Depth32Float => depth,
+        TextureFormat::Depth32FloatStencil8 if aspect == TextureAspect::DepthOnly => depth,
+        _ => return None,
+    })
+}
+
+/// Converts a half-precision float, given by its bits, to `f32`.
+fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32;
+    match exponent {
+        0 => sign * mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
@@ -0,0 +1,655 @@
+use super::ResourcePool;
+use crate::{
+    Buffer, BufferDescriptor, CommandBuffer, CommandEncoder, CommandEncoderDescriptor, Device,
+    Texture, TextureDescriptor, TextureView, TextureViewDescriptor,
+};
+use std::{
+    cmp::Reverse, collections::BinaryHeap, error, fmt, fmt::Write as _, ops::RangeInclusive,
+    sync::Arc,
+};
+
+/// Error returned when a [`RenderGraph`] can't be scheduled.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RenderGraphError {
+    /// The passes with these names depend on each other.
+    Cycle(Vec<String>),
+    /// A pass reads a transient resource that no pass writes.
+    Unwritten {
+        /// Name of the pass.
+        pass: String,
+        /// Name of the resource.
+        resource: String,
+    },
+}
+
+impl fmt::Display for RenderGraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            RenderGraphError::Cycle(ref passes) => {
+                write!(f, "Passes {:?} depend on each other", passes)
+            }
+            RenderGraphError::Unwritten {
+                ref pass,
+                ref resource,
+            } => write!(
+                f,
+                "Pass {:?} reads transient resource {:?}, which no pass writes",
+                pass, resource
+            ),
+        }
+    }
+}
+
+impl error::Error for RenderGraphError {}
+
+/// Texture declared in a [`RenderGraph`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RenderGraphTexture(usize);
+
+/// Buffer declared in a [`RenderGraph`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RenderGraphBuffer(usize);
+
+enum ResourceKind<'a> {
+    TransientTexture(wgt::TextureDescriptor<()>),
+    ImportedTexture(&'a Texture),
+    TransientBuffer(wgt::BufferDescriptor<()>),
+    ImportedBuffer(&'a Buffer),
+}
+
+struct Resource<'a> {
+    name: String,
+    kind: ResourceKind<'a>,
+}
+
+impl Resource<'_> {
+    fn is_imported(&self) -> bool {
+        matches!(
+            self.kind,
+            ResourceKind::ImportedTexture(_) | ResourceKind::ImportedBuffer(_)
+        )
+    }
+}
+
+type RecordPass<'a> = Box<dyn FnOnce(&mut CommandEncoder, &RenderGraphResources<'_>) + 'a>;
+
+struct Pass<'a> {
+    name: String,
+    reads: Vec<usize>,
+    writes: Vec<usize>,
+    keep: bool,
+    record: RecordPass<'a>,
+}
+
+/// Order of the passes that aren't culled.
+struct Schedule {
+    order: Vec<usize>,
+    kept: Vec<bool>,
+}
+
+/// Passes of a frame, along with the textures and buffers they read and write.
+///
+/// Resources are either imported, like the texture of a surface, or transient, in which
+/// case they are allocated from a [`RenderGraphAllocator`], such as a [`ResourcePool`],
+/// for the duration of the frame.
+///
+/// The passes writing a resource are ordered as they were added, and the passes only
+/// reading it come after all of them. Passes are culled unless they write an imported
+/// resource, are marked with [`RenderGraphPassBuilder::keep`], or write a resource read
+/// by a pass that isn't culled. The remaining passes are ordered topologically, keeping
+/// the order in which they were added where they don't depend on each other.
+///
+/// Transient resources with the same descriptor share the same texture or buffer when
+/// their lifetimes, from the first to the last pass using them, don't overlap. Their
+/// contents are thus undefined until they are written, so the first pass writing a
+/// transient texture has to clear it, or overwrite all of it.
+pub struct RenderGraph<'a> {
+    resources: Vec<Resource<'a>>,
+    passes: Vec<Pass<'a>>,
+}
+
+impl<'a> RenderGraph<'a> {
+    /// Create an empty graph.
+    pub fn new() -> Self {
+        Self {
+            resources: Vec::new(),
+            passes: Vec::new(),
+        }
+    }
+
+    fn add_resource(&mut self, name: Option<&str>, kind: ResourceKind<'a>) -> usize {
+        let index = self.resources.len();
+        self.resources.push(Resource {
+            name: match name {
+                Some(name) => name.to_string(),
+                None => format!("resource {}", index),
+            },
+            kind,
+        });
+        index
+    }
+
+    /// Declare a transient texture, named after the label of the descriptor.
+    pub fn create_texture(&mut self, desc: &TextureDescriptor) -> RenderGraphTexture {
+        let kind = ResourceKind::TransientTexture(desc.map_label(|_| ()));
+        RenderGraphTexture(self.add_resource(desc.label, kind))
+    }
+
+    /// Declare a texture owned outside of the graph.
+    pub fn import_texture(&mut self, name: &str, texture: &'a Texture) -> RenderGraphTexture {
+        RenderGraphTexture(self.add_resource(Some(name), ResourceKind::ImportedTexture(texture)))
+    }
+
+    /// Declare a transient buffer, named after the label of the descriptor.
+    pub fn create_buffer(&mut self, desc: &BufferDescriptor) -> RenderGraphBuffer {
+        let kind = ResourceKind::TransientBuffer(desc.map_label(|_| ()));
+        RenderGraphBuffer(self.add_resource(desc.label, kind))
+    }
+
+    /// Declare a buffer owned outside of the graph.
+    pub fn import_buffer(&mut self, name: &str, buffer: &'a Buffer) -> RenderGraphBuffer {
+        RenderGraphBuffer(self.add_resource(Some(name), ResourceKind::ImportedBuffer(buffer)))
+    }
+
+    /// Start declaring a pass.
+    pub fn add_pass<'g>(&'g mut self, name: &str) -> RenderGraphPassBuilder<'g, 'a> {
+        RenderGraphPassBuilder {
+            graph: self,
+            name: name.to_string(),
+            reads: Vec::new(),
+            writes: Vec::new(),
+            keep: false,
+        }
+    }
+
+    /// Passes each pass depends on.
+    fn dependencies(&self) -> Vec<Vec<usize>> {
+        let mut writers = vec![Vec::new(); self.resources.len()];
+        for (index, pass) in self.passes.iter().enumerate() {
+            for &resource in pass.writes.iter() {
+                writers[resource].push(index);
+            }
+        }
+        self.passes
+            .iter()
+            .enumerate()
+            .map(|(index, pass)| {
+                let mut dependencies = Vec::new();
+                for &resource in pass.writes.iter() {
+                    let position = writers[resource]
+                        .iter()
+                        .position(|&writer| writer == index)
+                        .unwrap();
+                    if position > 0 {
+                        dependencies.push(writers[resource][position - 1]);
+                    }
+                }
+                for &resource in pass.reads.iter() {
+                    if !pass.writes.contains(&resource) {
+                        dependencies.extend(writers[resource].last());
+                    }
+                }
+                dependencies.sort_unstable();
+                dependencies.dedup();
+                dependencies
+            })
+            .collect()
+    }
+
+    fn schedule(&self) -> Result<Schedule, RenderGraphError> {
+        let dependencies = self.dependencies();
+
+        let mut kept = vec![false; self.passes.len()];
+        let mut stack = self
+            .passes
+            .iter()
+            .enumerate()
+            .filter(|&(_, pass)| {
+                pass.keep
+                    || pass
+                        .writes
+                        .iter()
+                        .any(|&resource| self.resources[resource].is_imported())
+            })
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        while let Some(index) = stack.pop() {
+            if !kept[index] {
+                kept[index] = true;
+                stack.extend_from_slice(&dependencies[index]);
+            }
+        }
+
+        let mut written = vec![false; self.resources.len()];
+        for (index, pass) in self.passes.iter().enumerate() {
+            if kept[index] {
+                for &resource in pass.writes.iter() {
+                    written[resource] = true;
+                }
+            }
+        }
+        for (index, pass) in self.passes.iter().enumerate() {
+            if !kept[index] {
+                continue;
+            }
+            for &resource in pass.reads.iter() {
+                if !written[resource] && !self.resources[resource].is_imported() {
+                    return Err(RenderGraphError::Unwritten {
+                        pass: pass.name.clone(),
+                        resource: self.resources[resource].name.clone(),
+                    });
+                }
+            }
+        }
+
+        let mut remaining = vec![0; self.passes.len()];
+        let mut dependents = vec![Vec::new(); self.passes.len()];
+        for (index, dependencies) in dependencies.iter().enumerate() {
+            if kept[index] {
+                remaining[index] = dependencies.len();
+                for &dependency in dependencies.iter() {
+                    dependents[dependency].push(index);
+                }
+            }
+        }
+        let mut ready = (0..self.passes.len())
+            .filter(|&index| kept[index] && remaining[index] == 0)
+            .map(Reverse)
+            .collect::<BinaryHeap<_>>();
+        let mut order = Vec::new();
+        while let Some(Reverse(index)) = ready.pop() {
+            order.push(index);
+            for &dependent in dependents[index].iter() {
+                remaining[dependent] -= 1;
+                if remaining[dependent] == 0 {
+                    ready.push(Reverse(dependent));
+                }
+            }
+        }
+
+        if order.len() < kept.iter().filter(|&&kept| kept).count() {
+            return Err(RenderGraphError::Cycle(
+                (0..self.passes.len())
+                    .filter(|&index| kept[index] && remaining[index] != 0)
+                    .map(|index| self.passes[index].name.clone())
+                    .collect(),
+            ));
+        }
+        Ok(Schedule { order, kept })
+    }
+
+    /// Names of the passes that aren't culled, in the order they are recorded.
+    pub fn pass_order(&self) -> Result<Vec<&str>, RenderGraphError> {
+        Ok(self
+            .schedule()?
+            .order
+            .into_iter()
+            .map(|index| self.passes[index].name.as_str())
+            .collect())
+    }
+
+    /// Describe the graph in the DOT language of Graphviz.
+    ///
+    /// Passes are boxes, labeled with their position in the recording order, and culled
+    /// passes are dashed. Resources are ellipses, bold when imported, with edges from the
+    /// passes writing them and to the passes reading them.
+    pub fn to_dot(&self) -> String {
+        let schedule = self.schedule().ok();
+        let mut dot = String::from("digraph RenderGraph {\n");
+        for (index, pass) in self.passes.iter().enumerate() {
+            let name = pass.name.replace('"', "\\\"");
+            match schedule {
+                Some(ref schedule) if !schedule.kept[index] => {
+                    let _ = writeln!(
+                        dot,
+                        "    p{} [shape=box, style=dashed, label=\"{}\"];",
+                        index, name
+                    );
+                }
+                Some(ref schedule) => {
+                    let position = schedule.order.iter().position(|&pass| pass == index);
+                    let _ = writeln!(
+                        dot,
+                        "    p{} [shape=box, label=\"{} ({})\"];",
+                        index,
+                        name,
+                        position.unwrap()
+                    );
+                }
+                None => {
+                    let _ = writeln!(dot, "    p{} [shape=box, label=\"{}\"];", index, name);
+                }
+            }
+        }
+        for (index, resource) in self.resources.iter().enumerate() {
+            let style = if resource.is_imported() {
+                "bold"
+            } else {
+                "solid"
+            };
+            let _ = writeln!(
+                dot,
+                "    r{} [shape=ellipse, style={}, label=\"{}\"];",
+                index,
+                style,
+                resource.name.replace('"', "\\\"")
+            );
+        }
+        for (index, pass) in self.passes.iter().enumerate() {
+            for &resource in pass.reads.iter() {
+                let _ = writeln!(dot, "    r{} -> p{};", resource, index);
+            }
+            for &resource in pass.writes.iter() {
+                let _ = writeln!(dot, "    p{} -> r{};", index, resource);
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Allocate the transient resources from the `allocator`, and record the passes that
+    /// aren't culled into a single command buffer.
+    ///
+    /// The textures and buffers are released once the command buffer is recorded. With a
+    /// [`ResourcePool`], [`ResourcePool::end_frame`] has to be called after submitting it.
+    pub fn execute(
+        self,
+        device: &Device,
+        allocator: &mut impl RenderGraphAllocator,
+    ) -> Result<CommandBuffer, RenderGraphError> {
+        let schedule = self.schedule()?;
+
+        let mut first_use = vec![usize::MAX; self.resources.len()];
+        let mut last_use = vec![0; self.resources.len()];
+        for (position, &index) in schedule.order.iter().enumerate() {
+            let pass = &self.passes[index];
+            for &resource in pass.reads.iter().chain(pass.writes.iter()) {
+                first_use[resource] = first_use[resource].min(position);
+                last_use[resource] = last_use[resource].max(position);
+            }
+        }
+        let mut transients = (0..self.resources.len())
+            .filter(|&resource| {
+                first_use[resource] != usize::MAX && !self.resources[resource].is_imported()
+            })
+            .collect::<Vec<_>>();
+        transients.sort_by_key(|&resource| first_use[resource]);
+
+        let mut textures = Vec::new();
+        let mut buffers = Vec::new();
+        let mut allocations = vec![None; self.resources.len()];
+        for resource in transients {
+            let label = Some(self.resources[resource].name.as_str());
+            let uses = first_use[resource]..=last_use[resource];
+            let index = match self.resources[resource].kind {
+                ResourceKind::TransientTexture(ref desc) => {
+                    allocate(&mut textures, desc, uses, || {
+                        allocator.texture(device, &desc.map_label(|_| label))
+                    })
+                }
+                ResourceKind::TransientBuffer(ref desc) => {
+                    allocate(&mut buffers, desc, uses, || {
+                        allocator.buffer(device, &desc.map_label(|_| label))
+                    })
+                }
+                ResourceKind::ImportedTexture(_) | ResourceKind::ImportedBuffer(_) => {
+                    unreachable!()
+                }
+            };
+            allocations[resource] = Some(index);
+        }
+        let texture_views = textures
+            .iter()
+            .map(|allocation| {
+                allocation
+                    .resource
+                    .create_view(&TextureViewDescriptor::default())
+            })
+            .collect::<Vec<_>>();
+
+        let imported_views = self
+            .resources
+            .iter()
+            .enumerate()
+            .map(|(index, resource)| match resource.kind {
+                ResourceKind::ImportedTexture(texture) if first_use[index] != usize::MAX => {
+                    Some(texture.create_view(&TextureViewDescriptor::default()))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let entries = self
+            .resources
+            .iter()
+            .enumerate()
+            .map(
+                |(index, resource)| match (&resource.kind, allocations[index]) {
+                    (&ResourceKind::TransientTexture(_), Some(allocation)) => {
+                        ResolvedResource::Texture(
+                            &textures[allocation].resource,
+                            &texture_views[allocation],
+                        )
+                    }
+                    (&ResourceKind::TransientBuffer(_), Some(allocation)) => {
+                        ResolvedResource::Buffer(&buffers[allocation].resource)
+                    }
+                    (&ResourceKind::ImportedTexture(texture), _) => match imported_views[index] {
+                        Some(ref view) => ResolvedResource::Texture(texture, view),
+                        None => ResolvedResource::Unused,
+                    },
+                    (&ResourceKind::ImportedBuffer(buffer), _) => ResolvedResource::Buffer(buffer),
+                    _ => ResolvedResource::Unused,
+                },
+            )
+            .collect();
+        let resources = RenderGraphResources { entries };
+
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("RenderGraph"),
+        });
+        let mut passes = self.passes.into_iter().map(Some).collect::<Vec<_>>();
+        for &index in schedule.order.iter() {
+            let pass = passes[index].take().unwrap();
+            encoder.push_debug_group(&pass.name);
+            (pass.record)(&mut encoder, &resources);
+            encoder.pop_debug_group();
+        }
+        Ok(encoder.finish())
+    }
+}
+
+/// Source of the textures and buffers of the transient resources of a [`RenderGraph`].
+///
+/// [`ResourcePool`] implements it, keeping them from one frame to the next.
+pub trait RenderGraphAllocator {
+    /// Get a texture matching the descriptor, for the duration of the frame.
+    fn texture(&mut self, device: &Device, desc: &TextureDescriptor) -> Arc<Texture>;
+
+    /// Get a buffer matching the descriptor, for the duration of the frame.
+    fn buffer(&mut self, device: &Device, desc: &BufferDescriptor) -> Arc<Buffer>;
+}
+
+impl RenderGraphAllocator for ResourcePool {
+    fn texture(&mut self, device: &Device, desc: &TextureDescriptor) -> Arc<Texture> {
+        ResourcePool::texture(self, device, desc)
+    }
+
+    fn buffer(&mut self, device: &Device, desc: &BufferDescriptor) -> Arc<Buffer> {
+        ResourcePool::buffer(self, device, desc)
+    }
+}
+
+/// Texture or buffer of the allocator, shared by transient resources of a [`RenderGraph`].
+struct Allocation<'d, D, R> {
+    descriptor: &'d D,
+    resource: Arc<R>,
+    /// Position of the last pass using it.
+    last_use: usize,
+}
+
+/// Find an allocation for a transient resource with the descriptor `descriptor`, used
+/// by the passes at the positions `uses`, or make one with `acquire`, and return its index.
+fn allocate<'d, D: PartialEq, R>(
+    allocations: &mut Vec<Allocation<'d, D, R>>,
+    descriptor: &'d D,
+    uses: RangeInclusive<usize>,
+    acquire: impl FnOnce() -> Arc<R>,
+) -> usize {
+    let found = allocations.iter().position(|allocation| {
+        *allocation.descriptor == *descriptor && allocation.last_use < *uses.start()
+    });
+    let index = found.unwrap_or_else(|| {
+        allocations.push(Allocation {
+            descriptor,
+            resource: acquire(),
+            last_use: 0,
+        });
+        allocations.len() - 1
+    });
+    allocations[index].last_use = *uses.end();
+    index
+}
+
+impl Default for RenderGraph<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for RenderGraph<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RenderGraph")
+            .field("resources", &self.resources.len())
+            .field("passes", &self.passes.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Declares the resources used by a pass of a [`RenderGraph`], created by
+/// [`RenderGraph::add_pass`].
+#[must_use = "the pass is only added by RenderGraphPassBuilder::record"]
+pub struct RenderGraphPassBuilder<'g, 'a> {
+    graph: &'g mut RenderGraph<'a>,
+    name: String,
+    reads: Vec<usize>,
+    writes: Vec<usize>,
+    keep: bool,
+}
+
+impl<'g, 'a> RenderGraphPassBuilder<'g, 'a> {
+    /// Declare that the pass reads the `texture`.
+    pub fn read_texture(mut self, texture: RenderGraphTexture) -> Self {
+        self.reads.push(texture.0);
+        self
+    }
+
+    /// Declare that the pass writes the `texture`.
+    pub fn write_texture(mut self, texture: RenderGraphTexture) -> Self {
+        self.writes.push(texture.0);
+        self
+    }
+
+    /// Declare that the pass reads the `buffer`.
+    pub fn read_buffer(mut self, buffer: RenderGraphBuffer) -> Self {
+        self.reads.push(buffer.0);
+        self
+    }
+
+    /// Declare that the pass writes the `buffer`.
+    pub fn write_buffer(mut self, buffer: RenderGraphBuffer) -> Self {
+        self.writes.push(buffer.0);
+        self
+    }
+
+    /// Never cull the pass, for passes with side effects outside of the graph, like
+    /// queries.
+    pub fn keep(mut self) -> Self {
+        self.keep = true;
+        self
+    }
+
+    /// Add the pass to the graph, recorded by the `record` function when the graph is
+    /// executed.
+    pub fn record(
+        mut self,
+        record: impl FnOnce(&mut CommandEncoder, &RenderGraphResources<'_>) + 'a,
+    ) {
+        self.reads.sort_unstable();
+        self.reads.dedup();
+        self.writes.sort_unstable();
+        self.writes.dedup();
+        self.graph.passes.push(Pass {
+            name: self.name,
+            reads: self.reads,
+            writes: self.writes,
+            keep: self.keep,
+            record: Box::new(record),
+        });
+    }
+}
+
+impl fmt::Debug for RenderGraphPassBuilder<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RenderGraphPassBuilder")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+enum ResolvedResource<'r> {
+    Unused,
+    Texture(&'r Texture, &'r TextureView),
+    Buffer(&'r Buffer),
+}
+
+/// Textures and buffers of a [`RenderGraph`], given to its passes when they are recorded.
+pub struct RenderGraphResources<'r> {
+    entries: Vec<ResolvedResource<'r>>,
+}
+
+impl RenderGraphResources<'_> {
+    fn resolve_texture(&self, texture: RenderGraphTexture) -> (&Texture, &TextureView) {
+        match self.entries[texture.0] {
+            ResolvedResource::Texture(texture, view) => (texture, view),
+            _ => panic!("Texture {:?} isn't used by any pass", texture),
+        }
+    }
+
+    /// Texture allocated for the `texture`.
+    ///
+    /// # Panics
+    ///
+    /// - If no pass uses the `texture`.
+    pub fn texture(&self, texture: RenderGraphTexture) -> &Texture {
+        self.resolve_texture(texture).0
+    }
+
+    /// Default view of the texture allocated for the `texture`.
+    ///
+    /// # Panics
+    ///
+    /// - If no pass uses the `texture`.
+    pub fn view(&self, texture: RenderGraphTexture) -> &TextureView {
+        self.resolve_texture(texture).1
+    }
+
+    /// Buffer allocated for the `buffer`.
+    ///
+    /// # Panics
+    ///
+    /// - If no pass uses the `buffer`.
+    pub fn buffer(&self, buffer: RenderGraphBuffer) -> &Buffer {
+        match self.entries[buffer.0] {
+            ResolvedResource::Buffer(buffer) => buffer,
+            _ => panic!("Buffer {:?} isn't used by any pass", buffer),
+        }
+    }
+}
+
+impl fmt::Debug for RenderGraphResources<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RenderGraphResources")
+            .field("entries", &self.entries.len())
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,279 @@
+use crate::{
+    util::DownloadBuffer, BindGroup, BindGroupDescriptor, BindGroupEntry, BindingResource,
+    BufferAsyncError, BufferSlice, CommandEncoder, CommandEncoderDescriptor, ComputePassDescriptor,
+    ComputePipeline, ComputePipelineDescriptor, Device, Queue, ShaderModule, ShaderStages,
+};
+use std::{error, fmt};
+
+/// Error returned when a [`ComputeKernel`] can't be created or dispatched.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ComputeKernelError {
+    /// The backend can't reflect shader modules.
+    ReflectionUnavailable,
+    /// The module has no entry point with this name.
+    MissingEntryPoint(String),
+    /// The entry point with this name isn't a compute entry point.
+    NotCompute(String),
+    /// The entry point uses no resource with this name.
+    UnknownBinding(String),
+    /// No resource was given for a binding used by the entry point.
+    MissingBinding {
+        /// Bind group index.
+        group: u32,
+        /// Binding index in the group.
+        binding: u32,
+    },
+    /// The invocations don't fit in a grid of `max_compute_workgroups_per_dimension`
+    /// workgroups along each dimension.
+    TooManyInvocations(u32),
+}
+
+impl fmt::Display for ComputeKernelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ComputeKernelError::ReflectionUnavailable => {
+                write!(f, "Shader modules can't be reflected on this backend")
+            }
+            ComputeKernelError::MissingEntryPoint(ref name) => {
+                write!(f, "Module has no entry point named {:?}", name)
+            }
+            ComputeKernelError::NotCompute(ref name) => {
+                write!(f, "Entry point {:?} is not a compute entry point", name)
+            }
+            ComputeKernelError::UnknownBinding(ref name) => {
+                write!(f, "Entry point uses no resource named {:?}", name)
+            }
+            ComputeKernelError::MissingBinding { group, binding } => write!(
+                f,
+                "No resource given for group {} binding {}",
+                group, binding
+            ),
+            ComputeKernelError::TooManyInvocations(count) => {
+                write!(f, "{} invocations don't fit in a dispatch", count)
+            }
+        }
+    }
+}
+
+impl error::Error for ComputeKernelError {}
+
+/// Resource used by the entry point of a kernel.
+#[derive(Debug)]
+struct KernelBinding {
+    name: Option<String>,
+    group: u32,
+    binding: u32,
+}
+
+/// Compute pipeline of a WGSL entry point, dispatched over a number of invocations.
+///
+/// The pipeline layout is derived from the entry point, and its resources are bound by
+/// the names of their variables. Invocations are grouped by the workgroup size of the
+/// entry point, and the workgroups are laid out along x, then y and z when there are
+/// more than `max_compute_workgroups_per_dimension` of them. The index of an invocation
+/// is thus
+///
+/// ```wgsl
+/// (workgroup_id.x + (workgroup_id.y + workgroup_id.z * num_workgroups.y) * num_workgroups.x)
+///     * invocations_per_workgroup + local_invocation_index
+/// ```
+///
+/// and invocations with an index past the requested count have to return early, since
+/// the last workgroups are partially used.
+///
+/// Needs shader module reflection, which isn't available on the web backend.
+pub struct ComputeKernel {
+    pipeline: ComputePipeline,
+    workgroup_size: [u32; 3],
+    bindings: Vec<KernelBinding>,
+    group_count: u32,
+    max_workgroups: u32,
+}
+
+impl ComputeKernel {
+    /// Create the kernel of the compute entry point named `entry_point` in the `module`.
+    pub fn new(
+        device: &Device,
+        module: &ShaderModule,
+        entry_point: &str,
+    ) -> Result<Self, ComputeKernelError> {
+        let reflection = module
+            .reflect()
+            .ok_or(ComputeKernelError::ReflectionUnavailable)?;
+        let reflected = reflection
+            .entry_point(entry_point)
+            .ok_or_else(|| ComputeKernelError::MissingEntryPoint(entry_point.to_string()))?;
+        if reflected.stage != ShaderStages::COMPUTE {
+            return Err(ComputeKernelError::NotCompute(entry_point.to_string()));
+        }
+        let bindings = reflected
+            .bindings
+            .iter()
+            .map(|&(group, binding)| KernelBinding {
+                name: reflection
+                    .bindings
+                    .iter()
+                    .find(|reflected| reflected.group == group && reflected.binding == binding)
+                    .and_then(|reflected| reflected.name.clone()),
+                group,
+                binding,
+            })
+            .collect::<Vec<_>>();
+
+        let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some(entry_point),
+            layout: None,
+            module,
+            entry_point,
+        });
+        Ok(ComputeKernel {
+            pipeline,
+            workgroup_size: reflected.workgroup_size,
+            group_count: bindings
+                .iter()
+                .map(|binding| binding.group + 1)
+                .max()
+                .unwrap_or(0),
+            bindings,
+            max_workgroups: device.limits().max_compute_workgroups_per_dimension,
+        })
+    }
+
+    /// Compute pipeline of the kernel.
+    pub fn pipeline(&self) -> &ComputePipeline {
+        &self.pipeline
+    }
+
+    /// Workgroup size of the entry point.
+    pub fn workgroup_size(&self) -> [u32; 3] {
+        self.workgroup_size
+    }
+
+    /// Returns the number of workgroups to dispatch along each dimension for `count`
+    /// invocations.
+    pub fn grid(&self, count: u32) -> Result<[u32; 3], ComputeKernelError> {
+        let [x, y, z] = self.workgroup_size;
+        let invocations = (x * y * z).max(1) as u64;
+        let workgroups = (count as u64 + invocations - 1) / invocations;
+        if workgroups == 0 {
+            return Ok([0; 3]);
+        }
+        let max = self.max_workgroups.max(1) as u64;
+        let x = workgroups.min(max);
+        let rows = (workgroups + x - 1) / x;
+        let y = rows.min(max);
+        let z = (rows + y - 1) / y;
+        if z > max {
+            return Err(ComputeKernelError::TooManyInvocations(count));
+        }
+        Ok([x as u32, y as u32, z as u32])
+    }
+
+    /// Create the bind groups of the kernel, binding each resource used by the entry point
+    /// to the resource of the same name in `resources`.
+    ///
+    /// Unnamed resources can't be bound, and bind groups without resources are empty.
+    pub fn bind_groups(
+        &self,
+        device: &Device,
+        resources: &[(&str, BindingResource<'_>)],
+    ) -> Result<Vec<BindGroup>, ComputeKernelError> {
+        if let Some(&(name, _)) = resources.iter().find(|&&(name, _)| {
+            !self
+                .bindings
+                .iter()
+                .any(|binding| binding.name.as_deref() == Some(name))
+        }) {
+            return Err(ComputeKernelError::UnknownBinding(name.to_string()));
+        }
+
+        (0..self.group_count)
+            .map(|group| {
+                let entries = self
+                    .bindings
+                    .iter()
+                    .filter(|binding| binding.group == group)
+                    .map(|binding| {
+                        let resource = resources
+                            .iter()
+                            .find(|&&(name, _)| binding.name.as_deref() == Some(name))
+                            .ok_or(ComputeKernelError::MissingBinding {
+                                group,
+                                binding: binding.binding,
+                            })?;
+                        Ok(BindGroupEntry {
+                            binding: binding.binding,
+                            resource: resource.1.clone(),
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(device.create_bind_group(&BindGroupDescriptor {
+                    label: None,
+                    layout: &self.pipeline.get_bind_group_layout(group),
+                    entries: &entries,
+                }))
+            })
+            .collect()
+    }
+
+    /// Record a compute pass dispatching the kernel over `count` invocations, with the
+    /// `bind_groups` returned by [`ComputeKernel::bind_groups`].
+    pub fn dispatch(
+        &self,
+        encoder: &mut CommandEncoder,
+        bind_groups: &[BindGroup],
+        count: u32,
+    ) -> Result<(), ComputeKernelError> {
+        let [x, y, z] = self.grid(count)?;
+        let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor { label: None });
+        pass.set_pipeline(&self.pipeline);
+        for (index, bind_group) in bind_groups.iter().enumerate() {
+            pass.set_bind_group(index as u32, bind_group, &[]);
+        }
+        pass.dispatch_workgroups(x, y, z);
+        Ok(())
+    }
+
+    /// Dispatch the kernel over `count` invocations with the named `resources`, and submit
+    /// it to the `queue`.
+    pub fn run(
+        &self,
+        device: &Device,
+        queue: &Queue,
+        resources: &[(&str, BindingResource<'_>)],
+        count: u32,
+    ) -> Result<(), ComputeKernelError> {
+        let bind_groups = self.bind_groups(device, resources)?;
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor { label: None });
+        self.dispatch(&mut encoder, &bind_groups, count)?;
+        queue.submit(Some(encoder.finish()));
+        Ok(())
+    }
+
+    /// [`run`](ComputeKernel::run) the kernel, then asynchronously read the `output` back
+    /// like [`DownloadBuffer::read_buffer`].
+    ///
+    /// The buffer of the `output` needs the `COPY_SRC` usage.
+    pub fn run_and_read(
+        &self,
+        device: &Device,
+        queue: &Queue,
+        resources: &[(&str, BindingResource<'_>)],
+        count: u32,
+        output: &BufferSlice,
+        callback: impl FnOnce(Result<DownloadBuffer, BufferAsyncError>) + Send + 'static,
+    ) -> Result<(), ComputeKernelError> {
+        self.run(device, queue, resources, count)?;
+        DownloadBuffer::read_buffer(device, queue, output, callback);
+        Ok(())
+    }
+}
+
+impl fmt::Debug for ComputeKernel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ComputeKernel")
+            .field("workgroup_size", &self.workgroup_size)
+            .field("bindings", &self.bindings)
+            .finish_non_exhaustive()
+    }
+}
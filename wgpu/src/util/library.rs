@@ -0,0 +1,490 @@
+use crate::{
+    BufferAddress, ColorTargetState, ComputePipeline, ComputePipelineDescriptor, DepthStencilState,
+    Device, Error, ErrorFilter, FragmentState, MultisampleState, PipelineLayout, PrimitiveState,
+    RenderPipeline, RenderPipelineDescriptor, ShaderModule, ShaderModuleDescriptor, ShaderSource,
+    VertexAttribute, VertexBufferLayout, VertexState, VertexStepMode,
+};
+use std::{
+    borrow::Cow,
+    error, fmt,
+    future::Future,
+    io,
+    num::NonZeroU32,
+    path::{Path, PathBuf},
+    ptr,
+    sync::Arc,
+    task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+    time::SystemTime,
+};
+
+/// Error reported when a shader of a [`ShaderLibrary`], or a pipeline built from it,
+/// can't be created.
+#[derive(Debug)]
+pub enum ShaderLibraryError {
+    /// The source file can't be read.
+    Io {
+        /// Path of the source file.
+        path: PathBuf,
+        /// Error of the read.
+        error: io::Error,
+    },
+    /// The shader module failed to compile.
+    Shader {
+        /// Path of the source file.
+        path: PathBuf,
+        /// Error of the shader module creation.
+        error: Error,
+    },
+    /// A pipeline failed to build from the shader modules.
+    Pipeline {
+        /// Label of the pipeline.
+        label: Option<String>,
+        /// Error of the pipeline creation.
+        error: Error,
+    },
+}
+
+impl fmt::Display for ShaderLibraryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ShaderLibraryError::Io {
+                ref path,
+                ref error,
+            } => write!(f, "Couldn't read {}: {}", path.display(), error),
+            ShaderLibraryError::Shader {
+                ref path,
+                ref error,
+            } => write!(f, "Shader {} failed to compile: {}", path.display(), error),
+            ShaderLibraryError::Pipeline {
+                ref label,
+                ref error,
+            } => write!(f, "Pipeline {:?} failed to build: {}", label, error),
+        }
+    }
+}
+
+impl error::Error for ShaderLibraryError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            ShaderLibraryError::Io { ref error, .. } => Some(error),
+            ShaderLibraryError::Shader { ref error, .. }
+            | ShaderLibraryError::Pipeline { ref error, .. } => Some(error),
+        }
+    }
+}
+
+/// Handle of a shader module of a [`ShaderLibrary`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ShaderHandle(usize);
+
+/// Handle of a render pipeline of a [`ShaderLibrary`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RenderPipelineHandle(usize);
+
+/// Handle of a compute pipeline of a [`ShaderLibrary`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ComputePipelineHandle(usize);
+
+/// Layout of a vertex buffer of a [`LibraryRenderPipelineDescriptor`].
+///
+/// Owned version of [`VertexBufferLayout`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct LibraryVertexBufferLayout {
+    /// The stride, in bytes, between elements of this buffer.
+    pub array_stride: BufferAddress,
+    /// How often this vertex buffer is "stepped" forward.
+    pub step_mode: VertexStepMode,
+    /// The list of attributes which comprise a single vertex.
+    pub attributes: Vec<VertexAttribute>,
+}
+
+/// Vertex stage of a [`LibraryRenderPipelineDescriptor`].
+#[derive(Clone, Debug)]
+pub struct LibraryVertexState {
+    /// Shader module of the stage.
+    pub shader: ShaderHandle,
+    /// Name of the vertex entry point of the module.
+    pub entry_point: String,
+    /// The format of any vertex buffers used with this pipeline.
+    pub buffers: Vec<LibraryVertexBufferLayout>,
+}
+
+/// Fragment stage of a [`LibraryRenderPipelineDescriptor`].
+#[derive(Clone, Debug)]
+pub struct LibraryFragmentState {
+    /// Shader module of the stage.
+    pub shader: ShaderHandle,
+    /// Name of the fragment entry point of the module.
+    pub entry_point: String,
+    /// The color state of the render targets.
+    pub targets: Vec<Option<ColorTargetState>>,
+}
+
+/// Describes a render pipeline built from the shaders of a [`ShaderLibrary`].
+///
+/// Owned version of [`RenderPipelineDescriptor`], kept by the library to rebuild the
+/// pipeline when its shaders change.
+#[derive(Clone, Debug)]
+pub struct LibraryRenderPipelineDescriptor {
+    /// Debug label of the pipeline.
+    pub label: Option<String>,
+    /// The layout of bind groups for this pipeline, or `None` for a layout derived from
+    /// the shaders, which changes on each rebuild.
+    pub layout: Option<Arc<PipelineLayout>>,
+    /// The vertex stage.
+    pub vertex: LibraryVertexState,
+    /// The properties of the pipeline at the primitive assembly and rasterization level.
+    pub primitive: PrimitiveState,
+    /// The effect of draw calls on the depth and stencil aspects of the output target.
+    pub depth_stencil: Option<DepthStencilState>,
+    /// The multi-sampling properties of the pipeline.
+    pub multisample: MultisampleState,
+    /// The fragment stage.
+    pub fragment: Option<LibraryFragmentState>,
+    /// If the pipeline will be used with a multiview render pass, this indicates how many
+    /// array layers the attachments will have.
+    pub multiview: Option<NonZeroU32>,
+}
+
+/// Describes a compute pipeline built from a shader of a [`ShaderLibrary`].
+///
+/// Owned version of [`ComputePipelineDescriptor`], kept by the library to rebuild the
+/// pipeline when its shader changes.
+#[derive(Clone, Debug)]
+pub struct LibraryComputePipelineDescriptor {
+    /// Debug label of the pipeline.
+    pub label: Option<String>,
+    /// The layout of bind groups for this pipeline, or `None` for a layout derived from
+    /// the shader, which changes on each rebuild.
+    pub layout: Option<Arc<PipelineLayout>>,
+    /// Shader module of the pipeline.
+    pub shader: ShaderHandle,
+    /// Name of the compute entry point of the module.
+    pub entry_point: String,
+}
+
+/// WGSL shader loaded from a file.
+#[derive(Debug)]
+struct LibraryShader {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    module: ShaderModule,
+}
+
+#[derive(Debug)]
+struct LibraryRenderPipeline {
+    descriptor: LibraryRenderPipelineDescriptor,
+    pipeline: RenderPipeline,
+}
+
+#[derive(Debug)]
+struct LibraryComputePipeline {
+    descriptor: LibraryComputePipelineDescriptor,
+    pipeline: ComputePipeline,
+}
+
+/// Collection of WGSL shaders loaded from files, and of the pipelines built from them,
+/// which are rebuilt when the files change.
+///
+/// [`ShaderLibrary::poll`] checks the modification times of the files, typically once
+/// per frame. The shader modules of the files that changed are created again, along with
+/// all the pipelines using them, from their stored descriptors. When a shader module or
+/// a pipeline fails to be created, the error is reported and the previous version is
+/// kept, so the handles always resolve to the latest valid object.
+///
+/// Bind groups have to be created with explicit layouts, or recreated from the layouts
+/// of the rebuilt pipelines, since derived layouts change with each rebuild.
+///
+/// The library is only available on native platforms: it reads the files from the file
+/// system, and checks the errors of the rebuilds with error scopes, which only resolve
+/// immediately on the native backends.
+#[cfg_attr(docsrs, doc(cfg(not(target_arch = "wasm32"))))]
+pub struct ShaderLibrary {
+    shaders: Vec<LibraryShader>,
+    render_pipelines: Vec<LibraryRenderPipeline>,
+    compute_pipelines: Vec<LibraryComputePipeline>,
+}
+
+impl ShaderLibrary {
+    /// Create an empty library.
+    pub fn new() -> Self {
+        Self {
+            shaders: Vec::new(),
+            render_pipelines: Vec::new(),
+            compute_pipelines: Vec::new(),
+        }
+    }
+
+    /// Load the WGSL shader of the file at `path`.
+    pub fn add_shader(
+        &mut self,
+        device: &Device,
+        path: impl AsRef<Path>,
+    ) -> Result<ShaderHandle, ShaderLibraryError> {
+        let path = path.as_ref().to_path_buf();
+        let modified = modified_time(&path).ok();
+        let module = load_shader(device, &path)?;
+        self.shaders.push(LibraryShader {
+            path,
+            modified,
+            module,
+        });
+        Ok(ShaderHandle(self.shaders.len() - 1))
+    }
+
+    /// Build a render pipeline from the shaders of the library.
+    pub fn add_render_pipeline(
+        &mut self,
+        device: &Device,
+        descriptor: LibraryRenderPipelineDescriptor,
+    ) -> Result<RenderPipelineHandle, ShaderLibraryError> {
+        let pipeline = build_render_pipeline(device, &self.shaders, &descriptor)?;
+        self.render_pipelines.push(LibraryRenderPipeline {
+            descriptor,
+            pipeline,
+        });
+        Ok(RenderPipelineHandle(self.render_pipelines.len() - 1))
+    }
+
+    /// Build a compute pipeline from a shader of the library.
+    pub fn add_compute_pipeline(
+        &mut self,
+        device: &Device,
+        descriptor: LibraryComputePipelineDescriptor,
+    ) -> Result<ComputePipelineHandle, ShaderLibraryError> {
+        let pipeline = build_compute_pipeline(device, &self.shaders, &descriptor)?;
+        self.compute_pipelines.push(LibraryComputePipeline {
+            descriptor,
+            pipeline,
+        });
+        Ok(ComputePipelineHandle(self.compute_pipelines.len() - 1))
+    }
+
+    /// Latest valid shader module of the `shader`.
+    pub fn shader(&self, shader: ShaderHandle) -> &ShaderModule {
+        &self.shaders[shader.0].module
+    }
+
+    /// Path of the source file of the `shader`.
+    pub fn shader_path(&self, shader: ShaderHandle) -> &Path {
+        &self.shaders[shader.0].path
+    }
+
+    /// Latest valid version of the render `pipeline`.
+    pub fn render_pipeline(&self, pipeline: RenderPipelineHandle) -> &RenderPipeline {
+        &self.render_pipelines[pipeline.0].pipeline
+    }
+
+    /// Latest valid version of the compute `pipeline`.
+    pub fn compute_pipeline(&self, pipeline: ComputePipelineHandle) -> &ComputePipeline {
+        &self.compute_pipelines[pipeline.0].pipeline
+    }
+
+    /// Reload the shaders whose files were modified since they were last loaded, and
+    /// rebuild the pipelines using them.
+    ///
+    /// Returns the errors of the shaders and pipelines that kept their previous version.
+    /// A file that can't be accessed is reported once, until it is accessible again.
+    pub fn poll(&mut self, device: &Device) -> Vec<ShaderLibraryError> {
+        let mut errors = Vec::new();
+        for index in 0..self.shaders.len() {
+            let shader = &mut self.shaders[index];
+            let modified = match modified_time(&shader.path) {
+                Ok(modified) => Some(modified),
+                Err(error) => {
+                    if shader.modified.is_some() {
+                        errors.push(ShaderLibraryError::Io {
+                            path: shader.path.clone(),
+                            error,
+                        });
+                    }
+                    shader.modified = None;
+                    continue;
+                }
+            };
+            if modified != shader.modified {
+                shader.modified = modified;
+                self.reload(device, ShaderHandle(index), &mut errors);
+            }
+        }
+        errors
+    }
+
+    /// Reload the `shader` from its file, and rebuild the pipelines using it, whether the
+    /// file was modified or not.
+    ///
+    /// Returns the errors of the shader and pipelines that kept their previous version.
+    pub fn reload_shader(
+        &mut self,
+        device: &Device,
+        shader: ShaderHandle,
+    ) -> Vec<ShaderLibraryError> {
+        let mut errors = Vec::new();
+        self.shaders[shader.0].modified = modified_time(&self.shaders[shader.0].path).ok();
+        self.reload(device, shader, &mut errors);
+        errors
+    }
+
+    fn reload(
+        &mut self,
+        device: &Device,
+        shader: ShaderHandle,
+        errors: &mut Vec<ShaderLibraryError>,
+    ) {
+        match load_shader(device, &self.shaders[shader.0].path) {
+            Ok(module) => self.shaders[shader.0].module = module,
+            Err(error) => {
+                errors.push(error);
+                return;
+            }
+        }
+        log::info!("Reloaded shader {}", self.shaders[shader.0].path.display());
+
+        for index in 0..self.render_pipelines.len() {
+            let descriptor = &self.render_pipelines[index].descriptor;
+            let uses_shader = descriptor.vertex.shader == shader
+                || descriptor.fragment.as_ref().map(|fragment| fragment.shader) == Some(shader);
+            if uses_shader {
+                match build_render_pipeline(device, &self.shaders, descriptor) {
+                    Ok(pipeline) => self.render_pipelines[index].pipeline = pipeline,
+                    Err(error) => errors.push(error),
+                }
+            }
+        }
+        for index in 0..self.compute_pipelines.len() {
+            let descriptor = &self.compute_pipelines[index].descriptor;
+            if descriptor.shader == shader {
+                match build_compute_pipeline(device, &self.shaders, descriptor) {
+                    Ok(pipeline) => self.compute_pipelines[index].pipeline = pipeline,
+                    Err(error) => errors.push(error),
+                }
+            }
+        }
+    }
+}
+
+impl Default for ShaderLibrary {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for ShaderLibrary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShaderLibrary")
+            .field("shaders", &self.shaders.len())
+            .field("render_pipelines", &self.render_pipelines.len())
+            .field("compute_pipelines", &self.compute_pipelines.len())
+            .finish_non_exhaustive()
+    }
+}
+
+fn modified_time(path: &Path) -> io::Result<SystemTime> {
+    path.metadata()?.modified()
+}
+
+fn load_shader(device: &Device, path: &Path) -> Result<ShaderModule, ShaderLibraryError> {
+    let source = std::fs::read_to_string(path).map_err(|error| ShaderLibraryError::Io {
+        path: path.to_path_buf(),
+        error,
+    })?;
+    device.push_error_scope(ErrorFilter::Validation);
+    let module = device.create_shader_module(ShaderModuleDescriptor {
+        label: path.to_str(),
+        source: ShaderSource::Wgsl(Cow::Owned(source)),
+    });
+    match pop_error_scope(device) {
+        Some(error) => Err(ShaderLibraryError::Shader {
+            path: path.to_path_buf(),
+            error,
+        }),
+        None => Ok(module),
+    }
+}
+
+fn build_render_pipeline(
+    device: &Device,
+    shaders: &[LibraryShader],
+    descriptor: &LibraryRenderPipelineDescriptor,
+) -> Result<RenderPipeline, ShaderLibraryError> {
+    let buffers = descriptor
+        .vertex
+        .buffers
+        .iter()
+        .map(|buffer| VertexBufferLayout {
+            array_stride: buffer.array_stride,
+            step_mode: buffer.step_mode,
+            attributes: &buffer.attributes,
+        })
+        .collect::<Vec<_>>();
+    device.push_error_scope(ErrorFilter::Validation);
+    let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+        label: descriptor.label.as_deref(),
+        layout: descriptor.layout.as_deref(),
+        vertex: VertexState {
+            module: &shaders[descriptor.vertex.shader.0].module,
+            entry_point: &descriptor.vertex.entry_point,
+            buffers: &buffers,
+        },
+        primitive: descriptor.primitive,
+        depth_stencil: descriptor.depth_stencil.clone(),
+        multisample: descriptor.multisample,
+        fragment: descriptor.fragment.as_ref().map(|fragment| FragmentState {
+            module: &shaders[fragment.shader.0].module,
+            entry_point: &fragment.entry_point,
+            targets: &fragment.targets,
+        }),
+        multiview: descriptor.multiview,
+    });
+    match pop_error_scope(device) {
+        Some(error) => Err(ShaderLibraryError::Pipeline {
+            label: descriptor.label.clone(),
+            error,
+        }),
+        None => Ok(pipeline),
+    }
+}
+
+fn build_compute_pipeline(
+    device: &Device,
+    shaders: &[LibraryShader],
+    descriptor: &LibraryComputePipelineDescriptor,
+) -> Result<ComputePipeline, ShaderLibraryError> {
+    device.push_error_scope(ErrorFilter::Validation);
+    let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+        label: descriptor.label.as_deref(),
+        layout: descriptor.layout.as_deref(),
+        module: &shaders[descriptor.shader.0].module,
+        entry_point: &descriptor.entry_point,
+    });
+    match pop_error_scope(device) {
+        Some(error) => Err(ShaderLibraryError::Pipeline {
+            label: descriptor.label.clone(),
+            error,
+        }),
+        None => Ok(pipeline),
+    }
+}
+
+/// Pop the error scope of the `device`, which resolves as soon as it's popped on the
+/// native backends.
+fn pop_error_scope(device: &Device) -> Option<Error> {
+    fn noop_raw_waker() -> RawWaker {
+        fn clone(_: *const ()) -> RawWaker {
+            noop_raw_waker()
+        }
+        fn noop(_: *const ()) {}
+        const VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+        RawWaker::new(ptr::null(), &VTABLE)
+    }
+
+    let waker = unsafe { Waker::from_raw(noop_raw_waker()) };
+    let mut context = Context::from_waker(&waker);
+    match Box::pin(device.pop_error_scope())
+        .as_mut()
+        .poll(&mut context)
+    {
+        Poll::Ready(error) => error,
+        Poll::Pending => unreachable!("Error scopes of the native backends resolve immediately"),
+    }
+}
@@ -0,0 +1,332 @@
+use crate::{
+    util::DeviceExt, BindGroupDescriptor, BindGroupEntry, BindingResource, Buffer, CommandEncoder,
+    ComputePassDescriptor, ComputePipeline, ComputePipelineDescriptor, Device, FilterMode,
+    FragmentState, LoadOp, Operations, RenderPassColorAttachment, RenderPassDescriptor,
+    RenderPipeline, RenderPipelineDescriptor, Sampler, SamplerDescriptor, ShaderModule,
+    ShaderModuleDescriptor, ShaderSource, Texture, TextureDescriptor, TextureDimension,
+    TextureFormat, TextureSampleType, TextureUsages, TextureViewDescriptor, TextureViewDimension,
+    VertexState,
+};
+use std::{borrow::Cow, collections::HashMap, error, fmt, num::NonZeroU32};
+
+const SHADER: &str = include_str!("mipmap.wgsl");
+const COMPUTE_SHADER: &str = include_str!("mipmap_compute.wgsl");
+const WORKGROUP_SIZE: u32 = 8;
+
+/// Error returned when mipmaps can't be generated for a texture.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MipmapError {
+    /// The format can't be filtered, or isn't a color format.
+    UnfilterableFormat(TextureFormat),
+    /// Only 2D textures, including arrays and cubes, are supported.
+    UnsupportedDimension(TextureDimension),
+    /// The texture lacks the usages needed by both the compute and the render paths.
+    MissingUsages(TextureUsages),
+}
+
+impl fmt::Display for MipmapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            MipmapError::UnfilterableFormat(format) => {
+                write!(f, "Format {:?} is not a filterable color format", format)
+            }
+            MipmapError::UnsupportedDimension(dimension) => {
+                write!(f, "Texture dimension {:?} is not supported", dimension)
+            }
+            MipmapError::MissingUsages(usages) => {
+                write!(f, "Texture is missing usages {:?}", usages)
+            }
+        }
+    }
+}
+
+impl error::Error for MipmapError {}
+
+/// Generates the mip chain of textures from their first mip level.
+///
+/// Supports 2D, 2D-array and cube textures of filterable color formats. Each layer
+/// is processed separately, and each mip level is the 2x2 box-filtered previous one.
+///
+/// sRGB formats are filtered in linear space. Other formats can be filtered in linear
+/// space as well, by providing the gamma their data is encoded with, see
+/// [`MipmapGenerator::generate_with_gamma`].
+///
+/// The texture needs the `TEXTURE_BINDING` usage. When it also has the `STORAGE_BINDING`
+/// usage and the format is one of the WebGPU storage formats, mips are generated with
+/// a compute shader. Otherwise, a render pass is used, which needs the `RENDER_ATTACHMENT`
+/// usage.
+///
+/// Pipelines and the sampler are created once and reused across calls.
+pub struct MipmapGenerator {
+    sampler: Sampler,
+    render_shader: Option<ShaderModule>,
+    render_pipelines: HashMap<TextureFormat, RenderPipeline>,
+    compute_pipelines: HashMap<TextureFormat, ComputePipeline>,
+}
+
+impl MipmapGenerator {
+    /// Create a new mipmap generator.
+    pub fn new(device: &Device) -> Self {
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("(wgpu internal) MipmapGenerator sampler"),
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            mipmap_filter: FilterMode::Nearest,
+            ..Default::default()
+        });
+        MipmapGenerator {
+            sampler,
+            render_shader: None,
+            render_pipelines: HashMap::new(),
+            compute_pipelines: HashMap::new(),
+        }
+    }
+
+    /// Record the generation of all mip levels after the first one into the `encoder`.
+    ///
+    /// `desc` has to be the descriptor the texture was created with.
+    pub fn generate(
+        &mut self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        texture: &Texture,
+        desc: &TextureDescriptor,
+    ) -> Result<(), MipmapError> {
+        self.generate_with_gamma(device, encoder, texture, desc, 1.0)
+    }
+
+    /// Record the generation of all mip levels after the first one into the `encoder`,
+    /// treating the data as encoded with the `gamma`.
+    ///
+    /// Each sample is raised to the power of `gamma` before filtering, and the result is
+    /// raised to the power of `1 / gamma`. A `gamma` of 1 means the data is linear.
+    /// It's ignored for sRGB formats, which are always filtered in linear space.
+    pub fn generate_with_gamma(
+        &mut self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        texture: &Texture,
+        desc: &TextureDescriptor,
+        gamma: f32,
+    ) -> Result<(), MipmapError> {
+        if desc.dimension != TextureDimension::D2 {
+            return Err(MipmapError::UnsupportedDimension(desc.dimension));
+        }
+        let info = desc.format.describe();
+        if info.sample_type != (TextureSampleType::Float { filterable: true }) {
+            return Err(MipmapError::UnfilterableFormat(desc.format));
+        }
+        if !desc.usage.contains(TextureUsages::TEXTURE_BINDING) {
+            return Err(MipmapError::MissingUsages(TextureUsages::TEXTURE_BINDING));
+        }
+        let storage_format = match storage_format_name(desc.format) {
+            Some(name) if desc.usage.contains(TextureUsages::STORAGE_BINDING) => Some(name),
+            _ => None,
+        };
+        if storage_format.is_none() && !desc.usage.contains(TextureUsages::RENDER_ATTACHMENT) {
+            return Err(MipmapError::MissingUsages(TextureUsages::RENDER_ATTACHMENT));
+        }
+        if desc.mip_level_count < 2 {
+            return Ok(());
+        }
+
+        let gamma = if info.srgb { 1.0 } else { gamma };
+        let params = device.create_buffer_init(&super::BufferInitDescriptor {
+            label: Some("(wgpu internal) MipmapGenerator parameters"),
+            contents: &[gamma, 0.0, 0.0, 0.0]
+                .iter()
+                .flat_map(|value: &f32| value.to_le_bytes())
+                .collect::<Vec<u8>>(),
+            usage: crate::BufferUsages::UNIFORM,
+        });
+
+        match storage_format {
+            Some(name) => self.generate_compute(device, encoder, texture, desc, &params, name),
+            None => self.generate_render(device, encoder, texture, desc, &params),
+        }
+        Ok(())
+    }
+
+    fn generate_render(
+        &mut self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        texture: &Texture,
+        desc: &TextureDescriptor,
+        params: &Buffer,
+    ) {
+        let shader = self.render_shader.get_or_insert_with(|| {
+            device.create_shader_module(ShaderModuleDescriptor {
+                label: Some("(wgpu internal) MipmapGenerator shader"),
+                source: ShaderSource::Wgsl(Cow::Borrowed(SHADER)),
+            })
+        });
+        let pipeline = self.render_pipelines.entry(desc.format).or_insert_with(|| {
+            device.create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some("(wgpu internal) MipmapGenerator render pipeline"),
+                layout: None,
+                vertex: VertexState {
+                    module: shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                },
+                fragment: Some(FragmentState {
+                    module: shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(desc.format.into())],
+                }),
+                primitive: Default::default(),
+                depth_stencil: None,
+                multisample: Default::default(),
+                multiview: None,
+            })
+        });
+        let bind_group_layout = pipeline.get_bind_group_layout(0);
+
+        for layer in 0..desc.array_layer_count() {
+            let mut src_view = mip_view(texture, layer, 0);
+            for mip in 1..desc.mip_level_count {
+                let dst_view = mip_view(texture, layer, mip);
+                let bind_group = device.create_bind_group(&BindGroupDescriptor {
+                    label: None,
+                    layout: &bind_group_layout,
+                    entries: &[
+                        BindGroupEntry {
+                            binding: 0,
+                            resource: BindingResource::TextureView(&src_view),
+                        },
+                        BindGroupEntry {
+                            binding: 1,
+                            resource: BindingResource::Sampler(&self.sampler),
+                        },
+                        BindGroupEntry {
+                            binding: 2,
+                            resource: params.as_entire_binding(),
+                        },
+                    ],
+                });
+                let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                    label: Some("(wgpu internal) MipmapGenerator"),
+                    color_attachments: &[Some(RenderPassColorAttachment {
+                        view: &dst_view,
+                        resolve_target: None,
+                        ops: Operations {
+                            load: LoadOp::Load,
+                            store: true,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                });
+                pass.set_pipeline(pipeline);
+                pass.set_bind_group(0, &bind_group, &[]);
+                pass.draw(0..3, 0..1);
+                drop(pass);
+                src_view = dst_view;
+            }
+        }
+    }
+
+    fn generate_compute(
+        &mut self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        texture: &Texture,
+        desc: &TextureDescriptor,
+        params: &Buffer,
+        storage_format: &str,
+    ) {
+        let pipeline = self
+            .compute_pipelines
+            .entry(desc.format)
+            .or_insert_with(|| {
+                let source = format!(
+                    "{}\n{}",
+                    SHADER,
+                    COMPUTE_SHADER.replace("{format}", storage_format)
+                );
+                let shader = device.create_shader_module(ShaderModuleDescriptor {
+                    label: Some("(wgpu internal) MipmapGenerator compute shader"),
+                    source: ShaderSource::Wgsl(Cow::Owned(source)),
+                });
+                device.create_compute_pipeline(&ComputePipelineDescriptor {
+                    label: Some("(wgpu internal) MipmapGenerator compute pipeline"),
+                    layout: None,
+                    module: &shader,
+                    entry_point: "cs_main",
+                })
+            });
+        let bind_group_layout = pipeline.get_bind_group_layout(0);
+
+        for layer in 0..desc.array_layer_count() {
+            for mip in 1..desc.mip_level_count {
+                let src_view = mip_view(texture, layer, mip - 1);
+                let dst_view = mip_view(texture, layer, mip);
+                let bind_group = device.create_bind_group(&BindGroupDescriptor {
+                    label: None,
+                    layout: &bind_group_layout,
+                    entries: &[
+                        BindGroupEntry {
+                            binding: 0,
+                            resource: BindingResource::TextureView(&src_view),
+                        },
+                        BindGroupEntry {
+                            binding: 1,
+                            resource: BindingResource::Sampler(&self.sampler),
+                        },
+                        BindGroupEntry {
+                            binding: 2,
+                            resource: params.as_entire_binding(),
+                        },
+                        BindGroupEntry {
+                            binding: 3,
+                            resource: BindingResource::TextureView(&dst_view),
+                        },
+                    ],
+                });
+                let size = desc.mip_level_size(mip).unwrap();
+                let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                    label: Some("(wgpu internal) MipmapGenerator"),
+                });
+                pass.set_pipeline(pipeline);
+                pass.set_bind_group(0, &bind_group, &[]);
+                pass.dispatch_workgroups(
+                    (size.width + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE,
+                    (size.height + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE,
+                    1,
+                );
+            }
+        }
+    }
+}
+
+impl fmt::Debug for MipmapGenerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MipmapGenerator")
+            .field("render_pipelines", &self.render_pipelines.len())
+            .field("compute_pipelines", &self.compute_pipelines.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Returns the WGSL name of the format, if it can be used for storage textures
+/// without any features, and can be filtered.
+fn storage_format_name(format: TextureFormat) -> Option<&'static str> {
+    Some(match format {
+        TextureFormat::Rgba8Unorm => "rgba8unorm",
+        TextureFormat::Rgba8Snorm => "rgba8snorm",
+        TextureFormat::Rgba16Float => "rgba16float",
+        _ => return None,
+    })
+}
+
+fn mip_view(texture: &Texture, layer: u32, mip: u32) -> crate::TextureView {
+    texture.create_view(&TextureViewDescriptor {
+        label: Some("(wgpu internal) MipmapGenerator mip"),
+        dimension: Some(TextureViewDimension::D2),
+        base_mip_level: mip,
+        mip_level_count: NonZeroU32::new(1),
+        base_array_layer: layer,
+        array_layer_count: NonZeroU32::new(1),
+        ..Default::default()
+    })
+}
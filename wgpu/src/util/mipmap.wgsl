@@ -0,0 +1,60 @@
+// Generates a mip level from the previous one, used by `MipmapGenerator`.
+//
+// The destination texel is the average of the 4 source texels it covers,
+// each optionally decoded with `params.x` as the gamma before averaging.
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+@group(0) @binding(0)
+var src_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var src_sampler: sampler;
+@group(0) @binding(2)
+var<uniform> params: vec4<f32>;
+
+fn downsample(tex_coords: vec2<f32>) -> vec4<f32> {
+    // half of a source texel, so that the samples land on the source texel centers
+    let offset = 0.5 / vec2<f32>(textureDimensions(src_texture));
+    let gamma = params.x;
+    var sum = vec4<f32>(0.0);
+    for (var i = 0; i < 4; i = i + 1) {
+        let dir = vec2<f32>(f32(i & 1), f32(i >> 1u)) * 2.0 - 1.0;
+        var color = textureSampleLevel(src_texture, src_sampler, tex_coords + dir * offset, 0.0);
+        if (gamma != 1.0) {
+            color = vec4<f32>(pow(max(color.rgb, vec3<f32>(0.0)), vec3<f32>(gamma)), color.a);
+        }
+        sum += color;
+    }
+    let color = sum * 0.25;
+    if (gamma != 1.0) {
+        return vec4<f32>(pow(color.rgb, vec3<f32>(1.0 / gamma)), color.a);
+    }
+    return color;
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var result: VertexOutput;
+    let x = i32(vertex_index) / 2;
+    let y = i32(vertex_index) & 1;
+    let tc = vec2<f32>(
+        f32(x) * 2.0,
+        f32(y) * 2.0
+    );
+    result.position = vec4<f32>(
+        tc.x * 2.0 - 1.0,
+        1.0 - tc.y * 2.0,
+        0.0, 1.0
+    );
+    result.tex_coords = tc;
+    return result;
+}
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    return downsample(vertex.tex_coords);
+}
+
@@ -0,0 +1,15 @@
+// Compute entry point of `MipmapGenerator`, appended to "mipmap.wgsl"
+// with `{format}` replaced by the storage format.
+
+@group(0) @binding(3)
+var dst_texture: texture_storage_2d<{format}, write>;
+
+@compute @workgroup_size(8, 8)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = vec2<u32>(textureDimensions(dst_texture));
+    if (id.x >= size.x || id.y >= size.y) {
+        return;
+    }
+    let tex_coords = (vec2<f32>(id.xy) + 0.5) / vec2<f32>(size);
+    textureStore(dst_texture, vec2<i32>(id.xy), downsample(tex_coords));
+}
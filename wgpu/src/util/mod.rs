@@ -1,10 +1,28 @@
 //! Utility structures and functions.
 
+mod atlas;
 mod belt;
+mod blit;
+mod composer;
+pub mod compute;
+mod decompress;
 mod device;
+mod download;
 mod encoder;
+mod graph;
 mod indirect;
 mod init;
+mod kernel;
+#[cfg(not(target_arch = "wasm32"))]
+mod library;
+mod mipmap;
+mod pool;
+mod profiler;
+mod ring;
+#[cfg(feature = "texture-files")]
+mod texture_file;
+mod typed;
+mod vertex;
 
 use std::ops::{Add, Rem, Sub};
 use std::sync::Arc;
@@ -14,11 +32,47 @@ use std::{
     ptr::copy_nonoverlapping,
 };
 
-pub use belt::StagingBelt;
+pub use atlas::{
+    AtlasAllocation, AtlasPacking, TextureAtlas, TextureAtlasDescriptor, TextureAtlasError,
+};
+pub use belt::{ReadbackBelt, StagingBelt};
+pub use blit::{BlitError, BlitOptions, BlitRect, Blitter, Swizzle};
+pub use composer::{ComposedShader, ComposerError, ComposerLocation, ShaderComposer};
+pub use decompress::{decompress, decompressed_format};
 pub use device::{BufferInitDescriptor, DeviceExt};
+pub use download::TextureDownload;
 pub use encoder::RenderEncoder;
+pub use graph::{
+    RenderGraph, RenderGraphAllocator, RenderGraphBuffer, RenderGraphError, RenderGraphPassBuilder,
+    RenderGraphResources, RenderGraphTexture,
+};
 pub use indirect::*;
 pub use init::*;
+pub use kernel::{ComputeKernel, ComputeKernelError};
+#[cfg(not(target_arch = "wasm32"))]
+pub use library::{
+    ComputePipelineHandle, LibraryComputePipelineDescriptor, LibraryFragmentState,
+    LibraryRenderPipelineDescriptor, LibraryVertexBufferLayout, LibraryVertexState,
+    RenderPipelineHandle, ShaderHandle, ShaderLibrary, ShaderLibraryError,
+};
+pub use mipmap::{MipmapError, MipmapGenerator};
+pub use pool::{ResourcePool, ResourcePoolStats};
+pub use profiler::{
+    GpuProfiler, GpuProfilerError, GpuTimerScopeResult, PipelineStatistics,
+    ProfilerCommandRecorder, ProfilerPassRecorder,
+};
+pub use ring::{StorageRing, UniformRing};
+#[cfg(feature = "texture-files")]
+pub use texture_file::{TextureConversion, TextureFile, TextureFileError, TextureTarget};
+#[doc(hidden)]
+pub use typed::{wgsl_max, wgsl_round_up};
+pub use typed::{TypedBuffer, WgslLayout, WgslLayoutError, WgslMemberLayout, WgslTypeLayout};
+pub use vertex::{verify_vertex_buffers, VertexAttributeType, VertexLayout, VertexLayoutError};
+#[doc(hidden)]
+pub use vertex::{vertex_normalized_format, vertex_round_up};
+#[cfg(feature = "macros")]
+#[cfg_attr(docsrs, doc(cfg(feature = "macros")))]
+pub use wgpu_macros::{VertexLayout, WgslLayout};
 
 /// Treat the given byte slice as a SPIR-V module.
 ///
@@ -0,0 +1,287 @@
+use crate::{Buffer, BufferDescriptor, Device, Queue, Texture, TextureDescriptor};
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+/// Counters of a [`ResourcePool`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ResourcePoolStats {
+    /// Resources handed out by reusing a pooled one.
+    pub hits: u64,
+    /// Resources handed out by creating a new one.
+    pub misses: u64,
+    /// Resources destroyed by the pool, for being unused too long or over the budget.
+    pub evictions: u64,
+    /// Textures currently in the pool, in use or not.
+    pub textures: usize,
+    /// Buffers currently in the pool, in use or not.
+    pub buffers: usize,
+    /// Estimated size in bytes of the resources currently in the pool.
+    pub bytes: u64,
+}
+
+enum EntryState {
+    /// Handed out, or released during the current frame.
+    InUse,
+    /// Released, until the submissions made before its release complete.
+    Pending(Arc<AtomicBool>),
+    /// Ready to be handed out, since the given frame.
+    Free(u64),
+}
+
+struct Entry<R, D> {
+    descriptor: D,
+    resource: Arc<R>,
+    size: u64,
+    state: EntryState,
+}
+
+/// Resources of a kind, with the descriptor they were created with.
+struct Entries<R, D> {
+    entries: Vec<Entry<R, D>>,
+}
+
+impl<R, D: PartialEq> Entries<R, D> {
+    fn acquire(
+        &mut self,
+        descriptor: D,
+        stats: &mut ResourcePoolStats,
+        create: impl FnOnce() -> (R, u64),
+    ) -> Arc<R> {
+        let found = self.entries.iter_mut().find(|entry| {
+            entry.descriptor == descriptor
+                && match entry.state {
+                    EntryState::InUse => false,
+                    EntryState::Pending(ref done) => done.load(Ordering::Acquire),
+                    EntryState::Free(_) => true,
+                }
+        });
+        if let Some(entry) = found {
+            stats.hits += 1;
+            entry.state = EntryState::InUse;
+            return Arc::clone(&entry.resource);
+        }
+
+        stats.misses += 1;
+        let (resource, size) = create();
+        let resource = Arc::new(resource);
+        self.entries.push(Entry {
+            descriptor,
+            resource: Arc::clone(&resource),
+            size,
+            state: EntryState::InUse,
+        });
+        resource
+    }
+
+    /// Advance the entries to the given `frame`, marking the resources released since the
+    /// last frame as pending until `submitted` is set.
+    fn end_frame(&mut self, frame: u64, submitted: &Arc<AtomicBool>) {
+        for entry in self.entries.iter_mut() {
+            match entry.state {
+                EntryState::InUse if Arc::strong_count(&entry.resource) == 1 => {
+                    entry.state = EntryState::Pending(Arc::clone(submitted));
+                }
+                _ => {}
+            }
+        }
+        self.promote(frame);
+    }
+
+    /// Mark the pending entries whose submissions completed as free since `frame`.
+    fn promote(&mut self, frame: u64) {
+        for entry in self.entries.iter_mut() {
+            if let EntryState::Pending(ref done) = entry.state {
+                if done.load(Ordering::Acquire) {
+                    entry.state = EntryState::Free(frame);
+                }
+            }
+        }
+    }
+
+    /// Destroy the free entries for which `evict` returns true, given the frame since
+    /// which they are free.
+    fn evict(&mut self, stats: &mut ResourcePoolStats, evict: impl Fn(u64) -> bool) {
+        let count = self.entries.len();
+        self.entries.retain(|entry| match entry.state {
+            EntryState::Free(since) => !evict(since),
+            _ => true,
+        });
+        stats.evictions += (count - self.entries.len()) as u64;
+    }
+
+    /// Frame since which the oldest free entry is free, along with its index.
+    fn oldest_free(&self) -> Option<(u64, usize)> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| match entry.state {
+                EntryState::Free(since) => Some((since, index)),
+                _ => None,
+            })
+            .min()
+    }
+
+    fn bytes(&self) -> u64 {
+        self.entries.iter().map(|entry| entry.size).sum()
+    }
+}
+
+/// Pool of textures and buffers, handed out for descriptors and reused once released.
+///
+/// Pooled resources are handed out as [`Arc`]s, and are released when all the clones are
+/// dropped. Using a pool generally goes as follows, every frame:
+/// - Get resources with [`ResourcePool::texture`] and [`ResourcePool::buffer`], which
+///   reuse a free resource created with an identical descriptor, label aside, or create one.
+/// - Submit the work using them, and drop them.
+/// - Call [`ResourcePool::end_frame`].
+///
+/// Resources released during a frame are only reused once the submissions made before the
+/// end of that frame complete, which requires the device to be polled. Free resources
+/// unused for more than `max_unused_frames` frames are destroyed, and so are the free
+/// resources unused for the longest time while the estimated size of the pool is over
+/// the byte budget.
+///
+/// [`RenderGraph::execute`] can allocate the transient resources of a graph from a pool.
+///
+/// [`RenderGraph::execute`]: crate::util::RenderGraph::execute
+pub struct ResourcePool {
+    textures: Entries<Texture, wgt::TextureDescriptor<()>>,
+    buffers: Entries<Buffer, wgt::BufferDescriptor<()>>,
+    frame: u64,
+    max_unused_frames: u64,
+    byte_budget: Option<u64>,
+    stats: ResourcePoolStats,
+}
+
+impl ResourcePool {
+    /// Create an empty pool, destroying free resources unused for more than
+    /// `max_unused_frames` frames, or over the `byte_budget`.
+    pub fn new(max_unused_frames: u64, byte_budget: Option<u64>) -> Self {
+        Self {
+            textures: Entries {
+                entries: Vec::new(),
+            },
+            buffers: Entries {
+                entries: Vec::new(),
+            },
+            frame: 0,
+            max_unused_frames,
+            byte_budget,
+            stats: ResourcePoolStats::default(),
+        }
+    }
+
+    /// Get a texture matching the descriptor.
+    pub fn texture(&mut self, device: &Device, desc: &TextureDescriptor) -> Arc<Texture> {
+        self.textures
+            .acquire(desc.map_label(|_| ()), &mut self.stats, || {
+                (device.create_texture(desc), texture_size(desc))
+            })
+    }
+
+    /// Get a buffer matching the descriptor.
+    ///
+    /// Buffers mapped at creation are never reused, since their contents are expected to
+    /// be written on the CPU.
+    pub fn buffer(&mut self, device: &Device, desc: &BufferDescriptor) -> Arc<Buffer> {
+        if desc.mapped_at_creation {
+            self.stats.misses += 1;
+            return Arc::new(device.create_buffer(desc));
+        }
+        self.buffers
+            .acquire(desc.map_label(|_| ()), &mut self.stats, || {
+                (device.create_buffer(desc), desc.size)
+            })
+    }
+
+    /// Mark the resources released during the frame, free their memory if needed, and
+    /// start the next frame.
+    ///
+    /// Has to be called after submitting the work of the frame.
+    pub fn end_frame(&mut self, queue: &Queue) {
+        self.frame += 1;
+        let submitted = Arc::new(AtomicBool::new(false));
+        self.textures.end_frame(self.frame, &submitted);
+        self.buffers.end_frame(self.frame, &submitted);
+        if Arc::strong_count(&submitted) > 1 {
+            queue.on_submitted_work_done(move || submitted.store(true, Ordering::Release));
+        }
+
+        let (frame, max_unused_frames) = (self.frame, self.max_unused_frames);
+        let unused = |since| frame - since > max_unused_frames;
+        self.textures.evict(&mut self.stats, unused);
+        self.buffers.evict(&mut self.stats, unused);
+        if let Some(budget) = self.byte_budget {
+            while self.textures.bytes() + self.buffers.bytes() > budget {
+                match (self.textures.oldest_free(), self.buffers.oldest_free()) {
+                    (Some((texture, index)), Some((buffer, _))) if texture <= buffer => {
+                        self.textures.entries.remove(index);
+                    }
+                    (Some((_, index)), None) => {
+                        self.textures.entries.remove(index);
+                    }
+                    (_, Some((_, index))) => {
+                        self.buffers.entries.remove(index);
+                    }
+                    (None, None) => break,
+                }
+                self.stats.evictions += 1;
+            }
+        }
+
+        self.stats.textures = self.textures.entries.len();
+        self.stats.buffers = self.buffers.entries.len();
+        self.stats.bytes = self.textures.bytes() + self.buffers.bytes();
+    }
+
+    /// Counters of the pool, with the numbers of resources as of the last call to
+    /// [`ResourcePool::end_frame`].
+    pub fn stats(&self) -> ResourcePoolStats {
+        self.stats
+    }
+
+    /// Destroy all the free resources.
+    pub fn clear(&mut self) {
+        self.textures.promote(self.frame);
+        self.buffers.promote(self.frame);
+        self.textures.evict(&mut self.stats, |_| true);
+        self.buffers.evict(&mut self.stats, |_| true);
+        self.stats.textures = self.textures.entries.len();
+        self.stats.buffers = self.buffers.entries.len();
+        self.stats.bytes = self.textures.bytes() + self.buffers.bytes();
+    }
+}
+
+impl fmt::Debug for ResourcePool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResourcePool")
+            .field("frame", &self.frame)
+            .field("max_unused_frames", &self.max_unused_frames)
+            .field("byte_budget", &self.byte_budget)
+            .field("stats", &self.stats)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Estimated size in bytes of a texture.
+fn texture_size(desc: &TextureDescriptor) -> u64 {
+    let info = desc.format.describe();
+    let (block_width, block_height) = info.block_dimensions;
+    (0..desc.mip_level_count)
+        .map(|level| {
+            let size = desc
+                .mip_level_size(level)
+                .unwrap()
+                .physical_size(desc.format);
+            let blocks = (size.width / block_width as u32) as u64
+                * (size.height / block_height as u32) as u64;
+            blocks * size.depth_or_array_layers as u64 * info.block_size as u64
+        })
+        .sum::<u64>()
+        * desc.sample_count as u64
+}
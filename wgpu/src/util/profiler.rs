@@ -0,0 +1,710 @@
+use crate::{
+    Buffer, BufferDescriptor, BufferUsages, CommandEncoder, ComputePass, Device, Features, MapMode,
+    PipelineStatisticsTypes, QuerySet, QuerySetDescriptor, QueryType, Queue, RenderPass,
+};
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::{error, fmt};
+
+/// Count of queries of each pool, which is recycled once its results are read.
+const QUERIES_PER_POOL: u32 = 256;
+
+/// Count of values written by each pipeline statistics query, one per statistic.
+const PIPELINE_STATISTICS_VALUES: u32 = 5;
+
+/// Command recorders the [`GpuProfiler`] can write timestamps into.
+pub trait ProfilerCommandRecorder {
+    /// Returns true for render and compute passes, which can only write timestamps with
+    /// [`Features::WRITE_TIMESTAMP_INSIDE_PASSES`].
+    fn is_pass(&self) -> bool;
+
+    /// Issue a timestamp command, written to the specified query set, at the specified index.
+    fn write_timestamp(&mut self, query_set: &QuerySet, query_index: u32);
+}
+
+/// Passes the [`GpuProfiler`] can collect pipeline statistics of.
+pub trait ProfilerPassRecorder: ProfilerCommandRecorder {
+    /// Start a pipeline statistics query, written to the specified query set, at the
+    /// specified index.
+    fn begin_pipeline_statistics_query(&mut self, query_set: &QuerySet, query_index: u32);
+
+    /// Stop the pipeline statistics query started last.
+    fn end_pipeline_statistics_query(&mut self);
+}
+
+impl ProfilerCommandRecorder for CommandEncoder {
+    fn is_pass(&self) -> bool {
+        false
+    }
+
+    fn write_timestamp(&mut self, query_set: &QuerySet, query_index: u32) {
+        CommandEncoder::write_timestamp(self, query_set, query_index)
+    }
+}
+
+impl<'a> ProfilerCommandRecorder for RenderPass<'a> {
+    fn is_pass(&self) -> bool {
+        true
+    }
+
+    fn write_timestamp(&mut self, query_set: &QuerySet, query_index: u32) {
+        RenderPass::write_timestamp(self, query_set, query_index)
+    }
+}
+
+impl<'a> ProfilerCommandRecorder for ComputePass<'a> {
+    fn is_pass(&self) -> bool {
+        true
+    }
+
+    fn write_timestamp(&mut self, query_set: &QuerySet, query_index: u32) {
+        ComputePass::write_timestamp(self, query_set, query_index)
+    }
+}
+
+impl<'a> ProfilerPassRecorder for RenderPass<'a> {
+    fn begin_pipeline_statistics_query(&mut self, query_set: &QuerySet, query_index: u32) {
+        RenderPass::begin_pipeline_statistics_query(self, query_set, query_index)
+    }
+
+    fn end_pipeline_statistics_query(&mut self) {
+        RenderPass::end_pipeline_statistics_query(self)
+    }
+}
+
+impl<'a> ProfilerPassRecorder for ComputePass<'a> {
+    fn begin_pipeline_statistics_query(&mut self, query_set: &QuerySet, query_index: u32) {
+        ComputePass::begin_pipeline_statistics_query(self, query_set, query_index)
+    }
+
+    fn end_pipeline_statistics_query(&mut self) {
+        ComputePass::end_pipeline_statistics_query(self)
+    }
+}
+
+/// Counters of a pipeline statistics scope, see [`PipelineStatisticsTypes`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PipelineStatistics {
+    /// Invocations of the vertex shader.
+    pub vertex_shader_invocations: u64,
+    /// Invocations of the clipper, which is the count of primitives out of the vertex shader.
+    pub clipper_invocations: u64,
+    /// Primitives out of the clipper, which are rasterized.
+    pub clipper_primitives_out: u64,
+    /// Invocations of the fragment shader.
+    pub fragment_shader_invocations: u64,
+    /// Invocations of the compute shader.
+    pub compute_shader_invocations: u64,
+}
+
+/// Results of a scope of a frame, from [`GpuProfiler::process_finished_frame`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct GpuTimerScopeResult {
+    /// Label the scope was begun with.
+    pub label: String,
+    /// Start and end of the scope, in nanoseconds, unless it isn't timed.
+    ///
+    /// Absolute values have no meaning, but they can be compared between scopes
+    /// of the same device.
+    pub time: Option<Range<f64>>,
+    /// Counters of the scope, if it was begun with
+    /// [`GpuProfiler::begin_pipeline_statistics_scope`].
+    pub pipeline_statistics: Option<PipelineStatistics>,
+    /// Scopes begun within this one.
+    pub nested_scopes: Vec<GpuTimerScopeResult>,
+}
+
+/// Error returned by [`GpuProfiler::end_frame`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GpuProfilerError {
+    /// Some scopes of the frame weren't ended.
+    UnclosedScopes(usize),
+    /// Some timestamps of the frame weren't resolved with [`GpuProfiler::resolve_queries`].
+    UnresolvedQueries,
+    /// The results of too many frames weren't processed yet, so this frame was discarded.
+    TooManyPendingFrames,
+}
+
+impl fmt::Display for GpuProfilerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            GpuProfilerError::UnclosedScopes(count) => {
+                write!(f, "{} profiler scopes were not ended", count)
+            }
+            GpuProfilerError::UnresolvedQueries => {
+                write!(f, "Profiler queries were written after the last resolve")
+            }
+            GpuProfilerError::TooManyPendingFrames => {
+                write!(
+                    f,
+                    "Too many profiler frames are pending, the frame was discarded"
+                )
+            }
+        }
+    }
+}
+
+impl error::Error for GpuProfilerError {}
+
+struct QueryPool {
+    /// Whether the queries are pipeline statistics rather than timestamps.
+    statistics: bool,
+    query_set: QuerySet,
+    read_buffer: Buffer,
+    /// Count of queries written.
+    used: u32,
+    /// Count of queries resolved into the read buffer.
+    resolved: u32,
+}
+
+impl QueryPool {
+    /// Count of 64-bit values written by each query.
+    fn values_per_query(statistics: bool) -> u32 {
+        if statistics {
+            PIPELINE_STATISTICS_VALUES
+        } else {
+            1
+        }
+    }
+
+    /// Size of the results of the queries written, in bytes.
+    fn used_size(&self) -> u64 {
+        (self.used * Self::values_per_query(self.statistics) * wgt::QUERY_SIZE) as u64
+    }
+}
+
+/// Location of consecutive queries, in the pools of the frame.
+#[derive(Clone, Copy)]
+struct Queries {
+    pool: usize,
+    start: u32,
+}
+
+/// Where the timestamps of a scope are written.
+#[derive(Clone, Copy)]
+enum Timestamps {
+    /// Not timed, without [`Features::TIMESTAMP_QUERY`].
+    None,
+    /// At the beginning and end of the scope.
+    Scope(Queries),
+    /// At the boundaries of the enclosing pass, for scopes within passes without
+    /// [`Features::WRITE_TIMESTAMP_INSIDE_PASSES`].
+    PassBoundaries(Queries),
+}
+
+impl Timestamps {
+    fn queries(self) -> Option<Queries> {
+        match self {
+            Timestamps::None => None,
+            Timestamps::Scope(queries) | Timestamps::PassBoundaries(queries) => Some(queries),
+        }
+    }
+}
+
+struct OpenScope {
+    label: String,
+    timestamps: Timestamps,
+    /// Whether the scope was begun with [`GpuProfiler::begin_pass_scope`].
+    pass: bool,
+    /// Whether the scope was begun with [`GpuProfiler::begin_pipeline_statistics_scope`].
+    pipeline_statistics: bool,
+    /// Pipeline statistics query of the scope, unless the feature is missing.
+    statistics_query: Option<Queries>,
+    nested_scopes: Vec<ClosedScope>,
+}
+
+struct ClosedScope {
+    label: String,
+    timestamps: Option<Queries>,
+    statistics_query: Option<Queries>,
+    nested_scopes: Vec<ClosedScope>,
+}
+
+struct PendingFrame {
+    pools: Vec<QueryPool>,
+    scopes: Vec<ClosedScope>,
+    /// Count of pools whose read buffer is mapped, or failed to.
+    mapped: Arc<AtomicUsize>,
+    /// Whether mapping the read buffer failed, for each pool.
+    map_failed: Vec<Arc<AtomicBool>>,
+}
+
+/// Profiler measuring the time the GPU spends in nested named scopes, with timestamp queries,
+/// and the work done in scopes of passes, with pipeline statistics queries.
+///
+/// Using a profiler generally goes as follows, every frame:
+/// - Wrap commands in scopes with [`GpuProfiler::begin_scope`] and [`GpuProfiler::end_scope`],
+///   on command encoders and passes, and wrap passes in scopes begun with
+///   [`GpuProfiler::begin_pass_scope`].
+/// - Call [`GpuProfiler::resolve_queries`] on the last command encoder of the frame.
+/// - Submit the command encoders, and call [`GpuProfiler::end_frame`].
+/// - Call [`GpuProfiler::process_finished_frame`], which returns the results of the oldest
+///   pending frame once the device has been polled and the results have been read back,
+///   usually a few frames later.
+///
+/// Timestamps need [`Features::TIMESTAMP_QUERY`], without which no scope is timed.
+/// Scopes within passes also need [`Features::WRITE_TIMESTAMP_INSIDE_PASSES`]; without it
+/// they are given the timestamps of the innermost enclosing pass scope, written on the
+/// command encoder around the pass.
+///
+/// Pipeline statistics need [`Features::PIPELINE_STATISTICS_QUERY`], without which scopes
+/// begun with [`GpuProfiler::begin_pipeline_statistics_scope`] have none.
+pub struct GpuProfiler {
+    timestamps: bool,
+    inside_passes: bool,
+    pipeline_statistics: bool,
+    timestamp_period: f32,
+    max_pending_frames: usize,
+    /// Pools of the current frame.
+    pools: Vec<QueryPool>,
+    /// Pools whose results were read, ready to be reused.
+    free_pools: Vec<QueryPool>,
+    open_scopes: Vec<OpenScope>,
+    /// Ended scopes of the current frame, which aren't nested in another scope.
+    scopes: Vec<ClosedScope>,
+    pending_frames: VecDeque<PendingFrame>,
+}
+
+impl GpuProfiler {
+    /// Create a new profiler for the device the queue belongs to.
+    ///
+    /// Once results of `max_pending_frames` frames are waiting to be processed,
+    /// newer frames are discarded.
+    pub fn new(device: &Device, queue: &Queue, max_pending_frames: usize) -> Self {
+        let features = device.features();
+        GpuProfiler {
+            timestamps: features.contains(Features::TIMESTAMP_QUERY),
+            inside_passes: features.contains(Features::WRITE_TIMESTAMP_INSIDE_PASSES),
+            pipeline_statistics: features.contains(Features::PIPELINE_STATISTICS_QUERY),
+            timestamp_period: queue.get_timestamp_period(),
+            max_pending_frames,
+            pools: Vec::new(),
+            free_pools: Vec::new(),
+            open_scopes: Vec::new(),
+            scopes: Vec::new(),
+            pending_frames: VecDeque::new(),
+        }
+    }
+
+    /// Begin a scope named `label` on a command encoder or pass, nested in the current scope.
+    ///
+    /// The scope has to be ended with [`GpuProfiler::end_scope`], on the same recorder.
+    pub fn begin_scope<R: ProfilerCommandRecorder>(
+        &mut self,
+        label: impl Into<String>,
+        recorder: &mut R,
+        device: &Device,
+    ) {
+        let timestamps = self.begin_timestamps(recorder, device);
+        self.open_scopes.push(OpenScope {
+            label: label.into(),
+            timestamps,
+            pass: false,
+            pipeline_statistics: false,
+            statistics_query: None,
+            nested_scopes: Vec::new(),
+        });
+    }
+
+    /// Begin a scope named `label` on a command encoder, around the pass begun next.
+    ///
+    /// Its timestamps time the scopes within the pass, when they can't be written inside
+    /// passes. The scope has to be ended with [`GpuProfiler::end_scope`] on the encoder,
+    /// after the pass is dropped.
+    pub fn begin_pass_scope(
+        &mut self,
+        label: impl Into<String>,
+        encoder: &mut CommandEncoder,
+        device: &Device,
+    ) {
+        self.begin_scope(label, encoder, device);
+        self.open_scopes.last_mut().unwrap().pass = true;
+    }
+
+    /// Begin a scope named `label` on a pass, nested in the current scope, which collects
+    /// pipeline statistics as well as timestamps.
+    ///
+    /// The scope has to be ended with [`GpuProfiler::end_pipeline_statistics_scope`],
+    /// on the same pass.
+    ///
+    /// # Panics
+    ///
+    /// - If a pipeline statistics scope is already open, as their queries can't be nested.
+    pub fn begin_pipeline_statistics_scope<R: ProfilerPassRecorder>(
+        &mut self,
+        label: impl Into<String>,
+        pass: &mut R,
+        device: &Device,
+    ) {
+        assert!(
+            !self.open_scopes.iter().any(|scope| scope.pipeline_statistics),
+            "GpuProfiler::begin_pipeline_statistics_scope called within a pipeline statistics scope"
+        );
+        let timestamps = self.begin_timestamps(pass, device);
+        let statistics_query = if self.pipeline_statistics {
+            let query = self.allocate_queries(device, true, 1);
+            let pool = &self.pools[query.pool];
+            pass.begin_pipeline_statistics_query(&pool.query_set, query.start);
+            Some(query)
+        } else {
+            None
+        };
+        self.open_scopes.push(OpenScope {
+            label: label.into(),
+            timestamps,
+            pass: false,
+            pipeline_statistics: true,
+            statistics_query,
+            nested_scopes: Vec::new(),
+        });
+    }
+
+    /// End the innermost scope, on the recorder it was begun on.
+    ///
+    /// # Panics
+    ///
+    /// - If no scope is open.
+    /// - If the scope was begun with [`GpuProfiler::begin_pipeline_statistics_scope`].
+    pub fn end_scope<R: ProfilerCommandRecorder>(&mut self, recorder: &mut R) {
+        let scope = self
+            .open_scopes
+            .pop()
+            .expect("GpuProfiler::end_scope called without an open scope");
+        assert!(
+            !scope.pipeline_statistics,
+            "GpuProfiler::end_scope called for a pipeline statistics scope"
+        );
+        self.close_scope(scope, recorder);
+    }
+
+    /// End the innermost scope, begun with [`GpuProfiler::begin_pipeline_statistics_scope`]
+    /// on the same pass.
+    ///
+    /// # Panics
+    ///
+    /// - If no scope is open.
+    /// - If the scope wasn't begun with [`GpuProfiler::begin_pipeline_statistics_scope`].
+    pub fn end_pipeline_statistics_scope<R: ProfilerPassRecorder>(&mut self, pass: &mut R) {
+        let scope = self
+            .open_scopes
+            .pop()
+            .expect("GpuProfiler::end_pipeline_statistics_scope called without an open scope");
+        assert!(
+            scope.pipeline_statistics,
+            "GpuProfiler::end_pipeline_statistics_scope called for a scope without statistics"
+        );
+        if scope.statistics_query.is_some() {
+            pass.end_pipeline_statistics_query();
+        }
+        self.close_scope(scope, pass);
+    }
+
+    /// Resolve the queries written so far in the frame, for reading them back.
+    ///
+    /// The encoder has to be submitted after all the commands the scopes were begun and
+    /// ended in.
+    pub fn resolve_queries(&mut self, encoder: &mut CommandEncoder) {
+        for pool in self.pools.iter_mut() {
+            if pool.resolved == pool.used {
+                continue;
+            }
+            encoder.resolve_query_set(&pool.query_set, 0..pool.used, &pool.read_buffer, 0);
+            pool.resolved = pool.used;
+        }
+    }
+
+    /// End the frame, after the command encoders of its scopes and
+    /// [`GpuProfiler::resolve_queries`] are submitted, and start reading back its queries.
+    ///
+    /// The frame is discarded if it has unclosed scopes or unresolved queries, or if results
+    /// of too many frames are pending.
+    pub fn end_frame(&mut self) -> Result<(), GpuProfilerError> {
+        let error = if !self.open_scopes.is_empty() {
+            Some(GpuProfilerError::UnclosedScopes(self.open_scopes.len()))
+        } else if self.pools.iter().any(|pool| pool.resolved != pool.used) {
+            Some(GpuProfilerError::UnresolvedQueries)
+        } else if self.pending_frames.len() >= self.max_pending_frames {
+            Some(GpuProfilerError::TooManyPendingFrames)
+        } else {
+            None
+        };
+        if let Some(error) = error {
+            self.open_scopes.clear();
+            self.scopes.clear();
+            for mut pool in self.pools.drain(..) {
+                pool.used = 0;
+                pool.resolved = 0;
+                self.free_pools.push(pool);
+            }
+            return Err(error);
+        }
+
+        let mapped = Arc::new(AtomicUsize::new(0));
+        let mut map_failed = Vec::with_capacity(self.pools.len());
+        for pool in self.pools.iter() {
+            let mapped = Arc::clone(&mapped);
+            let failed = Arc::new(AtomicBool::new(false));
+            map_failed.push(Arc::clone(&failed));
+            pool.read_buffer
+                .slice(..pool.used_size())
+                .map_async(MapMode::Read, move |result| {
+                    if result.is_err() {
+                        failed.store(true, Ordering::Release);
+                    }
+                    mapped.fetch_add(1, Ordering::AcqRel);
+                });
+        }
+        self.pending_frames.push_back(PendingFrame {
+            pools: self.pools.drain(..).collect(),
+            scopes: std::mem::take(&mut self.scopes),
+            mapped,
+            map_failed,
+        });
+        Ok(())
+    }
+
+    /// Returns the results of the top level scopes of the oldest pending frame, once its
+    /// queries are read back.
+    ///
+    /// The device has to be polled for the queries to be read back. Frames whose
+    /// queries can't be read have no scopes.
+    pub fn process_finished_frame(&mut self) -> Option<Vec<GpuTimerScopeResult>> {
+        let frame = self.pending_frames.front()?;
+        if frame.mapped.load(Ordering::Acquire) < frame.pools.len() {
+            return None;
+        }
+        let frame = self.pending_frames.pop_front().unwrap();
+
+        let failed = frame
+            .map_failed
+            .iter()
+            .any(|map_failed| map_failed.load(Ordering::Acquire));
+        let mut values = Vec::with_capacity(frame.pools.len());
+        for (mut pool, map_failed) in frame.pools.into_iter().zip(frame.map_failed) {
+            if map_failed.load(Ordering::Acquire) {
+                // The read buffer is neither mapped nor able to be mapped again.
+                continue;
+            }
+            if !failed {
+                let slice = pool.read_buffer.slice(..pool.used_size());
+                let view = slice.get_mapped_range();
+                values.push(
+                    view.chunks_exact(wgt::QUERY_SIZE as usize)
+                        .map(|bytes| {
+                            let mut value = [0; 8];
+                            value.copy_from_slice(bytes);
+                            u64::from_le_bytes(value)
+                        })
+                        .collect::<Vec<_>>(),
+                );
+                drop(view);
+            }
+            pool.read_buffer.unmap();
+            pool.used = 0;
+            pool.resolved = 0;
+            self.free_pools.push(pool);
+        }
+        if failed {
+            return Some(Vec::new());
+        }
+
+        let period = self.timestamp_period as f64;
+        Some(
+            frame
+                .scopes
+                .into_iter()
+                .map(|scope| scope.into_result(&values, period))
+                .collect(),
+        )
+    }
+
+    /// Formats the timings of scopes as a trace in the JSON format of Chrome's `about:tracing`,
+    /// which can also be opened in other trace viewers.
+    ///
+    /// Scopes which aren't timed are left out, but not the scopes nested in them.
+    pub fn chrome_trace(scopes: &[GpuTimerScopeResult]) -> String {
+        fn write_events(scopes: &[GpuTimerScopeResult], events: &mut Vec<String>) {
+            for scope in scopes {
+                if let Some(ref time) = scope.time {
+                    events.push(format!(
+                        "{{\"name\":\"{}\",\"ph\":\"X\",\"ts\":{},\"dur\":{},\"pid\":0,\"tid\":0}}",
+                        escape_json(&scope.label),
+                        time.start / 1000.0,
+                        (time.end - time.start) / 1000.0,
+                    ));
+                }
+                write_events(&scope.nested_scopes, events);
+            }
+        }
+
+        let mut events = Vec::new();
+        write_events(scopes, &mut events);
+        format!("{{\"traceEvents\":[{}]}}", events.join(","))
+    }
+
+    /// Write the timestamp beginning a scope on `recorder`, or find the timestamps of the
+    /// enclosing pass scope when timestamps can't be written inside passes.
+    fn begin_timestamps<R: ProfilerCommandRecorder>(
+        &mut self,
+        recorder: &mut R,
+        device: &Device,
+    ) -> Timestamps {
+        if !self.timestamps {
+            Timestamps::None
+        } else if self.inside_passes || !recorder.is_pass() {
+            let queries = self.allocate_queries(device, false, 2);
+            let pool = &self.pools[queries.pool];
+            recorder.write_timestamp(&pool.query_set, queries.start);
+            Timestamps::Scope(queries)
+        } else {
+            match self
+                .open_scopes
+                .iter()
+                .rev()
+                .find(|scope| scope.pass)
+                .and_then(|scope| scope.timestamps.queries())
+            {
+                Some(queries) => Timestamps::PassBoundaries(queries),
+                None => Timestamps::None,
+            }
+        }
+    }
+
+    /// Write the timestamp ending `scope` on `recorder`, and add it to its parent.
+    fn close_scope<R: ProfilerCommandRecorder>(&mut self, scope: OpenScope, recorder: &mut R) {
+        if let Timestamps::Scope(queries) = scope.timestamps {
+            let pool = &self.pools[queries.pool];
+            recorder.write_timestamp(&pool.query_set, queries.start + 1);
+        }
+        let timestamps = scope.timestamps.queries();
+        let closed = if timestamps.is_none() && scope.statistics_query.is_none() {
+            // scopes nested in one without results move up to its parent
+            scope.nested_scopes
+        } else {
+            vec![ClosedScope {
+                label: scope.label,
+                timestamps,
+                statistics_query: scope.statistics_query,
+                nested_scopes: scope.nested_scopes,
+            }]
+        };
+        match self.open_scopes.last_mut() {
+            Some(parent) => parent.nested_scopes.extend(closed),
+            None => self.scopes.extend(closed),
+        }
+    }
+
+    /// Reserve `count` consecutive timestamp or pipeline statistics queries in the pools of
+    /// the current frame.
+    fn allocate_queries(&mut self, device: &Device, statistics: bool, count: u32) -> Queries {
+        let pool = match self
+            .pools
+            .iter()
+            .position(|pool| pool.statistics == statistics && pool.used + count <= QUERIES_PER_POOL)
+        {
+            Some(pool) => pool,
+            None => {
+                let pool = match self
+                    .free_pools
+                    .iter()
+                    .position(|pool| pool.statistics == statistics)
+                {
+                    Some(index) => self.free_pools.swap_remove(index),
+                    None => {
+                        let size = (QUERIES_PER_POOL
+                            * QueryPool::values_per_query(statistics)
+                            * wgt::QUERY_SIZE) as u64;
+                        QueryPool {
+                            statistics,
+                            query_set: device.create_query_set(&QuerySetDescriptor {
+                                label: Some("(wgpu internal) GpuProfiler query set"),
+                                ty: if statistics {
+                                    QueryType::PipelineStatistics(PipelineStatisticsTypes::all())
+                                } else {
+                                    QueryType::Timestamp
+                                },
+                                count: QUERIES_PER_POOL,
+                            }),
+                            read_buffer: device.create_buffer(&BufferDescriptor {
+                                label: Some("(wgpu internal) GpuProfiler read-back buffer"),
+                                size,
+                                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                                mapped_at_creation: false,
+                            }),
+                            used: 0,
+                            resolved: 0,
+                        }
+                    }
+                };
+                self.pools.push(pool);
+                self.pools.len() - 1
+            }
+        };
+        let start = self.pools[pool].used;
+        self.pools[pool].used += count;
+        Queries { pool, start }
+    }
+}
+
+impl fmt::Debug for GpuProfiler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GpuProfiler")
+            .field("timestamps", &self.timestamps)
+            .field("inside_passes", &self.inside_passes)
+            .field("pipeline_statistics", &self.pipeline_statistics)
+            .field("open_scopes", &self.open_scopes.len())
+            .field("pending_frames", &self.pending_frames.len())
+            .field("free_pools", &self.free_pools.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl ClosedScope {
+    /// Build the results of the scope from the `values` read back from each pool.
+    fn into_result(self, values: &[Vec<u64>], period: f64) -> GpuTimerScopeResult {
+        let time = self.timestamps.map(|queries| {
+            let pool = &values[queries.pool];
+            let start = pool[queries.start as usize];
+            let end = pool[queries.start as usize + 1];
+            start as f64 * period..end as f64 * period
+        });
+        let pipeline_statistics = self.statistics_query.map(|query| {
+            let start = (query.start * PIPELINE_STATISTICS_VALUES) as usize;
+            let counters = &values[query.pool][start..];
+            // the values are in the order the flags of `PipelineStatisticsTypes` are declared
+            PipelineStatistics {
+                vertex_shader_invocations: counters[0],
+                clipper_invocations: counters[1],
+                clipper_primitives_out: counters[2],
+                fragment_shader_invocations: counters[3],
+                compute_shader_invocations: counters[4],
+            }
+        });
+        GpuTimerScopeResult {
+            label: self.label,
+            time,
+            pipeline_statistics,
+            nested_scopes: self
+                .nested_scopes
+                .into_iter()
+                .map(|scope| scope.into_result(values, period))
+                .collect(),
+        }
+    }
+}
+
+fn escape_json(string: &str) -> String {
+    let mut escaped = String::with_capacity(string.len());
+    for c in string.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,255 @@
+use crate::{
+    util::{align_to, StagingBelt},
+    BindingResource, Buffer, BufferAddress, BufferBinding, BufferDescriptor, BufferSize,
+    BufferUsages, CommandEncoder, Device, DynamicOffset, Queue,
+};
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::mpsc;
+
+/// Sub-allocator of a buffer, handing out aligned slices which are reclaimed
+/// once the submissions of their frame are done.
+struct Ring {
+    buffer: Buffer,
+    size: BufferAddress,
+    alignment: BufferAddress,
+    /// Offset of the next slice.
+    head: BufferAddress,
+    /// Offset of the oldest slice still in use.
+    tail: BufferAddress,
+    /// Bytes from `tail` to `head`, including the end of the buffer skipped when wrapping.
+    used: BufferAddress,
+    /// Bytes used by the current frame.
+    frame_used: BufferAddress,
+    /// Bytes used by each finished frame whose submissions aren't done yet, oldest first.
+    pending_frames: VecDeque<BufferAddress>,
+    sender: mpsc::Sender<()>,
+    receiver: mpsc::Receiver<()>,
+}
+
+impl Ring {
+    fn new(
+        device: &Device,
+        size: BufferAddress,
+        alignment: u32,
+        usage: BufferUsages,
+        label: &str,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Ring {
+            buffer: device.create_buffer(&BufferDescriptor {
+                label: Some(label),
+                size,
+                usage: usage | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            size,
+            alignment: alignment as BufferAddress,
+            head: 0,
+            tail: 0,
+            used: 0,
+            frame_used: 0,
+            pending_frames: VecDeque::new(),
+            sender,
+            receiver,
+        }
+    }
+
+    fn allocate(&mut self, size: BufferSize) -> Option<BufferAddress> {
+        while self.receiver.try_recv().is_ok() {
+            let frame_used = self.pending_frames.pop_front().unwrap();
+            self.tail = (self.tail + frame_used) % self.size;
+            self.used -= frame_used;
+        }
+        if self.used == 0 {
+            self.head = 0;
+            self.tail = 0;
+        }
+
+        let size = align_to(size.get(), self.alignment);
+        let (offset, skipped) = if self.head > self.tail || self.used == 0 {
+            if self.head + size <= self.size {
+                (self.head, 0)
+            } else if size <= self.tail {
+                (0, self.size - self.head)
+            } else {
+                return None;
+            }
+        } else if self.head + size <= self.tail {
+            (self.head, 0)
+        } else {
+            return None;
+        };
+        self.head = offset + size;
+        self.used += skipped + size;
+        self.frame_used += skipped + size;
+        Some(offset)
+    }
+
+    fn finish(&mut self, queue: &Queue) {
+        self.pending_frames.push_back(self.frame_used);
+        self.frame_used = 0;
+        let sender = self.sender.clone();
+        queue.on_submitted_work_done(move || {
+            let _ = sender.send(());
+        });
+    }
+}
+
+macro_rules! ring_methods {
+    ($name:ident) => {
+        impl $name {
+            /// Allocate a slice of `size` bytes, to be written by the caller.
+            ///
+            /// Returns the dynamic offset of the slice, or `None` if the ring has no space left
+            /// until the submissions of previous frames are done.
+            pub fn allocate(&mut self, size: BufferSize) -> Option<DynamicOffset> {
+                self.ring
+                    .allocate(size)
+                    .map(|offset| offset as DynamicOffset)
+            }
+
+            /// Allocate a slice of `data.len()` bytes and write `data` into it through
+            /// [`Queue::write_buffer_with`].
+            ///
+            /// Returns the dynamic offset of the slice, or `None` if the ring has no space left.
+            ///
+            /// # Panics
+            ///
+            /// - If `data` is empty.
+            pub fn write(&mut self, queue: &Queue, data: &[u8]) -> Option<DynamicOffset> {
+                let size = BufferSize::new(data.len() as BufferAddress)
+                    .expect(concat!(stringify!($name), "::write called with empty data"));
+                self.write_with(queue, size, |view| view.copy_from_slice(data))
+            }
+
+            /// Allocate a slice of `size` bytes and fill it with `fill`, through
+            /// [`Queue::write_buffer_with`].
+            ///
+            /// Returns the dynamic offset of the slice, or `None` if the ring has no space left.
+            pub fn write_with(
+                &mut self,
+                queue: &Queue,
+                size: BufferSize,
+                fill: impl FnOnce(&mut [u8]),
+            ) -> Option<DynamicOffset> {
+                let offset = self.ring.allocate(size)?;
+                fill(&mut queue.write_buffer_with(&self.ring.buffer, offset, size));
+                Some(offset as DynamicOffset)
+            }
+
+            /// Allocate a slice of `size` bytes and fill it with `fill`, through a
+            /// [`StagingBelt`] copying into the ring with `encoder`.
+            ///
+            /// Returns the dynamic offset of the slice, or `None` if the ring has no space left.
+            pub fn write_staged(
+                &mut self,
+                belt: &mut StagingBelt,
+                encoder: &mut CommandEncoder,
+                device: &Device,
+                size: BufferSize,
+                fill: impl FnOnce(&mut [u8]),
+            ) -> Option<DynamicOffset> {
+                let offset = self.ring.allocate(size)?;
+                fill(&mut belt.write_buffer(encoder, &self.ring.buffer, offset, size, device));
+                Some(offset as DynamicOffset)
+            }
+
+            /// End the current frame, after submitting the commands using its slices.
+            ///
+            /// The slices of the frame are reclaimed once these submissions are done,
+            /// as the device is polled.
+            pub fn finish(&mut self, queue: &Queue) {
+                self.ring.finish(queue);
+            }
+
+            /// Returns the buffer slices are allocated from.
+            pub fn buffer(&self) -> &Buffer {
+                &self.ring.buffer
+            }
+
+            /// Returns a resource binding slices of `size` bytes of the ring, to be used with
+            /// the dynamic offsets it returns.
+            pub fn binding(&self, size: BufferSize) -> BindingResource<'_> {
+                BindingResource::Buffer(BufferBinding {
+                    buffer: &self.ring.buffer,
+                    offset: 0,
+                    size: Some(size),
+                })
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct(stringify!($name))
+                    .field("size", &self.ring.size)
+                    .field("alignment", &self.ring.alignment)
+                    .field("used", &self.ring.used)
+                    .field("pending_frames", &self.ring.pending_frames.len())
+                    .finish_non_exhaustive()
+            }
+        }
+    };
+}
+
+/// Ring-buffer allocator of uniform data, bound with dynamic offsets.
+///
+/// Instead of a buffer per object, per-draw uniforms are written into slices of a single
+/// buffer, aligned to [`Limits::min_uniform_buffer_offset_alignment`]. The dynamic offset
+/// of each slice is passed to `set_bind_group`, with a bind group created from
+/// [`UniformRing::binding`] and a layout with `has_dynamic_offset`.
+///
+/// Using a uniform ring generally goes as follows, every frame:
+/// - Write uniforms using [`UniformRing::write`], [`UniformRing::write_with`] or
+///   [`UniformRing::write_staged`].
+/// - Submit all command encoders using the slices.
+/// - Call [`UniformRing::finish`].
+///
+/// [`Limits::min_uniform_buffer_offset_alignment`]: crate::Limits::min_uniform_buffer_offset_alignment
+pub struct UniformRing {
+    ring: Ring,
+}
+
+impl UniformRing {
+    /// Create a new uniform ring of `size` bytes.
+    pub fn new(device: &Device, size: BufferAddress) -> Self {
+        UniformRing {
+            ring: Ring::new(
+                device,
+                size,
+                device.limits().min_uniform_buffer_offset_alignment,
+                BufferUsages::UNIFORM,
+                "(wgpu internal) UniformRing buffer",
+            ),
+        }
+    }
+}
+
+ring_methods!(UniformRing);
+
+/// Ring-buffer allocator of storage data, bound with dynamic offsets.
+///
+/// This is the storage buffer counterpart of [`UniformRing`], with slices aligned to
+/// [`Limits::min_storage_buffer_offset_alignment`].
+///
+/// [`Limits::min_storage_buffer_offset_alignment`]: crate::Limits::min_storage_buffer_offset_alignment
+pub struct StorageRing {
+    ring: Ring,
+}
+
+impl StorageRing {
+    /// Create a new storage ring of `size` bytes.
+    pub fn new(device: &Device, size: BufferAddress) -> Self {
+        StorageRing {
+            ring: Ring::new(
+                device,
+                size,
+                device.limits().min_storage_buffer_offset_alignment,
+                BufferUsages::STORAGE,
+                "(wgpu internal) StorageRing buffer",
+            ),
+        }
+    }
+}
+
+ring_methods!(StorageRing);
@@ -0,0 +1,276 @@
+//! Parsing of DDS files, with either the legacy or the DX10 header.
+
+use super::{read_u32, Texels, TextureFile, TextureFileError, ASTC_BLOCKS};
+use crate::{AstcChannel, Extent3d, TextureDimension, TextureFormat as Tf};
+
+pub(super) const MAGIC: [u8; 4] = *b"DDS ";
+
+/// Size of the magic and the header, which is followed by the optional DX10 header.
+const HEADER_SIZE: usize = 4 + 124;
+const DX10_HEADER_SIZE: usize = 20;
+
+const DDSD_DEPTH: u32 = 0x80_0000;
+const DDSD_MIPMAPCOUNT: u32 = 0x2_0000;
+
+const DDPF_ALPHAPIXELS: u32 = 0x1;
+const DDPF_FOURCC: u32 = 0x4;
+const DDPF_RGB: u32 = 0x40;
+const DDPF_LUMINANCE: u32 = 0x2_0000;
+
+const DDSCAPS2_CUBEMAP: u32 = 0x200;
+const DDSCAPS2_CUBEMAP_ALLFACES: u32 = 0xFC00;
+const DDSCAPS2_VOLUME: u32 = 0x20_0000;
+
+const RESOURCE_MISC_TEXTURECUBE: u32 = 0x4;
+
+pub(super) fn parse(bytes: &[u8]) -> Result<TextureFile, TextureFileError> {
+    if read_u32(bytes, 4)? != 124 || read_u32(bytes, 76)? != 32 {
+        return Err(TextureFileError::Malformed("invalid DDS header size"));
+    }
+    let flags = read_u32(bytes, 8)?;
+    let height = read_u32(bytes, 12)?.max(1);
+    let width = read_u32(bytes, 16)?;
+    let depth = read_u32(bytes, 24)?.max(1);
+    let mip_level_count = if flags & DDSD_MIPMAPCOUNT != 0 {
+        read_u32(bytes, 28)?.max(1)
+    } else {
+        1
+    };
+    let pixel_flags = read_u32(bytes, 80)?;
+    let four_cc = read_u32(bytes, 84)?;
+    let caps2 = read_u32(bytes, 112)?;
+
+    let (texels, dimension, layers, cube, data_offset) =
+        if pixel_flags & DDPF_FOURCC != 0 && four_cc == u32::from_le_bytes(*b"DX10") {
+            let dxgi_format = read_u32(bytes, HEADER_SIZE)?;
+            let texels = dxgi_texels(dxgi_format).ok_or_else(|| {
+                TextureFileError::UnsupportedFileFormat(format!("DXGI format {}", dxgi_format))
+            })?;
+            let dimension = match read_u32(bytes, HEADER_SIZE + 4)? {
+                2 => TextureDimension::D1,
+                3 => TextureDimension::D2,
+                4 => TextureDimension::D3,
+                _ => {
+                    return Err(TextureFileError::Malformed(
+                        "invalid DDS resource dimension",
+                    ))
+                }
+            };
+            let cube = read_u32(bytes, HEADER_SIZE + 8)? & RESOURCE_MISC_TEXTURECUBE != 0;
+            let layers = read_u32(bytes, HEADER_SIZE + 12)?
+                .max(1)
+                .checked_mul(if cube { 6 } else { 1 })
+                .ok_or(TextureFileError::Malformed("too many array layers"))?;
+            let data_offset = HEADER_SIZE + DX10_HEADER_SIZE;
+            (texels, dimension, layers, cube, data_offset)
+        } else {
+            let texels = legacy_texels(bytes)?;
+            let dimension = if caps2 & DDSCAPS2_VOLUME != 0 && flags & DDSD_DEPTH != 0 {
+                TextureDimension::D3
+            } else {
+                TextureDimension::D2
+            };
+            let cube = caps2 & DDSCAPS2_CUBEMAP != 0;
+            if cube && caps2 & DDSCAPS2_CUBEMAP_ALLFACES != DDSCAPS2_CUBEMAP_ALLFACES {
+                return Err(TextureFileError::Malformed("incomplete cube map"));
+            }
+            let layers = if cube { 6 } else { 1 };
+            (texels, dimension, layers, cube, HEADER_SIZE)
+        };
+
+    let size = Extent3d {
+        width,
+        height,
+        depth_or_array_layers: match dimension {
+            TextureDimension::D3 => depth,
+            TextureDimension::D1 | TextureDimension::D2 => layers,
+        },
+    };
+    let mut file = TextureFile::new(texels, size, dimension, mip_level_count, cube)?;
+    // each layer is stored with all its mip levels, as expected by `create_texture_with_data`
+    let data_size = file
+        .data_size()
+        .ok_or(TextureFileError::Malformed("texture too large"))?;
+    file.data = data_offset
+        .checked_add(data_size)
+        .and_then(|data_end| bytes.get(data_offset..data_end))
+        .ok_or(TextureFileError::Malformed("truncated texel data"))?
+        .to_vec();
+    Ok(file)
+}
+
+/// Texels described by the pixel format of a legacy header.
+fn legacy_texels(bytes: &[u8]) -> Result<Texels, TextureFileError> {
+    let flags = read_u32(bytes, 80)?;
+    let four_cc = read_u32(bytes, 84)?;
+    let bit_count = read_u32(bytes, 88)?;
+    let mut masks = [0; 4];
+    for (i, mask) in masks.iter_mut().enumerate() {
+        *mask = read_u32(bytes, 92 + i * 4)?;
+    }
+    if flags & DDPF_ALPHAPIXELS == 0 {
+        masks[3] = 0;
+    }
+
+    if flags & DDPF_FOURCC != 0 {
+        four_cc_texels(four_cc).ok_or_else(|| {
+            let code = four_cc.to_le_bytes();
+            TextureFileError::UnsupportedFileFormat(if code.iter().all(u8::is_ascii_graphic) {
+                format!("DDS FourCC {}", String::from_utf8_lossy(&code))
+            } else {
+                format!("DDS FourCC {}", four_cc)
+            })
+        })
+    } else if flags & (DDPF_RGB | DDPF_LUMINANCE) != 0 {
+        mask_texels(bit_count, masks, flags & DDPF_LUMINANCE != 0).ok_or_else(|| {
+            TextureFileError::UnsupportedFileFormat(format!(
+                "DDS pixel format of {} bits with masks {:x?}",
+                bit_count, masks
+            ))
+        })
+    } else {
+        Err(TextureFileError::UnsupportedFileFormat(format!(
+            "DDS pixel format with flags {:#x}",
+            flags
+        )))
+    }
+}
+
+fn four_cc_texels(four_cc: u32) -> Option<Texels> {
+    let format = match &four_cc.to_le_bytes() {
+        b"DXT1" => Tf::Bc1RgbaUnorm,
+        b"DXT2" | b"DXT3" => Tf::Bc2RgbaUnorm,
+        b"DXT4" | b"DXT5" => Tf::Bc3RgbaUnorm,
+        b"ATI1" | b"BC4U" => Tf::Bc4RUnorm,
+        b"BC4S" => Tf::Bc4RSnorm,
+        b"ATI2" | b"BC5U" => Tf::Bc5RgUnorm,
+        b"BC5S" => Tf::Bc5RgSnorm,
+        // D3DFORMAT values
+        _ => match four_cc {
+            36 => Tf::Rgba16Unorm,
+            110 => Tf::Rgba16Snorm,
+            111 => Tf::R16Float,
+            112 => Tf::Rg16Float,
+            113 => Tf::Rgba16Float,
+            114 => Tf::R32Float,
+            115 => Tf::Rg32Float,
+            116 => Tf::Rgba32Float,
+            _ => return None,
+        },
+    };
+    Some(Texels::Format(format))
+}
+
+/// Texels with the given red, green, blue and alpha channel masks, or luminance and alpha masks.
+fn mask_texels(bit_count: u32, masks: [u32; 4], luminance: bool) -> Option<Texels> {
+    let texels = match (bit_count, masks, luminance) {
+        (32, [0xff, 0xff00, 0xff_0000, 0xff00_0000], false) => Texels::Format(Tf::Rgba8Unorm),
+        (32, [0xff, 0xff00, 0xff_0000, 0], false) => Texels::RGBX8,
+        (32, [0xff_0000, 0xff00, 0xff, 0xff00_0000], false) => Texels::Format(Tf::Bgra8Unorm),
+        (32, [0xff_0000, 0xff00, 0xff, 0], false) => Texels::BGRX8,
+        (32, [0x3ff, 0xf_fc00, 0x3ff0_0000, 0xc000_0000], false) => {
+            Texels::Format(Tf::Rgb10a2Unorm)
+        }
+        (32, [0xffff, 0xffff_0000, 0, 0], false) => Texels::Format(Tf::Rg16Unorm),
+        (24, [0xff, 0xff00, 0xff_0000, 0], false) => Texels::RGB8,
+        (24, [0xff_0000, 0xff00, 0xff, 0], false) => Texels::BGR8,
+        (16, [0xff, 0xff00, 0, 0], false) => Texels::Format(Tf::Rg8Unorm),
+        (16, [0xffff, 0, 0, 0], false) => Texels::Format(Tf::R16Unorm),
+        (8, [0xff, 0, 0, 0], false) => Texels::Format(Tf::R8Unorm),
+        (16, [0xff, 0, 0, 0xff00], true) => Texels::Bytes {
+            texel_size: 2,
+            red: 0,
+            green: 0,
+            blue: 0,
+            alpha: Some(1),
+            srgb: false,
+        },
+        (8, [0xff, 0, 0, 0], true) => Texels::Bytes {
+            texel_size: 1,
+            red: 0,
+            green: 0,
+            blue: 0,
+            alpha: None,
+            srgb: false,
+        },
+        _ => return None,
+    };
+    Some(texels)
+}
+
+fn dxgi_texels(dxgi_format: u32) -> Option<Texels> {
+    let format = match dxgi_format {
+        2 => Tf::Rgba32Float,
+        3 => Tf::Rgba32Uint,
+        4 => Tf::Rgba32Sint,
+        10 => Tf::Rgba16Float,
+        11 => Tf::Rgba16Unorm,
+        12 => Tf::Rgba16Uint,
+        13 => Tf::Rgba16Snorm,
+        14 => Tf::Rgba16Sint,
+        16 => Tf::Rg32Float,
+        17 => Tf::Rg32Uint,
+        18 => Tf::Rg32Sint,
+        24 => Tf::Rgb10a2Unorm,
+        26 => Tf::Rg11b10Float,
+        27 | 28 => Tf::Rgba8Unorm,
+        29 => Tf::Rgba8UnormSrgb,
+        30 => Tf::Rgba8Uint,
+        31 => Tf::Rgba8Snorm,
+        32 => Tf::Rgba8Sint,
+        34 => Tf::Rg16Float,
+        35 => Tf::Rg16Unorm,
+        36 => Tf::Rg16Uint,
+        37 => Tf::Rg16Snorm,
+        38 => Tf::Rg16Sint,
+        40 => Tf::Depth32Float,
+        41 => Tf::R32Float,
+        42 => Tf::R32Uint,
+        43 => Tf::R32Sint,
+        49 => Tf::Rg8Unorm,
+        50 => Tf::Rg8Uint,
+        51 => Tf::Rg8Snorm,
+        52 => Tf::Rg8Sint,
+        54 => Tf::R16Float,
+        56 => Tf::R16Unorm,
+        57 => Tf::R16Uint,
+        58 => Tf::R16Snorm,
+        59 => Tf::R16Sint,
+        61 => Tf::R8Unorm,
+        62 => Tf::R8Uint,
+        63 => Tf::R8Snorm,
+        64 => Tf::R8Sint,
+        67 => Tf::Rgb9e5Ufloat,
+        70 | 71 => Tf::Bc1RgbaUnorm,
+        72 => Tf::Bc1RgbaUnormSrgb,
+        73 | 74 => Tf::Bc2RgbaUnorm,
+        75 => Tf::Bc2RgbaUnormSrgb,
+        76 | 77 => Tf::Bc3RgbaUnorm,
+        78 => Tf::Bc3RgbaUnormSrgb,
+        79 | 80 => Tf::Bc4RUnorm,
+        81 => Tf::Bc4RSnorm,
+        82 | 83 => Tf::Bc5RgUnorm,
+        84 => Tf::Bc5RgSnorm,
+        87 | 90 => Tf::Bgra8Unorm,
+        88 => return Some(Texels::BGRX8),
+        91 => Tf::Bgra8UnormSrgb,
+        93 => return Some(Texels::BGRX8.srgb(true)),
+        94 | 95 => Tf::Bc6hRgbUfloat,
+        96 => Tf::Bc6hRgbSfloat,
+        97 | 98 => Tf::Bc7RgbaUnorm,
+        99 => Tf::Bc7RgbaUnormSrgb,
+        // ASTC formats, as written by NVTT and Compressonator: typeless, unorm, sRGB and an unused value per block size
+        133..=188 => {
+            let index = dxgi_format - 133;
+            let channel = match index % 4 {
+                0 | 1 => AstcChannel::Unorm,
+                2 => AstcChannel::UnormSrgb,
+                _ => return None,
+            };
+            let block = ASTC_BLOCKS[index as usize / 4];
+            Tf::Astc { block, channel }
+        }
+        _ => return None,
+    };
+    Some(Texels::Format(format))
+}
@@ -0,0 +1,196 @@
+//! Parsing of KTX2 files without supercompression.
+
+use super::{read_u32, Texels, TextureFile, TextureFileError, ASTC_BLOCKS};
+use crate::{AstcChannel, Extent3d, TextureDimension, TextureFormat as Tf};
+use std::convert::TryFrom;
+
+pub(super) const IDENTIFIER: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+
+/// Size of the header and the index, which are followed by the level index.
+const HEADER_SIZE: usize = 80;
+const LEVEL_INDEX_ENTRY_SIZE: usize = 24;
+
+pub(super) fn parse(bytes: &[u8]) -> Result<TextureFile, TextureFileError> {
+    let vk_format = read_u32(bytes, 12)?;
+    let width = read_u32(bytes, 20)?;
+    let height = read_u32(bytes, 24)?;
+    let depth = read_u32(bytes, 28)?;
+    let layer_count = read_u32(bytes, 32)?;
+    let face_count = read_u32(bytes, 36)?;
+    let level_count = read_u32(bytes, 40)?.max(1);
+    let supercompression = read_u32(bytes, 44)?;
+
+    if supercompression != 0 {
+        return Err(TextureFileError::UnsupportedSupercompression(
+            supercompression,
+        ));
+    }
+    let texels = vk_texels(vk_format).ok_or_else(|| {
+        TextureFileError::UnsupportedFileFormat(format!("VkFormat {}", vk_format))
+    })?;
+    let dimension = if depth > 0 {
+        TextureDimension::D3
+    } else if height > 0 {
+        TextureDimension::D2
+    } else {
+        TextureDimension::D1
+    };
+    if face_count != 1 && face_count != 6 {
+        return Err(TextureFileError::Malformed("invalid face count"));
+    }
+    if dimension == TextureDimension::D3 && layer_count > 1 {
+        return Err(TextureFileError::Malformed(
+            "3D texture arrays are not supported",
+        ));
+    }
+
+    let size = Extent3d {
+        width,
+        height: height.max(1),
+        depth_or_array_layers: match dimension {
+            TextureDimension::D3 => depth,
+            TextureDimension::D1 | TextureDimension::D2 => layer_count
+                .max(1)
+                .checked_mul(face_count)
+                .ok_or(TextureFileError::Malformed("too many array layers"))?,
+        },
+    };
+    let mut file = TextureFile::new(texels, size, dimension, level_count, face_count == 6)?;
+
+    let layers = file.array_layer_count() as usize;
+    let mut levels = Vec::with_capacity(level_count as usize);
+    for mip in 0..level_count {
+        let entry = HEADER_SIZE + mip as usize * LEVEL_INDEX_ENTRY_SIZE;
+        let offset = read_u64(bytes, entry)?;
+        let length = read_u64(bytes, entry + 8)?;
+        let expected_length = file
+            .texels
+            .image_size(file.mip_size(mip))
+            .and_then(|image_size| image_size.checked_mul(layers));
+        if expected_length.map(|length| length as u64) != Some(length) {
+            return Err(TextureFileError::Malformed(
+                "unexpected size of a mip level",
+            ));
+        }
+        let level = usize::try_from(offset)
+            .ok()
+            .and_then(|offset| Some(offset..offset.checked_add(length as usize)?))
+            .and_then(|range| bytes.get(range))
+            .ok_or(TextureFileError::Malformed("truncated texel data"))?;
+        levels.push(level);
+    }
+
+    // the file stores all the layers of a mip level together,
+    // while `create_texture_with_data` expects all the mip levels of a layer together
+    let mut data = Vec::with_capacity(levels.iter().map(|level| level.len()).sum());
+    for layer in 0..layers {
+        for level in levels.iter() {
+            let image_size = level.len() / layers;
+            data.extend_from_slice(&level[layer * image_size..(layer + 1) * image_size]);
+        }
+    }
+    file.data = data;
+    Ok(file)
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Result<u64, TextureFileError> {
+    Ok(read_u32(bytes, offset)? as u64 | (read_u32(bytes, offset + 4)? as u64) << 32)
+}
+
+fn vk_texels(vk_format: u32) -> Option<Texels> {
+    let format = match vk_format {
+        9 => Tf::R8Unorm,
+        10 => Tf::R8Snorm,
+        13 => Tf::R8Uint,
+        14 => Tf::R8Sint,
+        16 => Tf::Rg8Unorm,
+        17 => Tf::Rg8Snorm,
+        20 => Tf::Rg8Uint,
+        21 => Tf::Rg8Sint,
+        23 => return Some(Texels::RGB8),
+        29 => return Some(Texels::RGB8.srgb(true)),
+        30 => return Some(Texels::BGR8),
+        36 => return Some(Texels::BGR8.srgb(true)),
+        37 => Tf::Rgba8Unorm,
+        38 => Tf::Rgba8Snorm,
+        41 => Tf::Rgba8Uint,
+        42 => Tf::Rgba8Sint,
+        43 => Tf::Rgba8UnormSrgb,
+        44 => Tf::Bgra8Unorm,
+        50 => Tf::Bgra8UnormSrgb,
+        64 => Tf::Rgb10a2Unorm,
+        70 => Tf::R16Unorm,
+        71 => Tf::R16Snorm,
+        74 => Tf::R16Uint,
+        75 => Tf::R16Sint,
+        76 => Tf::R16Float,
+        77 => Tf::Rg16Unorm,
+        78 => Tf::Rg16Snorm,
+        81 => Tf::Rg16Uint,
+        82 => Tf::Rg16Sint,
+        83 => Tf::Rg16Float,
+        91 => Tf::Rgba16Unorm,
+        92 => Tf::Rgba16Snorm,
+        95 => Tf::Rgba16Uint,
+        96 => Tf::Rgba16Sint,
+        97 => Tf::Rgba16Float,
+        98 => Tf::R32Uint,
+        99 => Tf::R32Sint,
+        100 => Tf::R32Float,
+        101 => Tf::Rg32Uint,
+        102 => Tf::Rg32Sint,
+        103 => Tf::Rg32Float,
+        107 => Tf::Rgba32Uint,
+        108 => Tf::Rgba32Sint,
+        109 => Tf::Rgba32Float,
+        122 => Tf::Rg11b10Float,
+        123 => Tf::Rgb9e5Ufloat,
+        126 => Tf::Depth32Float,
+        // BC1 without alpha decodes the same as with it, except for the transparent black texels
+        131 | 133 => Tf::Bc1RgbaUnorm,
+        132 | 134 => Tf::Bc1RgbaUnormSrgb,
+        135 => Tf::Bc2RgbaUnorm,
+        136 => Tf::Bc2RgbaUnormSrgb,
+        137 => Tf::Bc3RgbaUnorm,
+        138 => Tf::Bc3RgbaUnormSrgb,
+        139 => Tf::Bc4RUnorm,
+        140 => Tf::Bc4RSnorm,
+        141 => Tf::Bc5RgUnorm,
+        142 => Tf::Bc5RgSnorm,
+        143 => Tf::Bc6hRgbUfloat,
+        144 => Tf::Bc6hRgbSfloat,
+        145 => Tf::Bc7RgbaUnorm,
+        146 => Tf::Bc7RgbaUnormSrgb,
+        147 => Tf::Etc2Rgb8Unorm,
+        148 => Tf::Etc2Rgb8UnormSrgb,
+        149 => Tf::Etc2Rgb8A1Unorm,
+        150 => Tf::Etc2Rgb8A1UnormSrgb,
+        151 => Tf::Etc2Rgba8Unorm,
+        152 => Tf::Etc2Rgba8UnormSrgb,
+        153 => Tf::EacR11Unorm,
+        154 => Tf::EacR11Snorm,
+        155 => Tf::EacRg11Unorm,
+        156 => Tf::EacRg11Snorm,
+        // unorm and sRGB for each block size
+        157..=184 => {
+            let index = vk_format - 157;
+            Tf::Astc {
+                block: ASTC_BLOCKS[index as usize / 2],
+                channel: if index % 2 == 0 {
+                    AstcChannel::Unorm
+                } else {
+                    AstcChannel::UnormSrgb
+                },
+            }
+        }
+        // VK_EXT_texture_compression_astc_hdr
+        1_000_066_000..=1_000_066_013 => Tf::Astc {
+            block: ASTC_BLOCKS[(vk_format - 1_000_066_000) as usize],
+            channel: AstcChannel::Hdr,
+        },
+        _ => return None,
+    };
+    Some(Texels::Format(format))
+}
@@ -0,0 +1,471 @@
+//! Loading of textures from KTX2 and DDS files.
+
+mod dds;
+mod ktx2;
+
+use super::decompress::{decompress_texture, decompressed_format};
+use crate::{
+    util::DeviceExt, AstcBlock, Device, Extent3d, Features, Queue, Texture, TextureDescriptor,
+    TextureDimension, TextureFormat, TextureUsages, TextureViewDimension,
+};
+use std::{error, fmt};
+
+/// ASTC block sizes, in the order of both the Vulkan and DXGI formats.
+const ASTC_BLOCKS: [AstcBlock; 14] = [
+    AstcBlock::B4x4,
+    AstcBlock::B5x4,
+    AstcBlock::B5x5,
+    AstcBlock::B6x5,
+    AstcBlock::B6x6,
+    AstcBlock::B8x5,
+    AstcBlock::B8x6,
+    AstcBlock::B8x8,
+    AstcBlock::B10x5,
+    AstcBlock::B10x6,
+    AstcBlock::B10x8,
+    AstcBlock::B10x10,
+    AstcBlock::B12x10,
+    AstcBlock::B12x12,
+];
+
+/// Texels of a texture file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Texels {
+    /// Texels with an equivalent format.
+    Format(TextureFormat),
+    /// 8 bit unsigned normalized channels, found at the given byte offsets of each texel.
+    ///
+    /// The alpha channel is 1 when it's missing.
+    Bytes {
+        texel_size: u8,
+        red: u8,
+        green: u8,
+        blue: u8,
+        alpha: Option<u8>,
+        srgb: bool,
+    },
+}
+
+impl Texels {
+    const RGB8: Self = Texels::Bytes {
+        texel_size: 3,
+        red: 0,
+        green: 1,
+        blue: 2,
+        alpha: None,
+        srgb: false,
+    };
+    const BGR8: Self = Texels::Bytes {
+        texel_size: 3,
+        red: 2,
+        green: 1,
+        blue: 0,
+        alpha: None,
+        srgb: false,
+    };
+    const RGBX8: Self = Texels::Bytes {
+        texel_size: 4,
+        red: 0,
+        green: 1,
+        blue: 2,
+        alpha: None,
+        srgb: false,
+    };
+    const BGRX8: Self = Texels::Bytes {
+        texel_size: 4,
+        red: 2,
+        green: 1,
+        blue: 0,
+        alpha: None,
+        srgb: false,
+    };
+
+    /// Same layout, with the given color space.
+    fn srgb(self, srgb: bool) -> Self {
+        match self {
+            Texels::Format(_) => self,
+            Texels::Bytes {
+                texel_size,
+                red,
+                green,
+                blue,
+                alpha,
+                srgb: _,
+            } => Texels::Bytes {
+                texel_size,
+                red,
+                green,
+                blue,
+                alpha,
+                srgb,
+            },
+        }
+    }
+
+    /// Size in bytes of an image of the given size, which isn't empty, or `None` if it
+    /// overflows.
+    fn image_size(&self, size: Extent3d) -> Option<usize> {
+        let (blocks_wide, blocks_high, block_size) = match *self {
+            Texels::Format(format) => {
+                let info = format.describe();
+                // rounded up like `Extent3d::physical_size`, which can overflow
+                (
+                    (size.width - 1) / info.block_dimensions.0 as u32 + 1,
+                    (size.height - 1) / info.block_dimensions.1 as u32 + 1,
+                    info.block_size,
+                )
+            }
+            Texels::Bytes { texel_size, .. } => (size.width, size.height, texel_size),
+        };
+        (blocks_wide as usize)
+            .checked_mul(blocks_high as usize)?
+            .checked_mul(size.depth_or_array_layers as usize)?
+            .checked_mul(block_size as usize)
+    }
+}
+
+/// CPU conversion of the texels of a [`TextureFile`] before they are uploaded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TextureConversion {
+    /// The channels of each texel are rearranged, and a missing alpha channel is set to 1.
+    ///
+    /// Used for layouts without an equivalent [`TextureFormat`], like 24 bit RGB.
+    Rearrange,
+    /// Block compressed texels the device can't sample are decoded, see [`decompress`].
+    ///
+    /// [`decompress`]: crate::util::decompress
+    Decompress,
+}
+
+/// Format a [`TextureFile`] is uploaded with, see [`TextureFile::target`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TextureTarget {
+    /// Format of the created texture.
+    pub format: TextureFormat,
+    /// Conversion of the texels on the CPU, if they aren't uploaded as they are stored.
+    pub conversion: Option<TextureConversion>,
+}
+
+/// Error returned when a texture file can't be parsed or uploaded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TextureFileError {
+    /// The data starts with neither the KTX2 nor the DDS signature.
+    UnknownContainer,
+    /// The header is invalid, or the data is truncated.
+    Malformed(&'static str),
+    /// The texels are stored in a format that isn't supported, described by the string.
+    UnsupportedFileFormat(String),
+    /// The KTX2 file uses a supercompression scheme, like Basis Universal or Zstandard.
+    UnsupportedSupercompression(u32),
+    /// The device can't sample textures of the format of the file.
+    UnsupportedFormat {
+        /// Format of the texels of the file.
+        format: TextureFormat,
+        /// Features the device would need to sample the format.
+        missing_features: Features,
+    },
+}
+
+impl fmt::Display for TextureFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            TextureFileError::UnknownContainer => {
+                write!(f, "Data is neither a KTX2 nor a DDS file")
+            }
+            TextureFileError::Malformed(reason) => write!(f, "Malformed texture file: {}", reason),
+            TextureFileError::UnsupportedFileFormat(ref format) => {
+                write!(f, "Texel format {} is not supported", format)
+            }
+            TextureFileError::UnsupportedSupercompression(scheme) => {
+                write!(
+                    f,
+                    "KTX2 supercompression scheme {} is not supported",
+                    scheme
+                )
+            }
+            TextureFileError::UnsupportedFormat {
+                format,
+                missing_features,
+            } => write!(
+                f,
+                "Format {:?} can't be sampled by the device, missing features {:?}",
+                format, missing_features
+            ),
+        }
+    }
+}
+
+impl error::Error for TextureFileError {}
+
+/// Reads a little endian `u32` at `offset` of the header.
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, TextureFileError> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or(TextureFileError::Malformed("truncated header"))
+}
+
+/// Texture loaded from a KTX2 or DDS file.
+///
+/// The texels of all the mip levels and layers are kept in memory, in the order expected by
+/// [`DeviceExt::create_texture_with_data`]. Parse a file with [`TextureFile::from_bytes`],
+/// and create a texture from it with [`TextureFile::create_texture`]:
+///
+/// ```no_run
+/// # fn load(device: &wgpu::Device, queue: &wgpu::Queue) -> Result<(), wgpu::util::TextureFileError> {
+/// let file = wgpu::util::TextureFile::from_bytes(&std::fs::read("skybox.ktx2").unwrap())?;
+/// let texture = file.create_texture(device, queue, Some("skybox"), wgpu::TextureUsages::TEXTURE_BINDING)?;
+/// let view = texture.create_view(&wgpu::TextureViewDescriptor {
+///     dimension: Some(file.view_dimension()),
+///     ..Default::default()
+/// });
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct TextureFile {
+    texels: Texels,
+    size: Extent3d,
+    dimension: TextureDimension,
+    mip_level_count: u32,
+    cube: bool,
+    data: Vec<u8>,
+}
+
+impl TextureFile {
+    /// Parse a KTX2 or DDS file, depending on its signature.
+    ///
+    /// Legacy DDS headers carry no color space, so their texels are assumed to be linear.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, TextureFileError> {
+        if bytes.starts_with(&ktx2::IDENTIFIER) {
+            ktx2::parse(bytes)
+        } else if bytes.starts_with(&dds::MAGIC) {
+            dds::parse(bytes)
+        } else {
+            Err(TextureFileError::UnknownContainer)
+        }
+    }
+
+    /// Create a file without texels from its parsed header.
+    fn new(
+        texels: Texels,
+        size: Extent3d,
+        dimension: TextureDimension,
+        mip_level_count: u32,
+        cube: bool,
+    ) -> Result<Self, TextureFileError> {
+        if size.width == 0 || size.height == 0 || size.depth_or_array_layers == 0 {
+            return Err(TextureFileError::Malformed("empty texture"));
+        }
+        if mip_level_count == 0 || mip_level_count > size.max_mips(dimension) {
+            return Err(TextureFileError::Malformed("invalid mip level count"));
+        }
+        if cube && (dimension != TextureDimension::D2 || size.depth_or_array_layers % 6 != 0) {
+            return Err(TextureFileError::Malformed("incomplete cube map"));
+        }
+        if dimension == TextureDimension::D1 && size.depth_or_array_layers != 1 {
+            return Err(TextureFileError::Malformed(
+                "1D texture arrays are not supported",
+            ));
+        }
+        let file = TextureFile {
+            texels,
+            size,
+            dimension,
+            mip_level_count,
+            cube,
+            data: Vec::new(),
+        };
+        if file.data_size().is_none() {
+            return Err(TextureFileError::Malformed("texture too large"));
+        }
+        Ok(file)
+    }
+
+    /// Number of images of the texture, each of them with all its mip levels.
+    fn array_layer_count(&self) -> u32 {
+        match self.dimension {
+            TextureDimension::D1 | TextureDimension::D3 => 1,
+            TextureDimension::D2 => self.size.depth_or_array_layers,
+        }
+    }
+
+    /// Size of the mip level `mip` of a single layer.
+    fn mip_size(&self, mip: u32) -> Extent3d {
+        let is_3d = self.dimension == TextureDimension::D3;
+        let mut size = self.size.mip_level_size(mip, is_3d);
+        if !is_3d {
+            size.depth_or_array_layers = 1;
+        }
+        size
+    }
+
+    /// Size in bytes of all the mip levels of a single layer, or `None` if it overflows.
+    fn layer_size(&self) -> Option<usize> {
+        (0..self.mip_level_count).try_fold(0usize, |total, mip| {
+            total.checked_add(self.texels.image_size(self.mip_size(mip))?)
+        })
+    }
+
+    /// Size in bytes of the texels of all the layers, or `None` if it overflows.
+    fn data_size(&self) -> Option<usize> {
+        self.layer_size()?
+            .checked_mul(self.array_layer_count() as usize)
+    }
+
+    /// Format of the texels as they are stored, or `None` if it has no [`TextureFormat`] equivalent.
+    pub fn format(&self) -> Option<TextureFormat> {
+        match self.texels {
+            Texels::Format(format) => Some(format),
+            Texels::Bytes { .. } => None,
+        }
+    }
+
+    /// Size of the texture. The number of layers of cube maps includes their faces.
+    pub fn size(&self) -> Extent3d {
+        self.size
+    }
+
+    /// Dimension of the texture.
+    pub fn dimension(&self) -> TextureDimension {
+        self.dimension
+    }
+
+    /// Number of mip levels stored in the file.
+    pub fn mip_level_count(&self) -> u32 {
+        self.mip_level_count
+    }
+
+    /// Returns true if the layers of the texture are the faces of cube maps.
+    pub fn is_cube(&self) -> bool {
+        self.cube
+    }
+
+    /// Dimension of the views covering the whole texture.
+    pub fn view_dimension(&self) -> TextureViewDimension {
+        match self.dimension {
+            TextureDimension::D1 => TextureViewDimension::D1,
+            TextureDimension::D3 => TextureViewDimension::D3,
+            TextureDimension::D2 => match (self.cube, self.size.depth_or_array_layers) {
+                (true, 6) => TextureViewDimension::Cube,
+                (true, _) => TextureViewDimension::CubeArray,
+                (false, 1) => TextureViewDimension::D2,
+                (false, _) => TextureViewDimension::D2Array,
+            },
+        }
+    }
+
+    /// Texels of all the layers and mip levels, as they are stored.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Pick the format the texture is created with on a device with the given features.
+    ///
+    /// The format of the file is used if the device can sample it. Otherwise, or if it has
+    /// no [`TextureFormat`] equivalent, the texels are converted on the CPU into a supported
+    /// format when possible, decoding block compressed formats whose features are missing.
+    pub fn target(&self, features: Features) -> Result<TextureTarget, TextureFileError> {
+        match self.texels {
+            Texels::Format(format) => {
+                let info = format.describe();
+                let missing_features = info.required_features - features;
+                let sampleable = info
+                    .guaranteed_format_features
+                    .allowed_usages
+                    .contains(TextureUsages::TEXTURE_BINDING);
+                if !missing_features.is_empty() {
+                    if let Some(decompressed) = decompressed_format(format) {
+                        return Ok(TextureTarget {
+                            format: decompressed,
+                            conversion: Some(TextureConversion::Decompress),
+                        });
+                    }
+                }
+                if missing_features.is_empty() && sampleable {
+                    Ok(TextureTarget {
+                        format,
+                        conversion: None,
+                    })
+                } else {
+                    Err(TextureFileError::UnsupportedFormat {
+                        format,
+                        missing_features,
+                    })
+                }
+            }
+            Texels::Bytes { srgb, .. } => Ok(TextureTarget {
+                format: if srgb {
+                    TextureFormat::Rgba8UnormSrgb
+                } else {
+                    TextureFormat::Rgba8Unorm
+                },
+                conversion: Some(TextureConversion::Rearrange),
+            }),
+        }
+    }
+
+    /// Create a texture with all the mip levels and layers of the file, and upload them.
+    ///
+    /// The format of the texture is picked with [`TextureFile::target`] from the features
+    /// of the device. The `COPY_DST` usage is added implicitly.
+    pub fn create_texture(
+        &self,
+        device: &Device,
+        queue: &Queue,
+        label: Option<&str>,
+        usage: TextureUsages,
+    ) -> Result<Texture, TextureFileError> {
+        let target = self.target(device.features())?;
+        let desc = TextureDescriptor {
+            label,
+            size: self.size,
+            mip_level_count: self.mip_level_count,
+            sample_count: 1,
+            dimension: self.dimension,
+            format: target.format,
+            usage,
+        };
+        let texture = match target.conversion {
+            None => device.create_texture_with_data(queue, &desc, &self.data),
+            Some(TextureConversion::Rearrange) => {
+                device.create_texture_with_data(queue, &desc, &self.rearrange())
+            }
+            Some(TextureConversion::Decompress) => {
+                let compressed = TextureDescriptor {
+                    format: self.format().unwrap(),
+                    ..desc.clone()
+                };
+                let (_, texels) = decompress_texture(&compressed, &self.data).unwrap();
+                device.create_texture_with_data(queue, &desc, &texels)
+            }
+        };
+        Ok(texture)
+    }
+
+    /// Convert [`Texels::Bytes`] into 8 bit RGBA texels.
+    fn rearrange(&self) -> Vec<u8> {
+        let (texel_size, red, green, blue, alpha) = match self.texels {
+            Texels::Format(_) => unreachable!(),
+            Texels::Bytes {
+                texel_size,
+                red,
+                green,
+                blue,
+                alpha,
+                srgb: _,
+            } => (texel_size as usize, red, green, blue, alpha),
+        };
+        self.data
+            .chunks_exact(texel_size)
+            .flat_map(|texel| {
+                [
+                    texel[red as usize],
+                    texel[green as usize],
+                    texel[blue as usize],
+                    alpha.map_or(255, |alpha| texel[alpha as usize]),
+                ]
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,559 @@
+use crate::{
+    util::{BufferInitDescriptor, DeviceExt, DownloadBuffer},
+    BindingResource, Buffer, BufferAddress, BufferAsyncError, BufferDescriptor, BufferSize,
+    BufferSlice, BufferUsages, Device, Label, Queue,
+};
+use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds};
+use std::{error, fmt};
+
+/// Types laid out in buffers with the WGSL memory layout rules.
+///
+/// The layout inserts the padding WGSL expects between fields, so the Rust type itself
+/// doesn't need to be `#[repr(C)]`. It's implemented for `f32`, `i32` and `u32`, for arrays,
+/// and can be derived for structs with the `macros` feature. Arrays of 2 to 4 scalars are
+/// laid out as vectors, and arrays of them as matrices or arrays of vectors.
+///
+/// These are the layout rules of storage buffers. Uniform buffers additionally require
+/// the stride of arrays, and the alignment of structs they contain, to be multiples of 16.
+pub trait WgslLayout: Copy {
+    /// Alignment of the type, in bytes.
+    const ALIGNMENT: u64;
+    /// Size of the type in bytes, including its trailing padding.
+    const SIZE: u64;
+    #[doc(hidden)]
+    const IS_SCALAR: bool = false;
+
+    /// Write the value at the start of `bytes`, leaving padding bytes untouched.
+    fn write_bytes(&self, bytes: &mut [u8]);
+
+    /// Read a value from the start of `bytes`.
+    fn read_bytes(bytes: &[u8]) -> Self;
+
+    /// Returns the layout of the type, to compare it with the types of a shader.
+    fn layout() -> WgslTypeLayout;
+}
+
+/// Layout of a [`WgslLayout`] type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WgslTypeLayout {
+    /// Scalar of `size` bytes.
+    Scalar {
+        /// Size of the scalar, in bytes.
+        size: u64,
+    },
+    /// Vector of `count` scalars.
+    Vector {
+        /// Count of components.
+        count: u64,
+        /// Size of each component, in bytes.
+        component_size: u64,
+    },
+    /// Array, or matrix if its elements are vectors.
+    Array {
+        /// Layout of each element.
+        element: Box<WgslTypeLayout>,
+        /// Count of elements.
+        count: u64,
+        /// Bytes from the start of an element to the start of the next one.
+        stride: u64,
+    },
+    /// Struct, with its fields in order.
+    Struct {
+        /// Size of the struct, including its trailing padding.
+        size: u64,
+        /// Fields of the struct.
+        members: Vec<WgslMemberLayout>,
+    },
+}
+
+/// Layout of a field of a [`WgslTypeLayout::Struct`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WgslMemberLayout {
+    /// Name of the field.
+    pub name: &'static str,
+    /// Offset of the field from the start of the struct, in bytes.
+    pub offset: u64,
+    /// Layout of the field.
+    pub layout: WgslTypeLayout,
+}
+
+#[doc(hidden)]
+pub const fn wgsl_round_up(value: u64, alignment: u64) -> u64 {
+    (value + alignment - 1) / alignment * alignment
+}
+
+#[doc(hidden)]
+pub const fn wgsl_max(a: u64, b: u64) -> u64 {
+    if a > b {
+        a
+    } else {
+        b
+    }
+}
+
+macro_rules! impl_scalar {
+    ($($ty:ty),*) => {$(
+        impl WgslLayout for $ty {
+            const ALIGNMENT: u64 = 4;
+            const SIZE: u64 = 4;
+            const IS_SCALAR: bool = true;
+
+            fn write_bytes(&self, bytes: &mut [u8]) {
+                bytes[..4].copy_from_slice(&self.to_le_bytes());
+            }
+
+            fn read_bytes(bytes: &[u8]) -> Self {
+                let mut value = [0; 4];
+                value.copy_from_slice(&bytes[..4]);
+                Self::from_le_bytes(value)
+            }
+
+            fn layout() -> WgslTypeLayout {
+                WgslTypeLayout::Scalar { size: 4 }
+            }
+        }
+    )*};
+}
+
+impl_scalar!(f32, i32, u32);
+
+impl<T: WgslLayout, const N: usize> WgslLayout for [T; N] {
+    const ALIGNMENT: u64 = if T::IS_SCALAR && N == 2 {
+        2 * T::SIZE
+    } else if T::IS_SCALAR && (N == 3 || N == 4) {
+        4 * T::SIZE
+    } else {
+        T::ALIGNMENT
+    };
+    const SIZE: u64 = N as u64 * wgsl_round_up(T::SIZE, T::ALIGNMENT);
+
+    fn write_bytes(&self, bytes: &mut [u8]) {
+        let stride = wgsl_round_up(T::SIZE, T::ALIGNMENT) as usize;
+        for (i, element) in self.iter().enumerate() {
+            element.write_bytes(&mut bytes[i * stride..]);
+        }
+    }
+
+    /// # Panics
+    ///
+    /// - If `N` is 0.
+    fn read_bytes(bytes: &[u8]) -> Self {
+        let stride = wgsl_round_up(T::SIZE, T::ALIGNMENT) as usize;
+        let mut array = [T::read_bytes(bytes); N];
+        for (i, element) in array.iter_mut().enumerate().skip(1) {
+            *element = T::read_bytes(&bytes[i * stride..]);
+        }
+        array
+    }
+
+    fn layout() -> WgslTypeLayout {
+        if T::IS_SCALAR && (2..=4).contains(&N) {
+            WgslTypeLayout::Vector {
+                count: N as u64,
+                component_size: T::SIZE,
+            }
+        } else {
+            WgslTypeLayout::Array {
+                element: Box::new(T::layout()),
+                count: N as u64,
+                stride: wgsl_round_up(T::SIZE, T::ALIGNMENT),
+            }
+        }
+    }
+}
+
+/// Error returned by `TypedBuffer::check_layout`, with the `naga` feature.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WgslLayoutError {
+    /// The shader has no buffer at this binding.
+    MissingBinding {
+        /// Bind group index.
+        group: u32,
+        /// Binding index in the group.
+        binding: u32,
+    },
+    /// The type at `path` doesn't match the shader type.
+    TypeMismatch {
+        /// Path of the type, from the variable of the binding.
+        path: String,
+    },
+    /// The field at `path` has a different offset in the shader.
+    OffsetMismatch {
+        /// Path of the field, from the variable of the binding.
+        path: String,
+        /// Offset of the field in the shader.
+        shader: u64,
+        /// Offset of the field in the Rust type.
+        rust: u64,
+    },
+    /// The array at `path` has a different stride in the shader.
+    StrideMismatch {
+        /// Path of the array, from the variable of the binding.
+        path: String,
+        /// Stride of the array in the shader.
+        shader: u64,
+        /// Stride of the array in the Rust type.
+        rust: u64,
+    },
+    /// The array or struct at `path` has a different size in the shader.
+    SizeMismatch {
+        /// Path of the type, from the variable of the binding.
+        path: String,
+        /// Size of the type in the shader, in bytes or elements for arrays.
+        shader: u64,
+        /// Size of the type in Rust, in bytes or elements for arrays.
+        rust: u64,
+    },
+}
+
+impl fmt::Display for WgslLayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            WgslLayoutError::MissingBinding { group, binding } => {
+                write!(
+                    f,
+                    "No buffer is bound at group {}, binding {}",
+                    group, binding
+                )
+            }
+            WgslLayoutError::TypeMismatch { ref path } => {
+                write!(f, "Type of `{}` doesn't match the shader", path)
+            }
+            WgslLayoutError::OffsetMismatch {
+                ref path,
+                shader,
+                rust,
+            } => write!(
+                f,
+                "Offset of `{}` is {} in the shader, but {} in Rust",
+                path, shader, rust
+            ),
+            WgslLayoutError::StrideMismatch {
+                ref path,
+                shader,
+                rust,
+            } => write!(
+                f,
+                "Stride of `{}` is {} in the shader, but {} in Rust",
+                path, shader, rust
+            ),
+            WgslLayoutError::SizeMismatch {
+                ref path,
+                shader,
+                rust,
+            } => write!(
+                f,
+                "Size of `{}` is {} in the shader, but {} in Rust",
+                path, shader, rust
+            ),
+        }
+    }
+}
+
+impl error::Error for WgslLayoutError {}
+
+/// Buffer of elements of type `T`, laid out with the WGSL memory layout rules.
+///
+/// Indices and ranges of its methods are in elements, which are
+/// [`TypedBuffer::stride`] bytes apart.
+pub struct TypedBuffer<T> {
+    buffer: Buffer,
+    len: u64,
+    element: PhantomData<fn() -> T>,
+}
+
+impl<T: WgslLayout> TypedBuffer<T> {
+    /// Bytes from the start of an element to the start of the next one.
+    pub const fn stride() -> BufferAddress {
+        wgsl_round_up(T::SIZE, T::ALIGNMENT)
+    }
+
+    /// Create a buffer of `len` zeroed elements.
+    pub fn new(device: &Device, label: Label, len: u64, usage: BufferUsages) -> Self {
+        TypedBuffer {
+            buffer: device.create_buffer(&BufferDescriptor {
+                label,
+                size: len * Self::stride(),
+                usage,
+                mapped_at_creation: false,
+            }),
+            len,
+            element: PhantomData,
+        }
+    }
+
+    /// Create a buffer initialized with `data`.
+    pub fn new_init(device: &Device, label: Label, data: &[T], usage: BufferUsages) -> Self {
+        TypedBuffer {
+            buffer: device.create_buffer_init(&BufferInitDescriptor {
+                label,
+                contents: &Self::to_bytes(data),
+                usage,
+            }),
+            len: data.len() as u64,
+            element: PhantomData,
+        }
+    }
+
+    /// Lay out `data` as it is stored in the buffer.
+    pub fn to_bytes(data: &[T]) -> Vec<u8> {
+        let stride = Self::stride() as usize;
+        let mut bytes = vec![0; data.len() * stride];
+        for (value, bytes) in data.iter().zip(bytes.chunks_exact_mut(stride)) {
+            value.write_bytes(bytes);
+        }
+        bytes
+    }
+
+    /// Returns the count of elements of the buffer.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns true if the buffer has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the underlying buffer.
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    /// Returns a binding of the whole buffer.
+    pub fn as_entire_binding(&self) -> BindingResource<'_> {
+        self.buffer.as_entire_binding()
+    }
+
+    /// Use only a portion of this buffer, from a range of elements.
+    pub fn slice<S: RangeBounds<u64>>(&self, bounds: S) -> BufferSlice<'_> {
+        let stride = Self::stride();
+        let start = match bounds.start_bound() {
+            Bound::Included(&index) => index * stride,
+            Bound::Excluded(&index) => (index + 1) * stride,
+            Bound::Unbounded => 0,
+        };
+        let end = match bounds.end_bound() {
+            Bound::Included(&index) => (index + 1) * stride,
+            Bound::Excluded(&index) => index * stride,
+            Bound::Unbounded => self.len * stride,
+        };
+        self.buffer.slice(start..end)
+    }
+
+    /// Schedule a write of `data` into the buffer, starting at the element `index`,
+    /// through [`Queue::write_buffer_with`].
+    pub fn write(&self, queue: &Queue, index: u64, data: &[T]) {
+        let size = match BufferSize::new(data.len() as u64 * Self::stride()) {
+            Some(size) => size,
+            None => return,
+        };
+        let stride = Self::stride() as usize;
+        let mut view = queue.write_buffer_with(&self.buffer, index * Self::stride(), size);
+        view.fill(0);
+        for (value, bytes) in data.iter().zip(view.chunks_exact_mut(stride)) {
+            value.write_bytes(bytes);
+        }
+    }
+
+    /// Asynchronously read a range of elements of the buffer, which needs the
+    /// [`BufferUsages::COPY_SRC`] usage.
+    pub fn read<S: RangeBounds<u64>>(
+        &self,
+        device: &Device,
+        queue: &Queue,
+        bounds: S,
+        callback: impl FnOnce(Result<Vec<T>, BufferAsyncError>) + Send + 'static,
+    ) {
+        DownloadBuffer::read_buffer(device, queue, &self.slice(bounds), move |result| {
+            callback(result.map(|download| {
+                download
+                    .chunks_exact(Self::stride() as usize)
+                    .map(T::read_bytes)
+                    .collect()
+            }))
+        });
+    }
+
+    /// Check the layout of `T` against the type of the buffer bound at `group` and `binding`
+    /// in a shader module.
+    ///
+    /// The type of the binding is either `T`, or an array of `T`.
+    #[cfg(feature = "naga")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "naga")))]
+    pub fn check_layout(
+        module: &naga::Module,
+        group: u32,
+        binding: u32,
+    ) -> Result<(), WgslLayoutError> {
+        let (_, variable) = module
+            .global_variables
+            .iter()
+            .find(|(_, variable)| {
+                variable.binding == Some(naga::ResourceBinding { group, binding })
+                    && matches!(
+                        variable.space,
+                        naga::AddressSpace::Uniform | naga::AddressSpace::Storage { .. }
+                    )
+            })
+            .ok_or(WgslLayoutError::MissingBinding { group, binding })?;
+        let path = variable
+            .name
+            .clone()
+            .unwrap_or_else(|| "binding".to_string());
+        let layout = T::layout();
+        match module.types[variable.ty].inner {
+            // the elements of the array are tried first, so that a `T` which is an array
+            // itself, like a matrix, can still be matched against an array of `T`
+            naga::TypeInner::Array { base, stride, .. } => {
+                let element_path = format!("{}[]", path);
+                let element = if stride as u64 == Self::stride() {
+                    check_type(module, base, &layout, element_path)
+                } else {
+                    Err(WgslLayoutError::StrideMismatch {
+                        path: element_path,
+                        shader: stride as u64,
+                        rust: Self::stride(),
+                    })
+                };
+                match (element, &layout) {
+                    (Ok(()), _) => Ok(()),
+                    (Err(_), &WgslTypeLayout::Array { .. }) => {
+                        check_type(module, variable.ty, &layout, path)
+                    }
+                    (Err(error), _) => Err(error),
+                }
+            }
+            _ => check_type(module, variable.ty, &layout, path),
+        }
+    }
+}
+
+/// Compare the layout of a Rust type with the type `ty` of a shader module.
+#[cfg(feature = "naga")]
+fn check_type(
+    module: &naga::Module,
+    ty: naga::Handle<naga::Type>,
+    layout: &WgslTypeLayout,
+    path: String,
+) -> Result<(), WgslLayoutError> {
+    use naga::TypeInner as Ti;
+
+    let vector_size = |size: naga::VectorSize| size as u8 as u64;
+    match (&module.types[ty].inner, layout) {
+        (&Ti::Scalar { width, .. }, &WgslTypeLayout::Scalar { size })
+        | (&Ti::Atomic { width, .. }, &WgslTypeLayout::Scalar { size })
+            if width as u64 == size =>
+        {
+            Ok(())
+        }
+        (
+            &Ti::Vector { size, width, .. },
+            &WgslTypeLayout::Vector {
+                count,
+                component_size,
+            },
+        ) if vector_size(size) == count && width as u64 == component_size => Ok(()),
+        (
+            &Ti::Matrix {
+                columns,
+                rows,
+                width,
+            },
+            &WgslTypeLayout::Array {
+                ref element, count, ..
+            },
+        ) if vector_size(columns) == count
+            && **element
+                == (WgslTypeLayout::Vector {
+                    count: vector_size(rows),
+                    component_size: width as u64,
+                }) =>
+        {
+            Ok(())
+        }
+        (
+            &Ti::Array { base, size, stride },
+            &WgslTypeLayout::Array {
+                ref element,
+                count,
+                stride: rust_stride,
+            },
+        ) => {
+            if stride as u64 != rust_stride {
+                return Err(WgslLayoutError::StrideMismatch {
+                    path,
+                    shader: stride as u64,
+                    rust: rust_stride,
+                });
+            }
+            let shader_count = match size {
+                naga::ArraySize::Constant(constant) => match module.constants[constant].inner {
+                    naga::ConstantInner::Scalar {
+                        value: naga::ScalarValue::Uint(count),
+                        ..
+                    } => Some(count),
+                    naga::ConstantInner::Scalar {
+                        value: naga::ScalarValue::Sint(count),
+                        ..
+                    } => Some(count as u64),
+                    _ => None,
+                },
+                naga::ArraySize::Dynamic => None,
+            };
+            match shader_count {
+                Some(shader_count) if shader_count == count => {}
+                Some(shader_count) => {
+                    return Err(WgslLayoutError::SizeMismatch {
+                        path,
+                        shader: shader_count,
+                        rust: count,
+                    })
+                }
+                None => return Err(WgslLayoutError::TypeMismatch { path }),
+            }
+            check_type(module, base, element, format!("{}[]", path))
+        }
+        (
+            &Ti::Struct { ref members, span },
+            &WgslTypeLayout::Struct {
+                size,
+                members: ref rust_members,
+            },
+        ) => {
+            if members.len() != rust_members.len() {
+                return Err(WgslLayoutError::TypeMismatch { path });
+            }
+            for (member, rust_member) in members.iter().zip(rust_members) {
+                let path = format!("{}.{}", path, rust_member.name);
+                if member.offset as u64 != rust_member.offset {
+                    return Err(WgslLayoutError::OffsetMismatch {
+                        path,
+                        shader: member.offset as u64,
+                        rust: rust_member.offset,
+                    });
+                }
+                check_type(module, member.ty, &rust_member.layout, path)?;
+            }
+            if span as u64 != size {
+                return Err(WgslLayoutError::SizeMismatch {
+                    path,
+                    shader: span as u64,
+                    rust: size,
+                });
+            }
+            Ok(())
+        }
+        _ => Err(WgslLayoutError::TypeMismatch { path }),
+    }
+}
+
+impl<T> fmt::Debug for TypedBuffer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TypedBuffer")
+            .field("buffer", &self.buffer)
+            .field("len", &self.len)
+            .finish()
+    }
+}
@@ -0,0 +1,232 @@
+use crate::{
+    BufferAddress, EntryPointReflection, ShaderLocation, ShaderStages, VertexAttribute,
+    VertexBufferLayout, VertexFormat, VertexStepMode,
+};
+use std::{error, fmt};
+
+/// Types of fields of [`VertexLayout`] structs, read by the shader as a single attribute.
+///
+/// Arrays of 2 to 4 components map to vector formats, and the arrays of 8 and 16 bit
+/// integers can also be read as normalized floats.
+pub trait VertexAttributeType {
+    /// Format of the attribute.
+    const FORMAT: VertexFormat;
+    /// Format of the attribute read as unsigned normalized floats, if any.
+    const UNORM: Option<VertexFormat> = None;
+    /// Format of the attribute read as signed normalized floats, if any.
+    const SNORM: Option<VertexFormat> = None;
+}
+
+macro_rules! impl_vertex_attribute_type {
+    ($($ty:ty => $format:ident $(, unorm $unorm:ident)? $(, snorm $snorm:ident)?;)*) => {
+        $(
+            impl VertexAttributeType for $ty {
+                const FORMAT: VertexFormat = VertexFormat::$format;
+                $(const UNORM: Option<VertexFormat> = Some(VertexFormat::$unorm);)?
+                $(const SNORM: Option<VertexFormat> = Some(VertexFormat::$snorm);)?
+            }
+        )*
+    };
+}
+
+impl_vertex_attribute_type! {
+    [u8; 2] => Uint8x2, unorm Unorm8x2;
+    [u8; 4] => Uint8x4, unorm Unorm8x4;
+    [i8; 2] => Sint8x2, snorm Snorm8x2;
+    [i8; 4] => Sint8x4, snorm Snorm8x4;
+    [u16; 2] => Uint16x2, unorm Unorm16x2;
+    [u16; 4] => Uint16x4, unorm Unorm16x4;
+    [i16; 2] => Sint16x2, snorm Snorm16x2;
+    [i16; 4] => Sint16x4, snorm Snorm16x4;
+    f32 => Float32;
+    [f32; 1] => Float32;
+    [f32; 2] => Float32x2;
+    [f32; 3] => Float32x3;
+    [f32; 4] => Float32x4;
+    u32 => Uint32;
+    [u32; 1] => Uint32;
+    [u32; 2] => Uint32x2;
+    [u32; 3] => Uint32x3;
+    [u32; 4] => Uint32x4;
+    i32 => Sint32;
+    [i32; 1] => Sint32;
+    [i32; 2] => Sint32x2;
+    [i32; 3] => Sint32x3;
+    [i32; 4] => Sint32x4;
+    f64 => Float64;
+    [f64; 1] => Float64;
+    [f64; 2] => Float64x2;
+    [f64; 3] => Float64x3;
+    [f64; 4] => Float64x4;
+}
+
+/// Vertex types read from a vertex buffer, with an attribute per field.
+///
+/// Can be derived for `#[repr(C)]` structs with the `macros` feature. The format of each
+/// field is given by its [`VertexAttributeType`], and fields are assigned consecutive
+/// shader locations starting at 0:
+///
+/// ```ignore
+/// #[repr(C)]
+/// #[derive(Clone, Copy, wgpu::util::VertexLayout)]
+/// struct Vertex {
+///     position: [f32; 3],
+///     #[unorm]
+///     color: [u8; 4],
+///     #[vertex(location = 4)]
+///     uv: [f32; 2],
+///     #[vertex(format = "Float16x2")]
+///     normal: [u16; 2],
+///     #[vertex(skip)]
+///     padding: u32,
+/// }
+/// ```
+///
+/// `#[unorm]` and `#[snorm]` read integers as normalized floats, `location` sets the
+/// location of a field, the next fields following it, `format` overrides the format of a
+/// field, and `skip` leaves a field out of the attributes.
+pub trait VertexLayout: Copy {
+    /// Attributes of the fields, with their offsets in the struct.
+    const ATTRIBUTES: &'static [VertexAttribute];
+    /// Size of the struct, which is the stride of the vertex buffers.
+    const STRIDE: BufferAddress;
+
+    /// Layout of a buffer of the type, stepped with `step_mode`.
+    fn layout(step_mode: VertexStepMode) -> VertexBufferLayout<'static> {
+        VertexBufferLayout {
+            array_stride: Self::STRIDE,
+            step_mode,
+            attributes: Self::ATTRIBUTES,
+        }
+    }
+
+    /// Check that a buffer of the type provides all the inputs of the vertex
+    /// `entry_point`, with formats the shader can read.
+    fn verify(entry_point: &EntryPointReflection) -> Result<(), VertexLayoutError> {
+        verify_vertex_buffers(&[Self::layout(VertexStepMode::Vertex)], entry_point)
+    }
+}
+
+/// Error returned when vertex buffer layouts don't match the inputs of a vertex entry
+/// point.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VertexLayoutError {
+    /// The entry point with this name isn't a vertex entry point.
+    NotVertex(String),
+    /// No attribute has the location of an input.
+    MissingAttribute {
+        /// Location of the input.
+        location: ShaderLocation,
+        /// Format of the input, as the shader sees it.
+        input: VertexFormat,
+    },
+    /// An attribute has a format the shader can't read as its input.
+    Mismatch {
+        /// Location of the input.
+        location: ShaderLocation,
+        /// Format of the attribute.
+        format: VertexFormat,
+        /// Format of the input, as the shader sees it.
+        input: VertexFormat,
+    },
+    /// Several attributes have the same location.
+    DuplicateLocation(ShaderLocation),
+}
+
+impl fmt::Display for VertexLayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            VertexLayoutError::NotVertex(ref name) => {
+                write!(f, "Entry point {:?} is not a vertex entry point", name)
+            }
+            VertexLayoutError::MissingAttribute { location, input } => write!(
+                f,
+                "No attribute for input {:?} at location {}",
+                input, location
+            ),
+            VertexLayoutError::Mismatch {
+                location,
+                format,
+                input,
+            } => write!(
+                f,
+                "Attribute {:?} at location {} can't be read as {:?}",
+                format, location, input
+            ),
+            VertexLayoutError::DuplicateLocation(location) => {
+                write!(f, "Several attributes at location {}", location)
+            }
+        }
+    }
+}
+
+impl error::Error for VertexLayoutError {}
+
+/// Kind of the components of a format, as the shader sees them: float, unsigned or
+/// signed integer.
+fn shader_kind(format: VertexFormat) -> u8 {
+    use VertexFormat as Vf;
+    match format {
+        Vf::Uint8x2 | Vf::Uint8x4 | Vf::Uint16x2 | Vf::Uint16x4 => 1,
+        Vf::Uint32 | Vf::Uint32x2 | Vf::Uint32x3 | Vf::Uint32x4 => 1,
+        Vf::Sint8x2 | Vf::Sint8x4 | Vf::Sint16x2 | Vf::Sint16x4 => 2,
+        Vf::Sint32 | Vf::Sint32x2 | Vf::Sint32x3 | Vf::Sint32x4 => 2,
+        _ => 0,
+    }
+}
+
+/// Check that the vertex `buffers` provide all the inputs of the vertex `entry_point`,
+/// with formats the shader can read, as pipeline creation does.
+///
+/// Attributes the entry point doesn't use are allowed.
+pub fn verify_vertex_buffers(
+    buffers: &[VertexBufferLayout],
+    entry_point: &EntryPointReflection,
+) -> Result<(), VertexLayoutError> {
+    if entry_point.stage != ShaderStages::VERTEX {
+        return Err(VertexLayoutError::NotVertex(entry_point.name.clone()));
+    }
+    let mut attributes = buffers
+        .iter()
+        .flat_map(|buffer| buffer.attributes.iter())
+        .collect::<Vec<_>>();
+    attributes.sort_by_key(|attribute| attribute.shader_location);
+    for pair in attributes.windows(2) {
+        if pair[0].shader_location == pair[1].shader_location {
+            return Err(VertexLayoutError::DuplicateLocation(
+                pair[0].shader_location,
+            ));
+        }
+    }
+
+    for input in entry_point.inputs.iter() {
+        let attribute = attributes
+            .iter()
+            .find(|attribute| attribute.shader_location == input.location)
+            .ok_or(VertexLayoutError::MissingAttribute {
+                location: input.location,
+                input: input.format,
+            })?;
+        if shader_kind(attribute.format) != shader_kind(input.format) {
+            return Err(VertexLayoutError::Mismatch {
+                location: input.location,
+                format: attribute.format,
+                input: input.format,
+            });
+        }
+    }
+    Ok(())
+}
+
+#[doc(hidden)]
+pub const fn vertex_round_up(value: usize, alignment: usize) -> usize {
+    (value + alignment - 1) / alignment * alignment
+}
+
+#[doc(hidden)]
+pub const fn vertex_normalized_format(format: Option<VertexFormat>) -> VertexFormat {
+    match format {
+        Some(format) => format,
+        None => panic!("The field type can't be read as normalized floats"),
+    }
+}
@@ -0,0 +1,280 @@
+use std::{
+    num::NonZeroU32,
+    sync::{Arc, Mutex},
+};
+
+use wgpu::util::{BlitError, BlitOptions, BlitRect, Blitter, Swizzle, TextureDownload};
+
+use crate::common::{initialize_test, TestParameters, TestingContext};
+
+fn create_texture(
+    ctx: &TestingContext,
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32,
+    usage: wgpu::TextureUsages,
+) -> wgpu::Texture {
+    ctx.device.create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage,
+    })
+}
+
+fn write_texels(
+    ctx: &TestingContext,
+    texture: &wgpu::Texture,
+    width: u32,
+    height: u32,
+    data: &[u8],
+) {
+    ctx.queue.write_texture(
+        texture.as_image_copy(),
+        data,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: NonZeroU32::new(data.len() as u32 / height),
+            rows_per_image: None,
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+    );
+}
+
+fn read_rgba8(ctx: &TestingContext, texture: &wgpu::Texture, width: u32, height: u32) -> Vec<u8> {
+    let result = Arc::new(Mutex::new(None));
+    let result_clone = Arc::clone(&result);
+    TextureDownload::read_texture(
+        &ctx.device,
+        &ctx.queue,
+        &texture.as_image_copy(),
+        wgpu::TextureFormat::Rgba8Unorm,
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        move |r| *result_clone.lock().unwrap() = Some(r.unwrap().to_vec()),
+    );
+    ctx.device.poll(wgpu::Maintain::Wait);
+    let data = result.lock().unwrap().take();
+    data.unwrap()
+}
+
+#[test]
+fn blit_convert_flip_swizzle() {
+    initialize_test(TestParameters::default(), |ctx| {
+        // 2x1 texels of Rgba16Float: (1, 0.5, 0, 1) and (0, 0, 1, 0.5)
+        const ONE: u16 = 0x3C00;
+        const HALF: u16 = 0x3800;
+        let src_data = [ONE, HALF, 0, ONE, 0, 0, ONE, HALF]
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect::<Vec<u8>>();
+        let src = create_texture(
+            &ctx,
+            wgpu::TextureFormat::Rgba16Float,
+            2,
+            1,
+            wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        );
+        write_texels(&ctx, &src, 2, 1, &src_data);
+        let dst = create_texture(
+            &ctx,
+            wgpu::TextureFormat::Rgba8Unorm,
+            2,
+            1,
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        );
+
+        let mut blitter = Blitter::new(&ctx.device);
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        blitter
+            .blit(
+                &ctx.device,
+                &mut encoder,
+                &src.create_view(&wgpu::TextureViewDescriptor::default()),
+                wgpu::TextureFormat::Rgba16Float,
+                BlitRect::full(2, 1),
+                &dst.create_view(&wgpu::TextureViewDescriptor::default()),
+                wgpu::TextureFormat::Rgba8Unorm,
+                BlitRect::full(2, 1),
+                &BlitOptions {
+                    flip_x: true,
+                    swizzle: [Swizzle::B, Swizzle::G, Swizzle::R, Swizzle::One],
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        ctx.queue.submit(Some(encoder.finish()));
+
+        assert_eq!(
+            read_rgba8(&ctx, &dst, 2, 1),
+            [255, 0, 0, 255, 0, 128, 255, 255]
+        );
+    })
+}
+
+#[test]
+fn blit_scale_into_rect() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let format = wgpu::TextureFormat::Rgba8Unorm;
+        let src = create_texture(
+            &ctx,
+            format,
+            2,
+            2,
+            wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        );
+        let red = [255, 0, 0, 255];
+        let green = [0, 255, 0, 255];
+        let texels = [[0, 0, 0, 255], red, green, [0, 0, 255, 255]];
+        write_texels(&ctx, &src, 2, 2, &texels.concat());
+        let dst = create_texture(
+            &ctx,
+            format,
+            4,
+            4,
+            wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::COPY_DST,
+        );
+        write_texels(&ctx, &dst, 4, 4, &[255; 4 * 4 * 4]);
+
+        let mut blitter = Blitter::new(&ctx.device);
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        // the right column of the source, scaled up 2x into the right half
+        blitter
+            .blit(
+                &ctx.device,
+                &mut encoder,
+                &src.create_view(&wgpu::TextureViewDescriptor::default()),
+                format,
+                BlitRect {
+                    x: 1,
+                    y: 0,
+                    width: 1,
+                    height: 2,
+                },
+                &dst.create_view(&wgpu::TextureViewDescriptor::default()),
+                format,
+                BlitRect {
+                    x: 2,
+                    y: 0,
+                    width: 2,
+                    height: 4,
+                },
+                &BlitOptions::default(),
+            )
+            .unwrap();
+        ctx.queue.submit(Some(encoder.finish()));
+
+        let white = [255; 4];
+        let blue = [0, 0, 255, 255];
+        let expected = [
+            [white, white, red, red],
+            [white, white, red, red],
+            [white, white, blue, blue],
+            [white, white, blue, blue],
+        ]
+        .concat()
+        .concat();
+        assert_eq!(read_rgba8(&ctx, &dst, 4, 4), expected);
+    })
+}
+
+#[test]
+fn blit_unsupported_formats() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let float = create_texture(
+            &ctx,
+            wgpu::TextureFormat::R32Float,
+            1,
+            1,
+            wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+        )
+        .create_view(&wgpu::TextureViewDescriptor::default());
+        let uint = create_texture(
+            &ctx,
+            wgpu::TextureFormat::R32Uint,
+            1,
+            1,
+            wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+        )
+        .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let mut blitter = Blitter::new(&ctx.device);
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        let mut blit = |src, src_format, dst, dst_format, filter| {
+            blitter.blit(
+                &ctx.device,
+                &mut encoder,
+                src,
+                src_format,
+                BlitRect::full(1, 1),
+                dst,
+                dst_format,
+                BlitRect::full(1, 1),
+                &BlitOptions {
+                    filter,
+                    ..Default::default()
+                },
+            )
+        };
+        assert_eq!(
+            blit(
+                &uint,
+                wgpu::TextureFormat::R32Uint,
+                &float,
+                wgpu::TextureFormat::R32Float,
+                wgpu::FilterMode::Nearest
+            ),
+            Err(BlitError::UnsupportedSourceFormat(
+                wgpu::TextureFormat::R32Uint
+            ))
+        );
+        assert_eq!(
+            blit(
+                &float,
+                wgpu::TextureFormat::R32Float,
+                &uint,
+                wgpu::TextureFormat::R32Uint,
+                wgpu::FilterMode::Nearest
+            ),
+            Err(BlitError::UnsupportedDestinationFormat(
+                wgpu::TextureFormat::R32Uint
+            ))
+        );
+        // 32-bit floats aren't filterable
+        assert_eq!(
+            blit(
+                &float,
+                wgpu::TextureFormat::R32Float,
+                &float,
+                wgpu::TextureFormat::R32Float,
+                wgpu::FilterMode::Linear
+            ),
+            Err(BlitError::UnsupportedSourceFormat(
+                wgpu::TextureFormat::R32Float
+            ))
+        );
+        ctx.queue.submit(Some(encoder.finish()));
+    })
+}
@@ -0,0 +1,170 @@
+use std::borrow::Cow;
+
+use wgpu::util::{StagingBelt, StorageRing, UniformRing};
+use wgpu::BufferSize;
+
+use crate::common::{initialize_test, TestParameters};
+
+const SHADER: &str = "
+@group(0) @binding(0) var<storage, read> input: array<u32, 4>;
+@group(0) @binding(1) var<storage, read_write> output: array<u32, 4>;
+
+@compute @workgroup_size(1)
+fn main() {
+    for (var i = 0u; i < 4u; i = i + 1u) {
+        output[i] = input[i];
+    }
+}
+";
+
+#[test]
+fn uniform_ring_reclaim() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let alignment = ctx.device.limits().min_uniform_buffer_offset_alignment;
+        let mut ring = UniformRing::new(&ctx.device, 4 * alignment as u64);
+        let size = BufferSize::new(16).unwrap();
+
+        assert_eq!(ring.allocate(size), Some(0));
+        assert_eq!(ring.allocate(size), Some(alignment));
+        assert_eq!(ring.allocate(size), Some(2 * alignment));
+        assert_eq!(ring.allocate(size), Some(3 * alignment));
+        assert_eq!(ring.allocate(size), None);
+        ctx.queue.submit(None);
+        ring.finish(&ctx.queue);
+
+        // the whole ring is reclaimed once the submission is done
+        ctx.device.poll(wgpu::Maintain::Wait);
+        let whole = BufferSize::new(4 * alignment as u64).unwrap();
+        assert_eq!(ring.allocate(whole), Some(0));
+        assert_eq!(ring.allocate(size), None);
+    })
+}
+
+#[test]
+fn storage_ring_writes() {
+    let parameters = TestParameters::default()
+        .downlevel_flags(wgpu::DownlevelFlags::COMPUTE_SHADERS)
+        .limits(wgpu::Limits::downlevel_defaults());
+    initialize_test(parameters, |ctx| {
+        let alignment = ctx.device.limits().min_storage_buffer_offset_alignment;
+        let mut ring = StorageRing::new(&ctx.device, 4 * alignment as u64);
+        let mut belt = StagingBelt::new(256);
+        let size = BufferSize::new(16).unwrap();
+
+        let bgl = ctx
+            .device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: None,
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: true,
+                            min_binding_size: Some(size),
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: false },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+        let module = ctx
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: None,
+                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(SHADER)),
+            });
+        let layout = ctx
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&bgl],
+                push_constant_ranges: &[],
+            });
+        let pipeline = ctx
+            .device
+            .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: None,
+                layout: Some(&layout),
+                module: &module,
+                entry_point: "main",
+            });
+
+        let output = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 16,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 32,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &bgl,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: ring.binding(size),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: output.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        let unused = ring
+            .write(&ctx.queue, bytemuck::cast_slice(&[9u32; 4]))
+            .unwrap();
+        let written = ring
+            .write_with(&ctx.queue, size, |view| {
+                view.copy_from_slice(bytemuck::cast_slice(&[1u32, 2, 3, 4]))
+            })
+            .unwrap();
+        let staged = ring
+            .write_staged(&mut belt, &mut encoder, &ctx.device, size, |view| {
+                view.copy_from_slice(bytemuck::cast_slice(&[5u32, 6, 7, 8]))
+            })
+            .unwrap();
+        assert_eq!([unused, written, staged], [0, alignment, 2 * alignment]);
+        belt.finish();
+
+        for (offset, destination) in [(written, 0), (staged, 16)] {
+            {
+                let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+                pass.set_pipeline(&pipeline);
+                pass.set_bind_group(0, &bind_group, &[offset]);
+                pass.dispatch_workgroups(1, 1, 1);
+            }
+            encoder.copy_buffer_to_buffer(&output, 0, &readback, destination, 16);
+        }
+        ctx.queue.submit(Some(encoder.finish()));
+        ring.finish(&ctx.queue);
+        belt.recall();
+
+        readback.slice(..).map_async(wgpu::MapMode::Read, |_| ());
+        ctx.device.poll(wgpu::Maintain::Wait);
+        let data = readback.slice(..).get_mapped_range();
+        assert_eq!(
+            bytemuck::cast_slice::<u8, u32>(&data),
+            [1, 2, 3, 4, 5, 6, 7, 8]
+        );
+    })
+}
@@ -0,0 +1,153 @@
+use std::borrow::Cow;
+use std::sync::{Arc, Mutex};
+
+use wgpu::util::{ComputeKernel, ComputeKernelError, DeviceExt};
+
+use crate::common::{initialize_test, TestParameters, TestingContext};
+
+const SHADER: &str = "
+@group(0) @binding(0) var<storage, read> input: array<u32>;
+@group(0) @binding(1) var<storage, read_write> output: array<u32>;
+@group(1) @binding(0) var<uniform> factor: vec4<u32>;
+@group(1) @binding(1) var<storage, read_write> unrelated: array<u32>;
+
+@compute @workgroup_size(8, 8)
+fn scale(
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    let workgroup_index = workgroup_id.x + (workgroup_id.y + workgroup_id.z * num_workgroups.y) * num_workgroups.x;
+    let index = workgroup_index * 64u + local_index;
+    if (index >= arrayLength(&output)) {
+        return;
+    }
+    output[index] = input[index] * factor.x;
+}
+
+@compute @workgroup_size(1)
+fn clear() {
+    unrelated[0] = 0u;
+}
+
+@vertex
+fn vs_main() -> @builtin(position) vec4<f32> {
+    return vec4<f32>(0.0);
+}
+";
+
+fn parameters() -> TestParameters {
+    TestParameters::default()
+        .downlevel_flags(wgpu::DownlevelFlags::COMPUTE_SHADERS)
+        .limits(wgpu::Limits::downlevel_defaults())
+}
+
+fn shader_module(ctx: &TestingContext) -> wgpu::ShaderModule {
+    ctx.device
+        .create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(SHADER)),
+        })
+}
+
+#[test]
+fn compute_kernel_grid() {
+    initialize_test(parameters(), |ctx| {
+        let module = shader_module(&ctx);
+        let kernel = ComputeKernel::new(&ctx.device, &module, "scale").unwrap();
+        assert_eq!(kernel.workgroup_size(), [8, 8, 1]);
+
+        let max = ctx.device.limits().max_compute_workgroups_per_dimension;
+        assert_eq!(kernel.grid(0), Ok([0, 0, 0]));
+        assert_eq!(kernel.grid(1), Ok([1, 1, 1]));
+        assert_eq!(kernel.grid(65), Ok([2, 1, 1]));
+        assert_eq!(kernel.grid(64 * max + 1), Ok([max, 2, 1]));
+
+        assert_eq!(
+            ComputeKernel::new(&ctx.device, &module, "missing").unwrap_err(),
+            ComputeKernelError::MissingEntryPoint("missing".to_string())
+        );
+        assert_eq!(
+            ComputeKernel::new(&ctx.device, &module, "vs_main").unwrap_err(),
+            ComputeKernelError::NotCompute("vs_main".to_string())
+        );
+    })
+}
+
+#[test]
+fn compute_kernel_run() {
+    initialize_test(parameters(), |ctx| {
+        let module = shader_module(&ctx);
+        let kernel = ComputeKernel::new(&ctx.device, &module, "scale").unwrap();
+
+        let data = (0..1000).collect::<Vec<u32>>();
+        let input = ctx
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents: bytemuck::cast_slice(&data),
+                usage: wgpu::BufferUsages::STORAGE,
+            });
+        let output = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 4000,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let factor = ctx
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents: bytemuck::cast_slice(&[3u32; 4]),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+
+        // `unrelated` is only used by the other entry point
+        assert_eq!(
+            kernel
+                .bind_groups(&ctx.device, &[("unrelated", output.as_entire_binding())])
+                .unwrap_err(),
+            ComputeKernelError::UnknownBinding("unrelated".to_string())
+        );
+        assert_eq!(
+            kernel
+                .bind_groups(
+                    &ctx.device,
+                    &[
+                        ("input", input.as_entire_binding()),
+                        ("output", output.as_entire_binding()),
+                    ]
+                )
+                .unwrap_err(),
+            ComputeKernelError::MissingBinding {
+                group: 1,
+                binding: 0
+            }
+        );
+
+        let result = Arc::new(Mutex::new(None));
+        let result_clone = Arc::clone(&result);
+        kernel
+            .run_and_read(
+                &ctx.device,
+                &ctx.queue,
+                &[
+                    ("input", input.as_entire_binding()),
+                    ("output", output.as_entire_binding()),
+                    ("factor", factor.as_entire_binding()),
+                ],
+                data.len() as u32,
+                &output.slice(..),
+                move |download| {
+                    let download = download.unwrap();
+                    let values: &[u32] = bytemuck::cast_slice(&download);
+                    *result_clone.lock().unwrap() = Some(values.to_vec());
+                },
+            )
+            .unwrap();
+        ctx.device.poll(wgpu::Maintain::Wait);
+
+        let expected = data.iter().map(|value| value * 3).collect::<Vec<_>>();
+        assert_eq!(result.lock().unwrap().take().unwrap(), expected);
+    })
+}
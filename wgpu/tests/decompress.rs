@@ -0,0 +1,257 @@
+use std::sync::{Arc, Mutex};
+
+use wgpu::util::{decompress, decompressed_format, DeviceExt, TextureDownload};
+
+use crate::common::{initialize_test, TestParameters};
+
+const BLOCK: wgpu::Extent3d = wgpu::Extent3d {
+    width: 4,
+    height: 4,
+    depth_or_array_layers: 1,
+};
+
+/// Writes values into a little endian 128 bit block, from its lowest bit.
+struct BitWriter {
+    value: u128,
+    position: u32,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        BitWriter {
+            value: 0,
+            position: 0,
+        }
+    }
+
+    fn write(&mut self, value: u32, count: u32) -> &mut Self {
+        self.value |= (value as u128) << self.position;
+        self.position += count;
+        self
+    }
+}
+
+fn texel(texels: &[u8], index: usize, size: usize) -> &[u8] {
+    &texels[index * size..(index + 1) * size]
+}
+
+#[test]
+fn decompress_formats() {
+    assert_eq!(
+        decompressed_format(wgpu::TextureFormat::Bc1RgbaUnormSrgb),
+        Some(wgpu::TextureFormat::Rgba8UnormSrgb)
+    );
+    assert_eq!(
+        decompressed_format(wgpu::TextureFormat::Bc6hRgbUfloat),
+        Some(wgpu::TextureFormat::Rgba16Float)
+    );
+    assert_eq!(
+        decompressed_format(wgpu::TextureFormat::EacRg11Snorm),
+        Some(wgpu::TextureFormat::Rg16Float)
+    );
+    let hdr = wgpu::TextureFormat::Astc {
+        block: wgpu::AstcBlock::B4x4,
+        channel: wgpu::AstcChannel::Hdr,
+    };
+    assert_eq!(decompressed_format(hdr), None);
+    assert_eq!(decompress(hdr, BLOCK, &[0; 16]), None);
+    assert_eq!(decompressed_format(wgpu::TextureFormat::Rgba8Unorm), None);
+}
+
+#[test]
+fn decompress_bc() {
+    // red and blue endpoints, all texels red
+    let bc1 = decompress(
+        wgpu::TextureFormat::Bc1RgbaUnorm,
+        BLOCK,
+        &[0x00, 0xF8, 0x1F, 0x00, 0, 0, 0, 0],
+    )
+    .unwrap();
+    assert_eq!(bc1, [255, 0, 0, 255].repeat(16));
+    // endpoints in increasing order select the transparent black texel
+    let bc1 = decompress(
+        wgpu::TextureFormat::Bc1RgbaUnorm,
+        BLOCK,
+        &[0x1F, 0x00, 0x00, 0xF8, 0xFF, 0xFF, 0xFF, 0xFF],
+    )
+    .unwrap();
+    assert_eq!(bc1, [0; 64]);
+    // blocks are cropped to the size of the image
+    let bc1 = decompress(
+        wgpu::TextureFormat::Bc1RgbaUnorm,
+        wgpu::Extent3d {
+            width: 6,
+            height: 1,
+            depth_or_array_layers: 1,
+        },
+        &[[0x00, 0xF8, 0x1F, 0x00, 0, 0, 0, 0], [0; 8]].concat(),
+    )
+    .unwrap();
+    assert_eq!(
+        bc1,
+        [[255, 0, 0, 255].repeat(4), [0, 0, 0, 255].repeat(2)].concat()
+    );
+
+    // the first texel selects the first endpoint, the second one the third value
+    let bc4 = decompress(
+        wgpu::TextureFormat::Bc4RUnorm,
+        BLOCK,
+        &[255, 0, 0b0001_0000, 0, 0, 0, 0, 0],
+    )
+    .unwrap();
+    assert_eq!(&bc4[..2], [255, 218]);
+
+    // mode 6, with a red and a green endpoint
+    let mut bc7 = BitWriter::new();
+    bc7.write(1 << 6, 7);
+    for value in [127, 0, 0, 127, 0, 0, 127, 127] {
+        bc7.write(value, 7);
+    }
+    bc7.write(1, 1).write(1, 1).write(0, 3);
+    for _ in 1..16 {
+        bc7.write(15, 4);
+    }
+    let bc7 = decompress(
+        wgpu::TextureFormat::Bc7RgbaUnorm,
+        BLOCK,
+        &bc7.value.to_le_bytes(),
+    )
+    .unwrap();
+    assert_eq!(texel(&bc7, 0, 4), [255, 1, 1, 255]);
+    assert_eq!(texel(&bc7, 15, 4), [1, 255, 1, 255]);
+
+    // mode 11, with raw 10 bit endpoints of 1.0 red and 1.0 green
+    let mut bc6h = BitWriter::new();
+    bc6h.write(0b00011, 5);
+    for value in [495, 0, 0, 0, 495, 0] {
+        bc6h.write(value, 10);
+    }
+    bc6h.write(0, 3);
+    for _ in 1..15 {
+        bc6h.write(0, 4);
+    }
+    bc6h.write(15, 4);
+    let bc6h = decompress(
+        wgpu::TextureFormat::Bc6hRgbUfloat,
+        BLOCK,
+        &bc6h.value.to_le_bytes(),
+    )
+    .unwrap();
+    let half = |bits: u16| bits.to_le_bytes();
+    assert_eq!(
+        texel(&bc6h, 0, 8),
+        [half(0x3C00), half(0), half(0), half(0x3C00)].concat()
+    );
+    assert_eq!(
+        texel(&bc6h, 15, 8),
+        [half(0), half(0x3C00), half(0), half(0x3C00)].concat()
+    );
+}
+
+#[test]
+fn decompress_etc2() {
+    // individual mode, with a red left half and a black right half, offset by 2
+    let etc2 = decompress(
+        wgpu::TextureFormat::Etc2Rgb8Unorm,
+        BLOCK,
+        &[0xF0, 0, 0, 0, 0, 0, 0, 0],
+    )
+    .unwrap();
+    assert_eq!(texel(&etc2, 0, 4), [255, 2, 2, 255]);
+    assert_eq!(texel(&etc2, 3, 4), [2, 2, 2, 255]);
+
+    // the largest base value with the largest modifier, clamped to 2047
+    let eac = decompress(
+        wgpu::TextureFormat::EacR11Unorm,
+        BLOCK,
+        &[255, 0, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF],
+    )
+    .unwrap();
+    assert_eq!(eac, 0x3C00u16.to_le_bytes().repeat(16));
+}
+
+#[test]
+fn decompress_astc() {
+    // void extent block of a single color
+    let color = [0xFFFFu128, 0x8000, 0, 0xFFFF];
+    let void_extent = 0xDFC
+        | color
+            .iter()
+            .enumerate()
+            .fold(0, |block, (i, c)| block | c << (64 + 16 * i));
+    let astc = wgpu::TextureFormat::Astc {
+        block: wgpu::AstcBlock::B4x4,
+        channel: wgpu::AstcChannel::Unorm,
+    };
+    let texels = decompress(astc, BLOCK, &void_extent.to_le_bytes()).unwrap();
+    assert_eq!(texels, [255, 128, 0, 255].repeat(16));
+
+    // a 4x4 grid of 2 bit weights, with black and white direct RGB endpoints,
+    // and all the weights but the first one selecting white
+    let mut block = BitWriter::new();
+    block.write(66, 11).write(0, 2).write(8, 4);
+    for value in [0, 255, 0, 255, 0, 255] {
+        block.write(value, 8);
+    }
+    let block = block.value | ((1 << 30) - 1) << 96;
+    let texels = decompress(astc, BLOCK, &block.to_le_bytes()).unwrap();
+    assert_eq!(texel(&texels, 0, 4), [0, 0, 0, 255]);
+    assert_eq!(&texels[4..], [255; 60]);
+
+    // HDR endpoints decode to magenta
+    let mut block = BitWriter::new();
+    block.write(66, 11).write(0, 2).write(11, 4);
+    let texels = decompress(astc, BLOCK, &block.value.to_le_bytes()).unwrap();
+    assert_eq!(texels, [255, 0, 255, 255].repeat(16));
+}
+
+#[test]
+fn decompress_upload_fallback() {
+    initialize_test(TestParameters::default(), |ctx| {
+        // a red and a blue block
+        let mut data = vec![0x00, 0xF8, 0x1F, 0x00, 0, 0, 0, 0];
+        data.extend_from_slice(&[0x1F, 0x00, 0x00, 0xF8, 0, 0, 0, 0]);
+        let size = wgpu::Extent3d {
+            width: 8,
+            height: 4,
+            depth_or_array_layers: 1,
+        };
+        let (texture, fallback) = ctx.device.create_texture_with_fallback(
+            &ctx.queue,
+            &wgpu::TextureDescriptor {
+                label: None,
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Bc1RgbaUnorm,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_SRC,
+            },
+            &data,
+        );
+        if ctx
+            .device
+            .features()
+            .contains(wgpu::Features::TEXTURE_COMPRESSION_BC)
+        {
+            assert_eq!(fallback, None);
+            return;
+        }
+        assert_eq!(fallback, Some(wgpu::TextureFormat::Rgba8Unorm));
+
+        let result = Arc::new(Mutex::new(None));
+        let result_clone = Arc::clone(&result);
+        TextureDownload::read_texture(
+            &ctx.device,
+            &ctx.queue,
+            &texture.as_image_copy(),
+            wgpu::TextureFormat::Rgba8Unorm,
+            size,
+            move |r| *result_clone.lock().unwrap() = Some(r.unwrap().to_vec()),
+        );
+        ctx.device.poll(wgpu::Maintain::Wait);
+        let texels = result.lock().unwrap().take().unwrap();
+        let row = [[255, 0, 0, 255].repeat(4), [0, 0, 255, 255].repeat(4)].concat();
+        assert_eq!(texels, row.repeat(4));
+    })
+}
@@ -0,0 +1,230 @@
+use std::{
+    num::NonZeroU32,
+    sync::{Arc, Mutex},
+};
+
+use wgpu::util::{MipmapError, MipmapGenerator, TextureDownload};
+
+use crate::common::{initialize_test, TestParameters, TestingContext};
+
+const SIZE: u32 = 8;
+const LAYERS: u32 = 2;
+
+/// Creates a texture array with every layer of the first mip level filled with
+/// a black and white checkerboard, and generates the mipmaps.
+fn generate_checkerboard(
+    ctx: &TestingContext,
+    format: wgpu::TextureFormat,
+    usage: wgpu::TextureUsages,
+    gamma: f32,
+) -> wgpu::Texture {
+    let desc = wgpu::TextureDescriptor {
+        label: Some("mipmapped"),
+        size: wgpu::Extent3d {
+            width: SIZE,
+            height: SIZE,
+            depth_or_array_layers: LAYERS,
+        },
+        mip_level_count: 4,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: usage
+            | wgpu::TextureUsages::TEXTURE_BINDING
+            | wgpu::TextureUsages::COPY_DST
+            | wgpu::TextureUsages::COPY_SRC,
+    };
+    let texture = ctx.device.create_texture(&desc);
+
+    let checkerboard = (0..SIZE * SIZE)
+        .flat_map(|i| {
+            let value = if (i % SIZE + i / SIZE) % 2 == 0 {
+                0
+            } else {
+                255
+            };
+            [value, value, value, 255]
+        })
+        .collect::<Vec<u8>>();
+    for layer in 0..LAYERS {
+        ctx.queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: 0,
+                    y: 0,
+                    z: layer,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            &checkerboard,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(SIZE * 4),
+                rows_per_image: None,
+            },
+            wgpu::Extent3d {
+                width: SIZE,
+                height: SIZE,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    let mut generator = MipmapGenerator::new(&ctx.device);
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    generator
+        .generate_with_gamma(&ctx.device, &mut encoder, &texture, &desc, gamma)
+        .unwrap();
+    ctx.queue.submit(Some(encoder.finish()));
+    texture
+}
+
+/// Reads back the last mip level of the `layer`, which is 1x1.
+fn read_last_mip(
+    ctx: &TestingContext,
+    texture: &wgpu::Texture,
+    format: wgpu::TextureFormat,
+    layer: u32,
+) -> [u8; 4] {
+    let result = Arc::new(Mutex::new(None));
+    let result_clone = Arc::clone(&result);
+    TextureDownload::read_texture(
+        &ctx.device,
+        &ctx.queue,
+        &wgpu::ImageCopyTexture {
+            texture,
+            mip_level: 3,
+            origin: wgpu::Origin3d {
+                x: 0,
+                y: 0,
+                z: layer,
+            },
+            aspect: wgpu::TextureAspect::All,
+        },
+        format,
+        wgpu::Extent3d {
+            width: 1,
+            height: 1,
+            depth_or_array_layers: 1,
+        },
+        move |r| *result_clone.lock().unwrap() = Some(r.unwrap().to_vec()),
+    );
+    ctx.device.poll(wgpu::Maintain::Wait);
+    let data = result.lock().unwrap().take().unwrap();
+    [data[0], data[1], data[2], data[3]]
+}
+
+/// Checks that the last mip level of every layer is close to the `expected` gray.
+fn assert_gray(
+    ctx: &TestingContext,
+    texture: &wgpu::Texture,
+    format: wgpu::TextureFormat,
+    expected: u8,
+) {
+    for layer in 0..LAYERS {
+        let texel = read_last_mip(ctx, texture, format, layer);
+        for &channel in &texel[..3] {
+            assert!(
+                (channel as i32 - expected as i32).abs() <= 2,
+                "Texel {:?} of layer {} is not close to gray {}",
+                texel,
+                layer,
+                expected
+            );
+        }
+        assert_eq!(texel[3], 255);
+    }
+}
+
+// The GL backend can't sample from views with a non-zero base mip level or layer.
+
+#[test]
+fn mipmap_render() {
+    initialize_test(
+        TestParameters::default().backend_failure(wgpu::Backends::GL),
+        |ctx| {
+            let format = wgpu::TextureFormat::Rgba8Unorm;
+            let usage = wgpu::TextureUsages::RENDER_ATTACHMENT;
+            let texture = generate_checkerboard(&ctx, format, usage, 1.0);
+            assert_gray(&ctx, &texture, format, 128);
+        },
+    )
+}
+
+#[test]
+fn mipmap_compute() {
+    initialize_test(
+        TestParameters::default()
+            .downlevel_flags(wgpu::DownlevelFlags::COMPUTE_SHADERS)
+            .limits(wgpu::Limits::downlevel_defaults())
+            .backend_failure(wgpu::Backends::GL),
+        |ctx| {
+            let format = wgpu::TextureFormat::Rgba8Unorm;
+            let usage = wgpu::TextureUsages::STORAGE_BINDING;
+            let texture = generate_checkerboard(&ctx, format, usage, 1.0);
+            assert_gray(&ctx, &texture, format, 128);
+        },
+    )
+}
+
+#[test]
+fn mipmap_srgb_and_gamma() {
+    initialize_test(
+        TestParameters::default().backend_failure(wgpu::Backends::GL),
+        |ctx| {
+            let usage = wgpu::TextureUsages::RENDER_ATTACHMENT;
+
+            // half of the linear intensity, encoded as sRGB
+            let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+            let texture = generate_checkerboard(&ctx, format, usage, 1.0);
+            assert_gray(&ctx, &texture, format, 188);
+
+            // half of the linear intensity, encoded with the gamma of 2.2
+            let format = wgpu::TextureFormat::Rgba8Unorm;
+            let texture = generate_checkerboard(&ctx, format, usage, 2.2);
+            assert_gray(&ctx, &texture, format, 186);
+        },
+    )
+}
+
+#[test]
+fn mipmap_errors() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let mut desc = wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: SIZE,
+                height: SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 4,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R32Uint,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+        };
+        let texture = ctx.device.create_texture(&desc);
+        let mut generator = MipmapGenerator::new(&ctx.device);
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        assert_eq!(
+            generator.generate(&ctx.device, &mut encoder, &texture, &desc),
+            Err(MipmapError::UnfilterableFormat(desc.format))
+        );
+
+        desc.format = wgpu::TextureFormat::Rgba8Unorm;
+        desc.usage = wgpu::TextureUsages::TEXTURE_BINDING;
+        let texture = ctx.device.create_texture(&desc);
+        assert_eq!(
+            generator.generate(&ctx.device, &mut encoder, &texture, &desc),
+            Err(MipmapError::MissingUsages(
+                wgpu::TextureUsages::RENDER_ATTACHMENT
+            ))
+        );
+    })
+}
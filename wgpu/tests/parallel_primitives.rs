@@ -0,0 +1,294 @@
+use wgpu::util::compute::{Element, ParallelPrimitives, ReduceOp, ScanKind};
+use wgpu::util::DeviceExt;
+
+use crate::common::{initialize_test, TestParameters, TestingContext};
+
+fn parameters() -> TestParameters {
+    TestParameters::default()
+        .downlevel_flags(wgpu::DownlevelFlags::COMPUTE_SHADERS)
+        .limits(wgpu::Limits::downlevel_defaults())
+}
+
+/// Deterministic pseudo-random numbers.
+fn random_u32s(len: usize, seed: u32) -> Vec<u32> {
+    let mut state = seed;
+    (0..len)
+        .map(|_| {
+            state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+            state
+        })
+        .collect()
+}
+
+fn storage_buffer<T: bytemuck::Pod>(ctx: &TestingContext, data: &[T]) -> wgpu::Buffer {
+    ctx.device
+        .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(data),
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
+        })
+}
+
+fn read_buffer<T: bytemuck::Pod>(
+    ctx: &TestingContext,
+    buffer: &wgpu::Buffer,
+    len: usize,
+) -> Vec<T> {
+    let size = (len * 4) as wgpu::BufferAddress;
+    let readback = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    encoder.copy_buffer_to_buffer(buffer, 0, &readback, 0, size);
+    ctx.queue.submit(Some(encoder.finish()));
+    readback.slice(..).map_async(wgpu::MapMode::Read, |_| ());
+    ctx.device.poll(wgpu::Maintain::Wait);
+    let data = readback.slice(..).get_mapped_range();
+    bytemuck::cast_slice(&data).to_vec()
+}
+
+fn gpu_scan<T: Element + bytemuck::Pod>(
+    ctx: &TestingContext,
+    primitives: &mut ParallelPrimitives,
+    data: &[T],
+    op: ReduceOp,
+    kind: ScanKind,
+) -> Vec<T> {
+    let input = storage_buffer(ctx, data);
+    let output = storage_buffer(ctx, &vec![T::zeroed(); data.len()]);
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    primitives.scan::<T>(
+        &ctx.device,
+        &mut encoder,
+        &input,
+        &output,
+        data.len() as u32,
+        op,
+        kind,
+    );
+    ctx.queue.submit(Some(encoder.finish()));
+    read_buffer(ctx, &output, data.len())
+}
+
+fn gpu_reduce<T: Element + bytemuck::Pod>(
+    ctx: &TestingContext,
+    primitives: &mut ParallelPrimitives,
+    data: &[T],
+    op: ReduceOp,
+) -> T {
+    let input = storage_buffer(ctx, data);
+    let output = storage_buffer(ctx, &[T::zeroed()]);
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    primitives.reduce::<T>(
+        &ctx.device,
+        &mut encoder,
+        &input,
+        &output,
+        data.len() as u32,
+        op,
+    );
+    ctx.queue.submit(Some(encoder.finish()));
+    read_buffer(ctx, &output, 1)[0]
+}
+
+#[test]
+fn parallel_primitives_scan() {
+    initialize_test(parameters(), |ctx| {
+        let mut primitives = ParallelPrimitives::new(&ctx.device);
+        let workgroup_size = primitives.workgroup_size() as usize;
+        assert!(workgroup_size.is_power_of_two());
+        // three levels of blocks
+        let len = workgroup_size * workgroup_size + 37;
+
+        let data = random_u32s(len, 1);
+        let mut sum = 0u32;
+        let expected = data
+            .iter()
+            .map(|&value| {
+                let exclusive = sum;
+                sum = sum.wrapping_add(value);
+                exclusive
+            })
+            .collect::<Vec<_>>();
+        let result = gpu_scan(
+            &ctx,
+            &mut primitives,
+            &data,
+            ReduceOp::Sum,
+            ScanKind::Exclusive,
+        );
+        assert_eq!(result, expected);
+
+        let data = random_u32s(len, 2)
+            .into_iter()
+            .map(|value| value as i32)
+            .collect::<Vec<_>>();
+        let mut max = i32::MIN;
+        let expected = data
+            .iter()
+            .map(|&value| {
+                max = max.max(value);
+                max
+            })
+            .collect::<Vec<_>>();
+        let result = gpu_scan(
+            &ctx,
+            &mut primitives,
+            &data,
+            ReduceOp::Max,
+            ScanKind::Inclusive,
+        );
+        assert_eq!(result, expected);
+
+        let data = random_u32s(workgroup_size + 1, 3)
+            .into_iter()
+            .map(|value| (value >> 8) as f32)
+            .collect::<Vec<_>>();
+        let mut min = f32::INFINITY;
+        let expected = data
+            .iter()
+            .map(|&value| {
+                let exclusive = min;
+                min = min.min(value);
+                exclusive
+            })
+            .collect::<Vec<_>>();
+        let result = gpu_scan(
+            &ctx,
+            &mut primitives,
+            &data,
+            ReduceOp::Min,
+            ScanKind::Exclusive,
+        );
+        assert_eq!(result, expected);
+    })
+}
+
+#[test]
+fn parallel_primitives_reduce() {
+    initialize_test(parameters(), |ctx| {
+        let mut primitives = ParallelPrimitives::new(&ctx.device);
+        let workgroup_size = primitives.workgroup_size() as usize;
+        let len = workgroup_size * workgroup_size + 37;
+
+        let data = random_u32s(len, 4);
+        let sum = data
+            .iter()
+            .fold(0u32, |sum, &value| sum.wrapping_add(value));
+        assert_eq!(gpu_reduce(&ctx, &mut primitives, &data, ReduceOp::Sum), sum);
+
+        let data = data.iter().map(|&value| value as i32).collect::<Vec<_>>();
+        let min = data.iter().copied().min().unwrap();
+        assert_eq!(gpu_reduce(&ctx, &mut primitives, &data, ReduceOp::Min), min);
+
+        let data = data
+            .iter()
+            .map(|&value| (value >> 8) as f32)
+            .collect::<Vec<_>>();
+        let max = data.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        assert_eq!(gpu_reduce(&ctx, &mut primitives, &data, ReduceOp::Max), max);
+
+        // the reduction of nothing is the identity
+        let input = storage_buffer(&ctx, &[1u32]);
+        let output = storage_buffer(&ctx, &[1u32]);
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        primitives.reduce::<u32>(&ctx.device, &mut encoder, &input, &output, 0, ReduceOp::Min);
+        ctx.queue.submit(Some(encoder.finish()));
+        assert_eq!(read_buffer::<u32>(&ctx, &output, 1), [u32::MAX]);
+    })
+}
+
+#[test]
+fn parallel_primitives_compact() {
+    initialize_test(parameters(), |ctx| {
+        let mut primitives = ParallelPrimitives::new(&ctx.device);
+        let len = 3 * primitives.workgroup_size() as usize + 5;
+
+        let data = random_u32s(len, 5);
+        let flags = data.iter().map(|&value| value % 3).collect::<Vec<_>>();
+        let expected = data
+            .iter()
+            .zip(&flags)
+            .filter(|&(_, &flag)| flag != 0)
+            .map(|(&value, _)| value)
+            .collect::<Vec<_>>();
+
+        let input = storage_buffer(&ctx, &data);
+        let flags = storage_buffer(&ctx, &flags);
+        let output = storage_buffer(&ctx, &vec![0u32; len]);
+        let count = storage_buffer(&ctx, &[u32::MAX]);
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        primitives.compact(
+            &ctx.device,
+            &mut encoder,
+            &input,
+            &flags,
+            &output,
+            &count,
+            len as u32,
+        );
+        ctx.queue.submit(Some(encoder.finish()));
+
+        let count = read_buffer::<u32>(&ctx, &count, 1)[0] as usize;
+        assert_eq!(count, expected.len());
+        assert_eq!(read_buffer::<u32>(&ctx, &output, count), expected);
+    })
+}
+
+#[test]
+fn parallel_primitives_sort() {
+    initialize_test(parameters(), |ctx| {
+        let mut primitives = ParallelPrimitives::new(&ctx.device);
+        let len = 4 * primitives.workgroup_size() as usize + 11;
+
+        // few distinct low bits, to check the sort is stable
+        let keys = random_u32s(len, 6)
+            .into_iter()
+            .map(|key| key & 0xff00_0007)
+            .collect::<Vec<_>>();
+        let values = (0..len as u32).collect::<Vec<_>>();
+        let mut expected = keys
+            .iter()
+            .copied()
+            .zip(values.iter().copied())
+            .collect::<Vec<_>>();
+        expected.sort_by_key(|&(key, _)| key);
+
+        let key_buffer = storage_buffer(&ctx, &keys);
+        let value_buffer = storage_buffer(&ctx, &values);
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        primitives.sort(
+            &ctx.device,
+            &mut encoder,
+            &key_buffer,
+            &value_buffer,
+            len as u32,
+        );
+        ctx.queue.submit(Some(encoder.finish()));
+
+        let sorted_keys = read_buffer::<u32>(&ctx, &key_buffer, len);
+        let sorted_values = read_buffer::<u32>(&ctx, &value_buffer, len);
+        let result = sorted_keys
+            .into_iter()
+            .zip(sorted_values)
+            .collect::<Vec<_>>();
+        assert_eq!(result, expected);
+    })
+}
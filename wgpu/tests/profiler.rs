@@ -0,0 +1,163 @@
+use wgpu::util::{GpuProfiler, GpuProfilerError, GpuTimerScopeResult};
+
+use crate::common::{initialize_test, TestParameters};
+
+/// Records a frame of nested scopes, with a compute pass in the middle, and submits it.
+fn record_frame(ctx: &crate::common::TestingContext, profiler: &mut GpuProfiler) {
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    profiler.begin_scope("frame", &mut encoder, &ctx.device);
+    profiler.begin_pass_scope("pass", &mut encoder, &ctx.device);
+    {
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+        profiler.begin_scope("inside", &mut pass, &ctx.device);
+        profiler.end_scope(&mut pass);
+    }
+    profiler.end_scope(&mut encoder);
+    profiler.end_scope(&mut encoder);
+    profiler.resolve_queries(&mut encoder);
+    ctx.queue.submit(Some(encoder.finish()));
+    profiler.end_frame().unwrap();
+}
+
+#[test]
+fn profiler_timestamps() {
+    initialize_test(
+        TestParameters::default().features(wgpu::Features::TIMESTAMP_QUERY),
+        |ctx| {
+            let mut profiler = GpuProfiler::new(&ctx.device, &ctx.queue, 4);
+            // the second frame reuses the pools of the first one
+            for _ in 0..2 {
+                record_frame(&ctx, &mut profiler);
+                ctx.device.poll(wgpu::Maintain::Wait);
+                let scopes = profiler.process_finished_frame().unwrap();
+                assert_eq!(scopes.len(), 1);
+                let frame = &scopes[0];
+                assert_eq!(frame.label, "frame");
+                let time = frame.time.clone().unwrap();
+                assert!(time.start <= time.end);
+                let pass = &frame.nested_scopes[0];
+                assert_eq!(pass.label, "pass");
+                // without timestamps inside passes, the scope gets the ones of the pass
+                let inside = &pass.nested_scopes[0];
+                assert_eq!(inside.label, "inside");
+                assert!(inside.time.is_some());
+                assert_eq!(inside.pipeline_statistics, None);
+                assert!(profiler.process_finished_frame().is_none());
+            }
+        },
+    )
+}
+
+#[test]
+fn profiler_frames() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let mut profiler = GpuProfiler::new(&ctx.device, &ctx.queue, 1);
+        let timestamps = ctx
+            .device
+            .features()
+            .contains(wgpu::Features::TIMESTAMP_QUERY);
+
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        profiler.begin_scope("unclosed", &mut encoder, &ctx.device);
+        assert_eq!(
+            profiler.end_frame(),
+            Err(GpuProfilerError::UnclosedScopes(1))
+        );
+
+        record_frame(&ctx, &mut profiler);
+        // the previous frame wasn't processed yet
+        profiler.begin_scope("dropped", &mut encoder, &ctx.device);
+        profiler.end_scope(&mut encoder);
+        profiler.resolve_queries(&mut encoder);
+        assert_eq!(
+            profiler.end_frame(),
+            Err(GpuProfilerError::TooManyPendingFrames)
+        );
+        ctx.queue.submit(Some(encoder.finish()));
+
+        ctx.device.poll(wgpu::Maintain::Wait);
+        let scopes = profiler.process_finished_frame().unwrap();
+        assert_eq!(scopes.len(), timestamps as usize);
+        assert!(profiler.process_finished_frame().is_none());
+    })
+}
+
+#[test]
+fn profiler_pipeline_statistics() {
+    initialize_test(
+        TestParameters::default().features(wgpu::Features::PIPELINE_STATISTICS_QUERY),
+        |ctx| {
+            let mut profiler = GpuProfiler::new(&ctx.device, &ctx.queue, 4);
+            let module = ctx
+                .device
+                .create_shader_module(wgpu::ShaderModuleDescriptor {
+                    label: None,
+                    source: wgpu::ShaderSource::Wgsl(
+                        "@compute @workgroup_size(4) fn main() {}".into(),
+                    ),
+                });
+            let pipeline = ctx
+                .device
+                .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                    label: None,
+                    layout: None,
+                    module: &module,
+                    entry_point: "main",
+                });
+
+            let mut encoder = ctx
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+            {
+                let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+                pass.set_pipeline(&pipeline);
+                profiler.begin_pipeline_statistics_scope("dispatch", &mut pass, &ctx.device);
+                pass.dispatch_workgroups(2, 1, 1);
+                profiler.end_pipeline_statistics_scope(&mut pass);
+            }
+            profiler.resolve_queries(&mut encoder);
+            ctx.queue.submit(Some(encoder.finish()));
+            profiler.end_frame().unwrap();
+
+            ctx.device.poll(wgpu::Maintain::Wait);
+            let scopes = profiler.process_finished_frame().unwrap();
+            assert_eq!(scopes.len(), 1);
+            assert_eq!(scopes[0].label, "dispatch");
+            let statistics = scopes[0].pipeline_statistics.unwrap();
+            assert_eq!(statistics.compute_shader_invocations, 8);
+        },
+    )
+}
+
+#[test]
+fn profiler_chrome_trace() {
+    let scopes = [GpuTimerScopeResult {
+        label: "frame \"1\"".to_string(),
+        time: Some(1000.0..5000.0),
+        pipeline_statistics: None,
+        nested_scopes: vec![GpuTimerScopeResult {
+            label: "untimed".to_string(),
+            time: None,
+            pipeline_statistics: Some(wgpu::util::PipelineStatistics::default()),
+            nested_scopes: vec![GpuTimerScopeResult {
+                label: "shadows".to_string(),
+                time: Some(1500.0..2000.0),
+                pipeline_statistics: None,
+                nested_scopes: Vec::new(),
+            }],
+        }],
+    }];
+    assert_eq!(
+        GpuProfiler::chrome_trace(&scopes),
+        concat!(
+            "{\"traceEvents\":[",
+            "{\"name\":\"frame \\\"1\\\"\",\"ph\":\"X\",\"ts\":1,\"dur\":4,\"pid\":0,\"tid\":0},",
+            "{\"name\":\"shadows\",\"ph\":\"X\",\"ts\":1.5,\"dur\":0.5,\"pid\":0,\"tid\":0}",
+            "]}"
+        )
+    );
+}
@@ -0,0 +1,70 @@
+use std::sync::{Arc, Mutex};
+
+use wgpu::util::{DeviceExt, ReadbackBelt};
+
+use crate::common::{initialize_test, TestParameters};
+
+#[test]
+fn readback_belt_regions() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let contents = (0..256u32).collect::<Vec<_>>();
+        let source = ctx
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("source"),
+                contents: bytemuck::cast_slice(&contents),
+                usage: wgpu::BufferUsages::COPY_SRC,
+            });
+
+        let mut belt = ReadbackBelt::new(64);
+        let received = Arc::new(Mutex::new(Vec::new()));
+        // two frames, the second one reusing the chunks of the first, which were
+        // delivered when polling the device
+        let mut chunk_count = None;
+        for _ in 0..2 {
+            let mut encoder = ctx
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+            for &(offset, size) in &[(0, 16), (100, 12), (512, 128), (1020, 4)] {
+                let received = Arc::clone(&received);
+                belt.read_buffer(
+                    &mut encoder,
+                    &source,
+                    offset,
+                    wgpu::BufferSize::new(size).unwrap(),
+                    &ctx.device,
+                    move |result| {
+                        let data = result.unwrap().to_vec();
+                        received.lock().unwrap().push((offset, data));
+                    },
+                );
+            }
+            let future = belt.read_buffer_async(
+                &mut encoder,
+                &source,
+                8,
+                wgpu::BufferSize::new(8).unwrap(),
+                &ctx.device,
+            );
+            match chunk_count {
+                None => chunk_count = Some(belt.chunk_count()),
+                Some(count) => assert_eq!(belt.chunk_count(), count),
+            }
+            belt.finish();
+            ctx.queue.submit(Some(encoder.finish()));
+            belt.recall();
+            ctx.device.poll(wgpu::Maintain::Wait);
+
+            let data = pollster::block_on(future).unwrap();
+            assert_eq!(data, bytemuck::cast_slice::<_, u8>(&contents[2..4]));
+
+            let mut received = received.lock().unwrap();
+            assert_eq!(received.len(), 4);
+            for (offset, data) in received.drain(..) {
+                let start = offset as usize / 4;
+                let expected = &contents[start..start + data.len() / 4];
+                assert_eq!(data, bytemuck::cast_slice::<_, u8>(expected));
+            }
+        }
+    })
+}
@@ -0,0 +1,173 @@
+use std::sync::{Arc, Mutex};
+
+use wgpu::util::{RenderGraph, RenderGraphError, ResourcePool, TextureDownload};
+
+use crate::common::{initialize_test, TestParameters};
+
+const SIZE: wgpu::Extent3d = wgpu::Extent3d {
+    width: 4,
+    height: 4,
+    depth_or_array_layers: 1,
+};
+
+fn texture_descriptor(label: &str) -> wgpu::TextureDescriptor<'_> {
+    wgpu::TextureDescriptor {
+        label: Some(label),
+        size: SIZE,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+            | wgpu::TextureUsages::COPY_SRC
+            | wgpu::TextureUsages::COPY_DST,
+    }
+}
+
+fn copy(encoder: &mut wgpu::CommandEncoder, source: &wgpu::Texture, destination: &wgpu::Texture) {
+    encoder.copy_texture_to_texture(source.as_image_copy(), destination.as_image_copy(), SIZE);
+}
+
+#[test]
+fn render_graph_execute() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let output = ctx.device.create_texture(&texture_descriptor("output"));
+        let mut pool = ResourcePool::new(2, None);
+
+        for _ in 0..2 {
+            let mut graph = RenderGraph::new();
+            let output = graph.import_texture("output", &output);
+            let first = graph.create_texture(&texture_descriptor("first"));
+            let second = graph.create_texture(&texture_descriptor("second"));
+            let third = graph.create_texture(&texture_descriptor("third"));
+            let unused = graph.create_texture(&texture_descriptor("unused"));
+
+            // added out of order, to be sorted
+            graph
+                .add_pass("present")
+                .read_texture(third)
+                .write_texture(output)
+                .record(move |encoder, resources| {
+                    copy(encoder, resources.texture(third), resources.texture(output))
+                });
+            graph
+                .add_pass("blur")
+                .read_texture(second)
+                .write_texture(third)
+                .record(move |encoder, resources| {
+                    copy(encoder, resources.texture(second), resources.texture(third))
+                });
+            graph
+                .add_pass("clear")
+                .write_texture(first)
+                .record(move |encoder, resources| {
+                    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: None,
+                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                            view: resources.view(first),
+                            resolve_target: None,
+                            ops: wgpu::Operations {
+                                load: wgpu::LoadOp::Clear(wgpu::Color {
+                                    r: 0.2,
+                                    g: 0.4,
+                                    b: 0.6,
+                                    a: 1.0,
+                                }),
+                                store: true,
+                            },
+                        })],
+                        depth_stencil_attachment: None,
+                    });
+                });
+            graph
+                .add_pass("unused")
+                .write_texture(unused)
+                .record(|_, _| panic!("culled pass recorded"));
+            graph
+                .add_pass("copy")
+                .read_texture(first)
+                .write_texture(second)
+                .record(move |encoder, resources| {
+                    copy(encoder, resources.texture(first), resources.texture(second))
+                });
+
+            assert_eq!(
+                graph.pass_order().unwrap(),
+                ["clear", "copy", "blur", "present"]
+            );
+            let command_buffer = graph.execute(&ctx.device, &mut pool).unwrap();
+            ctx.queue.submit(Some(command_buffer));
+            pool.end_frame(&ctx.queue);
+            ctx.device.poll(wgpu::Maintain::Wait);
+            // the third texture aliases the first one, and the second frame reuses both
+            let stats = pool.stats();
+            assert_eq!(stats.textures, 2);
+            assert_eq!(stats.misses, 2);
+        }
+        assert_eq!(pool.stats().hits, 2);
+
+        let result = Arc::new(Mutex::new(None));
+        let result_clone = Arc::clone(&result);
+        TextureDownload::read_texture(
+            &ctx.device,
+            &ctx.queue,
+            &output.as_image_copy(),
+            wgpu::TextureFormat::Rgba8Unorm,
+            SIZE,
+            move |download| {
+                *result_clone.lock().unwrap() = Some(download.unwrap().to_rgba8().unwrap());
+            },
+        );
+        ctx.device.poll(wgpu::Maintain::Wait);
+        let pixels = result.lock().unwrap().take().unwrap();
+        assert_eq!(pixels, [51, 102, 153, 255].repeat(16));
+    })
+}
+
+#[test]
+fn render_graph_errors() {
+    let mut graph = RenderGraph::new();
+    let first = graph.create_texture(&texture_descriptor("first"));
+    let second = graph.create_texture(&texture_descriptor("second"));
+    graph
+        .add_pass("a")
+        .read_texture(second)
+        .write_texture(first)
+        .keep()
+        .record(|_, _| ());
+    graph
+        .add_pass("b")
+        .read_texture(first)
+        .write_texture(second)
+        .record(|_, _| ());
+    assert_eq!(
+        graph.pass_order(),
+        Err(RenderGraphError::Cycle(vec![
+            "a".to_string(),
+            "b".to_string()
+        ]))
+    );
+
+    let mut graph = RenderGraph::new();
+    let first = graph.create_texture(&texture_descriptor("first"));
+    let second = graph.create_texture(&texture_descriptor("second"));
+    graph
+        .add_pass("a")
+        .read_texture(first)
+        .write_texture(second)
+        .keep()
+        .record(|_, _| ());
+    graph.add_pass("b").write_texture(second).record(|_, _| ());
+    assert_eq!(
+        graph.pass_order(),
+        Err(RenderGraphError::Unwritten {
+            pass: "a".to_string(),
+            resource: "first".to_string(),
+        })
+    );
+
+    let dot = graph.to_dot();
+    assert!(dot.starts_with("digraph RenderGraph {"));
+    assert!(dot.contains("r0 -> p0;"));
+    assert!(dot.contains("p1 -> r1;"));
+}
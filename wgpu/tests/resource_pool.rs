@@ -0,0 +1,103 @@
+use wgpu::util::ResourcePool;
+
+use crate::common::{initialize_test, TestParameters, TestingContext};
+
+fn texture_descriptor(label: &str, format: wgpu::TextureFormat) -> wgpu::TextureDescriptor<'_> {
+    wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d {
+            width: 16,
+            height: 16,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+    }
+}
+
+fn end_frame(ctx: &TestingContext, pool: &mut ResourcePool) {
+    ctx.queue.submit(None);
+    pool.end_frame(&ctx.queue);
+    ctx.device.poll(wgpu::Maintain::Wait);
+}
+
+#[test]
+fn resource_pool_reuse() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let rgba = wgpu::TextureFormat::Rgba8Unorm;
+        let mut pool = ResourcePool::new(2, None);
+
+        let first = pool.texture(&ctx.device, &texture_descriptor("first", rgba));
+        let second = pool.texture(&ctx.device, &texture_descriptor("second", rgba));
+        let buffer = pool.buffer(
+            &ctx.device,
+            &wgpu::BufferDescriptor {
+                label: None,
+                size: 256,
+                usage: wgpu::BufferUsages::UNIFORM,
+                mapped_at_creation: false,
+            },
+        );
+        drop((first, second, buffer));
+        end_frame(&ctx, &mut pool);
+        let stats = pool.stats();
+        assert_eq!((stats.hits, stats.misses), (0, 3));
+        assert_eq!((stats.textures, stats.buffers), (2, 1));
+        assert_eq!(stats.bytes, 2 * 16 * 16 * 4 + 256);
+
+        // labels don't matter, but formats do
+        let reused = pool.texture(&ctx.device, &texture_descriptor("reused", rgba));
+        let other = pool.texture(
+            &ctx.device,
+            &texture_descriptor("other", wgpu::TextureFormat::R8Unorm),
+        );
+        assert_eq!((pool.stats().hits, pool.stats().misses), (1, 4));
+        end_frame(&ctx, &mut pool);
+        drop(other);
+        end_frame(&ctx, &mut pool);
+        assert_eq!(pool.stats().textures, 3);
+
+        // the texture and buffer unused since the first frame are evicted
+        end_frame(&ctx, &mut pool);
+        end_frame(&ctx, &mut pool);
+        let stats = pool.stats();
+        assert_eq!(stats.evictions, 2);
+        assert_eq!((stats.textures, stats.buffers), (2, 0));
+
+        // the texture still in use is kept
+        end_frame(&ctx, &mut pool);
+        end_frame(&ctx, &mut pool);
+        assert_eq!(pool.stats().textures, 1);
+        drop(reused);
+        pool.clear();
+        assert_eq!(pool.stats().textures, 1);
+        end_frame(&ctx, &mut pool);
+        pool.clear();
+        assert_eq!(pool.stats().textures, 0);
+    })
+}
+
+#[test]
+fn resource_pool_budget() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let rgba = wgpu::TextureFormat::Rgba8Unorm;
+        let mut pool = ResourcePool::new(100, Some(16 * 16 * 4 + 256));
+
+        let first = pool.texture(&ctx.device, &texture_descriptor("first", rgba));
+        let second = pool.texture(&ctx.device, &texture_descriptor("second", rgba));
+        end_frame(&ctx, &mut pool);
+        // resources in use are never evicted
+        assert_eq!(pool.stats().textures, 2);
+
+        drop((first, second));
+        end_frame(&ctx, &mut pool);
+        assert_eq!(pool.stats().textures, 2);
+        end_frame(&ctx, &mut pool);
+        let stats = pool.stats();
+        assert_eq!((stats.textures, stats.evictions), (1, 1));
+        assert_eq!(stats.bytes, 16 * 16 * 4);
+    })
+}
@@ -1,11 +1,37 @@
 // All files containing tests
 mod common;
 
+mod blit;
+mod buffer_ring;
 mod clear_texture;
+mod compute_kernel;
+mod decompress;
 mod device;
 mod example_wgsl;
 mod instance;
+mod mipmap_generator;
+mod parallel_primitives;
 mod poll;
+mod profiler;
+mod readback_belt;
+mod render_graph;
+mod resource_pool;
+#[cfg(feature = "shader-bundle")]
+mod shader_bundle;
+#[cfg(feature = "naga")]
+mod shader_composer;
+mod shader_library;
 mod shader_primitive_index;
+mod shader_reflection;
+mod texture_atlas;
+mod texture_download;
+#[cfg(feature = "texture-files")]
+mod texture_file;
+#[cfg(feature = "macros")]
+mod typed_buffer;
 mod vertex_indices;
+#[cfg(feature = "macros")]
+mod vertex_layout;
+#[cfg(feature = "macros")]
+mod wgsl_checked;
 mod zero_init_texture_after_discard;
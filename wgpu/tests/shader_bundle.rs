@@ -0,0 +1,253 @@
+use std::borrow::Cow;
+use std::sync::{Arc, Mutex};
+
+use wgpu::util::{ComputeKernel, DeviceExt};
+use wgpu_shader_bundle::{
+    compile_wgsl,
+    naga::{self, back},
+    wgt, Layout, PrecompiledCode, PrecompiledKey, ShaderBundle, ShaderBundleError, Target,
+};
+
+use crate::common::{initialize_test, TestParameters};
+
+const SHADER: &str = "
+@group(0) @binding(0) var<storage, read_write> values: array<u32>;
+
+@compute @workgroup_size(64)
+fn double(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x < arrayLength(&values)) {
+        values[id.x] = values[id.x] * 2u;
+    }
+}
+
+@vertex
+fn vs_main() -> @builtin(position) vec4<f32> {
+    return vec4<f32>(0.0);
+}
+";
+
+fn layout() -> Layout {
+    Layout {
+        bind_group_layouts: vec![vec![wgt::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgt::ShaderStages::COMPUTE,
+            ty: wgt::BindingType::Buffer {
+                ty: wgt::BufferBindingType::Storage { read_only: false },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }]],
+        push_constant_ranges: Vec::new(),
+    }
+}
+
+fn targets() -> Vec<Target> {
+    vec![
+        Target::SpirV,
+        Target::Glsl {
+            version: back::glsl::Version::new_gles(310),
+        },
+        Target::Msl {
+            lang_version: (2, 0),
+        },
+        Target::Hlsl {
+            shader_model: back::hlsl::ShaderModel::V5_1,
+        },
+    ]
+}
+
+#[test]
+fn shader_bundle_encoding() {
+    let bundle = compile_wgsl(
+        SHADER,
+        naga::valid::Capabilities::empty(),
+        &layout(),
+        &targets(),
+    )
+    .unwrap();
+    // SPIR-V and GLSL outputs are of each entry point
+    assert_eq!(bundle.precompiled.len(), 6);
+
+    let decoded = ShaderBundle::from_bytes(&bundle.to_bytes()).unwrap();
+    assert_eq!(decoded.module.entry_points.len(), 2);
+    assert_eq!(decoded.precompiled.len(), 6);
+    for (original, decoded) in bundle.precompiled.iter().zip(&decoded.precompiled) {
+        assert_eq!(original.key, decoded.key);
+    }
+    match decoded.precompiled[2].code {
+        PrecompiledCode::Glsl { ref source, .. } => {
+            assert!(source.contains("void main()"))
+        }
+        ref other => panic!("unexpected output {:?}", other),
+    }
+
+    assert!(matches!(
+        ShaderBundle::from_bytes(SHADER.as_bytes()),
+        Err(ShaderBundleError::NotABundle)
+    ));
+    let mut bytes = bundle.to_bytes();
+    bytes[8] = 0xff;
+    assert!(matches!(
+        ShaderBundle::from_bytes(&bytes),
+        Err(ShaderBundleError::Version(_))
+    ));
+}
+
+#[test]
+fn shader_bundle_keys() {
+    use back::spv;
+
+    let bundle = compile_wgsl(
+        SHADER,
+        naga::valid::Capabilities::empty(),
+        &layout(),
+        &targets(),
+    )
+    .unwrap();
+    let spv_key = &bundle.precompiled[0].key;
+    let glsl_key = &bundle.precompiled[2].key;
+
+    // runtime options of a Vulkan device with robust access
+    let mut options = spv::Options {
+        lang_version: (1, 0),
+        flags: spv::WriterFlags::DEBUG
+            | spv::WriterFlags::LABEL_VARYINGS
+            | spv::WriterFlags::FORCE_POINT_SIZE,
+        capabilities: Some([spv::Capability::Shader].into_iter().collect()),
+        bounds_check_policies: naga::proc::BoundsCheckPolicies {
+            index: naga::proc::BoundsCheckPolicy::Restrict,
+            buffer: naga::proc::BoundsCheckPolicy::Unchecked,
+            image: naga::proc::BoundsCheckPolicy::Unchecked,
+            binding_array: naga::proc::BoundsCheckPolicy::Unchecked,
+        },
+        binding_map: Default::default(),
+    };
+    let mut pipeline_options = spv::PipelineOptions {
+        shader_stage: naga::ShaderStage::Compute,
+        entry_point: "double".to_string(),
+    };
+    assert!(spv_key.accepts(&PrecompiledKey::spirv(&options, Some(&pipeline_options))));
+    assert!(!glsl_key.accepts(&PrecompiledKey::spirv(&options, Some(&pipeline_options))));
+
+    pipeline_options.entry_point = "vs_main".to_string();
+    assert!(!spv_key.accepts(&PrecompiledKey::spirv(&options, Some(&pipeline_options))));
+    pipeline_options.entry_point = "double".to_string();
+
+    options.binding_map.insert(
+        naga::ResourceBinding {
+            group: 0,
+            binding: 0,
+        },
+        spv::BindingInfo {
+            binding_array_size: Some(4),
+        },
+    );
+    assert!(!spv_key.accepts(&PrecompiledKey::spirv(&options, Some(&pipeline_options))));
+
+    // runtime options of a GL ES 3.2 context
+    let mut options = back::glsl::Options {
+        version: back::glsl::Version::new_gles(320),
+        writer_flags: back::glsl::WriterFlags::ADJUST_COORDINATE_SPACE,
+        binding_map: [(
+            naga::ResourceBinding {
+                group: 0,
+                binding: 0,
+            },
+            0,
+        )]
+        .into_iter()
+        .collect(),
+    };
+    let pipeline_options = back::glsl::PipelineOptions {
+        shader_stage: naga::ShaderStage::Compute,
+        entry_point: "double".to_string(),
+        multiview: None,
+    };
+    let policies = naga::proc::BoundsCheckPolicies::default();
+    assert!(glsl_key.accepts(&PrecompiledKey::glsl(
+        &options,
+        &pipeline_options,
+        &policies
+    )));
+
+    options.version = back::glsl::Version::new_gles(300);
+    assert!(!glsl_key.accepts(&PrecompiledKey::glsl(
+        &options,
+        &pipeline_options,
+        &policies
+    )));
+    options.version = back::glsl::Version::Desktop(430);
+    assert!(!glsl_key.accepts(&PrecompiledKey::glsl(
+        &options,
+        &pipeline_options,
+        &policies
+    )));
+}
+
+#[test]
+fn shader_bundle_module() {
+    let parameters = TestParameters::default()
+        .downlevel_flags(wgpu::DownlevelFlags::COMPUTE_SHADERS)
+        .limits(wgpu::Limits::downlevel_defaults());
+    initialize_test(parameters, |ctx| {
+        // the outputs of a shader tripling the values are bundled with the module doubling
+        // them, so the values are only tripled if the outputs are used
+        let tripling = SHADER.replace("* 2u", "* 3u");
+        let precompiled = compile_wgsl(
+            &tripling,
+            naga::valid::Capabilities::empty(),
+            &layout(),
+            &targets(),
+        )
+        .unwrap()
+        .precompiled;
+        let bundle = ShaderBundle {
+            precompiled,
+            ..compile_wgsl(SHADER, naga::valid::Capabilities::empty(), &layout(), &[]).unwrap()
+        };
+        let module = ctx
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: None,
+                source: wgpu::ShaderSource::Bundle(Cow::Owned(bundle.to_bytes())),
+            });
+        let kernel = ComputeKernel::new(&ctx.device, &module, "double").unwrap();
+
+        let data = (0..100).collect::<Vec<u32>>();
+        let values = ctx
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents: bytemuck::cast_slice(&data),
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            });
+        let result = Arc::new(Mutex::new(None));
+        let result_clone = Arc::clone(&result);
+        kernel
+            .run_and_read(
+                &ctx.device,
+                &ctx.queue,
+                &[("values", values.as_entire_binding())],
+                data.len() as u32,
+                &values.slice(..),
+                move |download| {
+                    let download = download.unwrap();
+                    let values: &[u32] = bytemuck::cast_slice(&download);
+                    *result_clone.lock().unwrap() = Some(values.to_vec());
+                },
+            )
+            .unwrap();
+        ctx.device.poll(wgpu::Maintain::Wait);
+        let expected = data.iter().map(|value| value * 3).collect::<Vec<_>>();
+        assert_eq!(result.lock().unwrap().take().unwrap(), expected);
+
+        ctx.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        ctx.device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: None,
+                source: wgpu::ShaderSource::Bundle(Cow::Borrowed(SHADER.as_bytes())),
+            });
+        assert!(pollster::block_on(ctx.device.pop_error_scope()).is_some());
+    })
+}
@@ -0,0 +1,197 @@
+use wgpu::util::{ComposerError, ComposerLocation, ShaderComposer};
+
+use crate::common::{initialize_test, TestParameters};
+
+const MATH: &str = "
+fn square(x: f32) -> f32 {
+    return x * x;
+}
+
+fn cube(x: f32) -> f32 {
+    return x * square(x);
+}
+";
+
+const LIGHTING: &str = "
+#import math::square
+
+struct Light {
+    position: vec3<f32>,
+    intensity: f32,
+}
+
+#ifdef SHADOWS
+fn shadow(light: Light) -> f32 {
+    return 0.5;
+}
+#else
+fn shadow(light: Light) -> f32 {
+    return 1.0;
+}
+#endif
+
+fn attenuate(light: Light, position: vec3<f32>) -> f32 {
+    return shadow(light) * light.intensity / square(distance(light.position, position));
+}
+";
+
+const SHADER: &str = "
+#define SHADOWS
+#import lighting::attenuate
+#import math::square
+
+@group(0) @binding(0) var<uniform> light: Light;
+
+@fragment
+fn fs_main(@location(0) position: vec3<f32>) -> @location(0) vec4<f32> {
+    return vec4<f32>(square(attenuate(light, position)));
+}
+";
+
+fn composer() -> ShaderComposer {
+    let mut composer = ShaderComposer::new();
+    composer.add_module("math", MATH);
+    composer.add_module("lighting", LIGHTING);
+    composer
+}
+
+#[test]
+fn shader_composer_imports() {
+    let composed = composer().compose("shader", SHADER, &[]).unwrap();
+    let source = composed.source();
+    assert_eq!(source.matches("fn square").count(), 1);
+    assert!(!source.contains("fn cube"));
+    assert!(source.contains("struct Light"));
+    assert!(source.contains("return 0.5;"));
+    assert!(!source.contains("return 1.0;"));
+    // declarations come before their uses
+    assert!(source.find("fn square").unwrap() < source.find("fn attenuate").unwrap());
+    assert!(source.find("fn shadow").unwrap() < source.find("fn attenuate").unwrap());
+    assert!(source.find("fn attenuate").unwrap() < source.find("fn fs_main").unwrap());
+
+    // lines map back to their module
+    let line = source[..source.find("fn attenuate").unwrap()]
+        .matches('\n')
+        .count() as u32
+        + 1;
+    assert_eq!(
+        composed.location(line, 4),
+        Some(ComposerLocation {
+            file: "lighting".to_string(),
+            line: 19,
+            column: 4,
+        })
+    );
+
+    // whole modules can be imported, and definitions can be given
+    let composed = composer()
+        .compose("shader", "#import lighting\n#import math", &["SHADOWS"])
+        .unwrap();
+    assert!(composed.source().contains("fn cube"));
+    assert!(composed.source().contains("return 0.5;"));
+}
+
+#[test]
+fn shader_composer_errors() {
+    let composer = composer();
+    assert_eq!(
+        composer
+            .compose("shader", "\n#import shapes::sphere", &[])
+            .unwrap_err(),
+        ComposerError::UnknownModule {
+            path: "shapes::sphere".to_string(),
+            location: ComposerLocation {
+                file: "shader".to_string(),
+                line: 2,
+                column: 1,
+            },
+        }
+    );
+    assert_eq!(
+        composer
+            .compose("shader", "#import math::sqrt", &[])
+            .unwrap_err(),
+        ComposerError::UnknownItem {
+            path: "math".to_string(),
+            item: "sqrt".to_string(),
+            location: ComposerLocation {
+                file: "shader".to_string(),
+                line: 1,
+                column: 1,
+            },
+        }
+    );
+    assert!(matches!(
+        composer.compose("shader", "#ifdef A\n#else\n#else\n#endif", &[]),
+        Err(ComposerError::InvalidDirective { .. })
+    ));
+    assert!(matches!(
+        composer.compose("shader", "#ifndef A\nfn f() {}", &[]),
+        Err(ComposerError::UnclosedConditional { .. })
+    ));
+
+    let mut composer = ShaderComposer::new();
+    composer.add_module("a", "#import b\nfn a() {}");
+    composer.add_module("b", "#import a\nfn b() {}");
+    assert!(matches!(
+        composer.compose("shader", "#import a", &[]),
+        Err(ComposerError::ImportCycle { .. })
+    ));
+}
+
+#[test]
+fn shader_composer_naga_errors() {
+    let mut composer = composer();
+    composer.add_module(
+        "broken",
+        "fn fine() -> f32 {\n    return 1.0;\n}\n\nfn broken() -> f32 {\n    return square(1u);\n}",
+    );
+    let error = composer
+        .compose("shader", "#import broken::broken\n", &[])
+        .unwrap()
+        .parse()
+        .unwrap_err();
+    match error {
+        ComposerError::Shader {
+            location: Some(location),
+            ..
+        } => {
+            assert_eq!(location.file, "broken");
+            assert_eq!(location.line, 6);
+        }
+        _ => panic!("unexpected error {:?}", error),
+    }
+
+    let error = composer
+        .compose(
+            "shader",
+            "#import math::square\n\nfn main() {\n    let x = ;\n}",
+            &[],
+        )
+        .unwrap()
+        .parse()
+        .unwrap_err();
+    match error {
+        ComposerError::Shader {
+            location: Some(location),
+            ..
+        } => {
+            assert_eq!(location.file, "shader");
+            assert_eq!(location.line, 4);
+        }
+        _ => panic!("unexpected error {:?}", error),
+    }
+}
+
+#[test]
+fn shader_composer_module() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let composed = composer().compose("shader", SHADER, &[]).unwrap();
+        composed.parse().unwrap();
+        ctx.device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: None,
+                source: composed.shader_source(),
+            });
+    })
+}
@@ -0,0 +1,217 @@
+use std::num::NonZeroU64;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::{fs, thread, time::Duration};
+
+use wgpu::util::{
+    DownloadBuffer, LibraryComputePipelineDescriptor, LibraryFragmentState,
+    LibraryRenderPipelineDescriptor, LibraryVertexState, ShaderLibrary, ShaderLibraryError,
+};
+
+use crate::common::{initialize_test, TestParameters, TestingContext};
+
+const COMPUTE: &str = "
+@group(0) @binding(0) var<storage, read_write> output: array<u32>;
+
+@compute @workgroup_size(1)
+fn main() {
+    output[0] = VALUE;
+}
+";
+
+const RENDER: &str = "
+@vertex
+fn vs_main() -> @builtin(position) vec4<f32> {
+    return vec4<f32>(0.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0);
+}
+";
+
+/// Write the `source` to the file at `path`, making sure its modification time changes.
+fn write_shader(path: &Path, source: &str) {
+    let previous = fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok();
+    loop {
+        fs::write(path, source).unwrap();
+        if fs::metadata(path).unwrap().modified().ok() != previous {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn run(
+    ctx: &TestingContext,
+    pipeline: &wgpu::ComputePipeline,
+    bind_group: &wgpu::BindGroup,
+    output: &wgpu::Buffer,
+) -> u32 {
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    {
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, bind_group, &[]);
+        pass.dispatch_workgroups(1, 1, 1);
+    }
+    ctx.queue.submit(Some(encoder.finish()));
+
+    let result = Arc::new(Mutex::new(None));
+    let result_clone = Arc::clone(&result);
+    DownloadBuffer::read_buffer(
+        &ctx.device,
+        &ctx.queue,
+        &output.slice(..),
+        move |download| {
+            let download = download.unwrap();
+            *result_clone.lock().unwrap() = Some(bytemuck::cast_slice::<u8, u32>(&download)[0]);
+        },
+    );
+    ctx.device.poll(wgpu::Maintain::Wait);
+    let value = result.lock().unwrap().take().unwrap();
+    value
+}
+
+#[test]
+fn shader_library_reload() {
+    let parameters = TestParameters::default()
+        .downlevel_flags(wgpu::DownlevelFlags::COMPUTE_SHADERS)
+        .limits(wgpu::Limits::downlevel_defaults());
+    initialize_test(parameters, |ctx| {
+        let dir = std::env::temp_dir().join(format!("wgpu-shader-library-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let compute_path = dir.join("compute.wgsl");
+        let render_path = dir.join("render.wgsl");
+        write_shader(&compute_path, &COMPUTE.replace("VALUE", "1u"));
+        write_shader(&render_path, RENDER);
+
+        let bind_group_layout =
+            ctx.device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: None,
+                    entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: false },
+                            has_dynamic_offset: false,
+                            min_binding_size: NonZeroU64::new(4),
+                        },
+                        count: None,
+                    }],
+                });
+        let layout = ctx
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let output = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 4,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let bind_group = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: output.as_entire_binding(),
+            }],
+        });
+
+        let mut library = ShaderLibrary::new();
+        let compute = library.add_shader(&ctx.device, &compute_path).unwrap();
+        let render = library.add_shader(&ctx.device, &render_path).unwrap();
+        let compute_pipeline = library
+            .add_compute_pipeline(
+                &ctx.device,
+                LibraryComputePipelineDescriptor {
+                    label: Some("compute".to_string()),
+                    layout: Some(Arc::new(layout)),
+                    shader: compute,
+                    entry_point: "main".to_string(),
+                },
+            )
+            .unwrap();
+        library
+            .add_render_pipeline(
+                &ctx.device,
+                LibraryRenderPipelineDescriptor {
+                    label: Some("render".to_string()),
+                    layout: None,
+                    vertex: LibraryVertexState {
+                        shader: render,
+                        entry_point: "vs_main".to_string(),
+                        buffers: Vec::new(),
+                    },
+                    primitive: wgpu::PrimitiveState::default(),
+                    depth_stencil: None,
+                    multisample: wgpu::MultisampleState::default(),
+                    fragment: Some(LibraryFragmentState {
+                        shader: render,
+                        entry_point: "fs_main".to_string(),
+                        targets: vec![Some(wgpu::TextureFormat::Rgba8Unorm.into())],
+                    }),
+                    multiview: None,
+                },
+            )
+            .unwrap();
+
+        assert!(library.poll(&ctx.device).is_empty());
+        let pipeline = library.compute_pipeline(compute_pipeline);
+        assert_eq!(run(&ctx, pipeline, &bind_group, &output), 1);
+
+        // a valid change rebuilds the pipeline
+        write_shader(&compute_path, &COMPUTE.replace("VALUE", "2u"));
+        assert!(library.poll(&ctx.device).is_empty());
+        let pipeline = library.compute_pipeline(compute_pipeline);
+        assert_eq!(run(&ctx, pipeline, &bind_group, &output), 2);
+
+        // an invalid shader keeps the previous version
+        write_shader(&compute_path, &COMPUTE.replace("VALUE", "2.0"));
+        let errors = library.poll(&ctx.device);
+        assert!(matches!(errors[..], [ShaderLibraryError::Shader { .. }]));
+        let pipeline = library.compute_pipeline(compute_pipeline);
+        assert_eq!(run(&ctx, pipeline, &bind_group, &output), 2);
+
+        // so does a pipeline that fails to build from a valid shader
+        write_shader(
+            &compute_path,
+            &COMPUTE
+                .replace("VALUE", "3u")
+                .replace("fn main", "fn other"),
+        );
+        let errors = library.poll(&ctx.device);
+        assert!(matches!(
+            errors[..],
+            [ShaderLibraryError::Pipeline { ref label, .. }] if label.as_deref() == Some("compute")
+        ));
+        let pipeline = library.compute_pipeline(compute_pipeline);
+        assert_eq!(run(&ctx, pipeline, &bind_group, &output), 2);
+
+        write_shader(&compute_path, &COMPUTE.replace("VALUE", "4u"));
+        assert!(library.poll(&ctx.device).is_empty());
+        let pipeline = library.compute_pipeline(compute_pipeline);
+        assert_eq!(run(&ctx, pipeline, &bind_group, &output), 4);
+
+        // a missing file is reported once
+        fs::remove_file(&render_path).unwrap();
+        let errors = library.poll(&ctx.device);
+        assert!(matches!(errors[..], [ShaderLibraryError::Io { .. }]));
+        assert!(library.poll(&ctx.device).is_empty());
+        write_shader(&render_path, RENDER);
+        assert!(library.poll(&ctx.device).is_empty());
+        assert!(library.reload_shader(&ctx.device, render).is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    })
+}
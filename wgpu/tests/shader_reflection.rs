@@ -0,0 +1,171 @@
+use std::borrow::Cow;
+use std::num::NonZeroU64;
+
+use crate::common::{initialize_test, TestParameters};
+
+const SHADER: &str = "
+struct Globals {
+    transform: mat4x4<f32>,
+    time: f32,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@group(0) @binding(0) var<uniform> globals: Globals;
+@group(0) @binding(1) var color_texture: texture_2d<f32>;
+@group(0) @binding(2) var color_sampler: sampler;
+@group(1) @binding(0) var<storage, read_write> counters: array<u32>;
+
+@vertex
+fn vs_main(
+    @location(0) position: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+    @location(1) id: u32,
+) -> VertexOutput {
+    return VertexOutput(globals.transform * vec4<f32>(position, 1.0), uv);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(color_texture, color_sampler, in.uv) * globals.time;
+}
+
+@compute @workgroup_size(8, 4, 1)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    counters[id.x] = counters[id.x] + 1u;
+}
+";
+
+#[test]
+fn shader_reflection() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let module = ctx
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: None,
+                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(SHADER)),
+            });
+        let reflection = module.reflect().unwrap();
+
+        let names = reflection
+            .entry_points
+            .iter()
+            .map(|entry_point| entry_point.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["cs_main", "fs_main", "vs_main"]);
+
+        let compute = reflection.entry_point("cs_main").unwrap();
+        assert_eq!(compute.stage, wgpu::ShaderStages::COMPUTE);
+        assert_eq!(compute.workgroup_size, [8, 4, 1]);
+        assert!(compute.inputs.is_empty());
+        assert_eq!(compute.bindings, [(1, 0)]);
+
+        let vertex = reflection.entry_point("vs_main").unwrap();
+        assert_eq!(vertex.bindings, [(0, 0)]);
+        assert_eq!(
+            vertex.vertex_attributes(),
+            wgpu::vertex_attr_array![0 => Float32x3, 1 => Uint32, 2 => Float32x2]
+        );
+        assert_eq!(
+            vertex.outputs,
+            [wgpu::VariableReflection {
+                location: 0,
+                format: wgpu::VertexFormat::Float32x2,
+            }]
+        );
+        let fragment = reflection.entry_point("fs_main").unwrap();
+        assert_eq!(
+            fragment.outputs,
+            [wgpu::VariableReflection {
+                location: 0,
+                format: wgpu::VertexFormat::Float32x4,
+            }]
+        );
+
+        let entries = reflection.bind_group_layout_entries(0);
+        assert_eq!(
+            entries,
+            [
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: NonZeroU64::new(80),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ]
+        );
+        let counters = &reflection.bindings[3];
+        assert_eq!(counters.name.as_deref(), Some("counters"));
+        assert_eq!((counters.group, counters.binding), (1, 0));
+        assert_eq!(
+            counters.ty,
+            wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: false },
+                has_dynamic_offset: false,
+                min_binding_size: NonZeroU64::new(4),
+            }
+        );
+
+        // the reflected layout is accepted by the render pipeline
+        let bgl = ctx
+            .device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: None,
+                entries: &entries,
+            });
+        let pipeline_layout = ctx
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&bgl],
+                push_constant_ranges: &[],
+            });
+        let attributes = vertex.vertex_attributes();
+        ctx.device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &module,
+                    entry_point: "vs_main",
+                    buffers: &[wgpu::VertexBufferLayout {
+                        array_stride: 24,
+                        step_mode: wgpu::VertexStepMode::Vertex,
+                        attributes: &attributes,
+                    }],
+                },
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                fragment: Some(wgpu::FragmentState {
+                    module: &module,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::TextureFormat::Rgba8Unorm.into())],
+                }),
+                multiview: None,
+            });
+    })
+}
@@ -0,0 +1,132 @@
+use std::sync::{Arc, Mutex};
+
+use wgpu::util::{
+    AtlasAllocation, AtlasPacking, TextureAtlas, TextureAtlasDescriptor, TextureAtlasError,
+    TextureDownload,
+};
+
+use crate::common::{initialize_test, TestParameters, TestingContext};
+
+fn atlas(ctx: &TestingContext, packing: AtlasPacking) -> TextureAtlas {
+    TextureAtlas::new(
+        &ctx.device,
+        &TextureAtlasDescriptor {
+            label: Some("atlas"),
+            width: 64,
+            height: 64,
+            layers: 1,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::empty(),
+            padding: 2,
+            packing,
+        },
+    )
+}
+
+fn read(ctx: &TestingContext, atlas: &TextureAtlas, allocation: &AtlasAllocation) -> Vec<u8> {
+    let result = Arc::new(Mutex::new(None));
+    let result_clone = Arc::clone(&result);
+    TextureDownload::read_texture(
+        &ctx.device,
+        &ctx.queue,
+        &wgpu::ImageCopyTexture {
+            texture: atlas.texture(),
+            mip_level: 0,
+            origin: wgpu::Origin3d {
+                x: allocation.origin[0],
+                y: allocation.origin[1],
+                z: allocation.layer,
+            },
+            aspect: wgpu::TextureAspect::All,
+        },
+        wgpu::TextureFormat::Rgba8Unorm,
+        wgpu::Extent3d {
+            width: allocation.size[0],
+            height: allocation.size[1],
+            depth_or_array_layers: 1,
+        },
+        move |download| {
+            *result_clone.lock().unwrap() = Some(download.unwrap().to_rgba8().unwrap());
+        },
+    );
+    ctx.device.poll(wgpu::Maintain::Wait);
+    let pixels = result.lock().unwrap().take().unwrap();
+    pixels
+}
+
+#[test]
+fn texture_atlas_shelf() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let mut atlas = atlas(&ctx, AtlasPacking::Shelf);
+        let allocate = |atlas: &mut TextureAtlas, width, height| {
+            atlas
+                .allocate(&ctx.device, &ctx.queue, width, height)
+                .unwrap()
+        };
+
+        let first = allocate(&mut atlas, 30, 30);
+        let second = allocate(&mut atlas, 30, 30);
+        let third = allocate(&mut atlas, 30, 20);
+        let fourth = allocate(&mut atlas, 10, 10);
+        assert_eq!((first.layer, first.origin), (0, [0, 0]));
+        assert_eq!((second.layer, second.origin), (0, [32, 0]));
+        assert_eq!((third.layer, third.origin), (0, [0, 32]));
+        assert_eq!((fourth.layer, fourth.origin), (0, [32, 32]));
+        assert_eq!(atlas.uv_rect(&second), [0.5, 0.0, 62.0 / 64.0, 30.0 / 64.0]);
+        assert_eq!(
+            atlas.allocate(&ctx.device, &ctx.queue, 63, 10),
+            Err(TextureAtlasError::TooLarge {
+                width: 63,
+                height: 10
+            })
+        );
+
+        let data = [1, 2, 3, 4].repeat(30 * 30);
+        atlas.write(&ctx.queue, &first, &data);
+
+        // no room left for this one, so a layer is added
+        let fifth = allocate(&mut atlas, 30, 30);
+        assert_eq!((fifth.layer, fifth.origin), (1, [0, 0]));
+        assert_eq!((atlas.layer_count(), atlas.generation()), (2, 1));
+        let other = [5, 6, 7, 8].repeat(30 * 30);
+        atlas.write(&ctx.queue, &fifth, &other);
+        assert_eq!(read(&ctx, &atlas, &first), data);
+        assert_eq!(read(&ctx, &atlas, &fifth), other);
+
+        // deallocated space is reused by regions that fit in the shelf
+        atlas.deallocate(second);
+        let sixth = allocate(&mut atlas, 20, 20);
+        assert_eq!((sixth.layer, sixth.origin), (0, [32, 0]));
+        let seventh = allocate(&mut atlas, 8, 30);
+        assert_eq!((seventh.layer, seventh.origin), (0, [54, 0]));
+    })
+}
+
+#[test]
+fn texture_atlas_skyline() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let mut atlas = atlas(&ctx, AtlasPacking::Skyline);
+        let allocate = |atlas: &mut TextureAtlas, width, height| {
+            atlas
+                .allocate(&ctx.device, &ctx.queue, width, height)
+                .unwrap()
+        };
+
+        let first = allocate(&mut atlas, 38, 18);
+        let second = allocate(&mut atlas, 18, 38);
+        let third = allocate(&mut atlas, 38, 8);
+        let fourth = allocate(&mut atlas, 60, 20);
+        assert_eq!(first.origin, [0, 0]);
+        assert_eq!(second.origin, [40, 0]);
+        assert_eq!(third.origin, [0, 20]);
+        assert_eq!(fourth.origin, [0, 40]);
+        assert_eq!(atlas.layer_count(), 1);
+
+        // space is reclaimed once the layer is empty
+        for allocation in [first, second, third, fourth] {
+            atlas.deallocate(allocation);
+        }
+        let fifth = allocate(&mut atlas, 62, 62);
+        assert_eq!((fifth.layer, fifth.origin), (0, [0, 0]));
+    })
+}
@@ -0,0 +1,202 @@
+use std::{
+    num::NonZeroU32,
+    sync::{Arc, Mutex},
+};
+
+use wgpu::util::TextureDownload;
+
+use crate::common::{initialize_test, TestParameters, TestingContext};
+
+fn create_texture(
+    ctx: &TestingContext,
+    format: wgpu::TextureFormat,
+    size: wgpu::Extent3d,
+    mip_level_count: u32,
+    usage: wgpu::TextureUsages,
+) -> wgpu::Texture {
+    ctx.device.create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size,
+        mip_level_count,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: usage | wgpu::TextureUsages::COPY_SRC,
+    })
+}
+
+fn download(
+    ctx: &TestingContext,
+    texture: wgpu::ImageCopyTexture,
+    format: wgpu::TextureFormat,
+    size: wgpu::Extent3d,
+) -> TextureDownload {
+    let result = Arc::new(Mutex::new(None));
+    let result_clone = Arc::clone(&result);
+    TextureDownload::read_texture(&ctx.device, &ctx.queue, &texture, format, size, move |r| {
+        *result_clone.lock().unwrap() = Some(r.unwrap());
+    });
+    ctx.device.poll(wgpu::Maintain::Wait);
+    let download = result.lock().unwrap().take();
+    download.expect("Download callback wasn't called")
+}
+
+fn write_texels(
+    ctx: &TestingContext,
+    texture: &wgpu::Texture,
+    mip_level: u32,
+    layer: u32,
+    size: wgpu::Extent3d,
+    data: &[u8],
+) {
+    ctx.queue.write_texture(
+        wgpu::ImageCopyTexture {
+            texture,
+            mip_level,
+            origin: wgpu::Origin3d {
+                x: 0,
+                y: 0,
+                z: layer,
+            },
+            aspect: wgpu::TextureAspect::All,
+        },
+        data,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: NonZeroU32::new(size.width * 4),
+            rows_per_image: None,
+        },
+        size,
+    );
+}
+
+#[test]
+fn download_mip_and_layer() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let format = wgpu::TextureFormat::Rgba8Unorm;
+        let texture = create_texture(
+            &ctx,
+            format,
+            wgpu::Extent3d {
+                width: 5,
+                height: 3,
+                depth_or_array_layers: 2,
+            },
+            2,
+            wgpu::TextureUsages::COPY_DST,
+        );
+
+        let mip0 = wgpu::Extent3d {
+            width: 5,
+            height: 3,
+            depth_or_array_layers: 1,
+        };
+        let mip1 = wgpu::Extent3d {
+            width: 2,
+            height: 1,
+            depth_or_array_layers: 1,
+        };
+        let data0 = (0..5 * 3 * 4).map(|i| i as u8).collect::<Vec<_>>();
+        let data1 = (0..2 * 4).map(|i| 200 + i as u8).collect::<Vec<_>>();
+        write_texels(&ctx, &texture, 0, 1, mip0, &data0);
+        write_texels(&ctx, &texture, 1, 1, mip1, &data1);
+
+        for &(mip_level, size, expected) in &[(0, mip0, &data0), (1, mip1, &data1)] {
+            let result = download(
+                &ctx,
+                wgpu::ImageCopyTexture {
+                    texture: &texture,
+                    mip_level,
+                    origin: wgpu::Origin3d { x: 0, y: 0, z: 1 },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                format,
+                size,
+            );
+            assert_eq!(result.bytes_per_row(), size.width * 4);
+            assert_eq!(result.rows_per_image(), size.height);
+            assert_eq!(&result[..], &expected[..]);
+            assert_eq!(result.to_rgba8().as_ref(), Some(expected));
+        }
+    })
+}
+
+#[test]
+fn download_bgra_as_rgba() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let format = wgpu::TextureFormat::Bgra8Unorm;
+        let size = wgpu::Extent3d {
+            width: 3,
+            height: 2,
+            depth_or_array_layers: 1,
+        };
+        let texture = create_texture(&ctx, format, size, 1, wgpu::TextureUsages::COPY_DST);
+        let bgra = [0u8, 128, 255, 64].repeat(6);
+        write_texels(&ctx, &texture, 0, 0, size, &bgra);
+
+        let result = download(&ctx, texture.as_image_copy(), format, size);
+        assert_eq!(result.to_rgba8(), Some([255u8, 128, 0, 64].repeat(6)));
+        let rgba32f = result.to_rgba32f().unwrap();
+        assert_eq!(rgba32f[0], 1.0);
+        assert_eq!(rgba32f[2], 0.0);
+    })
+}
+
+#[test]
+fn download_depth_aspect() {
+    initialize_test(
+        TestParameters::default()
+            .downlevel_flags(wgpu::DownlevelFlags::DEPTH_TEXTURE_AND_BUFFER_COPIES),
+        |ctx| {
+            let format = wgpu::TextureFormat::Depth32Float;
+            let size = wgpu::Extent3d {
+                width: 4,
+                height: 4,
+                depth_or_array_layers: 1,
+            };
+            let texture = create_texture(
+                &ctx,
+                format,
+                size,
+                1,
+                wgpu::TextureUsages::RENDER_ATTACHMENT,
+            );
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+            let mut encoder = ctx
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+            encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Depth Clear"),
+                color_attachments: &[],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(0.25),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+            ctx.queue.submit(Some(encoder.finish()));
+
+            let result = download(
+                &ctx,
+                wgpu::ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::DepthOnly,
+                },
+                format,
+                size,
+            );
+            assert_eq!(result.bytes_per_row(), 16);
+            let rgba32f = result.to_rgba32f().unwrap();
+            assert_eq!(rgba32f.len(), 16 * 4);
+            assert!(rgba32f
+                .chunks(4)
+                .all(|texel| texel == [0.25, 0.25, 0.25, 1.0]));
+        },
+    )
+}
@@ -0,0 +1,224 @@
+use std::sync::{Arc, Mutex};
+
+use wgpu::util::{TextureConversion, TextureDownload, TextureFile, TextureFileError};
+
+use crate::common::{initialize_test, TestParameters, TestingContext};
+
+const BC1_DDS: &[u8] = include_bytes!("../examples/skybox/images/bc1.dds");
+const BGRA_DDS: &[u8] = include_bytes!("../examples/skybox/images/bgra.dds");
+
+const VK_FORMAT_R8G8B8_SRGB: u32 = 29;
+const VK_FORMAT_ASTC_4X4_SFLOAT_BLOCK: u32 = 1_000_066_000;
+
+/// Builds a KTX2 file of a 2D array texture, with the texels of each mip level.
+fn ktx2_file(vk_format: u32, width: u32, height: u32, layers: u32, levels: &[Vec<u8>]) -> Vec<u8> {
+    let mut file = vec![
+        0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+    ];
+    // format, type size, size, layers, faces, levels, supercompression
+    for value in [
+        vk_format,
+        1,
+        width,
+        height,
+        0,
+        layers,
+        1,
+        levels.len() as u32,
+        0,
+    ] {
+        file.extend_from_slice(&value.to_le_bytes());
+    }
+    // no data format descriptor, key/value data or supercompression global data
+    file.extend_from_slice(&[0; 32]);
+    let mut offset = file.len() + levels.len() * 24;
+    for level in levels {
+        for value in [offset, level.len(), level.len()] {
+            file.extend_from_slice(&(value as u64).to_le_bytes());
+        }
+        offset += level.len();
+    }
+    for level in levels {
+        file.extend_from_slice(level);
+    }
+    file
+}
+
+fn read_texels(
+    ctx: &TestingContext,
+    texture: &wgpu::Texture,
+    format: wgpu::TextureFormat,
+    mip_level: u32,
+    layer: u32,
+    width: u32,
+    height: u32,
+) -> Vec<u8> {
+    let result = Arc::new(Mutex::new(None));
+    let result_clone = Arc::clone(&result);
+    TextureDownload::read_texture(
+        &ctx.device,
+        &ctx.queue,
+        &wgpu::ImageCopyTexture {
+            texture,
+            mip_level,
+            origin: wgpu::Origin3d {
+                x: 0,
+                y: 0,
+                z: layer,
+            },
+            aspect: wgpu::TextureAspect::All,
+        },
+        format,
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        move |r| *result_clone.lock().unwrap() = Some(r.unwrap().to_vec()),
+    );
+    ctx.device.poll(wgpu::Maintain::Wait);
+    let data = result.lock().unwrap().take();
+    data.unwrap()
+}
+
+// The GL backend can't copy from cube maps to buffers.
+#[test]
+fn texture_file_dds_cube() {
+    initialize_test(
+        TestParameters::default().backend_failure(wgpu::Backends::GL),
+        |ctx| {
+            let file = TextureFile::from_bytes(BGRA_DDS).unwrap();
+            assert_eq!(file.format(), Some(wgpu::TextureFormat::Bgra8Unorm));
+            assert_eq!(
+                file.size(),
+                wgpu::Extent3d {
+                    width: 128,
+                    height: 128,
+                    depth_or_array_layers: 6,
+                }
+            );
+            assert_eq!(file.mip_level_count(), 8);
+            assert_eq!(file.view_dimension(), wgpu::TextureViewDimension::Cube);
+
+            let texture = file
+                .create_texture(
+                    &ctx.device,
+                    &ctx.queue,
+                    None,
+                    wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_SRC,
+                )
+                .unwrap();
+            // the last face is stored last, with its 1x1 mip level at the very end
+            let texel = read_texels(&ctx, &texture, wgpu::TextureFormat::Bgra8Unorm, 7, 5, 1, 1);
+            assert_eq!(texel, &BGRA_DDS[BGRA_DDS.len() - 4..]);
+        },
+    )
+}
+
+#[test]
+fn texture_file_ktx2_rgb() {
+    initialize_test(TestParameters::default(), |ctx| {
+        // 2 layers of 2x2 texels, with 2 mip levels
+        let level0 = (0..2 * 2 * 2 * 3).map(|i| i as u8 * 10).collect::<Vec<_>>();
+        let level1 = vec![1, 2, 3, 4, 5, 6];
+        let bytes = ktx2_file(VK_FORMAT_R8G8B8_SRGB, 2, 2, 2, &[level0, level1]);
+
+        let file = TextureFile::from_bytes(&bytes).unwrap();
+        assert_eq!(file.format(), None);
+        assert_eq!(file.view_dimension(), wgpu::TextureViewDimension::D2Array);
+        let target = file.target(ctx.device.features()).unwrap();
+        assert_eq!(target.format, wgpu::TextureFormat::Rgba8UnormSrgb);
+        assert_eq!(target.conversion, Some(TextureConversion::Rearrange));
+
+        let texture = file
+            .create_texture(&ctx.device, &ctx.queue, None, wgpu::TextureUsages::COPY_SRC)
+            .unwrap();
+        assert_eq!(
+            read_texels(&ctx, &texture, target.format, 0, 1, 2, 2),
+            [120, 130, 140, 255, 150, 160, 170, 255, 180, 190, 200, 255, 210, 220, 230, 255]
+        );
+        assert_eq!(
+            read_texels(&ctx, &texture, target.format, 1, 1, 1, 1),
+            [4, 5, 6, 255]
+        );
+    })
+}
+
+#[test]
+fn texture_file_unsupported() {
+    let file = TextureFile::from_bytes(BC1_DDS).unwrap();
+    assert_eq!(file.format(), Some(wgpu::TextureFormat::Bc1RgbaUnorm));
+    let target = file.target(wgpu::Features::empty()).unwrap();
+    assert_eq!(target.format, wgpu::TextureFormat::Rgba8Unorm);
+    assert_eq!(target.conversion, Some(TextureConversion::Decompress));
+    let target = file.target(wgpu::Features::TEXTURE_COMPRESSION_BC).unwrap();
+    assert_eq!(target.format, wgpu::TextureFormat::Bc1RgbaUnorm);
+    assert_eq!(target.conversion, None);
+
+    let hdr = TextureFile::from_bytes(&ktx2_file(
+        VK_FORMAT_ASTC_4X4_SFLOAT_BLOCK,
+        4,
+        4,
+        0,
+        &[vec![0; 16]],
+    ))
+    .unwrap();
+    let format = wgpu::TextureFormat::Astc {
+        block: wgpu::AstcBlock::B4x4,
+        channel: wgpu::AstcChannel::Hdr,
+    };
+    assert_eq!(
+        hdr.target(wgpu::Features::empty()),
+        Err(TextureFileError::UnsupportedFormat {
+            format,
+            missing_features: wgpu::Features::TEXTURE_COMPRESSION_ASTC_HDR,
+        })
+    );
+
+    assert_eq!(
+        TextureFile::from_bytes(b"PNG").unwrap_err(),
+        TextureFileError::UnknownContainer
+    );
+    assert_eq!(
+        TextureFile::from_bytes(&BGRA_DDS[..BGRA_DDS.len() - 1]).unwrap_err(),
+        TextureFileError::Malformed("truncated texel data")
+    );
+    // the offset of the level added to its length overflows
+    let mut huge_offset = ktx2_file(VK_FORMAT_R8G8B8_SRGB, 1, 1, 0, &[vec![0; 3]]);
+    huge_offset[80..88].copy_from_slice(&(u64::MAX - 1).to_le_bytes());
+    assert_eq!(
+        TextureFile::from_bytes(&huge_offset).unwrap_err(),
+        TextureFileError::Malformed("truncated texel data")
+    );
+    let truncated = ktx2_file(VK_FORMAT_R8G8B8_SRGB, 2, 2, 0, &[vec![0; 12]]);
+    assert_eq!(
+        TextureFile::from_bytes(&truncated[..truncated.len() - 1]).unwrap_err(),
+        TextureFileError::Malformed("truncated texel data")
+    );
+    // the size of the texels overflows
+    let huge = ktx2_file(
+        VK_FORMAT_R8G8B8_SRGB,
+        u32::MAX,
+        u32::MAX,
+        u32::MAX,
+        &[vec![0; 3]],
+    );
+    assert_eq!(
+        TextureFile::from_bytes(&huge).unwrap_err(),
+        TextureFileError::Malformed("texture too large")
+    );
+    let mut huge_dds = BGRA_DDS.to_vec();
+    huge_dds[12..20].copy_from_slice(&[0xFF; 8]);
+    huge_dds[28..32].copy_from_slice(&1u32.to_le_bytes());
+    assert_eq!(
+        TextureFile::from_bytes(&huge_dds).unwrap_err(),
+        TextureFileError::Malformed("texture too large")
+    );
+
+    let mut zstd = ktx2_file(VK_FORMAT_R8G8B8_SRGB, 1, 1, 0, &[vec![0; 3]]);
+    zstd[44] = 2;
+    assert_eq!(
+        TextureFile::from_bytes(&zstd).unwrap_err(),
+        TextureFileError::UnsupportedSupercompression(2)
+    );
+}
@@ -0,0 +1,157 @@
+use std::sync::{Arc, Mutex};
+
+use wgpu::util::{TypedBuffer, WgslLayout, WgslTypeLayout};
+
+use crate::common::{initialize_test, TestParameters};
+
+#[derive(Clone, Copy, Debug, PartialEq, WgslLayout)]
+struct Light {
+    position: [f32; 3],
+    intensity: f32,
+    color: [f32; 3],
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, WgslLayout)]
+struct Scene {
+    count: u32,
+    lights: [Light; 2],
+    transform: [[f32; 4]; 4],
+}
+
+const LIGHT: Light = Light {
+    position: [1.0, 2.0, 3.0],
+    intensity: 4.0,
+    color: [5.0, 6.0, 7.0],
+};
+
+#[test]
+fn typed_buffer_layout() {
+    // the intensity fits in the padding of the position, unlike the count
+    assert_eq!((Light::ALIGNMENT, Light::SIZE), (16, 32));
+    assert_eq!((Scene::ALIGNMENT, Scene::SIZE), (16, 144));
+    assert_eq!(TypedBuffer::<Light>::stride(), 32);
+    assert_eq!(
+        <[f32; 3]>::layout(),
+        WgslTypeLayout::Vector {
+            count: 3,
+            component_size: 4,
+        }
+    );
+
+    let bytes = TypedBuffer::to_bytes(&[LIGHT]);
+    let floats = bytes
+        .chunks_exact(4)
+        .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .collect::<Vec<_>>();
+    assert_eq!(floats, [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 0.0]);
+    assert_eq!(Light::read_bytes(&bytes), LIGHT);
+
+    let mut scene = Scene {
+        count: 2,
+        lights: [LIGHT; 2],
+        transform: [[0.0; 4]; 4],
+    };
+    scene.lights[1].intensity = 8.0;
+    scene.transform[3] = [1.0, 2.0, 3.0, 1.0];
+    let bytes = TypedBuffer::to_bytes(&[scene]);
+    assert_eq!(&bytes[0..4], 2u32.to_le_bytes());
+    assert_eq!(&bytes[16 + 32 + 12..16 + 32 + 16], 8.0f32.to_le_bytes());
+    assert_eq!(Scene::read_bytes(&bytes), scene);
+}
+
+#[cfg(feature = "naga")]
+#[test]
+fn typed_buffer_check_layout() {
+    use wgpu::util::WgslLayoutError;
+
+    let module = naga::front::wgsl::parse_str(
+        "
+        struct Light {
+            position: vec3<f32>,
+            intensity: f32,
+            color: vec3<f32>,
+        }
+        struct Scene {
+            count: u32,
+            lights: array<Light, 2>,
+            transform: mat4x4<f32>,
+        }
+        struct Padded {
+            @size(16) position: vec3<f32>,
+            intensity: f32,
+            color: vec3<f32>,
+        }
+        @group(0) @binding(0) var<storage> lights: array<Light>;
+        @group(0) @binding(1) var<uniform> scene: Scene;
+        @group(0) @binding(2) var<storage> padded: Padded;
+        @group(0) @binding(3) var<storage> three_lights: array<Light, 3>;
+        @group(0) @binding(4) var<storage> transforms: array<mat4x4<f32>>;
+        ",
+    )
+    .unwrap();
+
+    assert_eq!(TypedBuffer::<Light>::check_layout(&module, 0, 0), Ok(()));
+    assert_eq!(TypedBuffer::<Scene>::check_layout(&module, 0, 1), Ok(()));
+    // an array of matrices, which are arrays themselves
+    assert_eq!(
+        TypedBuffer::<[[f32; 4]; 4]>::check_layout(&module, 0, 4),
+        Ok(())
+    );
+    assert_eq!(
+        TypedBuffer::<Light>::check_layout(&module, 0, 2),
+        Err(WgslLayoutError::OffsetMismatch {
+            path: "padded.intensity".to_string(),
+            shader: 16,
+            rust: 12,
+        })
+    );
+    assert_eq!(
+        TypedBuffer::<[Light; 2]>::check_layout(&module, 0, 3),
+        Err(WgslLayoutError::SizeMismatch {
+            path: "three_lights".to_string(),
+            shader: 3,
+            rust: 2,
+        })
+    );
+    assert_eq!(
+        TypedBuffer::<Scene>::check_layout(&module, 0, 0),
+        Err(WgslLayoutError::StrideMismatch {
+            path: "lights[]".to_string(),
+            shader: 32,
+            rust: 144,
+        })
+    );
+    assert_eq!(
+        TypedBuffer::<Light>::check_layout(&module, 1, 0),
+        Err(WgslLayoutError::MissingBinding {
+            group: 1,
+            binding: 0
+        })
+    );
+}
+
+#[test]
+fn typed_buffer_write_read() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let buffer = TypedBuffer::new_init(
+            &ctx.device,
+            None,
+            &[LIGHT; 3],
+            wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST,
+        );
+        assert_eq!(buffer.len(), 3);
+        let other = Light {
+            intensity: 9.0,
+            ..LIGHT
+        };
+        buffer.write(&ctx.queue, 1, &[other]);
+
+        let result = Arc::new(Mutex::new(None));
+        let result_clone = Arc::clone(&result);
+        buffer.read(&ctx.device, &ctx.queue, 1.., move |lights| {
+            *result_clone.lock().unwrap() = Some(lights.unwrap());
+        });
+        ctx.device.poll(wgpu::Maintain::Wait);
+        assert_eq!(result.lock().unwrap().take().unwrap(), [other, LIGHT]);
+    })
+}
@@ -0,0 +1,157 @@
+use std::borrow::Cow;
+
+use wgpu::util::{VertexLayout, VertexLayoutError};
+
+use crate::common::{initialize_test, TestParameters};
+
+#[repr(C)]
+#[derive(Clone, Copy, VertexLayout)]
+struct Vertex {
+    position: [f32; 3],
+    #[unorm]
+    color: [u8; 4],
+    #[vertex(location = 4)]
+    uv: [f32; 2],
+    #[vertex(format = "Float16x2")]
+    normal: [u16; 2],
+    #[vertex(skip)]
+    padding: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, VertexLayout)]
+struct Instance(#[snorm] [i16; 2], f32, [i32; 3]);
+
+const SHADER: &str = "
+struct Inputs {
+    @location(0) position: vec3<f32>,
+    @location(1) color: vec4<f32>,
+    @location(4) uv: vec2<f32>,
+    @location(5) normal: vec2<f32>,
+};
+
+@vertex
+fn vs_main(inputs: Inputs) -> @builtin(position) vec4<f32> {
+    return vec4<f32>(inputs.position + vec3<f32>(inputs.uv, inputs.normal.x), inputs.color.x);
+}
+
+@vertex
+fn vs_integer(@location(1) color: vec4<u32>) -> @builtin(position) vec4<f32> {
+    return vec4<f32>(color);
+}
+
+@vertex
+fn vs_missing(@location(6) value: f32) -> @builtin(position) vec4<f32> {
+    return vec4<f32>(value);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0);
+}
+";
+
+#[test]
+fn vertex_layout_attributes() {
+    assert_eq!(Vertex::STRIDE, 32);
+    assert_eq!(
+        Vertex::ATTRIBUTES,
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Unorm8x4, 4 => Float32x2, 5 => Float16x2]
+    );
+
+    // the C layout aligns the integers after the 2 shorts
+    assert_eq!(Instance::STRIDE, 20);
+    assert_eq!(
+        Instance::ATTRIBUTES,
+        [
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Snorm16x2,
+                offset: 0,
+                shader_location: 0,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32,
+                offset: 4,
+                shader_location: 1,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Sint32x3,
+                offset: 8,
+                shader_location: 2,
+            },
+        ]
+    );
+    let layout = Instance::layout(wgpu::VertexStepMode::Instance);
+    assert_eq!(layout.array_stride, 20);
+    assert_eq!(layout.step_mode, wgpu::VertexStepMode::Instance);
+}
+
+#[test]
+fn vertex_layout_verify() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let module = ctx
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: None,
+                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(SHADER)),
+            });
+        let reflection = module.reflect().unwrap();
+
+        assert_eq!(
+            Vertex::verify(reflection.entry_point("vs_main").unwrap()),
+            Ok(())
+        );
+        assert_eq!(
+            Vertex::verify(reflection.entry_point("vs_integer").unwrap()),
+            Err(VertexLayoutError::Mismatch {
+                location: 1,
+                format: wgpu::VertexFormat::Unorm8x4,
+                input: wgpu::VertexFormat::Uint32x4,
+            })
+        );
+        assert_eq!(
+            Vertex::verify(reflection.entry_point("vs_missing").unwrap()),
+            Err(VertexLayoutError::MissingAttribute {
+                location: 6,
+                input: wgpu::VertexFormat::Float32,
+            })
+        );
+        assert_eq!(
+            Vertex::verify(reflection.entry_point("fs_main").unwrap()),
+            Err(VertexLayoutError::NotVertex("fs_main".to_string()))
+        );
+        assert_eq!(
+            wgpu::util::verify_vertex_buffers(
+                &[
+                    Vertex::layout(wgpu::VertexStepMode::Vertex),
+                    Instance::layout(wgpu::VertexStepMode::Instance),
+                ],
+                reflection.entry_point("vs_main").unwrap(),
+            ),
+            Err(VertexLayoutError::DuplicateLocation(0))
+        );
+
+        // the verified layout is accepted by the pipeline
+        ctx.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        ctx.device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: None,
+                layout: None,
+                vertex: wgpu::VertexState {
+                    module: &module,
+                    entry_point: "vs_main",
+                    buffers: &[Vertex::layout(wgpu::VertexStepMode::Vertex)],
+                },
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                fragment: Some(wgpu::FragmentState {
+                    module: &module,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::TextureFormat::Rgba8Unorm.into())],
+                }),
+                multiview: None,
+            });
+        assert!(pollster::block_on(ctx.device.pop_error_scope()).is_none());
+    })
+}
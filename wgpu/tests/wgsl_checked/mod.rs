@@ -0,0 +1,92 @@
+use std::num::NonZeroU64;
+
+use crate::common::{initialize_test, TestParameters};
+
+wgpu::include_wgsl_checked!(mod shader = "tests/wgsl_checked/shader.wgsl");
+
+#[test]
+fn wgsl_checked_bindings() {
+    assert_eq!(shader::PARAMS_GROUP, 0);
+    assert_eq!(
+        shader::PARAMS,
+        wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: NonZeroU64::new(80),
+            },
+            count: None,
+        }
+    );
+    assert_eq!(
+        shader::COLOR_TEXTURE.ty,
+        wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        }
+    );
+    assert_eq!(
+        shader::COLOR_TEXTURE.visibility,
+        wgpu::ShaderStages::FRAGMENT
+    );
+    assert_eq!(
+        shader::COLOR_SAMPLER.ty,
+        wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering)
+    );
+    assert_eq!(
+        shader::GROUP_0
+            .iter()
+            .map(|entry| entry.binding)
+            .collect::<Vec<_>>(),
+        [0, 1, 2]
+    );
+
+    assert_eq!(shader::OUTPUT_GROUP, 1);
+    assert_eq!(shader::INPUT.visibility, wgpu::ShaderStages::COMPUTE);
+    assert_eq!(
+        shader::INPUT.ty,
+        wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: true },
+            has_dynamic_offset: false,
+            min_binding_size: NonZeroU64::new(4),
+        }
+    );
+    assert_eq!(
+        shader::OUTPUT.ty,
+        wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: false },
+            has_dynamic_offset: false,
+            min_binding_size: NonZeroU64::new(4),
+        }
+    );
+    assert_eq!(
+        shader::IMAGE.ty,
+        wgpu::BindingType::StorageTexture {
+            access: wgpu::StorageTextureAccess::WriteOnly,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            view_dimension: wgpu::TextureViewDimension::D2,
+        }
+    );
+    assert_eq!(shader::GROUP_1.len(), 3);
+}
+
+#[test]
+fn wgsl_checked_module() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let descriptor = wgpu::include_wgsl_checked!(
+            "tests/wgsl_checked/shader.wgsl",
+            capabilities = [PRIMITIVE_INDEX],
+        );
+        assert_eq!(descriptor.label, Some("tests/wgsl_checked/shader.wgsl"));
+        ctx.device.create_shader_module(descriptor);
+        ctx.device.create_shader_module(shader::descriptor());
+        ctx.device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: None,
+                entries: shader::GROUP_0,
+            });
+    })
+}
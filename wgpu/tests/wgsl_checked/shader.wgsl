@@ -0,0 +1,28 @@
+struct Params {
+    transform: mat4x4<f32>,
+    tint: vec4<f32>,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(2) var colorTexture: texture_2d<f32>;
+@group(0) @binding(1) var colorSampler: sampler;
+
+@group(1) @binding(0) var<storage, read> input: array<u32>;
+@group(1) @binding(1) var<storage, read_write> output: array<u32>;
+@group(1) @binding(2) var image: texture_storage_2d<rgba8unorm, write>;
+
+@vertex
+fn vs_main(@location(0) position: vec2<f32>) -> @builtin(position) vec4<f32> {
+    return params.transform * vec4<f32>(position, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    return params.tint * textureSample(colorTexture, colorSampler, position.xy);
+}
+
+@compute @workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    output[id.x] = input[id.x];
+    textureStore(image, vec2<i32>(id.xy), vec4<f32>(1.0));
+}